@@ -0,0 +1,292 @@
+use alvr_common::anyhow::{anyhow, bail, Result};
+use alvr_events::{Event, EventType};
+use alvr_filesystem::Layout;
+use alvr_packets::{ServerRequest, SetValuesRequest, SettingsChangeOrigin, SettingsHistoryEntry};
+use alvr_server_io::ServerSessionManager;
+use alvr_session::SessionConfig;
+use serde_json as json;
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tungstenite::{Message, WebSocket};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(200);
+const EVENTS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+// The events socket starts listening only after the handshake, requests sent before that would
+// have their response lost
+const RESEND_INTERVAL: Duration = Duration::from_millis(500);
+
+const STREAMER_NOT_RUNNING: &str = "The streamer (SteamVR) is not running";
+
+enum SessionSource {
+    Remote { port: u16, agent: ureq::Agent },
+    Local(Box<ServerSessionManager>),
+}
+
+// Like the dashboard, requests are sent to the streamer if it is running, otherwise they are
+// handled directly on session.json
+pub struct Connection {
+    layout: Layout,
+    source: SessionSource,
+}
+
+impl Connection {
+    pub fn new(layout: Layout, port: Option<u16>) -> Self {
+        let session_manager = ServerSessionManager::new(Some(layout.session()));
+        let port = port.unwrap_or(session_manager.settings().connection.web_server_port);
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(REQUEST_TIMEOUT)
+            .build();
+
+        let source = if agent
+            .get(&format!("http://127.0.0.1:{port}/api/ping"))
+            .call()
+            .is_ok()
+        {
+            SessionSource::Remote { port, agent }
+        } else {
+            SessionSource::Local(Box::new(session_manager))
+        };
+
+        Self { layout, source }
+    }
+
+    pub fn streamer_running(&self) -> bool {
+        matches!(self.source, SessionSource::Remote { .. })
+    }
+
+    fn open_events_socket(port: u16) -> Result<WebSocket<TcpStream>> {
+        let socket =
+            TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], port)), REQUEST_TIMEOUT)?;
+        socket.set_read_timeout(Some(EVENTS_POLL_INTERVAL))?;
+
+        let (ws, _) = tungstenite::client(format!("ws://127.0.0.1:{port}/api/events"), socket)
+            .map_err(|e| anyhow!("Failed to connect to the events socket: {e}"))?;
+
+        Ok(ws)
+    }
+
+    // Returns None if no event was received before the poll interval
+    fn read_event(ws: &mut WebSocket<TcpStream>) -> Result<Option<Event>> {
+        match ws.read() {
+            Ok(Message::Text(json_string)) => Ok(json::from_str(&json_string).ok()),
+            Ok(_) => Ok(None),
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                Ok(None)
+            }
+            Err(e) => bail!("Events socket closed: {e}"),
+        }
+    }
+
+    fn send_remote(port: u16, agent: &ureq::Agent, request: &ServerRequest) -> Result<()> {
+        agent
+            .get(&format!("http://127.0.0.1:{port}/api/dashboard-request"))
+            .send_json(request)?;
+
+        Ok(())
+    }
+
+    // Queries are answered by the streamer with an event. Queries without side effects are resent
+    // until answered
+    fn wait_response<T>(
+        ws: &mut WebSocket<TcpStream>,
+        port: u16,
+        agent: &ureq::Agent,
+        request: ServerRequest,
+        resend: bool,
+        mut extract: impl FnMut(EventType) -> Option<T>,
+    ) -> Result<T> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut resend_deadline = Instant::now() + RESEND_INTERVAL;
+        Self::send_remote(port, agent, &request)?;
+        while Instant::now() < deadline {
+            if resend && Instant::now() >= resend_deadline {
+                Self::send_remote(port, agent, &request)?;
+                resend_deadline += RESEND_INTERVAL;
+            }
+
+            if let Some(value) = Self::read_event(ws)?.and_then(|e| extract(e.event_type)) {
+                return Ok(value);
+            }
+        }
+
+        bail!("Timed out waiting for the streamer to respond")
+    }
+
+    fn query_remote<T>(
+        port: u16,
+        agent: &ureq::Agent,
+        request: ServerRequest,
+        extract: impl FnMut(EventType) -> Option<T>,
+    ) -> Result<T> {
+        let mut ws = Self::open_events_socket(port)?;
+
+        Self::wait_response(&mut ws, port, agent, request, true, extract)
+    }
+
+    pub fn session(&self) -> Result<SessionConfig> {
+        match &self.source {
+            SessionSource::Remote { port, agent } => Self::query_remote(
+                *port,
+                agent,
+                ServerRequest::GetSession,
+                |event| match event {
+                    EventType::Session(session) => Some(*session),
+                    _ => None,
+                },
+            ),
+            SessionSource::Local(session_manager) => Ok(session_manager.session().clone()),
+        }
+    }
+
+    pub fn settings_history(&self) -> Result<Vec<SettingsHistoryEntry>> {
+        match &self.source {
+            SessionSource::Remote { port, agent } => {
+                Self::query_remote(*port, agent, ServerRequest::GetSettingsHistory, |event| {
+                    match event {
+                        EventType::SettingsHistory(entries) => Some(entries),
+                        _ => None,
+                    }
+                })
+            }
+            SessionSource::Local(session_manager) => Ok(session_manager.settings_history()),
+        }
+    }
+
+    pub fn registered_drivers(&self) -> Result<Vec<PathBuf>> {
+        match &self.source {
+            SessionSource::Remote { port, agent } => Self::query_remote(
+                *port,
+                agent,
+                ServerRequest::GetDriverList,
+                |event| match event {
+                    EventType::DriversList(list) => Some(list),
+                    _ => None,
+                },
+            ),
+            SessionSource::Local(_) => alvr_server_io::get_registered_drivers(),
+        }
+    }
+
+    // Returns the path of the bundle
+    pub fn create_support_bundle(&self, redact: bool) -> Result<PathBuf> {
+        match &self.source {
+            SessionSource::Remote { port, agent } => {
+                let mut ws = Self::open_events_socket(*port)?;
+
+                // The request must be sent only once, wait for the socket to receive events first
+                Self::wait_response(
+                    &mut ws,
+                    *port,
+                    agent,
+                    ServerRequest::GetSession,
+                    true,
+                    |e| matches!(e, EventType::Session(_)).then_some(()),
+                )?;
+
+                Self::wait_response(
+                    &mut ws,
+                    *port,
+                    agent,
+                    ServerRequest::CreateSupportBundle { redact },
+                    false,
+                    |event| match event {
+                        EventType::SupportBundleCreated(path) => Some(path),
+                        _ => None,
+                    },
+                )
+            }
+            SessionSource::Local(session_manager) => alvr_server_io::create_support_bundle(
+                &self.layout,
+                session_manager.session(),
+                &[],
+                redact,
+            ),
+        }
+    }
+
+    // Fire and forget. For the streamer, success means only that the request was delivered
+    pub fn request(&mut self, request: ServerRequest) -> Result<()> {
+        let session_manager = match &mut self.source {
+            SessionSource::Remote { port, agent } => {
+                return Self::send_remote(*port, agent, &request);
+            }
+            SessionSource::Local(session_manager) => session_manager,
+        };
+
+        let profiles_dir = self.layout.button_mapping_profiles_dir();
+
+        match request {
+            // Queries have their own methods
+            ServerRequest::Log(_)
+            | ServerRequest::GetSession
+            | ServerRequest::GetSettingsHistory
+            | ServerRequest::GetAudioDevices
+            | ServerRequest::GetDriverList
+            | ServerRequest::GetButtonMappingProfiles
+            | ServerRequest::CreateSupportBundle { .. } => (),
+            ServerRequest::UpdateSession(session) => {
+                *session_manager.session_mut_from(SettingsChangeOrigin::Cli) = *session
+            }
+            ServerRequest::SetValues(SetValuesRequest { origin, values }) => {
+                session_manager.set_values(values, origin)?
+            }
+            ServerRequest::RollbackSettings(entry_id) => {
+                session_manager.rollback_settings(entry_id)?
+            }
+            ServerRequest::UpdateClientList { hostname, action } => {
+                session_manager.update_client_list(hostname, action)
+            }
+            ServerRequest::FirewallRules(action) => {
+                if let Err(code) = alvr_server_io::firewall_rules(action) {
+                    bail!("Setting firewall rules failed with code {code}");
+                }
+            }
+            ServerRequest::RegisterAlvrDriver => alvr_server_io::driver_registration(
+                &[self.layout.openvr_driver_root_dir.clone()],
+                true,
+            )?,
+            ServerRequest::UnregisterDriver(path) => {
+                alvr_server_io::driver_registration(&[path], false)?
+            }
+            ServerRequest::ImportButtonMappingProfile(content) => {
+                alvr_server_io::import_button_mapping_profile(&profiles_dir, &content)?
+            }
+            ServerRequest::RemoveButtonMappingProfile(name) => {
+                alvr_server_io::remove_button_mapping_profile(&profiles_dir, &name)?
+            }
+            ServerRequest::CaptureFrame
+            | ServerRequest::InsertIdr
+            | ServerRequest::StartRecording
+            | ServerRequest::StopRecording
+            | ServerRequest::RunConnectionDiagnostics(_)
+            | ServerRequest::RestartSteamvr
+            | ServerRequest::ShutdownSteamvr => bail!(STREAMER_NOT_RUNNING),
+        }
+
+        Ok(())
+    }
+
+    // Runs until the callback returns false or the streamer closes the socket
+    pub fn tail_events(&self, mut callback: impl FnMut(Event) -> bool) -> Result<()> {
+        let SessionSource::Remote { port, .. } = &self.source else {
+            bail!(STREAMER_NOT_RUNNING);
+        };
+
+        let mut ws = Self::open_events_socket(*port)?;
+        loop {
+            if let Some(event) = Self::read_event(&mut ws)? {
+                if !callback(event) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
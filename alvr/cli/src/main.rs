@@ -0,0 +1,308 @@
+mod connection;
+
+use alvr_common::anyhow::{anyhow, bail, Result};
+use alvr_events::Event;
+use alvr_packets::{
+    ClientListAction, FirewallRulesAction, PathSegment, PathValuePair, ServerRequest,
+    SetValuesRequest, SettingsChangeOrigin,
+};
+use connection::Connection;
+use pico_args::Arguments;
+use serde_json as json;
+use std::{env, net::IpAddr, path::PathBuf, process::ExitCode};
+
+const HELP_STR: &str = r#"
+alvr_cli
+Control the ALVR streamer from the command line. Requests are sent to the streamer if it is running,
+otherwise they are applied directly to the session file.
+
+USAGE:
+    alvr_cli [FLAGS] [ARGS] <SUBCOMMAND> [SUBCOMMAND ARGS]
+
+SUBCOMMANDS:
+    status                      Show if the streamer is running
+    get [PATH]                  Print a session value, or the whole session
+    set <PATH> <VALUE>          Set a session value. VALUE is parsed as JSON, or used as a string
+    history                     List the recorded settings changes
+    rollback <ID>               Restore the settings as they were before the change with this ID
+    clients                     List the known clients
+    add-client <HOSTNAME> [IP]  Add a trusted client, optionally with a manual IP
+    trust <HOSTNAME>            Trust a client
+    remove <HOSTNAME>           Remove a client
+    events [ID]...              Print the streamer events as they arrive, optionally filtered by ID
+                                (for example Log, Session, StatisticsSummary)
+    capture-frame               Save the next video frame
+    insert-idr                  Request a new IDR frame
+    start-recording             Start recording the video stream
+    stop-recording              Stop recording the video stream
+    restart-steamvr             Restart SteamVR
+    shutdown-steamvr            Shut down SteamVR
+    drivers                     List the registered OpenVR drivers
+    register-driver             Register the ALVR OpenVR driver
+    unregister-driver <PATH>    Unregister an OpenVR driver
+    firewall <add|remove>       Add or remove the firewall rules
+    support-bundle [--redact]   Save logs, session, drivers, GPU info and recent statistics to a zip
+                                file in the log directory. --redact removes client hostnames, IPs,
+                                the home directory path and secrets (pairing tokens, webhook URLs,
+                                rendezvous and relay hosts)
+    request <JSON>              Send a raw ServerRequest
+
+FLAGS:
+    -h, --help                  Print this text
+    --json                      Print compact JSON, one value per line. Useful for scripting
+
+ARGS:
+    --port <PORT>               Web server port. By default it is read from the session
+
+PATH is a session path like session_settings.video.preferred_fps or
+session_settings.connection.stream_protocol.variant
+"#;
+
+fn find_value<'a>(root: &'a json::Value, path: &[PathSegment]) -> Result<&'a json::Value> {
+    let mut value_ref = root;
+    for segment in path {
+        value_ref = match segment {
+            PathSegment::Name(name) => value_ref.get(name),
+            PathSegment::Index(index) => value_ref.get(index),
+        }
+        .ok_or_else(|| anyhow!("Path {path:?} not found"))?;
+    }
+
+    Ok(value_ref)
+}
+
+fn print_value(value: &json::Value, json_output: bool) -> Result<()> {
+    if json_output {
+        println!("{}", json::to_string(value)?);
+    } else {
+        println!("{}", json::to_string_pretty(value)?);
+    }
+
+    Ok(())
+}
+
+fn print_event(event: &Event, json_output: bool) -> Result<()> {
+    if json_output {
+        println!("{}", json::to_string(event)?);
+    } else {
+        let event_json = json::to_value(&event.event_type)?;
+        println!(
+            "{} {}: {}",
+            event.timestamp,
+            event_json["id"].as_str().unwrap_or_default(),
+            event_json["data"]
+        );
+    }
+
+    Ok(())
+}
+
+fn run(mut args: Arguments) -> Result<()> {
+    let json_output = args.contains("--json");
+    let port = args.opt_value_from_str::<_, u16>("--port")?;
+
+    let Some(subcommand) = args.subcommand()? else {
+        println!("{HELP_STR}");
+        return Ok(());
+    };
+
+    let layout = alvr_filesystem::filesystem_layout_from_dashboard_exe(&env::current_exe()?);
+    let mut connection = Connection::new(layout, port);
+
+    let request = match subcommand.as_str() {
+        "status" => {
+            let running = connection.streamer_running();
+            if json_output {
+                println!("{}", json::json!({ "streamer_running": running }));
+            } else if running {
+                println!("The streamer is running");
+            } else {
+                println!("The streamer is not running");
+            }
+
+            None
+        }
+        "get" => {
+            let path = args
+                .opt_free_from_str::<String>()?
+                .map(|path| alvr_packets::parse_path(&path))
+                .unwrap_or_default();
+
+            let session_json = json::to_value(connection.session()?)?;
+            print_value(find_value(&session_json, &path)?, json_output)?;
+
+            None
+        }
+        "set" => {
+            let path = alvr_packets::parse_path(&args.free_from_str::<String>()?);
+            let value_string = args.free_from_str::<String>()?;
+            let value = json::from_str::<json::Value>(&value_string)
+                .unwrap_or(json::Value::String(value_string));
+
+            // Check the path early, the streamer would just ignore the request
+            find_value(&json::to_value(connection.session()?)?, &path)?;
+
+            Some(ServerRequest::SetValues(SetValuesRequest {
+                origin: SettingsChangeOrigin::Cli,
+                values: vec![PathValuePair { path, value }],
+            }))
+        }
+        "history" => {
+            let entries = connection.settings_history()?;
+            if json_output {
+                println!("{}", json::to_string(&entries)?);
+            } else {
+                for entry in entries {
+                    println!("{} ({:?}):", entry.id, entry.origin);
+                    for change in entry.changes {
+                        let path = change
+                            .path
+                            .iter()
+                            .map(|segment| match segment {
+                                PathSegment::Name(name) => name.clone(),
+                                PathSegment::Index(index) => index.to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        println!("    {path}: {} -> {}", change.old_value, change.new_value);
+                    }
+                }
+            }
+
+            None
+        }
+        "rollback" => Some(ServerRequest::RollbackSettings(args.free_from_str()?)),
+        "clients" => {
+            let session = connection.session()?;
+            if json_output {
+                println!("{}", json::to_string(&session.client_connections)?);
+            } else {
+                for (hostname, client) in session.client_connections {
+                    println!(
+                        "{hostname} ({}): {:?}, {}",
+                        client.display_name,
+                        client.connection_state,
+                        if client.trusted {
+                            "trusted"
+                        } else {
+                            "not trusted"
+                        }
+                    );
+                }
+            }
+
+            None
+        }
+        "add-client" => {
+            let hostname = args.free_from_str()?;
+            let manual_ips = args.opt_free_from_str::<IpAddr>()?.into_iter().collect();
+
+            Some(ServerRequest::UpdateClientList {
+                hostname,
+                action: ClientListAction::AddIfMissing {
+                    trusted: true,
+                    manual_ips,
+                },
+            })
+        }
+        "trust" => Some(ServerRequest::UpdateClientList {
+            hostname: args.free_from_str()?,
+            action: ClientListAction::Trust,
+        }),
+        "remove" => Some(ServerRequest::UpdateClientList {
+            hostname: args.free_from_str()?,
+            action: ClientListAction::RemoveEntry,
+        }),
+        "events" => {
+            let mut ids = vec![];
+            while let Some(id) = args.opt_free_from_str::<String>()? {
+                ids.push(id);
+            }
+
+            connection.tail_events(|event| {
+                let id = json::to_value(&event.event_type)
+                    .ok()
+                    .and_then(|value| value["id"].as_str().map(String::from))
+                    .unwrap_or_default();
+
+                if ids.is_empty() || ids.contains(&id) {
+                    print_event(&event, json_output).is_ok()
+                } else {
+                    true
+                }
+            })?;
+
+            None
+        }
+        "capture-frame" => Some(ServerRequest::CaptureFrame),
+        "insert-idr" => Some(ServerRequest::InsertIdr),
+        "start-recording" => Some(ServerRequest::StartRecording),
+        "stop-recording" => Some(ServerRequest::StopRecording),
+        "restart-steamvr" => Some(ServerRequest::RestartSteamvr),
+        "shutdown-steamvr" => Some(ServerRequest::ShutdownSteamvr),
+        "drivers" => {
+            let drivers = connection.registered_drivers()?;
+            if json_output {
+                println!("{}", json::to_string(&drivers)?);
+            } else {
+                for path in drivers {
+                    println!("{}", path.display());
+                }
+            }
+
+            None
+        }
+        "register-driver" => Some(ServerRequest::RegisterAlvrDriver),
+        "unregister-driver" => Some(ServerRequest::UnregisterDriver(
+            args.free_from_str::<PathBuf>()?,
+        )),
+        "firewall" => {
+            let action = match args.free_from_str::<String>()?.as_str() {
+                "add" => FirewallRulesAction::Add,
+                "remove" => FirewallRulesAction::Remove,
+                _ => bail!("Expected \"add\" or \"remove\""),
+            };
+
+            Some(ServerRequest::FirewallRules(action))
+        }
+        "support-bundle" => {
+            let path = connection.create_support_bundle(args.contains("--redact"))?;
+            if json_output {
+                println!("{}", json::json!({ "path": path }));
+            } else {
+                println!("Support bundle saved to {}", path.display());
+            }
+
+            None
+        }
+        "request" => Some(json::from_str(&args.free_from_str::<String>()?)?),
+        _ => bail!("Unrecognized subcommand \"{subcommand}\". Use --help to list them"),
+    };
+
+    let remaining = args.finish();
+    if !remaining.is_empty() {
+        bail!("Unexpected arguments: {remaining:?}");
+    }
+
+    if let Some(request) = request {
+        connection.request(request)?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        println!("{HELP_STR}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
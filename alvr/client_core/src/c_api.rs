@@ -8,27 +8,37 @@ use alvr_common::{
     info,
     once_cell::sync::Lazy,
     parking_lot::Mutex,
-    warn, DeviceMotion, Fov, OptLazy, Pose,
+    warn, DeviceMotion, Fov, OptLazy, Pose, RelaxedAtomic,
 };
-use alvr_packets::{ButtonEntry, ButtonValue, FaceData, ViewParams};
-use alvr_session::{CodecType, FoveatedEncodingConfig};
+use alvr_packets::{ButtonEntry, ButtonValue, FaceData, NegotiatedStreamingConfig, ViewParams};
+use alvr_session::{ClientStreamingOverrides, CodecType, FoveatedEncodingConfig, Settings};
 use std::{
     cell::RefCell,
     collections::VecDeque,
     ffi::{c_char, c_void, CStr, CString},
-    ptr,
+    mem, ptr,
     rc::Rc,
     slice,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 
 static CLIENT_CORE_CONTEXT: OptLazy<ClientCoreContext> = alvr_common::lazy_mut_none();
 static HUD_MESSAGE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new("".into()));
 static SETTINGS: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new("".into()));
+static STREAMING_SETTINGS: Lazy<Mutex<Option<AlvrStreamingSettings>>> =
+    Lazy::new(|| Mutex::new(None));
+static ADVANCED_HAPTICS: RelaxedAtomic = RelaxedAtomic::new(false);
+static NEXT_HAPTICS_PCM_BUFFER_ID: AtomicU64 = AtomicU64::new(1);
+static HAPTICS_PCM_BUFFERS: Lazy<Mutex<VecDeque<(u64, Vec<f32>)>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
 #[allow(clippy::type_complexity)]
 static NAL_QUEUE: Lazy<Mutex<VecDeque<(u64, [ViewParams; 2], Vec<u8>)>>> =
     Lazy::new(|| Mutex::new(VecDeque::new()));
 
+// Buffers that are never retrieved are dropped, oldest first
+const MAX_HAPTICS_PCM_BUFFERS: usize = 64;
+
 // Core interface:
 
 #[repr(C)]
@@ -72,6 +82,98 @@ pub enum AlvrEvent {
         codec: AlvrCodec,
     },
     FrameReady,
+    // Variants added later must not change the size of the event. Fields are ordered to avoid
+    // padding.
+    /// Sent instead of Haptics after calling alvr_enable_advanced_haptics(). Attack and release are
+    /// linear amplitude ramps
+    HapticsWithEnvelope {
+        duration_s: f32,
+        frequency: f32,
+        amplitude: f32,
+        attack_s: f32,
+        release_s: f32,
+        device_id: u64,
+    },
+    /// Sent only after calling alvr_enable_advanced_haptics(). Call alvr_haptics_pcm_samples() with
+    /// buffer_id to get the samples
+    HapticsPcm {
+        sample_rate: f32,
+        append: bool,
+        device_id: u64,
+        buffer_id: u64,
+    },
+}
+
+/// Tracking sources requested by the streamer. They are all false if the corresponding tracking
+/// is disabled
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct AlvrTrackingSources {
+    pub combined_eye_gaze: bool,
+    pub eye_tracking_fb: bool,
+    pub face_tracking_fb: bool,
+    pub eye_expressions_htc: bool,
+    pub lip_expressions_htc: bool,
+    pub body_tracking_full_body_meta: bool,
+    /// Includes the legs, only valid with body_tracking_full_body_meta
+    pub enable_full_body_meta: bool,
+}
+
+/// Negotiated streaming configuration, together with the settings relevant to the client
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AlvrStreamingSettings {
+    /// Must be set to sizeof(AlvrStreamingSettings) before calling alvr_get_streaming_settings().
+    /// Fields past this size are not written, so new fields can be appended without breaking
+    /// older callers
+    pub struct_size: u64,
+    pub view_width: u32,
+    pub view_height: u32,
+    pub refresh_rate_hint: f32,
+    pub game_audio_sample_rate: u32,
+    pub enable_foveated_encoding: bool,
+    pub encoding_gamma: f32,
+    pub enable_hdr: bool,
+    pub use_full_range: bool,
+    pub tracking_sources: AlvrTrackingSources,
+}
+
+fn to_capi_streaming_settings(
+    settings: &Settings,
+    negotiated_config: &NegotiatedStreamingConfig,
+) -> AlvrStreamingSettings {
+    let mut tracking_sources = AlvrTrackingSources::default();
+    if let Some(config) = settings.headset.face_tracking.as_option() {
+        tracking_sources.combined_eye_gaze = config.sources.combined_eye_gaze;
+        tracking_sources.eye_tracking_fb = config.sources.eye_tracking_fb;
+        tracking_sources.face_tracking_fb = config.sources.face_tracking_fb;
+        tracking_sources.eye_expressions_htc = config.sources.eye_expressions_htc;
+        tracking_sources.lip_expressions_htc = config.sources.lip_expressions_htc;
+    }
+    if let Some(config) = settings
+        .headset
+        .body_tracking
+        .as_option()
+        .and_then(|config| config.sources.body_tracking_full_body_meta.as_option())
+    {
+        tracking_sources.body_tracking_full_body_meta = true;
+        tracking_sources.enable_full_body_meta = config.enable_full_body;
+    }
+
+    let encoder_config = &settings.video.encoder_config;
+
+    AlvrStreamingSettings {
+        struct_size: mem::size_of::<AlvrStreamingSettings>() as u64,
+        view_width: negotiated_config.view_resolution.x,
+        view_height: negotiated_config.view_resolution.y,
+        refresh_rate_hint: negotiated_config.refresh_rate_hint,
+        game_audio_sample_rate: negotiated_config.game_audio_sample_rate,
+        enable_foveated_encoding: negotiated_config.enable_foveated_encoding,
+        encoding_gamma: encoder_config.encoding_gamma,
+        enable_hdr: encoder_config.enable_hdr,
+        use_full_range: encoder_config.use_full_range,
+        tracking_sources,
+    }
 }
 
 #[repr(C)]
@@ -159,6 +261,56 @@ pub struct AlvrDeviceMotion {
     angular_velocity: [f32; 3],
 }
 
+/// Every pointer can be null if the corresponding data is not available. Expression arrays are
+/// forwarded with the length reported by the runtime, so newer versions of the extensions that add
+/// expressions are supported without API changes.
+#[repr(C)]
+pub struct AlvrFaceData {
+    /// Must be set to sizeof(AlvrFaceData). New fields are appended and read only if this size
+    /// includes them
+    pub struct_size: u64,
+    /// Left and right eye, each a single pose. They are in the same reference space as the device
+    /// motions, not relative to the head: the streamer makes them head-local using the head motion
+    pub eye_gazes: [*const AlvrPose; 2],
+    /// XR_FB_face_tracking weights
+    pub fb_face_expression: *const f32,
+    pub fb_face_expression_count: u64,
+    /// XR_HTC_facial_tracking eye weights
+    pub htc_eye_expression: *const f32,
+    pub htc_eye_expression_count: u64,
+    /// XR_HTC_facial_tracking lip weights
+    pub htc_lip_expression: *const f32,
+    pub htc_lip_expression_count: u64,
+}
+
+unsafe fn from_capi_expression(expression: *const f32, count: u64) -> Option<Vec<f32>> {
+    (!expression.is_null()).then(|| slice::from_raw_parts(expression, count as _).to_vec())
+}
+
+unsafe fn from_capi_face_data(face_data: &AlvrFaceData) -> FaceData {
+    let eye_gaze =
+        |eye_gaze: *const AlvrPose| (!eye_gaze.is_null()).then(|| from_capi_pose(*eye_gaze));
+
+    FaceData {
+        eye_gazes: [
+            eye_gaze(face_data.eye_gazes[0]),
+            eye_gaze(face_data.eye_gazes[1]),
+        ],
+        fb_face_expression: from_capi_expression(
+            face_data.fb_face_expression,
+            face_data.fb_face_expression_count,
+        ),
+        htc_eye_expression: from_capi_expression(
+            face_data.htc_eye_expression,
+            face_data.htc_eye_expression_count,
+        ),
+        htc_lip_expression: from_capi_expression(
+            face_data.htc_lip_expression,
+            face_data.htc_lip_expression_count,
+        ),
+    }
+}
+
 #[allow(dead_code)]
 #[repr(C)]
 pub enum AlvrButtonValue {
@@ -315,6 +467,8 @@ pub extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent) -> bool {
                     negotiated_config,
                 } => {
                     *SETTINGS.lock() = serde_json::to_string(&settings).unwrap();
+                    *STREAMING_SETTINGS.lock() =
+                        Some(to_capi_streaming_settings(&settings, &negotiated_config));
 
                     AlvrEvent::StreamingStarted {
                         view_width: negotiated_config.view_resolution.x,
@@ -329,12 +483,63 @@ pub extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent) -> bool {
                     duration,
                     frequency,
                     amplitude,
-                } => AlvrEvent::Haptics {
+                    envelope,
+                } => {
+                    if ADVANCED_HAPTICS.value() {
+                        AlvrEvent::HapticsWithEnvelope {
+                            duration_s: duration.as_secs_f32(),
+                            frequency,
+                            amplitude,
+                            attack_s: envelope.attack.as_secs_f32(),
+                            release_s: envelope.release.as_secs_f32(),
+                            device_id,
+                        }
+                    } else {
+                        AlvrEvent::Haptics {
+                            device_id,
+                            duration_s: duration.as_secs_f32(),
+                            frequency,
+                            amplitude,
+                        }
+                    }
+                }
+                ClientCoreEvent::HapticsPcm {
                     device_id,
-                    duration_s: duration.as_secs_f32(),
-                    frequency,
-                    amplitude,
-                },
+                    sample_rate,
+                    samples,
+                    append,
+                } => {
+                    if ADVANCED_HAPTICS.value() {
+                        let buffer_id = NEXT_HAPTICS_PCM_BUFFER_ID.fetch_add(1, Ordering::Relaxed);
+
+                        let mut buffers = HAPTICS_PCM_BUFFERS.lock();
+                        buffers.push_back((buffer_id, samples));
+                        if buffers.len() > MAX_HAPTICS_PCM_BUFFERS {
+                            buffers.pop_front();
+                        }
+
+                        AlvrEvent::HapticsPcm {
+                            sample_rate,
+                            append,
+                            device_id,
+                            buffer_id,
+                        }
+                    } else {
+                        // Approximated with a vibration at the mean amplitude
+                        let amplitude = if !samples.is_empty() {
+                            samples.iter().sum::<f32>() / samples.len() as f32
+                        } else {
+                            0.0
+                        };
+
+                        AlvrEvent::Haptics {
+                            device_id,
+                            duration_s: samples.len() as f32 / sample_rate.max(1.0),
+                            frequency: 0.0, // unspecified
+                            amplitude,
+                        }
+                    }
+                }
                 ClientCoreEvent::DecoderConfig { codec, config_nal } => {
                     NAL_QUEUE
                         .lock()
@@ -378,6 +583,30 @@ pub extern "C" fn alvr_get_settings_json(buffer: *mut c_char) -> u64 {
     string_to_c_str(buffer, &SETTINGS.lock())
 }
 
+/// Returns false if no stream has been started yet. Like alvr_get_settings_json(), it is updated
+/// after receiving StreamingStarted event
+#[no_mangle]
+pub extern "C" fn alvr_get_streaming_settings(out_settings: *mut AlvrStreamingSettings) -> bool {
+    if let Some(settings) = *STREAMING_SETTINGS.lock() {
+        // Copy only the fields known to the caller
+        let size = usize::min(
+            unsafe { (*out_settings).struct_size } as usize,
+            mem::size_of::<AlvrStreamingSettings>(),
+        );
+        unsafe {
+            ptr::copy_nonoverlapping(
+                (&settings as *const AlvrStreamingSettings).cast::<u8>(),
+                out_settings.cast::<u8>(),
+                size,
+            )
+        };
+
+        true
+    } else {
+        false
+    }
+}
+
 /// Call only with external decoder
 /// Returns the number of bytes of the next nal, or 0 if there are no nals ready.
 /// If out_nal or out_timestamp_ns is null, no nal is dequeued. Use to get the nal allocation size.
@@ -435,6 +664,74 @@ pub extern "C" fn alvr_hud_message(message_buffer: *mut c_char) -> u64 {
     cstring.as_bytes_with_nul().len() as u64
 }
 
+/// Enables HapticsWithEnvelope and HapticsPcm events. Without it, envelopes are ignored and PCM
+/// buffers are approximated with Haptics events.
+#[no_mangle]
+pub extern "C" fn alvr_enable_advanced_haptics() {
+    ADVANCED_HAPTICS.set(true);
+}
+
+/// Returns the number of samples of the buffer of a HapticsPcm event, or 0 if not found. Call with
+/// null out_samples to get the buffer size, then call again to get the samples and release the
+/// buffer.
+#[no_mangle]
+pub extern "C" fn alvr_haptics_pcm_samples(buffer_id: u64, out_samples: *mut f32) -> u64 {
+    let mut buffers = HAPTICS_PCM_BUFFERS.lock();
+    let Some(index) = buffers.iter().position(|(id, _)| *id == buffer_id) else {
+        return 0;
+    };
+
+    let samples_count = buffers[index].1.len();
+    if !out_samples.is_null() {
+        let (_, samples) = buffers.remove(index).unwrap();
+        unsafe { ptr::copy_nonoverlapping(samples.as_ptr(), out_samples, samples_count) };
+    }
+
+    samples_count as u64
+}
+
+/// Preferences sent to the streamer on the next connection, remembered by this device.
+/// Use a negative value (or zero) to leave the choice to the streamer settings.
+#[no_mangle]
+pub extern "C" fn alvr_set_streaming_overrides(
+    preferred_codec: i32,
+    max_bitrate_mbps: f32,
+    refresh_rate: f32,
+) {
+    crate::set_streaming_overrides(ClientStreamingOverrides {
+        preferred_codec: match preferred_codec {
+            0 => Some(CodecType::H264),
+            1 => Some(CodecType::Hevc),
+            2 => Some(CodecType::AV1),
+            _ => None,
+        },
+        max_bitrate_mbps: (max_bitrate_mbps > 0.0).then_some(max_bitrate_mbps),
+        refresh_rate: (refresh_rate > 0.0).then_some(refresh_rate),
+    });
+}
+
+/// Rendezvous addresses (host:port) used to find the streamer on other subnets or networks,
+/// remembered by this device. They are needed before the first connection, after that the
+/// addresses are also learned from the streamer settings. Use null or an empty string to go back
+/// to the learned address.
+#[no_mangle]
+pub unsafe extern "C" fn alvr_set_rendezvous_servers(
+    rendezvous_server: *const c_char,
+    internet_rendezvous_server: *const c_char,
+) {
+    let to_address = |address: *const c_char| {
+        (!address.is_null())
+            .then(|| CStr::from_ptr(address).to_string_lossy().trim().to_owned())
+            .filter(|address| !address.is_empty())
+    };
+
+    crate::set_rendezvous_servers(
+        to_address(rendezvous_server),
+        to_address(internet_rendezvous_server),
+    );
+}
+
+/// device_id can be the ID of any tracked device, including body trackers
 #[no_mangle]
 pub extern "C" fn alvr_send_battery(device_id: u64, gauge_value: f32, is_plugged: bool) {
     if let Some(context) = &*CLIENT_CORE_CONTEXT.lock() {
@@ -481,6 +778,7 @@ pub extern "C" fn alvr_send_button(path_id: u64, value: AlvrButtonValue) {
     }
 }
 
+/// Superseded by alvr_send_tracking_v2(), which also supports face expressions.
 /// view_params:
 /// * array of 2;
 /// hand_skeleton:
@@ -497,6 +795,52 @@ pub extern "C" fn alvr_send_tracking(
     device_motions_count: u64,
     hand_skeletons: *const *const AlvrPose,
     eye_gazes: *const *const AlvrPose,
+) {
+    let eye_gazes = if !eye_gazes.is_null() {
+        unsafe { [*eye_gazes, *eye_gazes.offset(1)] }
+    } else {
+        [ptr::null(); 2]
+    };
+
+    let face_data = AlvrFaceData {
+        struct_size: mem::size_of::<AlvrFaceData>() as u64,
+        eye_gazes,
+        fb_face_expression: ptr::null(),
+        fb_face_expression_count: 0,
+        htc_eye_expression: ptr::null(),
+        htc_eye_expression_count: 0,
+        htc_lip_expression: ptr::null(),
+        htc_lip_expression_count: 0,
+    };
+
+    alvr_send_tracking_v2(
+        target_timestamp_ns,
+        view_params,
+        device_motions,
+        device_motions_count,
+        hand_skeletons,
+        &face_data,
+    )
+}
+
+/// view_params:
+/// * array of 2;
+/// device_motions:
+/// * body joints are sent as device motions, with IDs obtained from their path, for example
+///   alvr_path_string_to_id("/user/body/chest")
+/// hand_skeleton:
+/// * outer ptr: array of 2 (can be null);
+/// * inner ptr: array of 26 (can be null if hand is absent)
+/// face_data:
+/// * can be null if face tracking is not available
+#[no_mangle]
+pub extern "C" fn alvr_send_tracking_v2(
+    target_timestamp_ns: u64,
+    view_params: *const AlvrViewParams,
+    device_motions: *const AlvrDeviceMotion,
+    device_motions_count: u64,
+    hand_skeletons: *const *const AlvrPose,
+    face_data: *const AlvrFaceData,
 ) {
     let view_params = unsafe {
         [
@@ -564,25 +908,13 @@ pub extern "C" fn alvr_send_tracking(
         [None, None]
     };
 
-    let eye_gazes = if !eye_gazes.is_null() {
-        let eye_gazes = unsafe { slice::from_raw_parts(eye_gazes, 2) };
-        let eye_gazes = eye_gazes
-            .iter()
-            .map(|&eye_gaze| {
-                (!eye_gaze.is_null()).then(|| {
-                    let eye_gaze = unsafe { &*eye_gaze };
-
-                    Pose {
-                        orientation: from_capi_quat(eye_gaze.orientation),
-                        position: Vec3::from_slice(&eye_gaze.position),
-                    }
-                })
-            })
-            .collect::<Vec<_>>();
-
-        [eye_gazes[0], eye_gazes[1]]
+    // Face data from an incompatible header is ignored
+    let face_data = if !face_data.is_null()
+        && unsafe { (*face_data).struct_size } as usize >= mem::size_of::<AlvrFaceData>()
+    {
+        unsafe { from_capi_face_data(&*face_data) }
     } else {
-        [None, None]
+        FaceData::default()
     };
 
     if let Some(context) = &*CLIENT_CORE_CONTEXT.lock() {
@@ -591,10 +923,7 @@ pub extern "C" fn alvr_send_tracking(
             view_params,
             device_motions,
             hand_skeletons,
-            FaceData {
-                eye_gazes,
-                ..Default::default()
-            },
+            face_data,
         );
     }
 }
@@ -831,3 +1160,35 @@ pub unsafe extern "C" fn alvr_render_stream_opengl(
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{align_of, size_of};
+
+    // Changing any of these values breaks the ABI. Structs with a struct_size field can only grow
+    // by appending fields
+    #[test]
+    fn layout() {
+        assert_eq!((size_of::<AlvrEvent>(), align_of::<AlvrEvent>()), (32, 8));
+        assert_eq!(
+            (
+                size_of::<AlvrTrackingSources>(),
+                align_of::<AlvrTrackingSources>()
+            ),
+            (7, 1)
+        );
+        assert_eq!(
+            (
+                size_of::<AlvrStreamingSettings>(),
+                align_of::<AlvrStreamingSettings>()
+            ),
+            (48, 8)
+        );
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            (size_of::<AlvrFaceData>(), align_of::<AlvrFaceData>()),
+            (72, 8)
+        );
+    }
+}
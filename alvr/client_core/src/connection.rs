@@ -2,6 +2,7 @@
 
 use crate::{
     decoder::{self, DecoderConfig, DecoderSink, DecoderSource},
+    diagnostics,
     logging_backend::{LogMirrorData, LOG_CHANNEL_SENDER},
     platform,
     sockets::AnnouncerSocket,
@@ -11,23 +12,25 @@ use crate::{
 };
 use alvr_audio::AudioDevice;
 use alvr_common::{
-    dbg_connection, debug, error, info,
-    parking_lot::{Condvar, Mutex, RwLock},
+    con_bail, dbg_connection, debug, error, info,
+    parking_lot::{Condvar, Mutex, RwLock, RwLockWriteGuard},
     wait_rwlock, warn, AnyhowToCon, ConResult, ConnectionError, ConnectionState, LifecycleState,
-    ALVR_VERSION,
+    RelaxedAtomic, ALVR_VERSION,
 };
 use alvr_packets::{
-    ClientConnectionResult, ClientControlPacket, ClientStatistics, Haptics, ServerControlPacket,
-    StreamConfigPacket, Tracking, VideoPacketHeader, VideoStreamingCapabilities, ViewParams, AUDIO,
-    HAPTICS, STATISTICS, TRACKING, VIDEO,
+    ClientConnectionResult, ClientControlPacket, ClientStatistics, ConnectionPath, HapticsPacket,
+    ServerControlPacket, StreamConfigPacket, Tracking, VideoPacketHeader,
+    VideoStreamingCapabilities, ViewParams, AUDIO, HAPTICS, STATISTICS, TRACKING, VIDEO,
 };
-use alvr_session::settings_schema::Switch;
+use alvr_session::{settings_schema::Switch, ConnectionConfig, SessionResumeConfig, Settings};
 use alvr_sockets::{
-    ControlSocketSender, PeerType, ProtoControlSocket, StreamSender, StreamSocketBuilder,
-    KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT,
+    ControlSocketReceiver, ControlSocketSender, InternetSocketBuilder, PeerType,
+    ProtoControlSocket, StreamSender, StreamSocket, StreamSocketBuilder, KEEPALIVE_INTERVAL,
+    KEEPALIVE_TIMEOUT,
 };
 use std::{
     collections::VecDeque,
+    net::SocketAddr,
     sync::{mpsc, Arc},
     thread,
     time::{Duration, Instant},
@@ -48,8 +51,12 @@ const STREAM_STARTING_MESSAGE: &str = "The stream will begin soon\nPlease wait..
 const SERVER_RESTART_MESSAGE: &str = "The streamer is restarting\nPlease wait...";
 const SERVER_DISCONNECTED_MESSAGE: &str = "The streamer has disconnected.";
 const CONNECTION_TIMEOUT_MESSAGE: &str = "Connection timeout.";
+const DIAGNOSTICS_MESSAGE: &str = "Testing the network connection\nPlease wait...";
+const DIAGNOSTICS_FINISHED_MESSAGE: &str =
+    "Network test finished.\nSee the results in the streamer dashboard.";
 
 const SOCKET_INIT_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+const RELAY_WAIT_INTERVAL: Duration = Duration::from_secs(2);
 const CONNECTION_RETRY_INTERVAL: Duration = Duration::from_secs(1);
 const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
 const STREAMING_RECV_TIMEOUT: Duration = Duration::from_millis(500);
@@ -69,13 +76,25 @@ pub struct ConnectionContext {
     // todo: the server is supposed to receive and send view configs for each frame
     pub view_params_queue: RwLock<VecDeque<(Duration, [ViewParams; 2])>>,
     pub last_good_view_params: RwLock<[ViewParams; 2]>,
+    // Kept on screen while the streamer keeps rejecting the connection
+    pub rejection_message: Mutex<Option<String>>,
 }
 
 fn set_hud_message(event_queue: &Mutex<VecDeque<ClientCoreEvent>>, message: &str) {
+    let config = Config::load();
+
+    let mut rendezvous_lines = String::new();
+    if let Some(address) = config.active_rendezvous_server() {
+        rendezvous_lines += &format!("rendezvous: {address}\n");
+    }
+    if let Some(address) = config.active_internet_rendezvous_server() {
+        rendezvous_lines += &format!("internet rendezvous: {address}\n");
+    }
+
     let message = format!(
-        "ALVR v{}\nhostname: {}\nIP: {}\n\n{message}",
+        "ALVR v{}\nhostname: {}\nIP: {}\n{rendezvous_lines}\n{message}",
         *ALVR_VERSION,
-        Config::load().hostname,
+        config.hostname,
         platform::local_ip(),
     );
 
@@ -123,6 +142,162 @@ pub fn connection_lifecycle_loop(
     dbg_connection!("connection_lifecycle_loop: End");
 }
 
+// Mirrors the streamer side: exchange public addresses, try hole punching, then let the streamer
+// decide between the direct and the relayed path
+fn connect_internet_stream_socket(
+    settings: &Settings,
+    server_address: SocketAddr,
+    control_sender: &mut ControlSocketSender<ClientControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ServerControlPacket>,
+) -> ConResult<StreamSocket> {
+    let Some(config) = settings.connection.internet_streaming.as_option() else {
+        con_bail!("Internet streaming is disabled");
+    };
+    let rendezvous_address =
+        alvr_sockets::resolve_rendezvous_address(&config.rendezvous_server).to_con()?;
+
+    let builder = InternetSocketBuilder::bind(
+        rendezvous_address,
+        settings.connection.stream_port,
+        settings.connection.dscp.clone(),
+        settings.connection.client_send_buffer_bytes.clone(),
+        settings.connection.client_recv_buffer_bytes.clone(),
+        HANDSHAKE_ACTION_TIMEOUT,
+    )
+    .to_con()?;
+
+    control_sender
+        .send(&ClientControlPacket::InternetEndpoint(
+            builder.public_address,
+        ))
+        .to_con()?;
+    control_sender
+        .send(&ClientControlPacket::StreamReady)
+        .to_con()?;
+
+    let punch_result = if config.hole_punching {
+        builder
+            .punch_hole(
+                server_address,
+                Duration::from_secs_f32(config.hole_punching_timeout_s),
+            )
+            .to_con()?
+    } else {
+        None
+    };
+
+    control_sender
+        .send(&ClientControlPacket::HolePunchResult {
+            success: punch_result.is_some(),
+            rendezvous_rtt: builder.rendezvous_rtt,
+        })
+        .to_con()?;
+
+    let max_packet_size = settings.connection.packet_size as _;
+    match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)? {
+        ServerControlPacket::InternetPath {
+            path: ConnectionPath::HolePunched,
+            ..
+        } => {
+            info!("Hole punching succeeded, streaming directly");
+            let address = punch_result.map_or(server_address, |(_, address)| address);
+
+            builder.connect_direct(address, max_packet_size, HANDSHAKE_ACTION_TIMEOUT)
+        }
+        ServerControlPacket::InternetPath {
+            path: ConnectionPath::Relayed,
+            relay_session,
+        } => {
+            info!("Streaming through the relay");
+
+            builder.connect_relayed(relay_session, max_packet_size, HANDSHAKE_ACTION_TIMEOUT)
+        }
+        _ => con_bail!("Got unexpected packet waiting for the internet path"),
+    }
+}
+
+// Announces the client again and waits for the streamer to move the interrupted stream to new
+// sockets. The decoder and the stream threads are kept running meanwhile.
+fn resume_stream(
+    ctx: &ConnectionContext,
+    lifecycle_state: &RwLock<LifecycleState>,
+    connection_config: &ConnectionConfig,
+    resume_config: &SessionResumeConfig,
+    token: u64,
+    control_receiver: &Mutex<ControlSocketReceiver<ServerControlPacket>>,
+    stream_socket: &Mutex<StreamSocket>,
+) -> ConResult {
+    // Free the stream port for the new connection
+    stream_socket.lock().detach();
+
+    let deadline = Instant::now() + Duration::from_secs_f32(resume_config.grace_period_s);
+
+    let config = Config::load();
+    let announcer_socket =
+        AnnouncerSocket::new(&config.hostname, config.active_rendezvous_server()).to_con()?;
+    let listener_socket = alvr_sockets::get_server_listener(HANDSHAKE_ACTION_TIMEOUT).to_con()?;
+
+    let (mut proto_control_socket, server_ip) = loop {
+        if Instant::now() > deadline {
+            con_bail!("The streamer did not come back in time");
+        }
+        if *lifecycle_state.read() != LifecycleState::Resumed {
+            con_bail!("The device went to sleep");
+        }
+
+        announcer_socket.announce().ok();
+
+        if let Ok(pair) = ProtoControlSocket::connect_to(
+            SOCKET_INIT_RETRY_INTERVAL,
+            PeerType::Server(&listener_socket),
+        ) {
+            break pair;
+        }
+    };
+
+    proto_control_socket
+        .send(&ClientConnectionResult::SessionResume { token })
+        .to_con()?;
+
+    let (mut new_control_sender, mut new_control_receiver) = proto_control_socket
+        .split(STREAMING_RECV_TIMEOUT)
+        .to_con()?;
+
+    if !matches!(
+        new_control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?,
+        ServerControlPacket::StartStream
+    ) {
+        con_bail!("Got unexpected packet waiting for stream start");
+    }
+
+    let stream_socket_builder = StreamSocketBuilder::listen_for_server(
+        Duration::from_secs(1),
+        connection_config.stream_port,
+        connection_config.stream_protocol.clone(),
+        connection_config.dscp.clone(),
+        connection_config.client_send_buffer_bytes.clone(),
+        connection_config.client_recv_buffer_bytes.clone(),
+    )
+    .to_con()?;
+
+    new_control_sender
+        .send(&ClientControlPacket::StreamReady)
+        .to_con()?;
+
+    let new_stream_socket = stream_socket_builder.accept_from_server(
+        server_ip,
+        connection_config.stream_port,
+        connection_config.packet_size as _,
+        HANDSHAKE_ACTION_TIMEOUT,
+    )?;
+
+    stream_socket.lock().reattach(new_stream_socket);
+    *ctx.control_sender.lock() = Some(new_control_sender);
+    *control_receiver.lock() = new_control_receiver;
+
+    Ok(())
+}
+
 fn connection_pipeline(
     capabilities: ClientCapabilities,
     ctx: Arc<ConnectionContext>,
@@ -131,9 +306,16 @@ fn connection_pipeline(
 ) -> ConResult {
     dbg_connection!("connection_pipeline: Begin");
 
-    let (mut proto_control_socket, server_ip) = {
+    let report_connected = || {
+        if ctx.rejection_message.lock().is_none() {
+            set_hud_message(&event_queue, SUCCESS_CONNECT_MESSAGE);
+        }
+    };
+
+    let (mut proto_control_socket, server_ip, relayed) = {
         let config = Config::load();
-        let announcer_socket = AnnouncerSocket::new(&config.hostname).to_con()?;
+        let announcer_socket =
+            AnnouncerSocket::new(&config.hostname, config.active_rendezvous_server()).to_con()?;
         let listener_socket =
             alvr_sockets::get_server_listener(HANDSHAKE_ACTION_TIMEOUT).to_con()?;
 
@@ -144,12 +326,32 @@ fn connection_pipeline(
 
             announcer_socket.announce().ok();
 
-            if let Ok(pair) = ProtoControlSocket::connect_to(
+            if let Ok((socket, server_ip)) = ProtoControlSocket::connect_to(
                 SOCKET_INIT_RETRY_INTERVAL,
                 PeerType::Server(&listener_socket),
             ) {
-                set_hud_message(&event_queue, SUCCESS_CONNECT_MESSAGE);
-                break pair;
+                report_connected();
+                break (socket, server_ip, false);
+            }
+
+            // Wait for the server on the relay too, in case it is on another network. This is
+            // possible only after the streamer shared the pairing token
+            if let (Some(relay_address), Some(pairing_token)) = (
+                config
+                    .active_internet_rendezvous_server()
+                    .and_then(|address| alvr_sockets::resolve_rendezvous_address(&address).ok()),
+                &config.relay_pairing_token,
+            ) {
+                if let Ok((socket, server_ip)) = ProtoControlSocket::connect_to(
+                    RELAY_WAIT_INTERVAL,
+                    PeerType::ServerThroughRelay {
+                        relay_address,
+                        pairing_token,
+                    },
+                ) {
+                    report_connected();
+                    break (socket, server_ip, true);
+                }
             }
         }
     };
@@ -179,6 +381,7 @@ fn connection_pipeline(
                     encoder_high_profile: capabilities.encoder_high_profile,
                     encoder_10_bits: capabilities.encoder_10_bits,
                     encoder_av1: capabilities.encoder_av1,
+                    streaming_overrides: Config::load().streaming_overrides,
                 })
                 .to_con()?,
             ),
@@ -188,9 +391,46 @@ fn connection_pipeline(
         proto_control_socket.recv::<StreamConfigPacket>(HANDSHAKE_ACTION_TIMEOUT)?;
     dbg_connection!("connection_pipeline: stream config received");
 
+    if let Some(rejection) = alvr_packets::decode_connection_rejection(&config_packet) {
+        warn!("Connection rejected by the streamer: {}", rejection.reason);
+
+        let message = format!(
+            "The streamer cannot accept this client.\n{}\nStreamer version: {}\n\
+            Please install client version {}.\n{}",
+            rejection.reason,
+            rejection.server_version,
+            rejection.suggested_client_version,
+            rejection.download_hint,
+        );
+        set_hud_message(&event_queue, &message);
+        *ctx.rejection_message.lock() = Some(message);
+
+        return Ok(());
+    }
+    *ctx.rejection_message.lock() = None;
+
     let (settings, negotiated_config) =
         alvr_packets::decode_stream_config(&config_packet).to_con()?;
 
+    let rendezvous_server = settings
+        .connection
+        .client_discovery
+        .as_option()
+        .and_then(|config| config.rendezvous_server.as_option().cloned());
+    let internet_rendezvous_server = settings
+        .connection
+        .internet_streaming
+        .as_option()
+        .map(|config| config.rendezvous_server.clone());
+    let mut config = Config::load();
+    if config.rendezvous_server != rendezvous_server
+        || config.internet_rendezvous_server != internet_rendezvous_server
+    {
+        config.rendezvous_server = rendezvous_server;
+        config.internet_rendezvous_server = internet_rendezvous_server;
+        config.store();
+    }
+
     let streaming_start_event = ClientCoreEvent::StreamingStarted {
         settings: Box::new(settings.clone()),
         negotiated_config: negotiated_config.clone(),
@@ -199,7 +439,7 @@ fn connection_pipeline(
     *ctx.statistics_manager.lock() = Some(StatisticsManager::new(
         settings.connection.statistics_history_size,
         Duration::from_secs_f32(1.0 / negotiated_config.refresh_rate_hint),
-        if let Switch::Enabled(config) = settings.headset.controllers {
+        if let Switch::Enabled(config) = &settings.headset.controllers {
             config.steamvr_pipeline_frames
         } else {
             0.0
@@ -210,53 +450,97 @@ fn connection_pipeline(
         .split(STREAMING_RECV_TIMEOUT)
         .to_con()?;
 
-    match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT) {
-        Ok(ServerControlPacket::StartStream) => {
-            info!("Stream starting");
-            set_hud_message(&event_queue, STREAM_STARTING_MESSAGE);
-        }
-        Ok(ServerControlPacket::Restarting) => {
-            info!("Server restarting");
-            set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
-            return Ok(());
-        }
-        Err(e) => {
-            info!("Server disconnected. Cause: {e}");
-            set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
-            return Ok(());
-        }
-        _ => {
-            info!("Unexpected packet");
-            set_hud_message(&event_queue, "Unexpected packet");
-            return Ok(());
+    // When relayed, the server sends its public stream address before starting the stream
+    let mut server_public_address = None;
+    let mut resume_token = None;
+    loop {
+        match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT) {
+            Ok(ServerControlPacket::InternetEndpoint(address)) if relayed => {
+                server_public_address = Some(address);
+            }
+            Ok(ServerControlPacket::ResumeToken(token)) => {
+                resume_token = Some(token);
+            }
+            Ok(ServerControlPacket::RelayPairingToken(token)) => {
+                let mut config = Config::load();
+                if config.relay_pairing_token.as_ref() != Some(&token) {
+                    config.relay_pairing_token = Some(token);
+                    config.store();
+                }
+            }
+            Ok(ServerControlPacket::StartStream) => {
+                info!("Stream starting");
+                set_hud_message(&event_queue, STREAM_STARTING_MESSAGE);
+                break;
+            }
+            Ok(ServerControlPacket::DiagnosticsPhase(phase)) => {
+                info!("Running connection diagnostics");
+                set_hud_message(&event_queue, DIAGNOSTICS_MESSAGE);
+
+                diagnostics::run_diagnostics(
+                    phase,
+                    server_ip,
+                    &settings.connection,
+                    &mut control_sender,
+                    &mut control_receiver,
+                )?;
+
+                set_hud_message(&event_queue, DIAGNOSTICS_FINISHED_MESSAGE);
+                return Ok(());
+            }
+            Ok(ServerControlPacket::Restarting) => {
+                info!("Server restarting");
+                set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+                return Ok(());
+            }
+            Err(e) => {
+                info!("Server disconnected. Cause: {e}");
+                set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                return Ok(());
+            }
+            _ => {
+                info!("Unexpected packet");
+                set_hud_message(&event_queue, "Unexpected packet");
+                return Ok(());
+            }
         }
     }
 
-    dbg_connection!("connection_pipeline: create StreamSocket");
-    let stream_socket_builder = StreamSocketBuilder::listen_for_server(
-        Duration::from_secs(1),
-        settings.connection.stream_port,
-        settings.connection.stream_protocol,
-        settings.connection.dscp,
-        settings.connection.client_send_buffer_bytes,
-        settings.connection.client_recv_buffer_bytes,
-    )
-    .to_con()?;
+    let mut stream_socket = if let Some(server_address) = server_public_address {
+        dbg_connection!("connection_pipeline: create StreamSocket through NAT");
+        connect_internet_stream_socket(
+            &settings,
+            server_address,
+            &mut control_sender,
+            &mut control_receiver,
+        )?
+    } else {
+        dbg_connection!("connection_pipeline: create StreamSocket");
+        let stream_socket_builder = StreamSocketBuilder::listen_for_server(
+            Duration::from_secs(1),
+            settings.connection.stream_port,
+            settings.connection.stream_protocol.clone(),
+            settings.connection.dscp.clone(),
+            settings.connection.client_send_buffer_bytes.clone(),
+            settings.connection.client_recv_buffer_bytes.clone(),
+        )
+        .to_con()?;
 
-    dbg_connection!("connection_pipeline: Send StreamReady");
-    if let Err(e) = control_sender.send(&ClientControlPacket::StreamReady) {
-        info!("Server disconnected. Cause: {e:?}");
-        set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
-        return Ok(());
-    }
+        dbg_connection!("connection_pipeline: Send StreamReady");
+        if let Err(e) = control_sender.send(&ClientControlPacket::StreamReady) {
+            info!("Server disconnected. Cause: {e:?}");
+            set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+            return Ok(());
+        }
 
-    dbg_connection!("connection_pipeline: accept connection");
-    let mut stream_socket = stream_socket_builder.accept_from_server(
-        server_ip,
-        settings.connection.stream_port,
-        settings.connection.packet_size as _,
-        HANDSHAKE_ACTION_TIMEOUT,
-    )?;
+        dbg_connection!("connection_pipeline: accept connection");
+        stream_socket_builder.accept_from_server(
+            server_ip,
+            settings.connection.stream_port,
+            settings.connection.packet_size as _,
+            HANDSHAKE_ACTION_TIMEOUT,
+        )?
+    };
 
     info!("Connected to server");
 
@@ -265,7 +549,7 @@ fn connection_pipeline(
     let mut game_audio_receiver = stream_socket.subscribe_to_stream(AUDIO, MAX_UNREAD_PACKETS);
     let tracking_sender = stream_socket.request_stream(TRACKING);
     let mut haptics_receiver =
-        stream_socket.subscribe_to_stream::<Haptics>(HAPTICS, MAX_UNREAD_PACKETS);
+        stream_socket.subscribe_to_stream::<HapticsPacket>(HAPTICS, MAX_UNREAD_PACKETS);
     let statistics_sender = stream_socket.request_stream(STATISTICS);
 
     let video_receive_thread = thread::spawn({
@@ -396,26 +680,45 @@ fn connection_pipeline(
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(ConnectionError::Other(_)) => return,
                 };
-                let Ok(haptics) = data.get_header() else {
+                let Ok(packet) = data.get_header() else {
                     return;
                 };
 
-                event_queue.lock().push_back(ClientCoreEvent::Haptics {
-                    device_id: haptics.device_id,
-                    duration: haptics.duration,
-                    frequency: haptics.frequency,
-                    amplitude: haptics.amplitude,
-                });
+                let event = match packet {
+                    HapticsPacket::Vibration { haptics, envelope } => ClientCoreEvent::Haptics {
+                        device_id: haptics.device_id,
+                        duration: haptics.duration,
+                        frequency: haptics.frequency,
+                        amplitude: haptics.amplitude,
+                        envelope,
+                    },
+                    HapticsPacket::Pcm(haptics) => ClientCoreEvent::HapticsPcm {
+                        device_id: haptics.device_id,
+                        sample_rate: haptics.sample_rate,
+                        samples: haptics.samples,
+                        append: haptics.append,
+                    },
+                };
+
+                event_queue.lock().push_back(event);
             }
         }
     });
 
+    // The sockets are replaced when the stream is resumed
+    let stream_socket = Arc::new(Mutex::new(stream_socket));
+    let control_receiver = Arc::new(Mutex::new(control_receiver));
+    let connection_lost = Arc::new(RelaxedAtomic::new(false));
+    let server_restarting = Arc::new(RelaxedAtomic::new(false));
+
     let (log_channel_sender, log_channel_receiver) = mpsc::channel();
 
     let control_send_thread = thread::spawn({
         let ctx = Arc::clone(&ctx);
+        let lifecycle_state = Arc::clone(&lifecycle_state);
         let event_queue = Arc::clone(&event_queue);
         let disconnect_notif = Arc::clone(&disconnect_notif);
+        let connection_lost = Arc::clone(&connection_lost);
         move || {
             let mut keepalive_deadline = Instant::now();
 
@@ -428,10 +731,13 @@ fn connection_pipeline(
                     &mut *ctx.control_sender.lock(),
                 ) {
                     if let Err(e) = sender.send(&packet) {
-                        info!("Server disconnected. Cause: {e:?}");
-                        set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        if !connection_lost.value() {
+                            info!("Server disconnected. Cause: {e:?}");
+                            set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        }
 
-                        break;
+                        connection_lost.set(true);
+                        disconnect_notif.notify_one();
                     }
                 }
 
@@ -468,10 +774,13 @@ fn connection_pipeline(
         let ctx = Arc::clone(&ctx);
         let event_queue = Arc::clone(&event_queue);
         let disconnect_notif = Arc::clone(&disconnect_notif);
+        let control_receiver = Arc::clone(&control_receiver);
+        let connection_lost = Arc::clone(&connection_lost);
+        let server_restarting = Arc::clone(&server_restarting);
         move || {
             let mut disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
             while is_streaming(&ctx) {
-                let maybe_packet = control_receiver.recv(STREAMING_RECV_TIMEOUT);
+                let maybe_packet = control_receiver.lock().recv(STREAMING_RECV_TIMEOUT);
 
                 match maybe_packet {
                     Ok(ServerControlPacket::DecoderConfig(config)) => {
@@ -512,22 +821,31 @@ fn connection_pipeline(
                     Ok(ServerControlPacket::Restarting) => {
                         info!("{SERVER_RESTART_MESSAGE}");
                         set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+                        server_restarting.set(true);
                         disconnect_notif.notify_one();
                     }
                     Ok(_) => (),
                     Err(ConnectionError::TryAgain(_)) => {
                         if Instant::now() > disconnection_deadline {
-                            info!("{CONNECTION_TIMEOUT_MESSAGE}");
-                            set_hud_message(&event_queue, CONNECTION_TIMEOUT_MESSAGE);
+                            if !connection_lost.value() {
+                                info!("{CONNECTION_TIMEOUT_MESSAGE}");
+                                set_hud_message(&event_queue, CONNECTION_TIMEOUT_MESSAGE);
+                            }
+                            connection_lost.set(true);
                             disconnect_notif.notify_one();
                         } else {
                             continue;
                         }
                     }
                     Err(e) => {
-                        info!("{SERVER_DISCONNECTED_MESSAGE} Cause: {e}");
-                        set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        if !connection_lost.value() {
+                            info!("{SERVER_DISCONNECTED_MESSAGE} Cause: {e}");
+                            set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        }
+                        connection_lost.set(true);
                         disconnect_notif.notify_one();
+
+                        thread::sleep(STREAMING_RECV_TIMEOUT);
                     }
                 }
 
@@ -540,15 +858,23 @@ fn connection_pipeline(
         let ctx = Arc::clone(&ctx);
         let event_queue = Arc::clone(&event_queue);
         let disconnect_notif = Arc::clone(&disconnect_notif);
+        let stream_socket = Arc::clone(&stream_socket);
+        let connection_lost = Arc::clone(&connection_lost);
         move || {
             while is_streaming(&ctx) {
-                match stream_socket.recv() {
+                let res = stream_socket.lock().recv();
+                match res {
                     Ok(()) => (),
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(e) => {
-                        info!("Client disconnected. Cause: {e}");
-                        set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        if !connection_lost.value() {
+                            info!("Client disconnected. Cause: {e}");
+                            set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
+                        }
+                        connection_lost.set(true);
                         disconnect_notif.notify_one();
+
+                        thread::sleep(STREAMING_RECV_TIMEOUT);
                     }
                 }
             }
@@ -575,8 +901,52 @@ fn connection_pipeline(
     // todo: send this data as part of the connection handshake
     ctx.view_params_queue.write().clear();
 
-    // Unlock CONNECTION_STATE and block thread
-    wait_rwlock(&disconnect_notif, &mut connection_state_lock);
+    loop {
+        // Unlock CONNECTION_STATE and block thread
+        wait_rwlock(&disconnect_notif, &mut connection_state_lock);
+
+        let Some((resume_config, token)) = settings
+            .connection
+            .session_resume
+            .as_option()
+            .zip(resume_token)
+            .filter(|_| {
+                connection_lost.value()
+                    && !server_restarting.value()
+                    && *lifecycle_state.read() == LifecycleState::Resumed
+            })
+        else {
+            break;
+        };
+
+        info!("Trying to resume the stream");
+        let res = RwLockWriteGuard::unlocked(&mut connection_state_lock, || {
+            resume_stream(
+                &ctx,
+                &lifecycle_state,
+                &settings.connection,
+                resume_config,
+                token,
+                &control_receiver,
+                &stream_socket,
+            )
+        });
+        match res {
+            Ok(()) => {
+                info!("Stream resumed");
+                connection_lost.set(false);
+
+                // The control send thread may have stopped while unlocked
+                if *lifecycle_state.read() != LifecycleState::Resumed {
+                    break;
+                }
+            }
+            Err(e) => {
+                info!("Could not resume the stream: {e}");
+                break;
+            }
+        }
+    }
 
     *connection_state_lock = ConnectionState::Disconnecting;
 
@@ -0,0 +1,172 @@
+use alvr_common::{con_bail, AnyhowToCon, ConResult, ConnectionError, RelaxedAtomic};
+use alvr_packets::{
+    ClientControlPacket, ClientDiagnosticsResult, DiagnosticsPhase, ServerControlPacket,
+    DIAGNOSTICS_DATA, DIAGNOSTICS_PING,
+};
+use alvr_session::{ConnectionConfig, SocketProtocol};
+use alvr_sockets::{
+    ControlSocketReceiver, ControlSocketSender, ProbeReceiver, StreamSocketBuilder,
+};
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
+const PHASE_END_TIMEOUT: Duration = Duration::from_secs(10);
+const PROBES_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_UNREAD_PACKETS: usize = 64;
+
+fn wait_for_phase_end(
+    control_receiver: &mut ControlSocketReceiver<ServerControlPacket>,
+) -> ConResult {
+    let deadline = Instant::now() + PHASE_END_TIMEOUT;
+    loop {
+        match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT) {
+            Ok(ServerControlPacket::DiagnosticsPhaseEnd) => return Ok(()),
+            Ok(_) => con_bail!("Got unexpected packet waiting for diagnostics phase end"),
+            Err(ConnectionError::TryAgain(_)) if Instant::now() < deadline => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Echoes the pings and counts the received data until the server ends the phase
+fn run_stream_phase(
+    protocol: SocketProtocol,
+    server_ip: IpAddr,
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ClientControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ServerControlPacket>,
+) -> ConResult {
+    let stream_socket_builder = StreamSocketBuilder::listen_for_server(
+        Duration::from_secs(1),
+        config.stream_port,
+        protocol,
+        config.dscp.clone(),
+        config.client_send_buffer_bytes.clone(),
+        config.client_recv_buffer_bytes.clone(),
+    )
+    .to_con()?;
+
+    control_sender
+        .send(&ClientControlPacket::DiagnosticsReady)
+        .to_con()?;
+
+    let mut stream_socket = stream_socket_builder.accept_from_server(
+        server_ip,
+        config.stream_port,
+        config.packet_size as _,
+        HANDSHAKE_ACTION_TIMEOUT,
+    )?;
+
+    let mut pong_sender = stream_socket.request_stream::<u32>(DIAGNOSTICS_PING);
+    let mut ping_receiver =
+        stream_socket.subscribe_to_stream::<u32>(DIAGNOSTICS_PING, MAX_UNREAD_PACKETS);
+    let mut data_receiver =
+        stream_socket.subscribe_to_stream::<u32>(DIAGNOSTICS_DATA, MAX_UNREAD_PACKETS);
+
+    let running = Arc::new(RelaxedAtomic::new(true));
+    let receive_thread = thread::spawn({
+        let running = Arc::clone(&running);
+        move || {
+            let mut result = ClientDiagnosticsResult::default();
+            let mut first_receive_instant = None;
+
+            while running.value() {
+                if let Err(ConnectionError::Other(_)) = stream_socket.recv() {
+                    break;
+                }
+
+                while let Ok(data) = ping_receiver.recv(Duration::ZERO) {
+                    if let Ok(index) = data.get_header() {
+                        pong_sender.send_header(&index).ok();
+                    }
+                }
+
+                while let Ok(data) = data_receiver.recv(Duration::ZERO) {
+                    if let Ok((_, payload)) = data.get() {
+                        let now = Instant::now();
+                        let first_receive_instant = *first_receive_instant.get_or_insert(now);
+
+                        result.received_packets += 1;
+                        result.received_bytes += payload.len() as u64;
+                        result.receive_duration = now - first_receive_instant;
+                    }
+                }
+            }
+
+            result
+        }
+    });
+
+    let res = wait_for_phase_end(control_receiver);
+
+    running.set(false);
+    let result = receive_thread.join().unwrap_or_default();
+
+    res?;
+
+    control_sender
+        .send(&ClientControlPacket::DiagnosticsResult(result))
+        .to_con()
+}
+
+fn run_packet_sizes_phase(
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ClientControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ServerControlPacket>,
+) -> ConResult {
+    let probe_receiver = ProbeReceiver::bind(config.stream_port).to_con()?;
+
+    control_sender
+        .send(&ClientControlPacket::DiagnosticsReady)
+        .to_con()?;
+
+    let (received_probes, probe_tos) = probe_receiver.recv_probes(PROBES_TIMEOUT).to_con()?;
+
+    wait_for_phase_end(control_receiver)?;
+
+    control_sender
+        .send(&ClientControlPacket::DiagnosticsResult(
+            ClientDiagnosticsResult {
+                received_probes: received_probes.into_iter().collect(),
+                probe_tos,
+                ..Default::default()
+            },
+        ))
+        .to_con()
+}
+
+// Follows the phases requested by the server, starting from the first one already received
+pub fn run_diagnostics(
+    first_phase: DiagnosticsPhase,
+    server_ip: IpAddr,
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ClientControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ServerControlPacket>,
+) -> ConResult {
+    let mut phase = first_phase;
+    loop {
+        match phase {
+            DiagnosticsPhase::Stream(protocol) => run_stream_phase(
+                protocol,
+                server_ip,
+                config,
+                control_sender,
+                control_receiver,
+            )?,
+            DiagnosticsPhase::PacketSizes => {
+                run_packet_sizes_phase(config, control_sender, control_receiver)?
+            }
+            DiagnosticsPhase::Finished => return Ok(()),
+        }
+
+        phase = match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)? {
+            ServerControlPacket::DiagnosticsPhase(phase) => phase,
+            _ => con_bail!("Got unexpected packet waiting for diagnostics phase"),
+        };
+    }
+}
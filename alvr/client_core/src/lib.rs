@@ -8,6 +8,7 @@
 mod c_api;
 mod connection;
 mod decoder;
+mod diagnostics;
 mod logging_backend;
 mod platform;
 mod sockets;
@@ -26,10 +27,10 @@ use alvr_common::{
     warn, ConnectionState, DeviceMotion, LifecycleState, Pose, HEAD_ID,
 };
 use alvr_packets::{
-    BatteryInfo, ButtonEntry, ClientControlPacket, FaceData, NegotiatedStreamingConfig,
-    ReservedClientControlPacket, Tracking, ViewParams, ViewsConfig,
+    BatteryInfo, ButtonEntry, ClientControlPacket, FaceData, HapticsEnvelope,
+    NegotiatedStreamingConfig, ReservedClientControlPacket, Tracking, ViewParams, ViewsConfig,
 };
-use alvr_session::{CodecType, Settings};
+use alvr_session::{ClientStreamingOverrides, CodecType, Settings};
 use connection::ConnectionContext;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -52,6 +53,29 @@ pub fn platform() -> Platform {
     platform::platform()
 }
 
+pub fn streaming_overrides() -> ClientStreamingOverrides {
+    Config::load().streaming_overrides
+}
+
+// Stored on the device, takes effect on the next connection
+pub fn set_streaming_overrides(overrides: ClientStreamingOverrides) {
+    let mut config = Config::load();
+    config.streaming_overrides = overrides;
+    config.store();
+}
+
+// Stored on the device, used to discover the streamer before the first connection. None restores
+// the addresses learned from the streamer settings. Takes effect on the next connection attempt
+pub fn set_rendezvous_servers(
+    rendezvous_server: Option<String>,
+    internet_rendezvous_server: Option<String>,
+) {
+    let mut config = Config::load();
+    config.manual_rendezvous_server = rendezvous_server;
+    config.manual_internet_rendezvous_server = internet_rendezvous_server;
+    config.store();
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum ClientCoreEvent {
     UpdateHudMessage(String),
@@ -65,6 +89,13 @@ pub enum ClientCoreEvent {
         duration: Duration,
         frequency: f32,
         amplitude: f32,
+        envelope: HapticsEnvelope,
+    },
+    HapticsPcm {
+        device_id: u64,
+        sample_rate: f32,
+        samples: Vec<f32>,
+        append: bool,
     },
     // Note: All subsequent DecoderConfig events should be ignored until reconnection
     DecoderConfig {
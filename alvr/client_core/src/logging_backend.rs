@@ -52,12 +52,12 @@ pub fn init_logging() {
             return false;
         }
 
-        let message = format!("{}", record.args());
-
-        if !alvr_common::filter_debug_groups(&message, &data.debug_groups_config) {
+        if !alvr_common::filter_debug_groups(record.target(), &data.debug_groups_config) {
             return false;
         }
 
+        let message = format!("{}", record.args());
+
         let mut last_log_event_lock = LAST_LOG_EVENT.lock();
 
         if last_log_event_lock.message == message
@@ -73,6 +73,7 @@ pub fn init_logging() {
                             "Last log line repeated {} times",
                             last_log_event_lock.repetition_times
                         ),
+                        debug_group: None,
                     })
                     .ok();
             }
@@ -84,7 +85,12 @@ pub fn init_logging() {
             };
 
             data.sender
-                .send(ClientControlPacket::Log { level, message })
+                .send(ClientControlPacket::Log {
+                    level,
+                    message,
+                    debug_group: alvr_common::debug_group_from_target(record.target())
+                        .map(String::from),
+                })
                 .ok();
         }
 
@@ -1,19 +1,28 @@
 use crate::platform;
 use alvr_common::anyhow::{bail, Result};
+use alvr_sockets::DiscoveryPacket;
 use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::{Ipv4Addr, UdpSocket};
 
 pub struct AnnouncerSocket {
     hostname: String,
     daemon: ServiceDaemon,
+    discovery_socket: UdpSocket,
+    rendezvous_server: Option<String>,
 }
 
 impl AnnouncerSocket {
-    pub fn new(hostname: &str) -> Result<Self> {
+    pub fn new(hostname: &str, rendezvous_server: Option<String>) -> Result<Self> {
         let daemon = ServiceDaemon::new()?;
 
+        let discovery_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        discovery_socket.set_broadcast(true)?;
+
         Ok(Self {
             daemon,
             hostname: hostname.to_owned(),
+            discovery_socket,
+            rendezvous_server,
         })
     }
 
@@ -23,6 +32,27 @@ impl AnnouncerSocket {
             bail!("IP is unspecified");
         }
 
+        let announcement = DiscoveryPacket::ClientAnnouncement {
+            hostname: self.hostname.clone(),
+            protocol_id: alvr_common::protocol_id(),
+        };
+
+        // Broadcast and rendezvous are best effort, mDNS errors are still reported
+        alvr_sockets::send_discovery_packet(
+            &self.discovery_socket,
+            &announcement,
+            (Ipv4Addr::BROADCAST, alvr_sockets::DISCOVERY_BROADCAST_PORT),
+        )
+        .ok();
+        if let Some(address) = self
+            .rendezvous_server
+            .as_deref()
+            .and_then(|address| alvr_sockets::resolve_rendezvous_address(address).ok())
+        {
+            alvr_sockets::send_discovery_packet(&self.discovery_socket, &announcement, address)
+                .ok();
+        }
+
         self.daemon.register(ServiceInfo::new(
             alvr_sockets::MDNS_SERVICE_TYPE,
             &format!("alvr{}", rand::random::<u16>()),
@@ -1,4 +1,5 @@
 use alvr_common::{error, info};
+use alvr_session::ClientStreamingOverrides;
 use app_dirs2::{AppDataType, AppInfo};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,24 @@ fn config_path() -> PathBuf {
 pub struct Config {
     pub hostname: String,
     pub protocol_id: String,
+    // Learned from the server settings, used for discovery across subnets
+    #[serde(default)]
+    pub rendezvous_server: Option<String>,
+    // Learned from the server settings, used to reach the server from other networks
+    #[serde(default)]
+    pub internet_rendezvous_server: Option<String>,
+    // Chosen on this device, needed before the first connection. They take precedence over the
+    // learned addresses
+    #[serde(default)]
+    pub manual_rendezvous_server: Option<String>,
+    #[serde(default)]
+    pub manual_internet_rendezvous_server: Option<String>,
+    // Received from the streamer after being trusted, identifies this device on the relay
+    #[serde(default)]
+    pub relay_pairing_token: Option<String>,
+    // Chosen on this device, sent to the server on each connection
+    #[serde(default)]
+    pub streaming_overrides: ClientStreamingOverrides,
 }
 
 impl Default for Config {
@@ -35,6 +54,12 @@ impl Default for Config {
                 rng.gen_range(0..10),
             ),
             protocol_id: alvr_common::protocol_id(),
+            rendezvous_server: None,
+            internet_rendezvous_server: None,
+            manual_rendezvous_server: None,
+            manual_internet_rendezvous_server: None,
+            relay_pairing_token: None,
+            streaming_overrides: ClientStreamingOverrides::default(),
         }
     }
 }
@@ -59,6 +84,18 @@ impl Config {
         config
     }
 
+    pub fn active_rendezvous_server(&self) -> Option<String> {
+        self.manual_rendezvous_server
+            .clone()
+            .or_else(|| self.rendezvous_server.clone())
+    }
+
+    pub fn active_internet_rendezvous_server(&self) -> Option<String> {
+        self.manual_internet_rendezvous_server
+            .clone()
+            .or_else(|| self.internet_rendezvous_server.clone())
+    }
+
     pub fn store(&self) {
         let config_string = serde_json::to_string(self).unwrap();
         if let Err(e) = fs::write(config_path(), config_string) {
@@ -289,7 +289,7 @@ fn client_thread(
                         thread.join().ok();
                     }
                 }
-                ClientCoreEvent::Haptics { .. } => (),
+                ClientCoreEvent::Haptics { .. } | ClientCoreEvent::HapticsPcm { .. } => (),
                 ClientCoreEvent::DecoderConfig { codec, .. } => {
                     window_output.decoder_codec = Some(codec)
                 }
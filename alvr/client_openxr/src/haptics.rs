@@ -0,0 +1,302 @@
+use crate::{interaction::InteractionContext, XrContext};
+use alvr_common::{warn, HAND_LEFT_ID};
+use alvr_packets::{Haptics, HapticsEnvelope};
+use openxr as xr;
+use std::{f32::consts::PI, ptr, time::Duration};
+
+// Typical resonance frequency of the linear actuators of VR controllers
+const DEFAULT_CARRIER_FREQUENCY: f32 = 160.0;
+// Used if the runtime cannot report the sample rate of the actuator
+const DEFAULT_DEVICE_SAMPLE_RATE: f32 = 2000.0;
+
+fn hand_index(device_id: u64) -> usize {
+    if device_id == *HAND_LEFT_ID {
+        0
+    } else {
+        1
+    }
+}
+
+fn envelope_gain(time_s: f32, duration_s: f32, envelope: HapticsEnvelope) -> f32 {
+    let attack_s = envelope.attack.as_secs_f32();
+    let release_s = envelope.release.as_secs_f32();
+
+    let attack_gain = if attack_s > 0.0 {
+        time_s / attack_s
+    } else {
+        1.0
+    };
+    let release_gain = if release_s > 0.0 {
+        (duration_s - time_s) / release_s
+    } else {
+        1.0
+    };
+
+    attack_gain.min(release_gain).clamp(0.0, 1.0)
+}
+
+#[derive(Default)]
+struct PcmChannel {
+    device_sample_rate: Option<f32>,
+    // Waveform samples not accepted by the runtime yet
+    pending_samples: Vec<f32>,
+    // Carrier phase at the end of the last generated sample, to join appended buffers smoothly
+    carrier_phase: f32,
+}
+
+impl PcmChannel {
+    // Amplitudes are sampled at the device sample rate and used to modulate a sine carrier, which
+    // is the waveform the actuator expects
+    fn push_amplitudes(
+        &mut self,
+        amplitudes: impl Iterator<Item = f32>,
+        carrier_frequency: f32,
+        append: bool,
+    ) {
+        if !append {
+            self.pending_samples.clear();
+            self.carrier_phase = 0.0;
+        }
+
+        let phase_step = 2.0 * PI * carrier_frequency / self.device_sample_rate.unwrap();
+        for amplitude in amplitudes {
+            self.pending_samples
+                .push(amplitude.clamp(0.0, 1.0) * f32::sin(self.carrier_phase));
+            self.carrier_phase = (self.carrier_phase + phase_step) % (2.0 * PI);
+        }
+    }
+}
+
+// Envelopes and PCM buffers need XR_FB_haptic_pcm. Without it, vibrations are played with a
+// constant amplitude and PCM buffers are approximated with a vibration at their mean amplitude.
+pub struct HapticsContext {
+    pcm_supported: bool,
+    channels: [PcmChannel; 2],
+}
+
+impl HapticsContext {
+    pub fn new(xr_ctx: &XrContext) -> Self {
+        Self {
+            pcm_supported: xr_ctx.instance.exts().fb_haptic_pcm.is_some(),
+            channels: Default::default(),
+        }
+    }
+
+    fn action_info(action: &xr::Action<xr::Haptic>) -> xr::sys::HapticActionInfo {
+        xr::sys::HapticActionInfo {
+            ty: xr::sys::HapticActionInfo::TYPE,
+            next: ptr::null(),
+            action: action.as_raw(),
+            subaction_path: xr::Path::NULL,
+        }
+    }
+
+    fn device_sample_rate(xr_ctx: &XrContext, action: &xr::Action<xr::Haptic>) -> f32 {
+        let Some(ext) = xr_ctx.instance.exts().fb_haptic_pcm.as_ref() else {
+            return DEFAULT_DEVICE_SAMPLE_RATE;
+        };
+
+        let mut state = xr::sys::DevicePcmSampleRateStateFB {
+            ty: xr::sys::DevicePcmSampleRateStateFB::TYPE,
+            next: ptr::null_mut(),
+            sample_rate: 0.0,
+        };
+        let result = unsafe {
+            (ext.get_device_sample_rate)(
+                xr_ctx.session.as_raw(),
+                &Self::action_info(action),
+                &mut state,
+            )
+        };
+
+        if result.into_raw() >= 0 && state.sample_rate > 0.0 {
+            state.sample_rate
+        } else {
+            DEFAULT_DEVICE_SAMPLE_RATE
+        }
+    }
+
+    fn channel(
+        &mut self,
+        xr_ctx: &XrContext,
+        interaction_ctx: &InteractionContext,
+        hand: usize,
+    ) -> &mut PcmChannel {
+        let channel = &mut self.channels[hand];
+        if channel.device_sample_rate.is_none() {
+            channel.device_sample_rate = Some(Self::device_sample_rate(
+                xr_ctx,
+                &interaction_ctx.hands_interaction[hand].vibration_action,
+            ));
+        }
+
+        channel
+    }
+
+    fn submit_pcm(
+        &mut self,
+        xr_ctx: &XrContext,
+        interaction_ctx: &InteractionContext,
+        hand: usize,
+        append: bool,
+    ) {
+        let channel = &mut self.channels[hand];
+        if channel.pending_samples.is_empty() {
+            return;
+        }
+
+        let mut samples_consumed = 0_u32;
+        let vibration = xr::sys::HapticPcmVibrationFB {
+            ty: xr::sys::HapticPcmVibrationFB::TYPE,
+            next: ptr::null(),
+            buffer_size: channel.pending_samples.len() as u32,
+            buffer: channel.pending_samples.as_ptr(),
+            sample_rate: channel
+                .device_sample_rate
+                .unwrap_or(DEFAULT_DEVICE_SAMPLE_RATE),
+            append: append.into(),
+            samples_consumed: &mut samples_consumed,
+        };
+        let result = unsafe {
+            (xr_ctx.instance.fp().apply_haptic_feedback)(
+                xr_ctx.session.as_raw(),
+                &Self::action_info(&interaction_ctx.hands_interaction[hand].vibration_action),
+                &vibration as *const _ as *const xr::sys::HapticBaseHeader,
+            )
+        };
+
+        if result.into_raw() >= 0 {
+            let consumed = usize::min(samples_consumed as usize, channel.pending_samples.len());
+            channel.pending_samples.drain(..consumed);
+        } else {
+            warn!("Failed to play PCM haptics: {result:?}");
+            channel.pending_samples.clear();
+        }
+    }
+
+    fn apply_vibration(
+        xr_ctx: &XrContext,
+        action: &xr::Action<xr::Haptic>,
+        duration: Duration,
+        frequency: f32,
+        amplitude: f32,
+    ) {
+        action
+            .apply_feedback(
+                &xr_ctx.session,
+                xr::Path::NULL,
+                &xr::HapticVibration::new()
+                    .amplitude(amplitude.clamp(0.0, 1.0))
+                    .frequency(frequency.max(0.0))
+                    .duration(xr::Duration::from_nanos(duration.as_nanos() as _)),
+            )
+            .unwrap();
+    }
+
+    pub fn play_vibration(
+        &mut self,
+        xr_ctx: &XrContext,
+        interaction_ctx: &InteractionContext,
+        haptics: Haptics,
+        envelope: HapticsEnvelope,
+    ) {
+        let Haptics {
+            device_id,
+            duration,
+            frequency,
+            amplitude,
+        } = haptics;
+        let hand = hand_index(device_id);
+        let has_envelope = !envelope.attack.is_zero() || !envelope.release.is_zero();
+
+        if self.pcm_supported && has_envelope {
+            let channel = self.channel(xr_ctx, interaction_ctx, hand);
+
+            let sample_rate = channel.device_sample_rate.unwrap();
+            let duration_s = duration.as_secs_f32();
+            let samples_count = (duration_s * sample_rate) as usize;
+            let carrier_frequency = if frequency > 0.0 {
+                frequency
+            } else {
+                DEFAULT_CARRIER_FREQUENCY
+            };
+
+            channel.push_amplitudes(
+                (0..samples_count).map(|idx| {
+                    amplitude * envelope_gain(idx as f32 / sample_rate, duration_s, envelope)
+                }),
+                carrier_frequency,
+                false,
+            );
+
+            self.submit_pcm(xr_ctx, interaction_ctx, hand, false);
+        } else {
+            // A new vibration interrupts any PCM buffer
+            self.channels[hand].pending_samples.clear();
+
+            Self::apply_vibration(
+                xr_ctx,
+                &interaction_ctx.hands_interaction[hand].vibration_action,
+                duration,
+                frequency,
+                amplitude,
+            );
+        }
+    }
+
+    // samples are amplitudes in the range [0, 1]
+    pub fn play_pcm(
+        &mut self,
+        xr_ctx: &XrContext,
+        interaction_ctx: &InteractionContext,
+        device_id: u64,
+        sample_rate: f32,
+        samples: &[f32],
+        append: bool,
+    ) {
+        let hand = hand_index(device_id);
+        let sample_rate = sample_rate.max(1.0);
+
+        if self.pcm_supported {
+            let channel = self.channel(xr_ctx, interaction_ctx, hand);
+
+            // Samples still pending are submitted again in append mode, so they must be kept
+            let was_playing = append && !channel.pending_samples.is_empty();
+
+            let device_sample_rate = channel.device_sample_rate.unwrap();
+            let samples_count =
+                (samples.len() as f32 * device_sample_rate / sample_rate).round() as usize;
+            channel.push_amplitudes(
+                (0..samples_count).map(|idx| {
+                    let source_idx = (idx as f32 * sample_rate / device_sample_rate) as usize;
+                    samples[usize::min(source_idx, samples.len() - 1)]
+                }),
+                DEFAULT_CARRIER_FREQUENCY,
+                append,
+            );
+
+            if !was_playing {
+                self.submit_pcm(xr_ctx, interaction_ctx, hand, append);
+            }
+        } else if !samples.is_empty() {
+            let amplitude = samples.iter().sum::<f32>() / samples.len() as f32;
+
+            Self::apply_vibration(
+                xr_ctx,
+                &interaction_ctx.hands_interaction[hand].vibration_action,
+                Duration::from_secs_f32(samples.len() as f32 / sample_rate),
+                0.0, // unspecified
+                amplitude,
+            );
+        }
+    }
+
+    // The runtime accepts a limited number of samples at a time, the rest is queued every frame
+    pub fn update(&mut self, xr_ctx: &XrContext, interaction_ctx: &InteractionContext) {
+        if self.pcm_supported {
+            for hand in 0..2 {
+                self.submit_pcm(xr_ctx, interaction_ctx, hand, true);
+            }
+        }
+    }
+}
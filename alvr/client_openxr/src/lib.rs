@@ -1,5 +1,6 @@
 mod c_api;
 mod graphics;
+mod haptics;
 mod interaction;
 mod lobby;
 mod stream;
@@ -11,8 +12,10 @@ use alvr_client_core::{
 use alvr_common::{
     error,
     glam::{Quat, UVec2, Vec3},
-    info, Fov, Pose, HAND_LEFT_ID,
+    info, Fov, Pose,
 };
+use alvr_packets::Haptics;
+use haptics::HapticsContext;
 use lobby::Lobby;
 use openxr as xr;
 use std::{
@@ -153,6 +156,7 @@ pub fn entry_point() {
     exts.meta_body_tracking_full_body = available_extensions.meta_body_tracking_full_body;
     exts.fb_foveation = available_extensions.fb_foveation;
     exts.fb_foveation_configuration = available_extensions.fb_foveation_configuration;
+    exts.fb_haptic_pcm = available_extensions.fb_haptic_pcm;
     exts.fb_swapchain_update_state = available_extensions.fb_swapchain_update_state;
     exts.htc_facial_tracking = available_extensions.htc_facial_tracking;
     exts.htc_vive_focus3_controller_interaction =
@@ -259,6 +263,7 @@ pub fn entry_point() {
             default_view_resolution,
             &last_lobby_message,
         );
+        let mut haptics_context = HapticsContext::new(&xr_context);
         let mut session_running = false;
         let mut stream_context = None::<StreamContext>;
 
@@ -373,30 +378,39 @@ pub fn entry_point() {
                         duration,
                         frequency,
                         amplitude,
-                    } => {
-                        let action = if device_id == *HAND_LEFT_ID {
-                            &interaction_context.hands_interaction[0].vibration_action
-                        } else {
-                            &interaction_context.hands_interaction[1].vibration_action
-                        };
-
-                        action
-                            .apply_feedback(
-                                &xr_session,
-                                xr::Path::NULL,
-                                &xr::HapticVibration::new()
-                                    .amplitude(amplitude.clamp(0.0, 1.0))
-                                    .frequency(frequency.max(0.0))
-                                    .duration(xr::Duration::from_nanos(duration.as_nanos() as _)),
-                            )
-                            .unwrap();
-                    }
+                        envelope,
+                    } => haptics_context.play_vibration(
+                        &xr_context,
+                        &interaction_context,
+                        Haptics {
+                            device_id,
+                            duration,
+                            frequency,
+                            amplitude,
+                        },
+                        envelope,
+                    ),
+                    ClientCoreEvent::HapticsPcm {
+                        device_id,
+                        sample_rate,
+                        samples,
+                        append,
+                    } => haptics_context.play_pcm(
+                        &xr_context,
+                        &interaction_context,
+                        device_id,
+                        sample_rate,
+                        &samples,
+                        append,
+                    ),
                     ClientCoreEvent::DecoderConfig { .. } | ClientCoreEvent::FrameReady { .. } => {
                         panic!()
                     }
                 }
             }
 
+            haptics_context.update(&xr_context, &interaction_context);
+
             let frame_state = match xr_frame_waiter.wait() {
                 Ok(state) => state,
                 Err(e) => {
@@ -2,7 +2,11 @@ use anyhow::Result;
 use backtrace::Backtrace;
 use serde::{Deserialize, Serialize};
 use settings_schema::SettingsSchema;
-use std::{error::Error, fmt::Display};
+use std::{
+    error::Error,
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub const SERVER_IMPL_DBG_LABEL: &str = "SERVER IMPL";
 pub const CLIENT_IMPL_DBG_LABEL: &str = "CLIENT IMPL";
@@ -15,11 +19,28 @@ pub const CLIENT_GFX_DBG_LABEL: &str = "CLIENT GFX";
 pub const ENCODER_DBG_LABEL: &str = "ENCODER";
 pub const DECODER_DBG_LABEL: &str = "DECODER";
 
+const DBG_LABELS: [&str; 10] = [
+    SERVER_IMPL_DBG_LABEL,
+    CLIENT_IMPL_DBG_LABEL,
+    SERVER_CORE_DBG_LABEL,
+    CLIENT_CORE_DBG_LABEL,
+    CONNECTION_DBG_LABEL,
+    SOCKETS_DBG_LABEL,
+    SERVER_GFX_DBG_LABEL,
+    CLIENT_GFX_DBG_LABEL,
+    ENCODER_DBG_LABEL,
+    DECODER_DBG_LABEL,
+];
+
+// Log targets used to tell the source of a log line, for lines not logged by the server core
+pub const DRIVER_LOG_TARGET: &str = "alvr_driver";
+pub const CLIENT_LOG_TARGET_PREFIX: &str = "alvr_client:";
+
 #[macro_export]
 macro_rules! _dbg_label {
     ($label:expr, $($args:tt)*) => {{
         #[cfg(debug_assertions)]
-        $crate::log::debug!("[{}] {}", $label, format_args!($($args)*));
+        $crate::log::debug!(target: $label, $($args)*);
     }};
 }
 
@@ -117,29 +138,38 @@ pub struct DebugGroupsConfig {
     pub decoder: bool,
 }
 
-pub fn filter_debug_groups(message: &str, config: &DebugGroupsConfig) -> bool {
-    if message.starts_with(&format!("[{SERVER_IMPL_DBG_LABEL}]")) {
-        config.server_impl
-    } else if message.starts_with(&format!("[{CLIENT_IMPL_DBG_LABEL}]")) {
-        config.client_impl
-    } else if message.starts_with(&format!("[{SERVER_CORE_DBG_LABEL}]")) {
-        config.server_core
-    } else if message.starts_with(&format!("[{CLIENT_CORE_DBG_LABEL}]")) {
-        config.client_core
-    } else if message.starts_with(&format!("[{CONNECTION_DBG_LABEL}]")) {
-        config.connection
-    } else if message.starts_with(&format!("[{SOCKETS_DBG_LABEL}]")) {
-        config.sockets
-    } else if message.starts_with(&format!("[{SERVER_GFX_DBG_LABEL}]")) {
-        config.server_gfx
-    } else if message.starts_with(&format!("[{CLIENT_GFX_DBG_LABEL}]")) {
-        config.client_gfx
-    } else if message.starts_with(&format!("[{ENCODER_DBG_LABEL}]")) {
-        config.encoder
-    } else if message.starts_with(&format!("[{DECODER_DBG_LABEL}]")) {
-        config.decoder
-    } else {
-        true
+// Debug groups are log targets. Log lines outside of debug groups are never filtered
+pub fn filter_debug_groups(target: &str, config: &DebugGroupsConfig) -> bool {
+    match target {
+        SERVER_IMPL_DBG_LABEL => config.server_impl,
+        CLIENT_IMPL_DBG_LABEL => config.client_impl,
+        SERVER_CORE_DBG_LABEL => config.server_core,
+        CLIENT_CORE_DBG_LABEL => config.client_core,
+        CONNECTION_DBG_LABEL => config.connection,
+        SOCKETS_DBG_LABEL => config.sockets,
+        SERVER_GFX_DBG_LABEL => config.server_gfx,
+        CLIENT_GFX_DBG_LABEL => config.client_gfx,
+        ENCODER_DBG_LABEL => config.encoder,
+        DECODER_DBG_LABEL => config.decoder,
+        _ => true,
+    }
+}
+
+pub fn debug_group_from_target(target: &str) -> Option<&'static str> {
+    let group = match target.strip_prefix(CLIENT_LOG_TARGET_PREFIX) {
+        Some(client_target) => client_target.split_once('/')?.1,
+        None => target,
+    };
+
+    DBG_LABELS.into_iter().find(|label| *label == group)
+}
+
+// Target for the log lines forwarded by a client. The debug group follows the hostname, after a
+// character that is not valid in hostnames
+pub fn client_log_target(hostname: &str, debug_group: Option<&str>) -> String {
+    match debug_group {
+        Some(group) => format!("{CLIENT_LOG_TARGET_PREFIX}{hostname}/{group}"),
+        None => format!("{CLIENT_LOG_TARGET_PREFIX}{hostname}"),
     }
 }
 
@@ -173,10 +203,72 @@ impl LogSeverity {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum LogSource {
+    #[default]
+    ServerCore,
+    Driver,
+    Client(String), // hostname
+    Dashboard,
+}
+
+impl LogSource {
+    pub fn from_log_target(target: &str) -> Self {
+        if target == DRIVER_LOG_TARGET {
+            LogSource::Driver
+        } else if let Some(client_target) = target.strip_prefix(CLIENT_LOG_TARGET_PREFIX) {
+            let hostname = client_target
+                .split_once('/')
+                .map_or(client_target, |(hostname, _)| hostname);
+
+            LogSource::Client(hostname.into())
+        } else {
+            LogSource::ServerCore
+        }
+    }
+}
+
+impl Display for LogSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogSource::ServerCore => write!(f, "Streamer"),
+            LogSource::Driver => write!(f, "Driver"),
+            LogSource::Client(hostname) => write!(f, "Client {hostname}"),
+            LogSource::Dashboard => write!(f, "Dashboard"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LogEntry {
     pub severity: LogSeverity,
     pub content: String,
+    #[serde(default)]
+    pub source: LogSource,
+    #[serde(default)]
+    pub debug_group: Option<String>,
+    #[serde(default)]
+    pub timestamp_ms: u64, // since the UNIX epoch
+}
+
+impl LogEntry {
+    pub fn new(
+        severity: LogSeverity,
+        content: String,
+        source: LogSource,
+        debug_group: Option<String>,
+    ) -> Self {
+        Self {
+            severity,
+            content,
+            source,
+            debug_group,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+        }
+    }
 }
 
 pub fn set_panic_hook() {
@@ -220,8 +312,8 @@ pub fn show_warn<T, E: Display + Send + 'static>(res: Result<T, E>) -> Option<T>
 }
 
 #[allow(unused_variables)]
-fn show_e_block<E: Display>(e: E, blocking: bool) {
-    log::error!("{e}");
+fn show_e_block<E: Display>(target: &str, e: E, blocking: bool) {
+    log::error!(target: target, "{e}");
 
     #[cfg(all(not(target_os = "android"), feature = "enable-messagebox"))]
     {
@@ -258,23 +350,28 @@ fn show_e_block<E: Display>(e: E, blocking: bool) {
 }
 
 pub fn show_e<E: Display>(e: E) {
-    show_e_block(e, false);
+    show_e_block(module_path!(), e, false);
+}
+
+// Like show_e(), with the log line attributed to the given log target
+pub fn show_e_with_target<E: Display>(target: &str, e: E) {
+    show_e_block(target, e, false);
 }
 
 pub fn show_e_dbg<E: std::fmt::Debug>(e: E) {
-    show_e_block(format!("{e:?}"), false);
+    show_e_block(module_path!(), format!("{e:?}"), false);
 }
 
 pub fn show_e_blocking<E: Display>(e: E) {
-    show_e_block(e, true);
+    show_e_block(module_path!(), e, true);
 }
 
 pub fn show_err<T, E: Display>(res: Result<T, E>) -> Option<T> {
-    res.map_err(|e| show_e_block(e, false)).ok()
+    res.map_err(|e| show_e_block(module_path!(), e, false)).ok()
 }
 
 pub fn show_err_blocking<T, E: Display>(res: Result<T, E>) -> Option<T> {
-    res.map_err(|e| show_e_block(e, true)).ok()
+    res.map_err(|e| show_e_block(module_path!(), e, true)).ok()
 }
 
 pub trait ToAny<T> {
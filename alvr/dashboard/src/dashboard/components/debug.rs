@@ -22,5 +22,24 @@ pub fn debug_tab_ui(ui: &mut Ui) -> Option<ServerRequest> {
         }
     });
 
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        if ui.button("Create support bundle").clicked() {
+            request = Some(ServerRequest::CreateSupportBundle { redact: false });
+        }
+
+        if ui
+            .button("Create redacted support bundle")
+            .on_hover_text(
+                "Client hostnames, IP addresses, the home directory path and secrets (pairing tokens, \
+                webhook URLs, rendezvous and relay hosts) are removed",
+            )
+            .clicked()
+        {
+            request = Some(ServerRequest::CreateSupportBundle { redact: true });
+        }
+    });
+
     request
 }
@@ -1,7 +1,7 @@
 use crate::dashboard::ServerRequest;
 use alvr_common::ConnectionState;
 use alvr_gui_common::theme::{self, log_colors};
-use alvr_packets::ClientListAction;
+use alvr_packets::{ClientListAction, DiagnosticsReport};
 use alvr_session::{ClientConnectionConfig, SessionConfig};
 use eframe::{
     egui::{self, Frame, Grid, Layout, RichText, TextEdit, Ui, Window},
@@ -19,6 +19,7 @@ pub struct DevicesTab {
     new_devices: Option<Vec<(String, ClientConnectionConfig)>>,
     trusted_devices: Option<Vec<(String, ClientConnectionConfig)>>,
     edit_popup_state: Option<EditPopupState>,
+    diagnostics_report: Option<DiagnosticsReport>,
 }
 
 impl DevicesTab {
@@ -27,6 +28,7 @@ impl DevicesTab {
             new_devices: None,
             trusted_devices: None,
             edit_popup_state: None,
+            diagnostics_report: None,
         }
     }
 
@@ -42,6 +44,10 @@ impl DevicesTab {
         self.new_devices = Some(untrusted_clients);
     }
 
+    pub fn update_diagnostics_report(&mut self, report: DiagnosticsReport) {
+        self.diagnostics_report = Some(report);
+    }
+
     pub fn ui(&mut self, ui: &mut Ui, connected_to_server: bool) -> Vec<ServerRequest> {
         let mut requests = vec![];
 
@@ -142,10 +148,80 @@ impl DevicesTab {
                 });
         }
 
+        if let Some(report) = &self.diagnostics_report {
+            let mut open = true;
+            Window::new(format!("Connection diagnostics: {}", report.hostname))
+                .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+                .resizable(false)
+                .collapsible(false)
+                .open(&mut open)
+                .show(ui.ctx(), |ui| diagnostics_report_ui(ui, report));
+
+            if !open {
+                self.diagnostics_report = None;
+            }
+        }
+
         requests
     }
 }
 
+fn diagnostics_report_ui(ui: &mut Ui, report: &DiagnosticsReport) {
+    Grid::new("diagnostics-protocols")
+        .num_columns(5)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Protocol");
+            ui.label("Round trip");
+            ui.label("Jitter");
+            ui.label("Throughput");
+            ui.label("Packet loss");
+            ui.end_row();
+
+            for protocol in &report.protocols {
+                ui.label(format!("{:?}", protocol.protocol));
+                ui.label(format!("{:.1} ms", protocol.round_trip_time_ms));
+                ui.label(format!("{:.1} ms", protocol.jitter_ms));
+                ui.label(format!("{:.0} Mbps", protocol.throughput_mbps));
+                ui.label(format!("{:.0}%", protocol.packet_loss * 100.0));
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+
+    Grid::new("diagnostics-packet-sizes")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Packet size");
+            ui.label("Delivered");
+            ui.end_row();
+
+            for packet_size in &report.packet_sizes {
+                ui.label(format!("{} B", packet_size.size));
+                ui.label(format!("{:.0}%", packet_size.delivery_ratio * 100.0));
+                ui.end_row();
+            }
+        });
+
+    ui.label(match report.dscp_preserved {
+        Some(true) => "DSCP marking: preserved",
+        Some(false) => "DSCP marking: cleared by the network",
+        None => "DSCP marking: unknown",
+    });
+
+    ui.separator();
+
+    ui.heading("Recommendations");
+    if report.recommendations.is_empty() {
+        ui.label("No issues found");
+    }
+    for recommendation in &report.recommendations {
+        ui.label(format!("• {recommendation}"));
+    }
+}
+
 fn new_clients_section(
     ui: &mut Ui,
     clients: &[(String, ClientConnectionConfig)],
@@ -159,7 +235,7 @@ fn new_clients_section(
                 ui.add_space(5.0);
                 ui.heading("New devices");
             });
-            for (hostname, _) in clients {
+            for (hostname, data) in clients {
                 Frame::group(ui.style())
                     .fill(theme::DARKER_BG)
                     .inner_margin(egui::vec2(15.0, 12.0))
@@ -170,6 +246,7 @@ fn new_clients_section(
                             .show(ui, |ui| {
                                 ui.horizontal(|ui| {
                                     ui.label(hostname);
+                                    incompatibility_label(ui, data);
                                 });
                                 ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                                     if ui.button("Trust").clicked() {
@@ -213,7 +290,13 @@ fn trusted_clients_section(
                                 .num_columns(2)
                                 .spacing(egui::vec2(8.0, 8.0))
                                 .show(ui, |ui| {
-                                    ui.label(&data.display_name);
+                                    ui.horizontal(|ui| {
+                                        ui.label(&data.display_name);
+                                        if data.cabled {
+                                            ui.label(RichText::new("USB").strong());
+                                        }
+                                        incompatibility_label(ui, data);
+                                    });
                                     ui.horizontal(|ui| {
                                         ui.with_layout(
                                             Layout::right_to_left(Align::Center),
@@ -255,6 +338,19 @@ fn trusted_clients_section(
                                                 action: ClientListAction::RemoveEntry,
                                             });
                                         }
+                                        if ui
+                                            .button("Diagnose")
+                                            .on_hover_text(
+                                                "Test the network connection the next time the \
+                                                device connects, instead of streaming.",
+                                            )
+                                            .clicked()
+                                        {
+                                            request =
+                                                Some(ServerRequest::RunConnectionDiagnostics(
+                                                    hostname.clone(),
+                                                ));
+                                        }
                                         if ui.button("Edit").clicked() {
                                             *edit_popup_state = Some(EditPopupState {
                                                 new_devices: false,
@@ -266,7 +362,36 @@ fn trusted_clients_section(
                                                     .collect::<Vec<String>>(),
                                             });
                                         }
+
+                                        let mut spectator = data.spectator;
+                                        if ui
+                                            .checkbox(&mut spectator, "Spectator")
+                                            .on_hover_text(
+                                                "Receive the video of the main client. \
+                                                Takes effect on the next connection.",
+                                            )
+                                            .changed()
+                                        {
+                                            request = Some(ServerRequest::UpdateClientList {
+                                                hostname: hostname.clone(),
+                                                action: ClientListAction::SetSpectator(spectator),
+                                            });
+                                        }
                                     });
+
+                                    let overrides = headset_overrides_text(data);
+                                    if !overrides.is_empty() {
+                                        ui.end_row();
+
+                                        ui.label(
+                                            RichText::new(format!("From headset: {overrides}"))
+                                                .italics(),
+                                        )
+                                        .on_hover_text(
+                                            "Streaming values chosen on the headset, overriding \
+                                            the settings.",
+                                        );
+                                    }
                                 });
                         });
                 }
@@ -283,3 +408,27 @@ fn trusted_clients_section(
 
     request
 }
+
+fn headset_overrides_text(data: &ClientConnectionConfig) -> String {
+    let overrides = &data.streaming_overrides;
+
+    let mut values = vec![];
+    if let Some(codec) = overrides.preferred_codec {
+        values.push(format!("{codec:?} codec"));
+    }
+    if let Some(rate) = overrides.refresh_rate {
+        values.push(format!("{rate} Hz"));
+    }
+    if let Some(bitrate) = overrides.max_bitrate_mbps {
+        values.push(format!("max {bitrate} Mbps"));
+    }
+
+    values.join(", ")
+}
+
+fn incompatibility_label(ui: &mut Ui, data: &ClientConnectionConfig) {
+    if let Some(reason) = &data.incompatibility {
+        ui.colored_label(log_colors::ERROR_LIGHT, "Incompatible version")
+            .on_hover_text(reason);
+    }
+}
@@ -1,11 +1,14 @@
+use alvr_common::error;
 use alvr_gui_common::theme;
 use alvr_packets::{FirewallRulesAction, ServerRequest};
+use alvr_session::ButtonMappingProfile;
 use eframe::{
-    egui::{Frame, Grid, Layout, RichText, Ui},
+    egui::{Frame, Grid, Layout, RichText, TextEdit, Ui},
     emath::Align,
 };
 use std::{
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
@@ -18,6 +21,8 @@ pub enum InstallationTabRequest {
 
 pub struct InstallationTab {
     drivers: Vec<PathBuf>,
+    button_mapping_profiles: Vec<ButtonMappingProfile>,
+    profile_file_path: String,
     last_update_instant: Instant,
 }
 
@@ -25,6 +30,8 @@ impl InstallationTab {
     pub fn new() -> Self {
         Self {
             drivers: vec![],
+            button_mapping_profiles: vec![],
+            profile_file_path: "".into(),
             last_update_instant: Instant::now(),
         }
     }
@@ -33,6 +40,10 @@ impl InstallationTab {
         self.drivers = list;
     }
 
+    pub fn update_button_mapping_profiles(&mut self, profiles: Vec<ButtonMappingProfile>) {
+        self.button_mapping_profiles = profiles;
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) -> Vec<InstallationTabRequest> {
         let mut requests = vec![];
 
@@ -41,6 +52,9 @@ impl InstallationTab {
             requests.push(InstallationTabRequest::ServerRequest(
                 ServerRequest::GetDriverList,
             ));
+            requests.push(InstallationTabRequest::ServerRequest(
+                ServerRequest::GetButtonMappingProfiles,
+            ));
 
             self.last_update_instant = now;
         }
@@ -86,6 +100,58 @@ impl InstallationTab {
                         ));
                     }
                 });
+
+            Frame::group(ui.style())
+                .fill(theme::SECTION_BG)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Button mapping profiles").size(18.0));
+                    Grid::new(1).num_columns(3).show(ui, |ui| {
+                        for profile in &self.button_mapping_profiles {
+                            ui.label(&profile.name);
+                            ui.label(profile.steamvr_app_keys.join(", "));
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.button("Remove").clicked() {
+                                    requests.push(InstallationTabRequest::ServerRequest(
+                                        ServerRequest::RemoveButtonMappingProfile(
+                                            profile.name.clone(),
+                                        ),
+                                    ));
+                                }
+                                // Files are written by the dashboard, the streamer never
+                                // accesses paths chosen by the user
+                                if ui.button("Export").clicked()
+                                    && !self.profile_file_path.is_empty()
+                                {
+                                    if let Err(e) = alvr_server_io::export_button_mapping_profile(
+                                        profile,
+                                        Path::new(&self.profile_file_path),
+                                    ) {
+                                        error!("Failed to export button mapping profile: {e}");
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("File path:");
+                        ui.add(
+                            TextEdit::singleline(&mut self.profile_file_path)
+                                .hint_text("profile.json"),
+                        );
+                        if ui.button("Import").clicked() && !self.profile_file_path.is_empty() {
+                            match fs::read_to_string(&self.profile_file_path) {
+                                Ok(content) => {
+                                    requests.push(InstallationTabRequest::ServerRequest(
+                                        ServerRequest::ImportButtonMappingProfile(content),
+                                    ))
+                                }
+                                Err(e) => error!("Failed to read button mapping profile: {e}"),
+                            }
+                        }
+                    });
+                });
         });
 
         requests
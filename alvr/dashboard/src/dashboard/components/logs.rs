@@ -1,25 +1,89 @@
-use alvr_common::LogSeverity;
+use alvr_common::{LogSeverity, LogSource};
 use alvr_events::{Event, EventType};
 use alvr_gui_common::theme::log_colors;
 use alvr_session::{RawEventsConfig, Settings};
 use eframe::{
-    egui::{Grid, OpenUrl, RichText, ScrollArea, Ui},
+    egui::{ComboBox, Grid, RichText, ScrollArea, TextEdit, Ui},
     epaint::Color32,
 };
 use settings_schema::Switch;
-use std::{collections::VecDeque, env};
+use std::collections::{BTreeSet, VecDeque};
+
+const LEVELS: [LogSeverity; 4] = [
+    LogSeverity::Debug,
+    LogSeverity::Info,
+    LogSeverity::Warning,
+    LogSeverity::Error,
+];
+
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_RANGES: [(&str, Option<u64>); 4] = [
+    ("Last 5 minutes", Some(5 * 60 * 1000)),
+    ("Last 30 minutes", Some(30 * 60 * 1000)),
+    ("Last hour", Some(60 * 60 * 1000)),
+    ("All", None),
+];
+
+#[derive(Clone, PartialEq)]
+enum SourceFilter {
+    All,
+    Source(LogSource),
+    Events,
+}
+
+impl SourceFilter {
+    fn display_name(&self) -> String {
+        match self {
+            SourceFilter::All => "All sources".into(),
+            SourceFilter::Source(source) => source.to_string(),
+            SourceFilter::Events => "Events".into(),
+        }
+    }
+}
 
 struct Entry {
     color: Color32,
     timestamp: String,
+    timestamp_ms: u64,
+    severity: Option<LogSeverity>, // None for raw events
+    source: Option<LogSource>,     // None for raw events
+    debug_group: Option<String>,
     ty: String,
     message: String,
 }
 
+impl Entry {
+    fn to_line(&self) -> String {
+        let source = self
+            .source
+            .as_ref()
+            .map(|source| format!(" ({source})"))
+            .unwrap_or_default();
+        let debug_group = self
+            .debug_group
+            .as_ref()
+            .map(|group| format!(" [{group}]"))
+            .unwrap_or_default();
+
+        format!(
+            "{} [{}]{source}{debug_group} {}\n",
+            self.timestamp, self.ty, self.message
+        )
+    }
+}
+
 pub struct LogsTab {
     raw_events_config: Switch<RawEventsConfig>,
     entries: VecDeque<Entry>,
     log_limit: usize,
+    search: String,
+    min_level: LogSeverity,
+    source_filter: SourceFilter,
+    seen_sources: BTreeSet<LogSource>,
+    paused: bool,
+    paused_entries: VecDeque<Entry>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_range_index: usize,
 }
 
 impl LogsTab {
@@ -30,6 +94,14 @@ impl LogsTab {
             }),
             entries: VecDeque::new(),
             log_limit: 1000,
+            search: String::new(),
+            min_level: LogSeverity::Debug,
+            source_filter: SourceFilter::All,
+            seen_sources: BTreeSet::new(),
+            paused: false,
+            paused_entries: VecDeque::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            export_range_index: 0,
         }
     }
 
@@ -38,7 +110,9 @@ impl LogsTab {
     }
 
     pub fn push_event(&mut self, event: Event) {
-        match event.event_type {
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+
+        let entry = match event.event_type {
             EventType::Log(log_event) => {
                 let color;
                 let ty;
@@ -61,62 +135,193 @@ impl LogsTab {
                     }
                 };
 
-                self.entries.push_back(Entry {
+                self.seen_sources.insert(log_event.source.clone());
+
+                Entry {
                     color,
                     timestamp: event.timestamp,
+                    // Older streamers don't send the timestamp
+                    timestamp_ms: if log_event.timestamp_ms != 0 {
+                        log_event.timestamp_ms
+                    } else {
+                        now_ms
+                    },
+                    severity: Some(log_event.severity),
+                    source: Some(log_event.source),
+                    debug_group: log_event.debug_group,
                     ty: ty.into(),
                     message: log_event.content,
-                });
+                }
             }
             event_type => {
-                if let Switch::Enabled(config) = &self.raw_events_config {
-                    if !config.hide_spammy_events
-                        || !matches!(
-                            event_type,
-                            EventType::StatisticsSummary(_)
-                                | EventType::GraphStatistics(_)
-                                | EventType::Tracking(_)
-                        )
-                    {
-                        self.entries.push_back(Entry {
-                            color: log_colors::EVENT_LIGHT,
-                            timestamp: event.timestamp,
-                            ty: "EVENT".into(),
-                            message: format!("{event_type:?}"),
-                        });
-                    }
+                let Switch::Enabled(config) = &self.raw_events_config else {
+                    return;
+                };
+                if config.hide_spammy_events
+                    && matches!(
+                        event_type,
+                        EventType::StatisticsSummary(_)
+                            | EventType::SpectatorStatistics(_)
+                            | EventType::GraphStatistics(_)
+                            | EventType::Tracking(_)
+                    )
+                {
+                    return;
+                }
+
+                Entry {
+                    color: log_colors::EVENT_LIGHT,
+                    timestamp: event.timestamp,
+                    timestamp_ms: now_ms,
+                    severity: None,
+                    source: None,
+                    debug_group: None,
+                    ty: "EVENT".into(),
+                    message: format!("{event_type:?}"),
                 }
             }
+        };
+
+        // While paused, entries are held back so the view doesn't scroll away
+        let entries = if self.paused {
+            &mut self.paused_entries
+        } else {
+            &mut self.entries
+        };
+        entries.push_back(entry);
+        if entries.len() > self.log_limit {
+            entries.pop_front();
         }
+    }
+
+    fn is_visible(&self, entry: &Entry) -> bool {
+        let source_visible = match &self.source_filter {
+            SourceFilter::All => true,
+            SourceFilter::Source(source) => entry.source.as_ref() == Some(source),
+            SourceFilter::Events => entry.source.is_none(),
+        };
+        // Raw events have no severity and are not hidden by the level filter
+        let level_visible = entry
+            .severity
+            .map(|severity| severity >= self.min_level)
+            .unwrap_or(true);
+        let search = self.search.to_lowercase();
+        let search_visible = search.is_empty()
+            || entry.message.to_lowercase().contains(&search)
+            || entry
+                .debug_group
+                .as_ref()
+                .is_some_and(|group| group.to_lowercase().contains(&search));
+
+        source_visible && level_visible && search_visible
+    }
 
-        if self.entries.len() > self.log_limit {
+    fn filtered_text(&self, min_timestamp_ms: u64) -> String {
+        self.entries
+            .iter()
+            .filter(|entry| entry.timestamp_ms >= min_timestamp_ms && self.is_visible(entry))
+            .map(Entry::to_line)
+            .collect()
+    }
+
+    fn resume(&mut self) {
+        self.entries.append(&mut self.paused_entries);
+        while self.entries.len() > self.log_limit {
             self.entries.pop_front();
         }
     }
 
-    pub fn ui(&self, ui: &mut Ui) {
+    // Exported to the log directory, together with session.json and the crash log
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export(&self) {
+        let layout = alvr_filesystem::filesystem_layout_from_dashboard_exe(
+            &std::env::current_exe().unwrap(),
+        );
+
+        let min_timestamp_ms = EXPORT_RANGES[self.export_range_index]
+            .1
+            .map(|range_ms| (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(range_ms))
+            .unwrap_or(0);
+
+        let archive_path = layout.log_dir.join(format!(
+            "alvr_logs_{}.zip",
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+
+        match alvr_server_io::write_logs_archive(
+            &archive_path,
+            &layout,
+            &self.filtered_text(min_timestamp_ms),
+        ) {
+            Ok(()) => alvr_common::info!("Logs exported to {}", archive_path.display()),
+            Err(e) => alvr_common::error!("Failed to export the logs: {e}"),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.search)
+                    .hint_text("Search")
+                    .desired_width(200.0),
+            );
+
+            ComboBox::from_id_source("logs_level_filter")
+                .selected_text(format!("{:?}", self.min_level))
+                .show_ui(ui, |ui| {
+                    for level in LEVELS {
+                        ui.selectable_value(&mut self.min_level, level, format!("{level:?}"));
+                    }
+                });
+
+            ComboBox::from_id_source("logs_source_filter")
+                .selected_text(self.source_filter.display_name())
+                .show_ui(ui, |ui| {
+                    let filters = [SourceFilter::All]
+                        .into_iter()
+                        .chain(self.seen_sources.iter().cloned().map(SourceFilter::Source))
+                        .chain([SourceFilter::Events]);
+                    for filter in filters {
+                        let name = filter.display_name();
+                        ui.selectable_value(&mut self.source_filter, filter, name);
+                    }
+                });
+
+            if ui.checkbox(&mut self.paused, "Pause").changed() && !self.paused {
+                self.resume();
+            }
+        });
+
         ui.horizontal(|ui| {
             if ui.button("Copy all").clicked() {
-                ui.output_mut(|out| {
-                    out.copied_text = self.entries.iter().fold(String::new(), |acc, entry| {
-                        format!(
-                            "{}{} [{}] {}\n",
-                            acc, entry.timestamp, entry.ty, entry.message
-                        )
-                    })
-                })
+                let text = self.filtered_text(0);
+                ui.output_mut(|out| out.copied_text = text);
             }
-            if ui.button("Open logs directory").clicked() {
-                let log_dir = alvr_filesystem::filesystem_layout_from_dashboard_exe(
-                    &env::current_exe().unwrap(),
-                )
-                .log_dir;
-                ui.output_mut(|f| {
-                    f.open_url = Some(OpenUrl::same_tab(format!(
-                        "file://{}",
-                        log_dir.to_string_lossy()
-                    )))
-                });
+            // The browser dashboard cannot access the streamer filesystem
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ComboBox::from_id_source("logs_export_range").show_index(
+                    ui,
+                    &mut self.export_range_index,
+                    EXPORT_RANGES.len(),
+                    |idx| EXPORT_RANGES[idx].0,
+                );
+                if ui.button("Export").clicked() {
+                    self.export();
+                }
+
+                if ui.button("Open logs directory").clicked() {
+                    let log_dir = alvr_filesystem::filesystem_layout_from_dashboard_exe(
+                        &std::env::current_exe().unwrap(),
+                    )
+                    .log_dir;
+                    ui.output_mut(|f| {
+                        f.open_url = Some(eframe::egui::OpenUrl::same_tab(format!(
+                            "file://{}",
+                            log_dir.to_string_lossy()
+                        )))
+                    });
+                }
             }
         });
 
@@ -126,16 +331,27 @@ impl LogsTab {
             .show(ui, |ui| {
                 Grid::new(0)
                     .spacing((10.0, 2.0))
-                    .num_columns(3)
+                    .num_columns(4)
                     .striped(true)
                     .show(ui, |ui| {
-                        for entry in &self.entries {
+                        for entry in self.entries.iter().filter(|e| self.is_visible(e)) {
+                            let source = entry
+                                .source
+                                .as_ref()
+                                .map(|source| source.to_string())
+                                .unwrap_or_default();
+                            let message = match &entry.debug_group {
+                                Some(group) => format!("[{group}] {}", entry.message),
+                                None => entry.message.clone(),
+                            };
+
                             ui.colored_label(
                                 entry.color,
                                 RichText::new(&entry.timestamp).size(12.0),
                             );
                             ui.colored_label(entry.color, RichText::new(&entry.ty).size(12.0));
-                            ui.colored_label(entry.color, RichText::new(&entry.message).size(12.0));
+                            ui.colored_label(entry.color, RichText::new(source).size(12.0));
+                            ui.colored_label(entry.color, RichText::new(message).size(12.0));
 
                             ui.end_row();
                         }
@@ -5,6 +5,7 @@ mod logs;
 mod notifications;
 mod settings;
 mod settings_controls;
+mod settings_history;
 mod setup_wizard;
 mod statistics;
 
@@ -18,6 +19,7 @@ pub use logs::*;
 pub use notifications::*;
 pub use settings::*;
 pub use settings_controls::*;
+pub use settings_history::*;
 pub use setup_wizard::*;
 pub use statistics::*;
 
@@ -37,7 +37,7 @@ const NOTIFICATION_TIPS: &[&str] = &[
     r#"When using external trackers or controllers you should set both "Position/Rotation recentering mode" to "Disabled"."#,
     r#"You can enable tilt mode. Set "Position recentering mode to "Local" and "Rotation recentering mode" to "Tilted"."#,
     r#"If you often experience image glitching, you can trade that with stutter frames using "Avoid video glitching"."#,
-    r#"You can run custom commands/programs at headset connection/disconnection using "On connect/disconnect script"."#,
+    r#"You can run custom commands/programs or send webhooks on connection events using "Event hooks"."#,
     r#"In case you want to report a bug, to get a log file enable "Log to disk". The log will be inside "session_log.txt"."#,
     r#"For hacking purposes, you can enable "Log tracking", "Log button presses", "Log haptics". You can get the data using a websocket at ws://localhost:8082/api/events"#,
     r#"In case you want to report a bug and share your log, you should enable "Prefer backtrace"."#,
@@ -1,10 +1,12 @@
 use super::{
     presets::{builtin_schema, PresetControl},
-    NestingInfo, SettingControl,
+    NestingInfo, SettingControl, SettingsHistoryView,
 };
 use crate::dashboard::ServerRequest;
 use alvr_gui_common::{theme, DisplayString};
-use alvr_packets::AudioDevicesList;
+use alvr_packets::{
+    AudioDevicesList, SetValuesRequest, SettingsChangeOrigin, SettingsHistoryEntry,
+};
 use alvr_session::{SessionSettings, Settings};
 use eframe::egui::{self, Align, Frame, Grid, Layout, RichText, ScrollArea, Ui};
 #[cfg(target_arch = "wasm32")]
@@ -31,7 +33,9 @@ pub struct SettingsTab {
     microphone_preset: Option<PresetControl>,
     hand_tracking_interaction_preset: PresetControl,
     eye_face_tracking_preset: PresetControl,
+    user_presets: Vec<PresetControl>,
     top_level_entries: Vec<TopLevelEntry>,
+    history_view: SettingsHistoryView,
     session_settings_json: Option<json::Value>,
     last_update_instant: Instant,
 }
@@ -65,6 +69,19 @@ impl SettingsTab {
             })
             .collect();
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let user_presets = super::presets::user_presets::load_user_presets(
+            &alvr_filesystem::filesystem_layout_from_dashboard_exe(
+                &std::env::current_exe().unwrap(),
+            )
+            .presets_dir(),
+        )
+        .into_iter()
+        .map(PresetControl::new)
+        .collect();
+        #[cfg(target_arch = "wasm32")]
+        let user_presets = vec![];
+
         Self {
             selected_top_tab_id: "presets".into(),
             resolution_preset: PresetControl::new(builtin_schema::resolution_schema()),
@@ -76,7 +93,9 @@ impl SettingsTab {
                 builtin_schema::hand_tracking_interaction_schema(),
             ),
             eye_face_tracking_preset: PresetControl::new(builtin_schema::eye_face_tracking_schema()),
+            user_presets,
             top_level_entries,
+            history_view: SettingsHistoryView::new(),
             session_settings_json: None,
             last_update_instant: Instant::now(),
         }
@@ -100,6 +119,9 @@ impl SettingsTab {
             .update_session_settings(&settings_json);
         self.eye_face_tracking_preset
             .update_session_settings(&settings_json);
+        for preset in &mut self.user_presets {
+            preset.update_session_settings(&settings_json);
+        }
 
         self.session_settings_json = Some(settings_json);
     }
@@ -119,6 +141,10 @@ impl SettingsTab {
         }
     }
 
+    pub fn update_settings_history(&mut self, entries: Vec<SettingsHistoryEntry>) {
+        self.history_view.update_history(entries);
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) -> Vec<ServerRequest> {
         let mut requests = vec![];
 
@@ -135,6 +161,7 @@ impl SettingsTab {
             self.last_update_instant = now;
         }
 
+        let mut preset_path_value_pairs = vec![];
         let mut path_value_pairs = vec![];
         ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
             Frame::group(ui.style())
@@ -154,6 +181,11 @@ impl SettingsTab {
                                 RichText::new(entry.id.display.clone()).raised().size(15.0),
                             );
                         }
+                        ui.selectable_value(
+                            &mut self.selected_top_tab_id,
+                            "history".into(),
+                            RichText::new("History").raised().size(15.0),
+                        );
                     })
                 })
         });
@@ -166,32 +198,40 @@ impl SettingsTab {
                         .striped(true)
                         .num_columns(2)
                         .show(ui, |ui| {
-                            path_value_pairs.extend(self.resolution_preset.ui(ui));
+                            preset_path_value_pairs.extend(self.resolution_preset.ui(ui));
                             ui.end_row();
 
-                            path_value_pairs.extend(self.framerate_preset.ui(ui));
+                            preset_path_value_pairs.extend(self.framerate_preset.ui(ui));
                             ui.end_row();
 
-                            path_value_pairs.extend(self.encoder_preset.ui(ui));
+                            preset_path_value_pairs.extend(self.encoder_preset.ui(ui));
                             ui.end_row();
 
                             if let Some(preset) = &mut self.game_audio_preset {
-                                path_value_pairs.extend(preset.ui(ui));
+                                preset_path_value_pairs.extend(preset.ui(ui));
                                 ui.end_row();
                             }
 
                             if let Some(preset) = &mut self.microphone_preset {
-                                path_value_pairs.extend(preset.ui(ui));
+                                preset_path_value_pairs.extend(preset.ui(ui));
                                 ui.end_row();
                             }
 
-                            path_value_pairs.extend(self.hand_tracking_interaction_preset.ui(ui));
+                            preset_path_value_pairs
+                                .extend(self.hand_tracking_interaction_preset.ui(ui));
                             ui.end_row();
 
-                            path_value_pairs.extend(self.eye_face_tracking_preset.ui(ui));
+                            preset_path_value_pairs.extend(self.eye_face_tracking_preset.ui(ui));
                             ui.end_row();
+
+                            for preset in &mut self.user_presets {
+                                preset_path_value_pairs.extend(preset.ui(ui));
+                                ui.end_row();
+                            }
                         })
                 });
+        } else if self.selected_top_tab_id == "history" {
+            requests.extend(self.history_view.ui(ui));
         } else {
             ScrollArea::new([false, true])
                 .id_source(format!("{}_scroll", self.selected_top_tab_id))
@@ -228,8 +268,17 @@ impl SettingsTab {
                 });
         }
 
+        if !preset_path_value_pairs.is_empty() {
+            requests.push(ServerRequest::SetValues(SetValuesRequest {
+                origin: SettingsChangeOrigin::Preset,
+                values: preset_path_value_pairs,
+            }));
+        }
         if !path_value_pairs.is_empty() {
-            requests.push(ServerRequest::SetValues(path_value_pairs));
+            requests.push(ServerRequest::SetValues(SetValuesRequest {
+                origin: SettingsChangeOrigin::Dashboard,
+                values: path_value_pairs,
+            }));
         }
 
         requests
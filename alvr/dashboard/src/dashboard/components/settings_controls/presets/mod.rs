@@ -4,6 +4,9 @@ mod mirror;
 pub mod builtin_schema;
 pub mod schema;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod user_presets;
+
 use self::schema::PresetSchemaNode;
 use alvr_packets::PathValuePair;
 use eframe::egui::Ui;
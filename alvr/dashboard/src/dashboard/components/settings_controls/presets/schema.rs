@@ -19,13 +19,16 @@ pub struct PresetModifier {
 pub struct HigherOrderChoiceOption {
     pub display_name: String,
     pub modifiers: Vec<PresetModifier>,
+    #[serde(default)]
     pub content: Option<PresetSchemaNode>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct HigherOrderChoiceSchema {
     pub name: String,
+    #[serde(default)]
     pub strings: HashMap<String, String>,
+    #[serde(default)]
     pub flags: HashSet<String>,
     pub options: Vec<HigherOrderChoiceOption>,
     pub default_option_index: usize,
@@ -0,0 +1,93 @@
+use super::schema::{PresetModifierOperation, PresetSchemaNode};
+use alvr_common::{
+    anyhow::{anyhow, bail, Result},
+    error,
+};
+use alvr_packets::PathSegment;
+use serde_json as json;
+use std::{fs, path::Path};
+
+fn validate_target_path(session_settings_json: &json::Value, target_path: &str) -> Result<()> {
+    let path = alvr_packets::parse_path(target_path);
+
+    if !matches!(path.first(), Some(PathSegment::Name(name)) if name == "session_settings") {
+        bail!("Path \"{target_path}\" does not start with \"session_settings\"");
+    }
+
+    let mut session_ref = session_settings_json;
+    for segment in &path[1..] {
+        session_ref = match segment {
+            PathSegment::Name(name) => session_ref.get(name),
+            PathSegment::Index(index) => session_ref.get(index),
+        }
+        .ok_or_else(|| anyhow!("Path \"{target_path}\" not found in the settings"))?;
+    }
+
+    Ok(())
+}
+
+fn validate_node(node: &PresetSchemaNode, session_settings_json: &json::Value) -> Result<()> {
+    match node {
+        PresetSchemaNode::HigherOrderChoice(schema) => {
+            if schema.default_option_index >= schema.options.len() {
+                bail!(
+                    "Preset \"{}\": default option index {} out of range",
+                    schema.name,
+                    schema.default_option_index
+                );
+            }
+
+            for option in &schema.options {
+                for modifier in &option.modifiers {
+                    match &modifier.operation {
+                        PresetModifierOperation::Assign(_) => {
+                            validate_target_path(session_settings_json, &modifier.target_path)?
+                        }
+                    }
+                }
+
+                if let Some(content) = &option.content {
+                    validate_node(content, session_settings_json)?;
+                }
+            }
+        }
+        PresetSchemaNode::Mirror(_) => bail!("Mirror presets are not supported"),
+    }
+
+    Ok(())
+}
+
+fn load_preset(path: &Path, session_settings_json: &json::Value) -> Result<PresetSchemaNode> {
+    let preset = json::from_str::<PresetSchemaNode>(&fs::read_to_string(path)?)?;
+    validate_node(&preset, session_settings_json)?;
+
+    Ok(preset)
+}
+
+// Every json file in the directory is a preset. Invalid presets are reported and skipped
+pub fn load_user_presets(presets_dir: &Path) -> Vec<PresetSchemaNode> {
+    let Ok(entries) = fs::read_dir(presets_dir) else {
+        return vec![];
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.is_file() && path.extension()? == "json").then_some(path)
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let session_settings_json = json::to_value(alvr_session::session_settings_default()).unwrap();
+
+    paths
+        .into_iter()
+        .filter_map(|path| match load_preset(&path, &session_settings_json) {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                error!("Invalid preset {}: {e}", path.display());
+                None
+            }
+        })
+        .collect()
+}
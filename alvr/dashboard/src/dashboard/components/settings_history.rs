@@ -0,0 +1,130 @@
+use crate::dashboard::ServerRequest;
+use alvr_gui_common::theme;
+use alvr_packets::{PathSegment, SettingsChangeOrigin, SettingsHistoryEntry};
+use chrono::{Local, TimeZone};
+use eframe::{
+    egui::{self, Frame, Layout, RichText, ScrollArea, Ui},
+    emath::Align,
+};
+use serde_json as json;
+
+const MAX_VALUE_LENGTH: usize = 60;
+
+fn path_string(path: &[PathSegment]) -> String {
+    let mut string = String::new();
+
+    // Skip "session_settings"
+    for segment in path.iter().skip(1) {
+        match segment {
+            PathSegment::Name(name) => {
+                if !string.is_empty() {
+                    string.push('.');
+                }
+                string.push_str(name);
+            }
+            PathSegment::Index(index) => string.push_str(&format!("[{index}]")),
+        }
+    }
+
+    string
+}
+
+fn value_string(value: &json::Value) -> String {
+    let string = value.to_string();
+    if string.chars().count() > MAX_VALUE_LENGTH {
+        format!(
+            "{}…",
+            string.chars().take(MAX_VALUE_LENGTH).collect::<String>()
+        )
+    } else {
+        string
+    }
+}
+
+fn origin_string(origin: SettingsChangeOrigin) -> &'static str {
+    match origin {
+        SettingsChangeOrigin::Dashboard => "Dashboard",
+        SettingsChangeOrigin::WebApi => "Web API",
+        SettingsChangeOrigin::Preset => "Preset",
+        SettingsChangeOrigin::Rollback => "Rollback",
+        SettingsChangeOrigin::Server => "Streamer",
+        SettingsChangeOrigin::Cli => "Command line",
+    }
+}
+
+pub struct SettingsHistoryView {
+    entries: Option<Vec<SettingsHistoryEntry>>,
+}
+
+impl SettingsHistoryView {
+    pub fn new() -> Self {
+        Self { entries: None }
+    }
+
+    pub fn update_history(&mut self, entries: Vec<SettingsHistoryEntry>) {
+        self.entries = Some(entries);
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Vec<ServerRequest> {
+        let mut requests = vec![];
+
+        let Some(entries) = &self.entries else {
+            requests.push(ServerRequest::GetSettingsHistory);
+            ui.label("Loading settings history...");
+
+            return requests;
+        };
+
+        if entries.is_empty() {
+            ui.label("No settings changes recorded yet.");
+
+            return requests;
+        }
+
+        ScrollArea::new([false, true])
+            .id_source("history_scroll")
+            .show(ui, |ui| {
+                // Newest first
+                for entry in entries.iter().rev() {
+                    Frame::group(ui.style())
+                        .fill(theme::SECTION_BG)
+                        .inner_margin(egui::vec2(10.0, 8.0))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                let time = Local
+                                    .timestamp_millis_opt(entry.timestamp_ms as i64)
+                                    .single()
+                                    .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                                    .unwrap_or_default();
+                                ui.label(RichText::new(time).strong());
+                                ui.label(origin_string(entry.origin));
+
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    if ui
+                                        .button("Revert to before this")
+                                        .on_hover_text(
+                                            "Undo this change and all the ones made after it",
+                                        )
+                                        .clicked()
+                                    {
+                                        requests.push(ServerRequest::RollbackSettings(entry.id));
+                                    }
+                                });
+                            });
+
+                            for change in &entry.changes {
+                                ui.label(format!(
+                                    "{}: {} → {}",
+                                    path_string(&change.path),
+                                    value_string(&change.old_value),
+                                    value_string(&change.new_value),
+                                ));
+                            }
+                        });
+                    ui.add_space(5.0);
+                }
+            });
+
+        requests
+    }
+}
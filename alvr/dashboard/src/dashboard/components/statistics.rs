@@ -1,6 +1,7 @@
 use crate::dashboard::{theme::graph_colors, ServerRequest};
 use alvr_events::{GraphStatistics, StatisticsSummary};
 use alvr_gui_common::theme;
+use alvr_packets::ConnectionPath;
 use eframe::{
     egui::{
         popup, pos2, vec2, Align2, Color32, FontId, Frame, Grid, Id, Painter, Rect, RichText,
@@ -404,6 +405,19 @@ impl StatisticsTab {
             ui[0].label("Streamer FPS:");
             ui[1].label(&format!("{} FPS", statistics.server_fps));
 
+            ui[0].label("Connection path:");
+            ui[1].label(match statistics.connection_path {
+                ConnectionPath::Lan => "Local network".into(),
+                ConnectionPath::HolePunched => format!(
+                    "Internet, direct ({:.0} ms round trip)",
+                    statistics.connection_path_rtt_ms
+                ),
+                ConnectionPath::Relayed => format!(
+                    "Internet, relayed ({:.0} ms round trip)",
+                    statistics.connection_path_rtt_ms
+                ),
+            });
+
             ui[0].label("Headset battery");
             ui[1].label(&format!(
                 "{}% ({})",
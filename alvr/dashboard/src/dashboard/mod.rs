@@ -7,7 +7,7 @@ use crate::{dashboard::components::StatisticsTab, DataSources};
 use alvr_common::parking_lot::{Condvar, Mutex};
 use alvr_events::EventType;
 use alvr_gui_common::theme;
-use alvr_packets::{PathValuePair, ServerRequest};
+use alvr_packets::{PathValuePair, ServerRequest, SetValuesRequest, SettingsChangeOrigin};
 use alvr_session::SessionConfig;
 use eframe::egui::{self, Align, CentralPanel, Frame, Layout, Margin, RichText, SidePanel, Stroke};
 use std::{collections::BTreeMap, sync::Arc};
@@ -83,6 +83,7 @@ impl Dashboard {
     }
 
     // This call may block
+    #[cfg(not(target_arch = "wasm32"))]
     fn restart_steamvr(&self, requests: &mut Vec<ServerRequest>) {
         requests.push(ServerRequest::RestartSteamvr);
 
@@ -95,7 +96,6 @@ impl Dashboard {
 
         *server_restarting_lock = true;
 
-        #[cfg(not(target_arch = "wasm32"))]
         std::thread::spawn({
             let server_restarting = Arc::clone(&self.server_restarting);
             let condvar = Arc::clone(&self.server_restarting_condvar);
@@ -146,10 +146,22 @@ impl eframe::App for Dashboard {
 
                     self.session = Some(*session);
                 }
+                EventType::ConnectionDiagnostics(report) => {
+                    self.connections_tab.update_diagnostics_report(*report)
+                }
+                // Restarting needs the native dashboard, which launches SteamVR
+                #[cfg(not(target_arch = "wasm32"))]
                 EventType::ServerRequestsSelfRestart => self.restart_steamvr(&mut requests),
                 EventType::AudioDevices(list) => self.settings_tab.update_audio_devices(list),
+                EventType::SettingsHistory(entries) => {
+                    self.settings_tab.update_settings_history(entries)
+                }
                 #[cfg(not(target_arch = "wasm32"))]
                 EventType::DriversList(list) => self.installation_tab.update_drivers(list),
+                #[cfg(not(target_arch = "wasm32"))]
+                EventType::ButtonMappingProfiles(profiles) => self
+                    .installation_tab
+                    .update_button_mapping_profiles(profiles),
                 _ => (),
             }
         }
@@ -177,12 +189,15 @@ impl eframe::App for Dashboard {
                         }
                         SetupWizardRequest::Close { finished } => {
                             if finished {
-                                requests.push(ServerRequest::SetValues(vec![PathValuePair {
-                                    path: alvr_packets::parse_path(
-                                        "session_settings.extra.open_setup_wizard",
-                                    ),
-                                    value: serde_json::Value::Bool(false),
-                                }]))
+                                requests.push(ServerRequest::SetValues(SetValuesRequest {
+                                    origin: SettingsChangeOrigin::Dashboard,
+                                    values: vec![PathValuePair {
+                                        path: alvr_packets::parse_path(
+                                            "session_settings.extra.open_setup_wizard",
+                                        ),
+                                        value: serde_json::Value::Bool(false),
+                                    }],
+                                }))
                             }
 
                             self.setup_wizard_open = false;
@@ -301,6 +316,7 @@ impl eframe::App for Dashboard {
             self.data_sources.request(request);
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
         if context.input(|state| state.viewport().close_requested())
             && self
                 .session
@@ -1,12 +1,13 @@
 use alvr_common::{debug, error, info, parking_lot::Mutex, warn, RelaxedAtomic};
 use alvr_events::{Event, EventType};
-use alvr_packets::ServerRequest;
+use alvr_packets::{ServerRequest, SetValuesRequest, SettingsChangeOrigin};
 use alvr_server_io::ServerSessionManager;
 use eframe::egui;
 use std::{
     env,
     io::ErrorKind,
     net::{SocketAddr, TcpStream},
+    path::PathBuf,
     str::FromStr,
     sync::{mpsc, Arc},
     thread::{self, JoinHandle},
@@ -29,6 +30,11 @@ pub fn get_local_session_source() -> ServerSessionManager {
     ServerSessionManager::new(Some(session_file_path))
 }
 
+fn button_mapping_profiles_dir() -> PathBuf {
+    alvr_filesystem::filesystem_layout_from_dashboard_exe(&env::current_exe().unwrap())
+        .button_mapping_profiles_dir()
+}
+
 fn report_event_local(
     context: &egui::Context,
     sender: &mpsc::Sender<PolledEvent>,
@@ -55,7 +61,12 @@ fn report_session_local(
         context,
         sender,
         EventType::Session(Box::new(session_manager.session().clone())),
-    )
+    );
+    report_event_local(
+        context,
+        sender,
+        EventType::SettingsHistory(session_manager.settings_history()),
+    );
 }
 
 pub struct PolledEvent {
@@ -109,17 +120,35 @@ impl DataSources {
                                     report_session_local(&context, &events_sender, session_manager);
                                 }
                                 ServerRequest::UpdateSession(session) => {
-                                    *session_manager.session_mut() = *session;
+                                    *session_manager
+                                        .session_mut_from(SettingsChangeOrigin::Dashboard) =
+                                        *session;
 
                                     report_session_local(&context, &events_sender, session_manager);
                                 }
-                                ServerRequest::SetValues(descs) => {
-                                    if let Err(e) = session_manager.set_values(descs) {
+                                ServerRequest::SetValues(SetValuesRequest { origin, values }) => {
+                                    if let Err(e) = session_manager.set_values(values, origin) {
                                         error!("Failed to set session value: {e}")
                                     }
 
                                     report_session_local(&context, &events_sender, session_manager);
                                 }
+                                ServerRequest::GetSettingsHistory => {
+                                    report_event_local(
+                                        &context,
+                                        &events_sender,
+                                        EventType::SettingsHistory(
+                                            session_manager.settings_history(),
+                                        ),
+                                    );
+                                }
+                                ServerRequest::RollbackSettings(entry_id) => {
+                                    if let Err(e) = session_manager.rollback_settings(entry_id) {
+                                        error!("Failed to roll back settings: {e}")
+                                    }
+
+                                    report_session_local(&context, &events_sender, session_manager);
+                                }
                                 ServerRequest::UpdateClientList { hostname, action } => {
                                     session_manager.update_client_list(hostname, action);
 
@@ -179,10 +208,76 @@ impl DataSources {
                                         )
                                     }
                                 }
+                                ServerRequest::GetButtonMappingProfiles => report_event_local(
+                                    &context,
+                                    &events_sender,
+                                    EventType::ButtonMappingProfiles(
+                                        alvr_server_io::get_button_mapping_profiles(
+                                            &button_mapping_profiles_dir(),
+                                        ),
+                                    ),
+                                ),
+                                ServerRequest::ImportButtonMappingProfile(content) => {
+                                    let profiles_dir = button_mapping_profiles_dir();
+                                    if let Err(e) = alvr_server_io::import_button_mapping_profile(
+                                        &profiles_dir,
+                                        &content,
+                                    ) {
+                                        error!("Failed to import button mapping profile: {e}");
+                                    }
+
+                                    report_event_local(
+                                        &context,
+                                        &events_sender,
+                                        EventType::ButtonMappingProfiles(
+                                            alvr_server_io::get_button_mapping_profiles(
+                                                &profiles_dir,
+                                            ),
+                                        ),
+                                    )
+                                }
+                                ServerRequest::RemoveButtonMappingProfile(name) => {
+                                    let profiles_dir = button_mapping_profiles_dir();
+                                    if let Err(e) = alvr_server_io::remove_button_mapping_profile(
+                                        &profiles_dir,
+                                        &name,
+                                    ) {
+                                        error!("Failed to remove button mapping profile: {e}");
+                                    }
+
+                                    report_event_local(
+                                        &context,
+                                        &events_sender,
+                                        EventType::ButtonMappingProfiles(
+                                            alvr_server_io::get_button_mapping_profiles(
+                                                &profiles_dir,
+                                            ),
+                                        ),
+                                    )
+                                }
+                                ServerRequest::CreateSupportBundle { redact } => {
+                                    // Statistics are available only while streaming
+                                    match alvr_server_io::create_support_bundle(
+                                        &alvr_filesystem::filesystem_layout_from_dashboard_exe(
+                                            &env::current_exe().unwrap(),
+                                        ),
+                                        session_manager.session(),
+                                        &[],
+                                        redact,
+                                    ) {
+                                        Ok(path) => {
+                                            info!("Support bundle saved to {}", path.display())
+                                        }
+                                        Err(e) => {
+                                            error!("Failed to create the support bundle: {e}")
+                                        }
+                                    }
+                                }
                                 ServerRequest::CaptureFrame
                                 | ServerRequest::InsertIdr
                                 | ServerRequest::StartRecording
-                                | ServerRequest::StopRecording => {
+                                | ServerRequest::StopRecording
+                                | ServerRequest::RunConnectionDiagnostics(_) => {
                                     warn!("Cannot perform action, streamer (SteamVR) is not connected.")
                                 }
                                 ServerRequest::RestartSteamvr | ServerRequest::ShutdownSteamvr => {
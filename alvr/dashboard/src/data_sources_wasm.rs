@@ -4,6 +4,12 @@ use eframe::{egui, web_sys};
 use ewebsock::{WsEvent, WsMessage, WsReceiver};
 use gloo_net::http::Request;
 
+pub struct PolledEvent {
+    pub inner: Event,
+    // The browser dashboard has no local logging, every event comes from the streamer
+    pub from_dashboard: bool,
+}
+
 pub struct DataSources {
     context: egui::Context,
     ws_receiver: Option<WsReceiver>,
@@ -30,7 +36,7 @@ impl DataSources {
         })
     }
 
-    pub fn poll_event(&mut self) -> Option<Event> {
+    pub fn poll_event(&mut self) -> Option<PolledEvent> {
         if self.ws_receiver.is_none() {
             let host = web_sys::window().unwrap().location().host().unwrap();
             let Ok((_, receiver)) = ewebsock::connect(format!("ws://{host}/api/events")) else {
@@ -42,7 +48,12 @@ impl DataSources {
         if let Some(event) = self.ws_receiver.as_ref().unwrap().try_recv() {
             match event {
                 WsEvent::Message(WsMessage::Text(json_string)) => {
-                    serde_json::from_str(&json_string).ok()
+                    serde_json::from_str(&json_string)
+                        .ok()
+                        .map(|event| PolledEvent {
+                            inner: event,
+                            from_dashboard: false,
+                        })
                 }
                 WsEvent::Error(_) | WsEvent::Closed => {
                     // recreate the ws connection next poll_event invocation
@@ -1,5 +1,5 @@
 use crate::data_sources::PolledEvent;
-use alvr_common::{log::LevelFilter, parking_lot::Mutex, LogEntry, LogSeverity};
+use alvr_common::{log::LevelFilter, parking_lot::Mutex, LogEntry, LogSeverity, LogSource};
 use alvr_events::{Event, EventType};
 use std::{
     io::Write,
@@ -10,7 +10,6 @@ pub fn init_logging(event_sender: mpsc::Sender<PolledEvent>) {
     let event_sender = Arc::new(Mutex::new(event_sender));
 
     env_logger::Builder::new()
-        .filter(Some("alvr_events"), LevelFilter::Off)
         .filter(Some("naga"), LevelFilter::Off)
         .filter(Some("ureq"), LevelFilter::Off)
         .filter(Some("wgpu_core"), LevelFilter::Off)
@@ -28,10 +27,12 @@ pub fn init_logging(event_sender: mpsc::Sender<PolledEvent>) {
                 .send(PolledEvent {
                     inner: Event {
                         timestamp: timestamp.clone(),
-                        event_type: EventType::Log(LogEntry {
-                            severity: LogSeverity::from_log_level(record.level()),
-                            content: format!("{}", record.args()),
-                        }),
+                        event_type: EventType::Log(LogEntry::new(
+                            LogSeverity::from_log_level(record.level()),
+                            format!("{}", record.args()),
+                            LogSource::Dashboard,
+                            alvr_common::debug_group_from_target(record.target()).map(String::from),
+                        )),
                     },
                     from_dashboard: true,
                 })
@@ -126,7 +126,10 @@ fn main() {
             .start("dashboard_canvas", eframe::WebOptions::default(), {
                 Box::new(move |creation_context| {
                     let context = creation_context.egui_ctx.clone();
-                    Box::new(Dashboard::new(creation_context, DataSources::new(context)))
+                    Ok(Box::new(Dashboard::new(
+                        creation_context,
+                        DataSources::new(context),
+                    )))
                 })
             })
             .await
@@ -1,6 +1,8 @@
-use alvr_common::{info, DeviceMotion, LogEntry, Pose};
-use alvr_packets::{AudioDevicesList, ButtonValue};
-use alvr_session::SessionConfig;
+use alvr_common::{DeviceMotion, LogEntry, OptLazy, Pose};
+use alvr_packets::{
+    AudioDevicesList, ButtonValue, ConnectionPath, DiagnosticsReport, SettingsHistoryEntry,
+};
+use alvr_session::{ButtonMappingProfile, SessionConfig};
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, time::Duration};
 
@@ -20,6 +22,20 @@ pub struct StatisticsSummary {
     pub server_fps: u32,
     pub battery_hmd: u32,
     pub hmd_plugged: bool,
+    pub connection_path: ConnectionPath,
+    pub connection_path_rtt_ms: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SpectatorStatisticsSummary {
+    pub hostname: String,
+    pub video_packets_total: usize,
+    pub video_packets_per_sec: usize,
+    pub video_mbits_per_sec: f32,
+    pub video_packets_dropped_total: usize,
+    pub total_latency_ms: f32,
+    pub decode_latency_ms: f32,
+    pub client_fps: u32,
 }
 
 // Bitrate statistics minus the empirical output value
@@ -81,13 +97,18 @@ pub enum EventType {
     Log(LogEntry),
     Session(Box<SessionConfig>),
     StatisticsSummary(StatisticsSummary),
+    SpectatorStatistics(SpectatorStatisticsSummary),
     GraphStatistics(GraphStatistics),
     Tracking(Box<TrackingEvent>),
     Buttons(Vec<ButtonEvent>),
     Haptics(HapticsEvent),
     AudioDevices(AudioDevicesList),
     DriversList(Vec<PathBuf>),
+    ButtonMappingProfiles(Vec<ButtonMappingProfile>),
     ServerRequestsSelfRestart,
+    ConnectionDiagnostics(Box<DiagnosticsReport>),
+    SettingsHistory(Vec<SettingsHistoryEntry>),
+    SupportBundleCreated(PathBuf),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -96,6 +117,15 @@ pub struct Event {
     pub event_type: EventType,
 }
 
+static EVENT_LISTENER: OptLazy<Box<dyn Fn(EventType) + Send>> = alvr_common::lazy_mut_none();
+
+// Events are published independently from logging. Without a listener, events are dropped
+pub fn set_event_listener(listener: impl Fn(EventType) + Send + 'static) {
+    *EVENT_LISTENER.lock() = Some(Box::new(listener));
+}
+
 pub fn send_event(event_type: EventType) {
-    info!("{}", serde_json::to_string(&event_type).unwrap());
+    if let Some(listener) = &*EVENT_LISTENER.lock() {
+        listener(event_type);
+    }
 }
@@ -199,6 +199,10 @@ impl Layout {
         self.executables_dir.join(dashboard_fname())
     }
 
+    pub fn cli_exe(&self) -> PathBuf {
+        self.executables_dir.join(exec_fname("alvr_cli"))
+    }
+
     pub fn resources_dir(&self) -> PathBuf {
         self.openvr_driver_root_dir.join("resources")
     }
@@ -211,6 +215,11 @@ impl Layout {
         self.static_resources_dir.join("presets")
     }
 
+    // Profiles are imported at runtime, so they can't live with the static resources
+    pub fn button_mapping_profiles_dir(&self) -> PathBuf {
+        self.config_dir.join("button_mappings")
+    }
+
     pub fn session(&self) -> PathBuf {
         self.config_dir.join("session.json")
     }
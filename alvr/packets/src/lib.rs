@@ -3,13 +3,13 @@ use alvr_common::{
     glam::{UVec2, Vec2},
     ConnectionState, DeviceMotion, Fov, LogEntry, LogSeverity, Pose, ToAny,
 };
-use alvr_session::{CodecType, SessionConfig, Settings};
+use alvr_session::{ClientStreamingOverrides, CodecType, SessionConfig, Settings, SocketProtocol};
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::{self, Debug},
-    net::IpAddr,
+    net::{IpAddr, SocketAddr},
     path::PathBuf,
     time::Duration,
 };
@@ -19,6 +19,8 @@ pub const HAPTICS: u16 = 1;
 pub const AUDIO: u16 = 2;
 pub const VIDEO: u16 = 3;
 pub const STATISTICS: u16 = 4;
+pub const DIAGNOSTICS_PING: u16 = 5;
+pub const DIAGNOSTICS_DATA: u16 = 6;
 
 // todo: use simple string
 #[derive(Serialize, Deserialize, Clone)]
@@ -38,6 +40,7 @@ pub struct VideoStreamingCapabilities {
     pub encoder_high_profile: bool,
     pub encoder_10_bits: bool,
     pub encoder_av1: bool,
+    pub streaming_overrides: ClientStreamingOverrides,
 }
 
 // Nasty workaround to make the packet extensible, pushing the limits of protocol compatibility
@@ -92,6 +95,8 @@ pub fn decode_video_streaming_capabilities(
         encoder_high_profile: caps_json["encoder_high_profile"].as_bool().unwrap_or(true),
         encoder_10_bits: caps_json["encoder_10_bits"].as_bool().unwrap_or(true),
         encoder_av1: caps_json["encoder_av1"].as_bool().unwrap_or(true),
+        streaming_overrides: json::from_value(caps_json["streaming_overrides"].clone())
+            .unwrap_or_default(),
     })
 }
 
@@ -104,6 +109,10 @@ pub enum ClientConnectionResult {
         streaming_capabilities: Option<VideoStreamingCapabilitiesLegacy>, // todo: use String
     },
     ClientStandby,
+    // Reattach to a stream that was interrupted, using the token received with the stream start
+    SessionResume {
+        token: u64,
+    },
 }
 
 // Note: not a network packet
@@ -125,12 +134,44 @@ pub fn encode_stream_config(
     session: &SessionConfig,
     negotiated: &NegotiatedStreamingConfig,
 ) -> Result<StreamConfigPacket> {
+    // The client list contains the addresses and relay pairing tokens of the other clients
+    let session = SessionConfig {
+        client_connections: HashMap::new(),
+        ..session.clone()
+    };
+
     Ok(StreamConfigPacket {
-        session: json::to_string(session)?,
+        session: json::to_string(&session)?,
         negotiated: json::to_string(negotiated)?,
     })
 }
 
+// Sent in place of the stream config when the client cannot connect. It is stored as JSON inside
+// StreamConfigPacket, so clients of other versions can still read it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConnectionRejection {
+    pub reason: String,
+    pub server_version: String,
+    pub suggested_client_version: String,
+    pub download_hint: String,
+}
+
+pub fn encode_connection_rejection(rejection: &ConnectionRejection) -> Result<StreamConfigPacket> {
+    Ok(StreamConfigPacket {
+        session: String::new(),
+        negotiated: json::to_string(rejection)?,
+    })
+}
+
+// Returns None if the packet contains a valid stream config
+pub fn decode_connection_rejection(packet: &StreamConfigPacket) -> Option<ConnectionRejection> {
+    if packet.session.is_empty() {
+        json::from_str(&packet.negotiated).ok()
+    } else {
+        None
+    }
+}
+
 pub fn decode_stream_config(
     packet: &StreamConfigPacket,
 ) -> Result<(Settings, NegotiatedStreamingConfig)> {
@@ -159,6 +200,56 @@ pub fn decode_stream_config(
     ))
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ConnectionPath {
+    #[default]
+    Lan,
+    HolePunched,
+    Relayed,
+}
+
+// The streamer drives the connection diagnostics, the client follows the phases
+#[derive(Serialize, Deserialize, Clone)]
+pub enum DiagnosticsPhase {
+    Stream(SocketProtocol),
+    PacketSizes,
+    Finished,
+}
+
+// Measurements done on the receiving side
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ClientDiagnosticsResult {
+    pub received_packets: u32,
+    pub received_bytes: u64,
+    pub receive_duration: Duration,
+    pub received_probes: Vec<(u32, u32)>, // packet size, count
+    pub probe_tos: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProtocolDiagnostics {
+    pub protocol: SocketProtocol,
+    pub round_trip_time_ms: f32,
+    pub jitter_ms: f32,
+    pub throughput_mbps: f32,
+    pub packet_loss: f32, // range [0, 1]
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PacketSizeDiagnostics {
+    pub size: u32,
+    pub delivery_ratio: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiagnosticsReport {
+    pub hostname: String,
+    pub protocols: Vec<ProtocolDiagnostics>,
+    pub packet_sizes: Vec<PacketSizeDiagnostics>,
+    pub dscp_preserved: Option<bool>,
+    pub recommendations: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DecoderInitializationConfig {
     pub codec: CodecType,
@@ -174,6 +265,17 @@ pub enum ServerControlPacket {
     ServerPredictionAverage(Duration), // todo: remove
     Reserved(String),
     ReservedBuffer(Vec<u8>),
+    // Internet streaming handshake. The endpoint is the public address of the stream socket
+    InternetEndpoint(SocketAddr),
+    InternetPath {
+        path: ConnectionPath,
+        relay_session: u64,
+    },
+    ResumeToken(u64),
+    DiagnosticsPhase(DiagnosticsPhase),
+    DiagnosticsPhaseEnd,
+    // Secret used by the client to wait for the streamer on the relay
+    RelayPairingToken(String),
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -227,10 +329,24 @@ pub enum ClientControlPacket {
     Battery(BatteryInfo),
     VideoErrorReport, // legacy
     Buttons(Vec<ButtonEntry>),
-    ActiveInteractionProfile { device_id: u64, profile_id: u64 },
-    Log { level: LogSeverity, message: String },
+    ActiveInteractionProfile {
+        device_id: u64,
+        profile_id: u64,
+    },
+    Log {
+        level: LogSeverity,
+        message: String,
+        debug_group: Option<String>,
+    },
     Reserved(String),
     ReservedBuffer(Vec<u8>),
+    InternetEndpoint(SocketAddr),
+    HolePunchResult {
+        success: bool,
+        rendezvous_rtt: Duration,
+    },
+    DiagnosticsReady,
+    DiagnosticsResult(ClientDiagnosticsResult),
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -264,6 +380,32 @@ pub struct Haptics {
     pub amplitude: f32,
 }
 
+// Linear amplitude ramps at the start and end of a vibration pulse
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct HapticsEnvelope {
+    pub attack: Duration,
+    pub release: Duration,
+}
+
+// Amplitude samples in the range [0, 1]. If append is true, the buffer is queued after the one
+// currently playing, otherwise playback is restarted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PcmHaptics {
+    pub device_id: u64,
+    pub sample_rate: f32,
+    pub samples: Vec<f32>,
+    pub append: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum HapticsPacket {
+    Vibration {
+        haptics: Haptics,
+        envelope: HapticsEnvelope,
+    },
+    Pcm(PcmHaptics),
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AudioDevicesList {
     pub output: Vec<String>,
@@ -308,6 +450,32 @@ pub fn parse_path(path: &str) -> Vec<PathSegment> {
     path.split('.').map(|s| s.into()).collect()
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsChangeOrigin {
+    Dashboard,
+    WebApi,
+    Preset,
+    Rollback,
+    Server,
+    Cli,
+}
+
+// Change of a single settings value. The path starts from the session root, like for PathValuePair
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingsValueChange {
+    pub path: Vec<PathSegment>,
+    pub old_value: json::Value,
+    pub new_value: json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingsHistoryEntry {
+    pub id: u64,
+    pub timestamp_ms: u64, // since the UNIX epoch
+    pub origin: SettingsChangeOrigin,
+    pub changes: Vec<SettingsValueChange>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum ClientListAction {
     AddIfMissing {
@@ -320,6 +488,10 @@ pub enum ClientListAction {
     RemoveEntry,
     UpdateCurrentIp(Option<IpAddr>),
     SetConnectionState(ConnectionState),
+    SetSpectator(bool),
+    SetStreamingOverrides(ClientStreamingOverrides),
+    SetIncompatibility(Option<String>),
+    SetCabled(bool),
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
@@ -339,6 +511,39 @@ pub struct PathValuePair {
     pub value: json::Value,
 }
 
+// The origin is recorded in the settings history
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "SetValuesRequestCompat")]
+pub struct SetValuesRequest {
+    pub origin: SettingsChangeOrigin,
+    pub values: Vec<PathValuePair>,
+}
+
+// Web API clients can still send the values alone, like before the origin was added
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SetValuesRequestCompat {
+    Values(Vec<PathValuePair>),
+    WithOrigin {
+        origin: SettingsChangeOrigin,
+        values: Vec<PathValuePair>,
+    },
+}
+
+impl From<SetValuesRequestCompat> for SetValuesRequest {
+    fn from(request: SetValuesRequestCompat) -> Self {
+        match request {
+            SetValuesRequestCompat::Values(values) => SetValuesRequest {
+                origin: SettingsChangeOrigin::WebApi,
+                values,
+            },
+            SetValuesRequestCompat::WithOrigin { origin, values } => {
+                SetValuesRequest { origin, values }
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum FirewallRulesAction {
     Add,
@@ -350,7 +555,10 @@ pub enum ServerRequest {
     Log(LogEntry),
     GetSession,
     UpdateSession(Box<SessionConfig>),
-    SetValues(Vec<PathValuePair>),
+    SetValues(SetValuesRequest),
+    GetSettingsHistory,
+    // Restores the settings as they were before the change with this ID
+    RollbackSettings(u64),
     UpdateClientList {
         hostname: String,
         action: ClientListAction,
@@ -366,6 +574,16 @@ pub enum ServerRequest {
     GetDriverList,
     RestartSteamvr,
     ShutdownSteamvr,
+    GetButtonMappingProfiles,
+    // Content of a profile file. Profiles are exported from the ButtonMappingProfiles event
+    ImportButtonMappingProfile(String),
+    RemoveButtonMappingProfile(String),
+    RunConnectionDiagnostics(String),
+    // If redact is true, client hostnames, IPs, the home directory path and secrets (pairing
+    // tokens, webhook URLs, rendezvous and relay hosts) are removed from the bundle
+    CreateSupportBundle {
+        redact: bool,
+    },
 }
 
 // Per eye view parameters
@@ -375,3 +593,30 @@ pub struct ViewParams {
     pub pose: Pose,
     pub fov: Fov,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_values_request_compat() {
+        let ServerRequest::SetValues(request) = json::from_str(
+            r#"{"SetValues":[{"path":[{"Name":"session_settings"}],"value":true}]}"#,
+        )
+        .unwrap() else {
+            panic!("Expected a SetValues request");
+        };
+        assert_eq!(request.origin, SettingsChangeOrigin::WebApi);
+        assert_eq!(request.values.len(), 1);
+
+        let request_json = json::to_string(&ServerRequest::SetValues(SetValuesRequest {
+            origin: SettingsChangeOrigin::Cli,
+            values: vec![],
+        }))
+        .unwrap();
+        let ServerRequest::SetValues(request) = json::from_str(&request_json).unwrap() else {
+            panic!("Expected a SetValues request");
+        };
+        assert_eq!(request.origin, SettingsChangeOrigin::Cli);
+    }
+}
@@ -0,0 +1,33 @@
+// Lightweight rendezvous server for client discovery across subnets. Clients register to it and
+// the streamer queries the list of registered clients, then connects to them directly.
+// For internet streaming it also reports public addresses and relays the connections that cannot
+// be established directly. In that case it must be reachable from the internet on both the UDP and
+// TCP ports.
+// Usage: alvr_rendezvous [port]
+
+use alvr_common::anyhow::{Context, Result};
+use std::{thread, time::Duration};
+
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn main() -> Result<()> {
+    let port = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().context("Invalid port")?,
+        None => alvr_sockets::RENDEZVOUS_DEFAULT_PORT,
+    };
+
+    let mut server = alvr_sockets::RendezvousServer::new(port)?;
+    println!("ALVR rendezvous server listening on UDP and TCP port {port}");
+
+    thread::spawn(move || {
+        if let Err(e) = alvr_sockets::run_control_relay(port) {
+            eprintln!("Control relay error: {e}");
+        }
+    });
+
+    loop {
+        if let Err(e) = server.poll(POLL_TIMEOUT) {
+            eprintln!("Rendezvous error: {e}");
+        }
+    }
+}
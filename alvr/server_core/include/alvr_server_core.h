@@ -0,0 +1,256 @@
+/* ALVR is licensed under the MIT license. https://github.com/alvr-org/ALVR/blob/master/LICENSE */
+
+#pragma once
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Version of this API. It is incremented on every breaking change, like a change to the layout of
+// a type or to the signature of a function. Compare it with alvr_api_version() to check that the
+// header matches the library.
+#define ALVR_SERVER_API_VERSION 1
+
+// Number of joints of a hand skeleton, following the OpenXR XR_EXT_hand_tracking layout
+#define ALVR_HAND_SKELETON_JOINT_COUNT 26
+
+enum AlvrCodecType
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+    ALVR_CODEC_TYPE_H264 = 0,
+    ALVR_CODEC_TYPE_HEVC = 1,
+    ALVR_CODEC_TYPE_AV1 = 2,
+};
+#ifndef __cplusplus
+typedef uint8_t AlvrCodecType;
+#endif // __cplusplus
+
+typedef struct AlvrFov {
+    // Negative, radians
+    float left;
+    // Positive, radians
+    float right;
+    // Positive, radians
+    float up;
+    // Negative, radians
+    float down;
+} AlvrFov;
+
+typedef struct AlvrQuat {
+    float x;
+    float y;
+    float z;
+    float w;
+} AlvrQuat;
+
+typedef struct AlvrPose {
+    AlvrQuat orientation;
+    // Meters
+    float position[3];
+} AlvrPose;
+
+typedef struct AlvrSpaceRelation {
+    AlvrPose pose;
+    // Meters per second
+    float linear_velocity[3];
+    // Radians per second
+    float angular_velocity[3];
+} AlvrSpaceRelation;
+
+typedef struct AlvrJoint {
+    AlvrSpaceRelation relation;
+    // Meters
+    float radius;
+} AlvrJoint;
+
+// Which field is valid depends on the button, it is implied by the button ID. The fields were
+// named scalar (bool) and float before the API was versioned, they are not reused to avoid silent
+// type changes
+typedef union AlvrButtonValue {
+    bool binary;
+    float analog;
+} AlvrButtonValue;
+
+// The interaction profile is implied
+typedef struct AlvrButtonEntry {
+    // Obtained with alvr_path_to_id()
+    uint64_t id;
+    AlvrButtonValue value;
+} AlvrButtonEntry;
+
+typedef struct AlvrBatteryInfo {
+    uint64_t device_id;
+    // range [0, 1]
+    float gauge_value;
+    bool is_plugged;
+} AlvrBatteryInfo;
+
+// Events returned by alvr_poll_event()
+enum AlvrEvent_Tag
+#ifdef __cplusplus
+  : uint8_t
+#endif // __cplusplus
+ {
+    ALVR_EVENT_CLIENT_CONNECTED,
+    ALVR_EVENT_CLIENT_DISCONNECTED,
+    ALVR_EVENT_BATTERY,
+    // Playspace width and length, meters
+    ALVR_EVENT_PLAYSPACE_SYNC,
+    ALVR_EVENT_VIEWS_CONFIG,
+    // Poses and skeletons are available with alvr_get_device_relation() and
+    // alvr_get_hand_skeleton() until alvr_advance_tracking_queue() is called
+    ALVR_EVENT_TRACKING_UPDATED,
+    // Buttons are available with alvr_get_buttons()
+    ALVR_EVENT_BUTTONS_UPDATED,
+    ALVR_EVENT_REQUEST_IDR,
+    ALVR_EVENT_CAPTURE_FRAME,
+    ALVR_EVENT_RESTART_PENDING,
+    ALVR_EVENT_SHUTDOWN_PENDING,
+};
+#ifndef __cplusplus
+typedef uint8_t AlvrEvent_Tag;
+#endif // __cplusplus
+
+typedef struct AlvrEvent_ViewsConfig_Body {
+    AlvrEvent_Tag tag;
+    // Eye poses relative to the head
+    AlvrPose local_view_transform[2];
+    AlvrFov fov[2];
+} AlvrEvent_ViewsConfig_Body;
+
+typedef struct AlvrEvent_TrackingUpdated_Body {
+    AlvrEvent_Tag tag;
+    uint64_t target_timestamp_ns;
+} AlvrEvent_TrackingUpdated_Body;
+
+typedef union AlvrEvent {
+    AlvrEvent_Tag tag;
+    struct {
+        AlvrEvent_Tag battery_tag;
+        AlvrBatteryInfo battery;
+    };
+    struct {
+        AlvrEvent_Tag playspace_sync_tag;
+        float playspace_sync[2];
+    };
+    AlvrEvent_ViewsConfig_Body views_config;
+    AlvrEvent_TrackingUpdated_Body tracking_updated;
+} AlvrEvent;
+
+// Resolutions in pixels, per eye
+typedef struct AlvrTargetConfig {
+    uint32_t game_render_width;
+    uint32_t game_render_height;
+    uint32_t stream_width;
+    uint32_t stream_height;
+} AlvrTargetConfig;
+
+typedef struct AlvrDynamicEncoderParams {
+    uint64_t bitrate_bps;
+    float framerate;
+} AlvrDynamicEncoderParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns ALVR_SERVER_API_VERSION of the library. If it differs from the one of the header in use,
+// the library must not be used.
+uint32_t alvr_api_version(void);
+
+// Get ALVR server time. The libalvr user should provide timestamps in the provided time frame of
+// reference in the following functions
+uint64_t alvr_get_time_ns(void);
+
+// The libalvr user is responsible of interpreting values and calling functions using
+// device/input/output identifiers obtained using this function
+uint64_t alvr_path_to_id(const char *path_string);
+
+void alvr_log_error(const char *string_ptr);
+
+void alvr_log_warn(const char *string_ptr);
+
+void alvr_log_info(const char *string_ptr);
+
+void alvr_log_debug(const char *string_ptr);
+
+void alvr_dbg_server_impl(const char *string_ptr);
+
+void alvr_dbg_encoder(const char *string_ptr);
+
+// Should not be used in production
+void alvr_log_periodically(const char *tag_ptr, const char *message_ptr);
+
+// Call with null buffer to get the string length, including the null terminator. Then call again
+// with a buffer of at least that length to get the settings JSON.
+uint64_t alvr_get_settings_json(char *buffer);
+
+// This must be called before alvr_initialize()
+void alvr_initialize_environment(const char *config_dir, const char *log_dir);
+
+// Either session_log_path or crash_log_path can be null, in which case log is outputted to
+// stdout/stderr on Windows.
+void alvr_initialize_logging(const char *session_log_path, const char *crash_log_path);
+
+// Starts the server core. Call alvr_start_connection() after setting up the graphics and video
+// pipeline to begin accepting clients.
+AlvrTargetConfig alvr_initialize(void);
+
+void alvr_start_connection(void);
+
+// Returns false if no event was received within the timeout
+bool alvr_poll_event(AlvrEvent *out_event, uint64_t timeout_ns);
+
+// Returns false if current tracking frame has no relation for the requested device or there is no
+// tracking frame
+bool alvr_get_device_relation(uint64_t device_id, AlvrSpaceRelation *out_tracking);
+
+// out_skeleton must be an array of length ALVR_HAND_SKELETON_JOINT_COUNT
+// Returns false if current tracking frame has no data for the requested device or there is no
+// tracking frame
+bool alvr_get_hand_skeleton(uint64_t device_id, AlvrJoint *out_skeleton);
+
+void alvr_advance_tracking_queue(void);
+
+// Call with null out_entries to get the buffer length
+// call with non-null out_entries to get the buttons and advanced the internal queue
+uint64_t alvr_get_buttons(AlvrButtonEntry *out_entries);
+
+void alvr_send_haptics(uint64_t device_id, float duration_s, float frequency, float amplitude);
+
+// samples_ptr: amplitude samples in the range [0, 1]
+// append: if true, queue the buffer after the one currently playing
+void alvr_send_haptics_pcm(uint64_t device_id,
+                           float sample_rate,
+                           const float *samples_ptr,
+                           uint64_t samples_count,
+                           bool append);
+
+// Configuration NALs (SPS, PPS, VPS) must be set before sending the first IDR frame
+void alvr_set_video_config_nals(AlvrCodecType codec, const uint8_t *buffer_ptr, int32_t len);
+
+void alvr_send_video_nal(uint64_t timestamp_ns, uint8_t *buffer_ptr, int32_t len, bool is_idr);
+
+// Returns true if updated
+bool alvr_get_dynamic_encoder_params(AlvrDynamicEncoderParams *out_params);
+
+void alvr_report_composed(uint64_t timestamp_ns, uint64_t offset_ns);
+
+void alvr_report_present(uint64_t timestamp_ns, uint64_t offset_ns);
+
+// Retrun true if a valid value is provided
+bool alvr_duration_until_next_vsync(uint64_t *out_ns);
+
+// Shuts down the server core and requests the dashboard to relaunch SteamVR
+void alvr_restart(void);
+
+void alvr_shutdown(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
@@ -10,6 +10,33 @@ use std::{
 
 const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
 
+// The cap chosen on the headset can only lower the bitrate inside the range allowed by the streamer
+// settings. Returns None if the cap would have no effect or would go below the minimum bitrate.
+pub fn applicable_client_max_bitrate(
+    config: &BitrateConfig,
+    client_max_bitrate_mbps: Option<f32>,
+) -> Option<f32> {
+    let (min_mbps, max_mbps) = match &config.mode {
+        BitrateMode::ConstantMbps(bitrate_mbps) => (0.0, *bitrate_mbps as f32),
+        BitrateMode::Adaptive {
+            max_bitrate_mbps,
+            min_bitrate_mbps,
+            ..
+        } => (
+            min_bitrate_mbps
+                .as_option()
+                .map(|mbps| *mbps as f32)
+                .unwrap_or(0.0),
+            max_bitrate_mbps
+                .as_option()
+                .map(|mbps| *mbps as f32)
+                .unwrap_or(f32::MAX),
+        ),
+    };
+
+    client_max_bitrate_mbps.filter(|mbps| *mbps > 0.0 && (min_mbps..max_mbps).contains(mbps))
+}
+
 pub struct DynamicEncoderParams {
     pub bitrate_bps: u64,
     pub framerate: f32,
@@ -28,6 +55,7 @@ pub struct BitrateManager {
     last_frame_instant: Instant,
     last_update_instant: Instant,
     dynamic_max_bitrate: f32,
+    client_max_bitrate: Option<f32>,
     previous_config: Option<BitrateConfig>,
     update_needed: bool,
 }
@@ -54,6 +82,7 @@ impl BitrateManager {
             last_frame_instant: Instant::now(),
             last_update_instant: Instant::now(),
             dynamic_max_bitrate: f32::MAX,
+            client_max_bitrate: None,
             previous_config: None,
             update_needed: true,
         }
@@ -146,6 +175,12 @@ impl BitrateManager {
         }
     }
 
+    // Limit requested by the headset, applied on top of the bitrate settings
+    pub fn set_client_max_bitrate(&mut self, max_bitrate_mbps: Option<f32>) {
+        self.client_max_bitrate = max_bitrate_mbps.map(|mbps| mbps * 1e6);
+        self.update_needed = true;
+    }
+
     pub fn get_encoder_params(
         &mut self,
         config: &BitrateConfig,
@@ -173,7 +208,12 @@ impl BitrateManager {
         let mut stats = NominalBitrateStats::default();
 
         let bitrate_bps = match &config.mode {
-            BitrateMode::ConstantMbps(bitrate_mbps) => *bitrate_mbps as f32 * 1e6,
+            BitrateMode::ConstantMbps(bitrate_mbps) => {
+                let bitrate_bps = *bitrate_mbps as f32 * 1e6;
+
+                self.client_max_bitrate
+                    .map_or(bitrate_bps, |max| f32::min(bitrate_bps, max))
+            }
             BitrateMode::Adaptive {
                 saturation_multiplier,
                 max_bitrate_mbps,
@@ -218,6 +258,10 @@ impl BitrateManager {
 
                     stats.manual_max_bps = Some(max);
                 }
+                // The settings may have changed since the cap was checked, the minimum still wins
+                if let Some(max) = self.client_max_bitrate {
+                    bitrate_bps = f32::min(bitrate_bps, max);
+                }
                 if let Switch::Enabled(min) = min_bitrate_mbps {
                     let min = *min as f32 * 1e6;
                     bitrate_bps = f32::max(bitrate_bps, min);
@@ -246,3 +290,80 @@ impl BitrateManager {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_session::{BitrateModeDefaultVariant, SessionConfig};
+
+    fn adaptive_config(min_mbps: Option<u64>, max_mbps: Option<u64>) -> BitrateConfig {
+        let mut session = SessionConfig::default();
+        session.session_settings.video.bitrate.mode.variant = BitrateModeDefaultVariant::Adaptive;
+
+        let mut config = session.to_settings().video.bitrate;
+        let BitrateMode::Adaptive {
+            max_bitrate_mbps,
+            min_bitrate_mbps,
+            ..
+        } = &mut config.mode
+        else {
+            unreachable!()
+        };
+        *min_bitrate_mbps = min_mbps.map_or(Switch::Disabled, Switch::Enabled);
+        *max_bitrate_mbps = max_mbps.map_or(Switch::Disabled, Switch::Enabled);
+
+        config
+    }
+
+    #[test]
+    fn test_client_max_bitrate_in_range() {
+        let config = adaptive_config(Some(10), Some(200));
+
+        assert_eq!(
+            applicable_client_max_bitrate(&config, Some(50.0)),
+            Some(50.0)
+        );
+        assert_eq!(
+            applicable_client_max_bitrate(&config, Some(10.0)),
+            Some(10.0)
+        );
+        assert_eq!(applicable_client_max_bitrate(&config, None), None);
+    }
+
+    #[test]
+    fn test_client_max_bitrate_out_of_range() {
+        let config = adaptive_config(Some(10), Some(200));
+
+        // No effect above the streamer maximum
+        assert_eq!(applicable_client_max_bitrate(&config, Some(200.0)), None);
+        assert_eq!(applicable_client_max_bitrate(&config, Some(500.0)), None);
+        // The streamer minimum takes precedence
+        assert_eq!(applicable_client_max_bitrate(&config, Some(5.0)), None);
+        assert_eq!(applicable_client_max_bitrate(&config, Some(0.0)), None);
+    }
+
+    #[test]
+    fn test_client_max_bitrate_unbounded() {
+        let config = adaptive_config(None, None);
+
+        assert_eq!(applicable_client_max_bitrate(&config, Some(1.0)), Some(1.0));
+        assert_eq!(
+            applicable_client_max_bitrate(&config, Some(5000.0)),
+            Some(5000.0)
+        );
+    }
+
+    #[test]
+    fn test_client_max_bitrate_constant() {
+        let config = BitrateConfig {
+            mode: BitrateMode::ConstantMbps(30),
+            ..adaptive_config(None, None)
+        };
+
+        assert_eq!(
+            applicable_client_max_bitrate(&config, Some(20.0)),
+            Some(20.0)
+        );
+        assert_eq!(applicable_client_max_bitrate(&config, Some(30.0)), None);
+    }
+}
@@ -1,13 +1,15 @@
 use alvr_common::{
-    anyhow::Result, once_cell::sync::Lazy, DeviceMotion, BODY_CHEST_ID, BODY_HIPS_ID,
-    BODY_LEFT_ELBOW_ID, BODY_LEFT_FOOT_ID, BODY_LEFT_KNEE_ID, BODY_RIGHT_ELBOW_ID,
+    anyhow::Result, glam::EulerRot, once_cell::sync::Lazy, DeviceMotion, BODY_CHEST_ID,
+    BODY_HIPS_ID, BODY_LEFT_ELBOW_ID, BODY_LEFT_FOOT_ID, BODY_LEFT_KNEE_ID, BODY_RIGHT_ELBOW_ID,
     BODY_RIGHT_FOOT_ID, BODY_RIGHT_KNEE_ID, HEAD_ID,
 };
-use alvr_session::BodyTrackingSinkConfig;
+use alvr_session::{BodyTrackingSinkConfig, OscCoordinateConvention};
 use rosc::{OscMessage, OscPacket, OscType};
-use std::{collections::HashMap, net::UdpSocket};
+use std::{collections::HashMap, f32::consts::PI, net::UdpSocket};
 
-use crate::tracking::TrackingManager;
+use crate::{osc_output, tracking::TrackingManager};
+
+const RAD_TO_DEG: f32 = 180.0 / PI;
 
 static BODY_TRACKER_OSC_PATH_MAP: Lazy<HashMap<u64, &'static str>> = Lazy::new(|| {
     HashMap::from([
@@ -69,20 +71,28 @@ impl BodyTrackingSink {
         match self.config {
             BodyTrackingSinkConfig::VrchatBodyOsc { .. } => {
                 for (id, motion) in device_motions {
-                    if BODY_TRACKER_OSC_PATH_MAP.contains_key(id) {
-                        // Only do position because rotation isn't quite right
-                        let pose = tracking_manager.recenter_pose(motion.pose);
+                    if let Some(path) = BODY_TRACKER_OSC_PATH_MAP.get(id) {
+                        let pose = osc_output::convert_pose(
+                            tracking_manager.recenter_pose(motion.pose),
+                            OscCoordinateConvention::Unity,
+                        );
+                        self.send_osc_message(
+                            &format!("{path}position"),
+                            pose.position
+                                .to_array()
+                                .into_iter()
+                                .map(OscType::Float)
+                                .collect(),
+                        );
+
+                        // VRChat expects Unity euler angles, applied in Z, X, Y order
+                        let (yaw, pitch, roll) = pose.orientation.to_euler(EulerRot::YXZ);
                         self.send_osc_message(
-                            format!(
-                                "{}{}",
-                                BODY_TRACKER_OSC_PATH_MAP.get(id).unwrap(),
-                                "position"
-                            )
-                            .as_str(),
+                            &format!("{path}rotation"),
                             vec![
-                                OscType::Float(pose.position.x),
-                                OscType::Float(pose.position.y),
-                                OscType::Float(-pose.position.z),
+                                OscType::Float(pitch * RAD_TO_DEG),
+                                OscType::Float(yaw * RAD_TO_DEG),
+                                OscType::Float(roll * RAD_TO_DEG),
                             ],
                         );
                     }
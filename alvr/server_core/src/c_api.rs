@@ -8,7 +8,7 @@ use alvr_common::{
     parking_lot::{Mutex, RwLock},
     Fov, Pose, HAND_LEFT_ID, HAND_RIGHT_ID,
 };
-use alvr_packets::{ButtonEntry, ButtonValue, Haptics, Tracking};
+use alvr_packets::{ButtonEntry, ButtonValue, Haptics, PcmHaptics, Tracking};
 use alvr_session::CodecType;
 use std::{
     collections::{HashMap, VecDeque},
@@ -28,6 +28,14 @@ static TRACKING_QUEUE: Lazy<Mutex<VecDeque<Tracking>>> = Lazy::new(|| Mutex::new
 static BUTTONS_QUEUE: Lazy<Mutex<VecDeque<Vec<ButtonEntry>>>> =
     Lazy::new(|| Mutex::new(VecDeque::new()));
 
+/// Version of this API. It is incremented on every breaking change, like a change to the layout of
+/// a type or to the signature of a function. Compare it with alvr_api_version() to check that the
+/// header matches the library.
+pub const ALVR_SERVER_API_VERSION: u32 = 1;
+
+/// Number of joints of a hand skeleton, following the OpenXR XR_EXT_hand_tracking layout
+pub const ALVR_HAND_SKELETON_JOINT_COUNT: usize = 26;
+
 #[repr(C)]
 pub struct AlvrFov {
     /// Negative, radians
@@ -68,39 +76,40 @@ pub enum AlvrCodecType {
 
 #[repr(C)]
 pub struct AlvrPose {
-    orientation: AlvrQuat,
-    position: [f32; 3],
+    pub orientation: AlvrQuat,
+    /// Meters
+    pub position: [f32; 3],
 }
 
 #[repr(C)]
 pub struct AlvrSpaceRelation {
     pub pose: AlvrPose,
+    /// Meters per second
     pub linear_velocity: [f32; 3],
+    /// Radians per second
     pub angular_velocity: [f32; 3],
 }
 
 #[repr(C)]
 pub struct AlvrJoint {
-    relation: AlvrSpaceRelation,
-    radius: f32,
+    pub relation: AlvrSpaceRelation,
+    /// Meters
+    pub radius: f32,
 }
 
-// #[repr(C)]
-// pub struct AlvrJointSet {
-//     values: [AlvrJoint; 26],
-//     global_hand_relation: AlvrSpaceRelation,
-//     is_active: bool,
-// }
-
+/// Which field is valid depends on the button, it is implied by the button ID. The fields were
+/// named scalar (bool) and float before the API was versioned, they are not reused to avoid silent
+/// type changes
 #[repr(C)]
 pub union AlvrButtonValue {
-    pub scalar: bool,
-    pub float: f32,
+    pub binary: bool,
+    pub analog: f32,
 }
 
-// the profile is implied
+/// The interaction profile is implied
 #[repr(C)]
 pub struct AlvrButtonEntry {
+    /// Obtained with alvr_path_to_id()
     pub id: u64,
     pub value: AlvrButtonValue,
 }
@@ -113,19 +122,25 @@ pub struct AlvrBatteryInfo {
     pub is_plugged: bool,
 }
 
+/// Events returned by alvr_poll_event()
 #[repr(u8)]
 pub enum AlvrEvent {
     ClientConnected,
     ClientDisconnected,
     Battery(AlvrBatteryInfo),
+    /// Playspace width and length, meters
     PlayspaceSync([f32; 2]),
     ViewsConfig {
+        /// Eye poses relative to the head
         local_view_transform: [AlvrPose; 2],
         fov: [AlvrFov; 2],
     },
+    /// Poses and skeletons are available with alvr_get_device_relation() and
+    /// alvr_get_hand_skeleton() until alvr_advance_tracking_queue() is called
     TrackingUpdated {
         target_timestamp_ns: u64,
     },
+    /// Buttons are available with alvr_get_buttons()
     ButtonsUpdated,
     RequestIDR,
     CaptureFrame,
@@ -133,24 +148,25 @@ pub enum AlvrEvent {
     ShutdownPending,
 }
 
+/// Resolutions in pixels, per eye
 #[repr(C)]
 pub struct AlvrTargetConfig {
-    game_render_width: u32,
-    game_render_height: u32,
-    stream_width: u32,
-    stream_height: u32,
+    pub game_render_width: u32,
+    pub game_render_height: u32,
+    pub stream_width: u32,
+    pub stream_height: u32,
 }
 
 #[repr(C)]
 pub struct AlvrDeviceConfig {
-    device_id: u64,
-    interaction_profile_id: u64,
+    pub device_id: u64,
+    pub interaction_profile_id: u64,
 }
 
 #[repr(C)]
 pub struct AlvrDynamicEncoderParams {
-    bitrate_bps: u64,
-    framerate: f32,
+    pub bitrate_bps: u64,
+    pub framerate: f32,
 }
 
 fn pose_to_capi(pose: &Pose) -> AlvrPose {
@@ -185,15 +201,22 @@ fn string_to_c_str(buffer: *mut c_char, value: &str) -> u64 {
     cstring.as_bytes_with_nul().len() as u64
 }
 
-// Get ALVR server time. The libalvr user should provide timestamps in the provided time frame of
-// reference in the following functions
+/// Returns ALVR_SERVER_API_VERSION of the library. If it differs from the one of the header in use,
+/// the library must not be used.
+#[no_mangle]
+pub extern "C" fn alvr_api_version() -> u32 {
+    ALVR_SERVER_API_VERSION
+}
+
+/// Get ALVR server time. The libalvr user should provide timestamps in the provided time frame of
+/// reference in the following functions
 #[no_mangle]
 pub unsafe extern "C" fn alvr_get_time_ns() -> u64 {
     Instant::now().elapsed().as_nanos() as u64
 }
 
-// The libalvr user is responsible of interpreting values and calling functions using
-// device/input/output identifiers obtained using this function
+/// The libalvr user is responsible of interpreting values and calling functions using
+/// device/input/output identifiers obtained using this function
 #[no_mangle]
 pub unsafe extern "C" fn alvr_path_to_id(path_string: *const c_char) -> u64 {
     alvr_common::hash_string(CStr::from_ptr(path_string).to_str().unwrap())
@@ -201,11 +224,19 @@ pub unsafe extern "C" fn alvr_path_to_id(path_string: *const c_char) -> u64 {
 
 #[no_mangle]
 pub unsafe extern "C" fn alvr_log_error(string_ptr: *const c_char) {
-    alvr_common::show_e(CStr::from_ptr(string_ptr).to_string_lossy());
+    alvr_common::show_e_with_target(
+        alvr_common::DRIVER_LOG_TARGET,
+        CStr::from_ptr(string_ptr).to_string_lossy(),
+    );
 }
 
 pub unsafe fn log(level: log::Level, string_ptr: *const c_char) {
-    log::log!(level, "{}", CStr::from_ptr(string_ptr).to_string_lossy());
+    log::log!(
+        target: alvr_common::DRIVER_LOG_TARGET,
+        level,
+        "{}",
+        CStr::from_ptr(string_ptr).to_string_lossy()
+    );
 }
 
 #[no_mangle]
@@ -233,7 +264,7 @@ pub unsafe extern "C" fn alvr_dbg_encoder(string_ptr: *const c_char) {
     alvr_common::dbg_encoder!("{}", CStr::from_ptr(string_ptr).to_str().unwrap());
 }
 
-// Should not be used in production
+/// Should not be used in production
 #[no_mangle]
 pub unsafe extern "C" fn alvr_log_periodically(tag_ptr: *const c_char, message_ptr: *const c_char) {
     const INTERVAL: Duration = Duration::from_secs(1);
@@ -250,10 +281,12 @@ pub unsafe extern "C" fn alvr_log_periodically(tag_ptr: *const c_char, message_p
     if *old_timestamp + INTERVAL < Instant::now() {
         *old_timestamp += INTERVAL;
 
-        log::warn!("{}: {}", tag, message);
+        log::warn!(target: alvr_common::DRIVER_LOG_TARGET, "{}: {}", tag, message);
     }
 }
 
+/// Call with null buffer to get the string length, including the null terminator. Then call again
+/// with a buffer of at least that length to get the settings JSON.
 #[no_mangle]
 pub extern "C" fn alvr_get_settings_json(buffer: *mut c_char) -> u64 {
     string_to_c_str(buffer, &serde_json::to_string(&crate::settings()).unwrap())
@@ -290,6 +323,8 @@ pub unsafe extern "C" fn alvr_initialize_logging(
     logging_backend::init_logging(session_log_path, crash_log_path);
 }
 
+/// Starts the server core. Call alvr_start_connection() after setting up the graphics and video
+/// pipeline to begin accepting clients.
 #[no_mangle]
 pub unsafe extern "C" fn alvr_initialize() -> AlvrTargetConfig {
     let (context, receiver) = ServerCoreContext::new();
@@ -314,6 +349,7 @@ pub unsafe extern "C" fn alvr_start_connection() {
     }
 }
 
+/// Returns false if no event was received within the timeout
 #[no_mangle]
 pub unsafe extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent, timeout_ns: u64) -> bool {
     if let Some(receiver) = &*EVENTS_RECEIVER.lock() {
@@ -403,7 +439,7 @@ pub unsafe extern "C" fn alvr_get_device_relation(
     }
 }
 
-/// out_skeleton must be an array of length 26
+/// out_skeleton must be an array of length ALVR_HAND_SKELETON_JOINT_COUNT
 /// Returns false if current tracking frame has no data for the requested device or there is no
 /// tracking frame
 #[no_mangle]
@@ -459,8 +495,8 @@ pub unsafe extern "C" fn alvr_get_buttons(out_entries: *mut AlvrButtonEntry) ->
             let out_entry = &mut (*out_entries.add(i));
             out_entry.id = entry.path_id;
             match entry.value {
-                ButtonValue::Binary(value) => out_entry.value.scalar = value,
-                ButtonValue::Scalar(value) => out_entry.value.float = value,
+                ButtonValue::Binary(value) => out_entry.value.binary = value,
+                ButtonValue::Scalar(value) => out_entry.value.analog = value,
             }
         }
 
@@ -487,6 +523,32 @@ pub extern "C" fn alvr_send_haptics(
     }
 }
 
+/// samples_ptr: amplitude samples in the range [0, 1]
+/// append: if true, queue the buffer after the one currently playing
+#[no_mangle]
+pub unsafe extern "C" fn alvr_send_haptics_pcm(
+    device_id: u64,
+    sample_rate: f32,
+    samples_ptr: *const f32,
+    samples_count: u64,
+    append: bool,
+) {
+    if samples_ptr.is_null() || sample_rate <= 0.0 {
+        return;
+    }
+
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        let samples = std::slice::from_raw_parts(samples_ptr, samples_count as usize);
+        context.send_haptics_pcm(PcmHaptics {
+            device_id,
+            sample_rate,
+            samples: samples.to_vec(),
+            append,
+        });
+    }
+}
+
+/// Configuration NALs (SPS, PPS, VPS) must be set before sending the first IDR frame
 #[no_mangle]
 pub unsafe extern "C" fn alvr_set_video_config_nals(
     codec: AlvrCodecType,
@@ -575,6 +637,7 @@ pub unsafe extern "C" fn alvr_duration_until_next_vsync(out_ns: *mut u64) -> boo
     }
 }
 
+/// Shuts down the server core and requests the dashboard to relaunch SteamVR
 #[no_mangle]
 pub unsafe extern "C" fn alvr_restart() {
     if let Some(context) = SERVER_CORE_CONTEXT.write().take() {
@@ -586,3 +649,221 @@ pub unsafe extern "C" fn alvr_restart() {
 pub unsafe extern "C" fn alvr_shutdown() {
     SERVER_CORE_CONTEXT.write().take();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{align_of, size_of};
+
+    // Changing any of these values breaks the ABI and requires bumping ALVR_SERVER_API_VERSION
+    #[test]
+    fn layout() {
+        assert_eq!((size_of::<AlvrFov>(), align_of::<AlvrFov>()), (16, 4));
+        assert_eq!((size_of::<AlvrQuat>(), align_of::<AlvrQuat>()), (16, 4));
+        assert_eq!(
+            (size_of::<AlvrCodecType>(), align_of::<AlvrCodecType>()),
+            (1, 1)
+        );
+        assert_eq!((size_of::<AlvrPose>(), align_of::<AlvrPose>()), (28, 4));
+        assert_eq!(
+            (
+                size_of::<AlvrSpaceRelation>(),
+                align_of::<AlvrSpaceRelation>()
+            ),
+            (52, 4)
+        );
+        assert_eq!((size_of::<AlvrJoint>(), align_of::<AlvrJoint>()), (56, 4));
+        assert_eq!(
+            (size_of::<AlvrButtonValue>(), align_of::<AlvrButtonValue>()),
+            (4, 4)
+        );
+        assert_eq!(
+            (size_of::<AlvrButtonEntry>(), align_of::<AlvrButtonEntry>()),
+            (16, 8)
+        );
+        assert_eq!(
+            (size_of::<AlvrBatteryInfo>(), align_of::<AlvrBatteryInfo>()),
+            (16, 8)
+        );
+        assert_eq!((size_of::<AlvrEvent>(), align_of::<AlvrEvent>()), (96, 8));
+        assert_eq!(
+            (
+                size_of::<AlvrTargetConfig>(),
+                align_of::<AlvrTargetConfig>()
+            ),
+            (16, 4)
+        );
+        assert_eq!(
+            (
+                size_of::<AlvrDeviceConfig>(),
+                align_of::<AlvrDeviceConfig>()
+            ),
+            (16, 8)
+        );
+        assert_eq!(
+            (
+                size_of::<AlvrDynamicEncoderParams>(),
+                align_of::<AlvrDynamicEncoderParams>()
+            ),
+            (16, 8)
+        );
+    }
+
+    // The checked in header must be regenerated when the API changes
+    #[test]
+    fn header_version() {
+        let header = include_str!("../include/alvr_server_core.h");
+
+        assert!(header.contains(&format!(
+            "#define ALVR_SERVER_API_VERSION {ALVR_SERVER_API_VERSION}\n"
+        )));
+    }
+
+    fn c_type(rust_type: &str) -> String {
+        match rust_type {
+            "bool" => "bool".into(),
+            "f32" => "float".into(),
+            "f64" => "double".into(),
+            "u8" => "uint8_t".into(),
+            "u32" => "uint32_t".into(),
+            "u64" => "uint64_t".into(),
+            "i32" => "int32_t".into(),
+            "i64" => "int64_t".into(),
+            _ => {
+                if let Some(pointee) = rust_type.strip_prefix("*const ") {
+                    format!("const {} *", c_type(pointee))
+                } else if let Some(pointee) = rust_type.strip_prefix("*mut ") {
+                    format!("{} *", c_type(pointee))
+                } else {
+                    rust_type.into()
+                }
+            }
+        }
+    }
+
+    // Name, keyword (struct or union) and C field declarations of the #[repr(C)] types
+    fn rust_declarations(source: &str) -> Vec<(String, String, Vec<String>)> {
+        let mut declarations = vec![];
+        let mut lines = source.lines().map(str::trim);
+        while let Some(line) = lines.next() {
+            if line != "#[repr(C)]" {
+                continue;
+            }
+
+            let Some(definition) = lines.find(|line| !line.starts_with("#[")) else {
+                break;
+            };
+            let Some((keyword, name)) = definition
+                .strip_prefix("pub ")
+                .and_then(|definition| definition.strip_suffix(" {"))
+                .and_then(|definition| definition.split_once(' '))
+            else {
+                continue;
+            };
+
+            let fields = lines
+                .by_ref()
+                .take_while(|line| *line != "}")
+                .filter(|line| !line.starts_with("///"))
+                .map(|line| {
+                    let (field_name, field_type) = line
+                        .trim_start_matches("pub ")
+                        .trim_end_matches(',')
+                        .split_once(": ")
+                        .unwrap();
+
+                    match field_type
+                        .strip_prefix('[')
+                        .and_then(|array| array.strip_suffix(']'))
+                        .and_then(|array| array.split_once("; "))
+                    {
+                        Some((element_type, length)) => {
+                            format!("{} {field_name}[{length}];", c_type(element_type))
+                        }
+                        None => format!("{} {field_name};", c_type(field_type)),
+                    }
+                })
+                .collect();
+
+            declarations.push((name.to_owned(), keyword.to_owned(), fields));
+        }
+
+        declarations
+    }
+
+    // The header must be regenerated with cbindgen when the types change
+    #[test]
+    fn header_declarations() {
+        let header = include_str!("../include/alvr_server_core.h");
+        let declarations = rust_declarations(include_str!("c_api.rs"));
+        assert!(declarations
+            .iter()
+            .any(|(name, ..)| name == "AlvrButtonValue"));
+
+        for (name, keyword, fields) in &declarations {
+            // cbindgen skips the types not used by the API
+            if !header.contains(name.as_str()) {
+                continue;
+            }
+
+            let header_fields = header
+                .split_once(&format!("typedef {keyword} {name} {{\n"))
+                .and_then(|(_, rest)| rest.split_once(&format!("}} {name};")))
+                .map(|(body, _)| {
+                    body.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.starts_with("//"))
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_else(|| panic!("{name} not found in the header"));
+
+            assert_eq!(&header_fields, fields, "{name} differs from the header");
+        }
+
+        for line in header.lines() {
+            if let Some(name) = line
+                .strip_prefix("typedef struct ")
+                .or_else(|| line.strip_prefix("typedef union "))
+                .and_then(|line| line.strip_suffix(" {"))
+            {
+                // Variants of AlvrEvent are generated from the enum
+                assert!(
+                    name.starts_with("AlvrEvent")
+                        || declarations.iter().any(|(rust_name, ..)| rust_name == name),
+                    "{name} is not declared in Rust"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn header_functions() {
+        let header = include_str!("../include/alvr_server_core.h");
+        let source = include_str!("c_api.rs");
+
+        let rust_functions = source
+            .lines()
+            .filter_map(|line| {
+                line.strip_prefix("pub extern \"C\" fn ")
+                    .or_else(|| line.strip_prefix("pub unsafe extern \"C\" fn "))
+            })
+            .map(|line| line.split('(').next().unwrap())
+            .collect::<Vec<_>>();
+        let header_functions = header
+            .lines()
+            .filter(|line| !line.starts_with(' ') && !line.starts_with("//"))
+            .filter_map(|line| line.split_once(" alvr_").or(line.split_once(" *alvr_")))
+            .map(|(_, rest)| format!("alvr_{}", rest.split('(').next().unwrap()))
+            .collect::<Vec<_>>();
+
+        assert!(!rust_functions.is_empty());
+        assert_eq!(
+            rust_functions,
+            header_functions
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+    }
+}
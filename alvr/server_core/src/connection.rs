@@ -1,12 +1,19 @@
 use crate::{
-    bitrate::BitrateManager,
+    bitrate::{self, BitrateManager},
     body_tracking::BodyTrackingSink,
+    diagnostics,
+    event_hooks::{self, HookEventData},
+    face_expressions,
     face_tracking::FaceTrackingSink,
     hand_gestures::{trigger_hand_gesture_actions, HandGestureManager, HAND_GESTURE_BUTTON_SET},
-    input_mapping::ButtonMappingManager,
+    input_mapping::{self, ButtonMappingManager},
+    internet,
+    osc_output::OscOutputSink,
     sockets::WelcomeSocket,
+    spectator,
     statistics::StatisticsManager,
     tracking::{self, TrackingManager},
+    usb::UsbConnectionManager,
     ConnectionContext, ServerCoreEvent, ViewsConfig, SESSION_MANAGER,
 };
 use alvr_audio::AudioDevice;
@@ -14,58 +21,79 @@ use alvr_common::{
     con_bail, dbg_connection, debug, error,
     glam::{Quat, UVec2, Vec2, Vec3},
     info,
-    parking_lot::{Condvar, Mutex, RwLock},
+    parking_lot::{Condvar, Mutex, RwLock, RwLockWriteGuard},
     settings_schema::Switch,
     warn, AnyhowToCon, ConResult, ConnectionError, ConnectionState, LifecycleState, Pose,
-    BUTTON_INFO, CONTROLLER_PROFILE_INFO, DEVICE_ID_TO_PATH, HAND_LEFT_ID, HAND_RIGHT_ID, HEAD_ID,
-    QUEST_CONTROLLER_PROFILE_PATH,
+    RelaxedAtomic, ALVR_VERSION, BUTTON_INFO, CONTROLLER_PROFILE_INFO, DEVICE_ID_TO_PATH,
+    HAND_LEFT_ID, HAND_RIGHT_ID, HEAD_ID, QUEST_CONTROLLER_PROFILE_PATH,
 };
 use alvr_events::{ButtonEvent, EventType, TrackingEvent};
 use alvr_packets::{
     BatteryInfo, ClientConnectionResult, ClientControlPacket, ClientListAction, ClientStatistics,
-    NegotiatedStreamingConfig, ReservedClientControlPacket, ServerControlPacket, Tracking,
-    VideoPacketHeader, AUDIO, HAPTICS, STATISTICS, TRACKING, VIDEO,
+    ConnectionRejection, FaceData, NegotiatedStreamingConfig, ReservedClientControlPacket,
+    ServerControlPacket, Tracking, VideoPacketHeader, AUDIO, HAPTICS, STATISTICS, TRACKING, VIDEO,
 };
+use alvr_server_io::ServerSessionManager;
 use alvr_session::{
-    BodyTrackingConfig, BodyTrackingSinkConfig, CodecType, ControllersEmulationMode, FrameSize,
-    H264Profile, OpenvrConfig, SessionConfig,
+    BodyTrackingConfig, BodyTrackingSinkConfig, ClientStreamingOverrides, CodecType,
+    ConnectionConfig, ControllersEmulationMode, FrameSize, H264Profile, HookEvent,
+    OpenvrBootConfig, OpenvrConfig, OpenvrStreamConfig, SessionConfig, SessionResumeConfig,
+    SocketProtocol, SocketProtocolDefaultVariant,
 };
 use alvr_sockets::{
-    PeerType, ProtoControlSocket, StreamSocketBuilder, KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT,
+    ControlSocketReceiver, ControlSocketSender, InternetSocketBuilder, PeerType,
+    ProtoControlSocket, StreamSocket, StreamSocketBuilder, KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT,
 };
 use std::{
-    collections::HashMap,
-    net::IpAddr,
-    process::Command,
-    sync::{mpsc::RecvTimeoutError, Arc},
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
     thread,
     time::{Duration, Instant},
 };
 
 const RETRY_CONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
-const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
-const STREAMING_RECV_TIMEOUT: Duration = Duration::from_millis(500);
+pub const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
+pub const STREAMING_RECV_TIMEOUT: Duration = Duration::from_millis(500);
 
-const MAX_UNREAD_PACKETS: usize = 10; // Applies per stream
+pub const MAX_UNREAD_PACKETS: usize = 10; // Applies per stream
 
 pub struct VideoPacket {
     pub header: VideoPacketHeader,
     pub payload: Vec<u8>,
 }
 
+// Stream that lost its connection and is waiting for the client to come back
+pub struct ResumableSession {
+    token: u64,
+    socket_sender: mpsc::Sender<(ProtoControlSocket, IpAddr)>,
+}
+
 fn align32(value: f32) -> u32 {
     ((value / 32.).floor() * 32.) as u32
 }
 
-fn is_streaming(client_hostname: &str) -> bool {
-    SESSION_MANAGER
-        .read()
+pub fn is_streaming(client_hostname: &str) -> bool {
+    is_client_streaming(&SESSION_MANAGER.read(), client_hostname)
+}
+
+// For when SESSION_MANAGER is already locked
+fn is_client_streaming(session_manager: &ServerSessionManager, client_hostname: &str) -> bool {
+    session_manager
         .client_list()
         .get(client_hostname)
         .map(|c| c.connection_state == ConnectionState::Streaming)
         .unwrap_or(false)
 }
 
+// Clients with a resumable stream are connected again too
+fn accepts_connection(ctx: &ConnectionContext, hostname: &str, state: &ConnectionState) -> bool {
+    *state == ConnectionState::Disconnected || ctx.resumable_sessions.lock().contains_key(hostname)
+}
+
 pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
     let old_config = session.openvr_config.clone();
     let settings = session.to_settings();
@@ -115,6 +143,13 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
         false
     };
 
+    let eye_tracking_enabled = settings
+        .headset
+        .face_tracking
+        .as_option()
+        .map(|config| config.openvr_eye_tracking)
+        .unwrap_or(false);
+
     let mut foveation_center_size_x = 0.0;
     let mut foveation_center_size_y = 0.0;
     let mut foveation_center_shift_x = 0.0;
@@ -155,86 +190,91 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
     let amf_controls = settings.video.encoder_config.amf;
 
     OpenvrConfig {
-        tracking_ref_only: settings.headset.tracking_ref_only,
-        enable_vive_tracker_proxy: settings.headset.enable_vive_tracker_proxy,
-        aggressive_keyframe_resend: settings.connection.aggressive_keyframe_resend,
-        adapter_index: settings.video.adapter_index,
-        codec: settings.video.preferred_codec as _,
-        h264_profile: settings.video.encoder_config.h264_profile as u32,
-        rate_control_mode: settings.video.encoder_config.rate_control_mode as u32,
-        filler_data: settings.video.encoder_config.filler_data,
-        entropy_coding: settings.video.encoder_config.entropy_coding as u32,
-        use_10bit_encoder: settings.video.encoder_config.use_10bit,
-        use_full_range_encoding: settings.video.encoder_config.use_full_range,
-        encoding_gamma: settings.video.encoder_config.encoding_gamma,
-        enable_hdr: settings.video.encoder_config.enable_hdr,
-        force_hdr_srgb_correction: settings.video.encoder_config.force_hdr_srgb_correction,
-        clamp_hdr_extended_range: settings.video.encoder_config.clamp_hdr_extended_range,
-        enable_pre_analysis: amf_controls.enable_pre_analysis,
-        enable_vbaq: amf_controls.enable_vbaq,
-        enable_hmqb: amf_controls.enable_hmqb,
-        use_preproc: amf_controls.use_preproc,
-        preproc_sigma: amf_controls.preproc_sigma,
-        preproc_tor: amf_controls.preproc_tor,
-        nvenc_quality_preset: nvenc_overrides.quality_preset as u32,
-        amd_encoder_quality_preset: amf_controls.quality_preset as u32,
-        force_sw_encoding: settings
-            .video
-            .encoder_config
-            .software
-            .force_software_encoding,
-        sw_thread_count: settings.video.encoder_config.software.thread_count,
-        controllers_enabled,
-        controller_is_tracker,
-        body_tracking_vive_enabled,
-        body_tracking_has_legs,
-        enable_foveated_encoding,
-        foveation_center_size_x,
-        foveation_center_size_y,
-        foveation_center_shift_x,
-        foveation_center_shift_y,
-        foveation_edge_ratio_x,
-        foveation_edge_ratio_y,
-        enable_color_correction,
-        brightness,
-        contrast,
-        saturation,
-        gamma,
-        sharpening,
-        linux_async_compute: settings.extra.patches.linux_async_compute,
-        linux_async_reprojection: settings.extra.patches.linux_async_reprojection,
-        nvenc_tuning_preset: nvenc_overrides.tuning_preset as u32,
-        nvenc_multi_pass: nvenc_overrides.multi_pass as u32,
-        nvenc_adaptive_quantization_mode: nvenc_overrides.adaptive_quantization_mode as u32,
-        nvenc_low_delay_key_frame_scale: nvenc_overrides.low_delay_key_frame_scale,
-        nvenc_refresh_rate: nvenc_overrides.refresh_rate,
-        enable_intra_refresh: nvenc_overrides.enable_intra_refresh,
-        intra_refresh_period: nvenc_overrides.intra_refresh_period,
-        intra_refresh_count: nvenc_overrides.intra_refresh_count,
-        max_num_ref_frames: nvenc_overrides.max_num_ref_frames,
-        gop_length: nvenc_overrides.gop_length,
-        p_frame_strategy: nvenc_overrides.p_frame_strategy,
-        nvenc_rate_control_mode: nvenc_overrides.rate_control_mode,
-        rc_buffer_size: nvenc_overrides.rc_buffer_size,
-        rc_initial_delay: nvenc_overrides.rc_initial_delay,
-        rc_max_bitrate: nvenc_overrides.rc_max_bitrate,
-        rc_average_bitrate: nvenc_overrides.rc_average_bitrate,
-        nvenc_enable_weighted_prediction: nvenc_overrides.enable_weighted_prediction,
-        capture_frame_dir: settings.extra.capture.capture_frame_dir,
-        amd_bitrate_corruption_fix: settings.video.bitrate.image_corruption_fix,
-        use_separate_hand_trackers,
-        _controller_profile,
-        _server_impl_debug: settings.extra.logging.debug_groups.server_impl,
-        _client_impl_debug: settings.extra.logging.debug_groups.client_impl,
-        _server_core_debug: settings.extra.logging.debug_groups.server_core,
-        _client_core_debug: settings.extra.logging.debug_groups.client_core,
-        _conncection_debug: settings.extra.logging.debug_groups.connection,
-        _sockets_debug: settings.extra.logging.debug_groups.sockets,
-        _server_gfx_debug: settings.extra.logging.debug_groups.server_gfx,
-        _client_gfx_debug: settings.extra.logging.debug_groups.client_gfx,
-        _encoder_debug: settings.extra.logging.debug_groups.encoder,
-        _decoder_debug: settings.extra.logging.debug_groups.decoder,
-        ..old_config
+        boot: OpenvrBootConfig {
+            tracking_ref_only: settings.headset.tracking_ref_only,
+            enable_vive_tracker_proxy: settings.headset.enable_vive_tracker_proxy,
+            adapter_index: settings.video.adapter_index,
+            controllers_enabled,
+            controller_is_tracker,
+            body_tracking_vive_enabled,
+            body_tracking_has_legs,
+            eye_tracking_enabled,
+            linux_async_compute: settings.extra.patches.linux_async_compute,
+            linux_async_reprojection: settings.extra.patches.linux_async_reprojection,
+            use_separate_hand_trackers,
+            _controller_profile,
+            _server_impl_debug: settings.extra.logging.debug_groups.server_impl,
+            _client_impl_debug: settings.extra.logging.debug_groups.client_impl,
+            _server_core_debug: settings.extra.logging.debug_groups.server_core,
+            _client_core_debug: settings.extra.logging.debug_groups.client_core,
+            _conncection_debug: settings.extra.logging.debug_groups.connection,
+            _sockets_debug: settings.extra.logging.debug_groups.sockets,
+            _server_gfx_debug: settings.extra.logging.debug_groups.server_gfx,
+            _client_gfx_debug: settings.extra.logging.debug_groups.client_gfx,
+            _encoder_debug: settings.extra.logging.debug_groups.encoder,
+            _decoder_debug: settings.extra.logging.debug_groups.decoder,
+            ..old_config.boot
+        },
+        stream: OpenvrStreamConfig {
+            aggressive_keyframe_resend: settings.connection.aggressive_keyframe_resend,
+            codec: settings.video.preferred_codec as _,
+            h264_profile: settings.video.encoder_config.h264_profile as u32,
+            rate_control_mode: settings.video.encoder_config.rate_control_mode as u32,
+            filler_data: settings.video.encoder_config.filler_data,
+            entropy_coding: settings.video.encoder_config.entropy_coding as u32,
+            use_10bit_encoder: settings.video.encoder_config.use_10bit,
+            use_full_range_encoding: settings.video.encoder_config.use_full_range,
+            encoding_gamma: settings.video.encoder_config.encoding_gamma,
+            enable_hdr: settings.video.encoder_config.enable_hdr,
+            force_hdr_srgb_correction: settings.video.encoder_config.force_hdr_srgb_correction,
+            clamp_hdr_extended_range: settings.video.encoder_config.clamp_hdr_extended_range,
+            enable_pre_analysis: amf_controls.enable_pre_analysis,
+            enable_vbaq: amf_controls.enable_vbaq,
+            enable_hmqb: amf_controls.enable_hmqb,
+            use_preproc: amf_controls.use_preproc,
+            preproc_sigma: amf_controls.preproc_sigma,
+            preproc_tor: amf_controls.preproc_tor,
+            nvenc_quality_preset: nvenc_overrides.quality_preset as u32,
+            amd_encoder_quality_preset: amf_controls.quality_preset as u32,
+            force_sw_encoding: settings
+                .video
+                .encoder_config
+                .software
+                .force_software_encoding,
+            sw_thread_count: settings.video.encoder_config.software.thread_count,
+            enable_foveated_encoding,
+            foveation_center_size_x,
+            foveation_center_size_y,
+            foveation_center_shift_x,
+            foveation_center_shift_y,
+            foveation_edge_ratio_x,
+            foveation_edge_ratio_y,
+            enable_color_correction,
+            brightness,
+            contrast,
+            saturation,
+            gamma,
+            sharpening,
+            nvenc_tuning_preset: nvenc_overrides.tuning_preset as u32,
+            nvenc_multi_pass: nvenc_overrides.multi_pass as u32,
+            nvenc_adaptive_quantization_mode: nvenc_overrides.adaptive_quantization_mode as u32,
+            nvenc_low_delay_key_frame_scale: nvenc_overrides.low_delay_key_frame_scale,
+            nvenc_refresh_rate: nvenc_overrides.refresh_rate,
+            enable_intra_refresh: nvenc_overrides.enable_intra_refresh,
+            intra_refresh_period: nvenc_overrides.intra_refresh_period,
+            intra_refresh_count: nvenc_overrides.intra_refresh_count,
+            max_num_ref_frames: nvenc_overrides.max_num_ref_frames,
+            gop_length: nvenc_overrides.gop_length,
+            p_frame_strategy: nvenc_overrides.p_frame_strategy,
+            nvenc_rate_control_mode: nvenc_overrides.rate_control_mode,
+            rc_buffer_size: nvenc_overrides.rc_buffer_size,
+            rc_initial_delay: nvenc_overrides.rc_initial_delay,
+            rc_max_bitrate: nvenc_overrides.rc_max_bitrate,
+            rc_average_bitrate: nvenc_overrides.rc_average_bitrate,
+            nvenc_enable_weighted_prediction: nvenc_overrides.enable_weighted_prediction,
+            capture_frame_dir: settings.extra.capture.capture_frame_dir,
+            amd_bitrate_corruption_fix: settings.video.bitrate.image_corruption_fix,
+        },
     }
 }
 
@@ -242,24 +282,34 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
 pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<LifecycleState>>) {
     dbg_connection!("handshake_loop: Begin");
 
-    let mut welcome_socket = match WelcomeSocket::new() {
-        Ok(socket) => socket,
-        Err(e) => {
-            error!("Failed to create discovery socket: {e:?}");
-            return;
-        }
-    };
+    let mut welcome_socket = WelcomeSocket::new();
+    let mut usb_connection_manager = UsbConnectionManager::new();
 
     while *lifecycle_state.read() != LifecycleState::ShuttingDown {
+        let (wired_config, stream_port) = {
+            let session_manager = SESSION_MANAGER.read();
+            let connection = &session_manager.settings().connection;
+
+            (connection.wired_connection.clone(), connection.stream_port)
+        };
+        if let Switch::Enabled(config) = wired_config {
+            dbg_connection!("handshake_loop: Update USB devices");
+
+            usb_connection_manager.update(&config, stream_port);
+        }
+
         dbg_connection!("handshake_loop: Try connect to manual IPs");
 
         let available_manual_client_ips = {
             let mut manual_client_ips = HashMap::new();
-            for (hostname, connection_info) in SESSION_MANAGER
-                .read()
-                .client_list()
-                .iter()
-                .filter(|(_, info)| info.connection_state == ConnectionState::Disconnected)
+            for (hostname, connection_info) in
+                SESSION_MANAGER
+                    .read()
+                    .client_list()
+                    .iter()
+                    .filter(|(hostname, info)| {
+                        accepts_connection(&ctx, hostname, &info.connection_state)
+                    })
             {
                 for ip in &connection_info.manual_ips {
                     manual_client_ips.insert(*ip, hostname.clone());
@@ -289,25 +339,27 @@ pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<L
         if let Switch::Enabled(config) = discovery_config {
             dbg_connection!("handshake_loop: Discovering clients");
 
-            let clients = match welcome_socket.recv_all() {
-                Ok(clients) => clients,
-                Err(e) => {
-                    warn!("mDNS listening error: {e:?}");
-
-                    thread::sleep(RETRY_CONNECT_MIN_INTERVAL);
-                    continue;
-                }
-            };
-
-            if clients.is_empty() {
-                thread::sleep(RETRY_CONNECT_MIN_INTERVAL);
-                continue;
-            }
+            let clients = welcome_socket.recv_all(&config).unwrap_or_else(|e| {
+                warn!("Discovery listening error: {e:?}");
+                HashMap::new()
+            });
 
-            for (client_hostname, client_ip) in clients {
+            for (client_hostname, client) in clients {
+                let client_ip = client.ip;
                 let trusted = {
                     let mut session_manager = SESSION_MANAGER.write();
 
+                    let hook_data = HookEventData {
+                        hostname: Some(client_hostname.clone()),
+                        ip: Some(client_ip),
+                        ..Default::default()
+                    };
+                    let hooks_config = session_manager.settings().connection.event_hooks.clone();
+
+                    let maybe_client = session_manager.client_list().get(&client_hostname);
+                    let is_new = maybe_client.is_none();
+                    let was_trusted = maybe_client.map(|c| c.trusted).unwrap_or(false);
+
                     session_manager.update_client_list(
                         client_hostname.clone(),
                         ClientListAction::AddIfMissing {
@@ -315,10 +367,29 @@ pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<L
                             manual_ips: vec![],
                         },
                     );
+                    session_manager.update_client_list(
+                        client_hostname.clone(),
+                        ClientListAction::SetIncompatibility(client.incompatibility),
+                    );
+                    if is_new {
+                        event_hooks::trigger(
+                            &hooks_config,
+                            HookEvent::ClientDiscovered,
+                            hook_data.clone(),
+                        );
+                    }
 
                     if config.auto_trust_clients {
                         session_manager
                             .update_client_list(client_hostname.clone(), ClientListAction::Trust);
+
+                        if !was_trusted {
+                            event_hooks::trigger(
+                                &hooks_config,
+                                HookEvent::ClientTrusted,
+                                hook_data,
+                            );
+                        }
                     }
 
                     session_manager
@@ -334,7 +405,7 @@ pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<L
                         .read()
                         .client_list()
                         .get(&client_hostname)
-                        .map(|c| c.connection_state == ConnectionState::Disconnected)
+                        .map(|c| accepts_connection(&ctx, &client_hostname, &c.connection_state))
                         .unwrap_or(false)
                 {
                     if let Err(e) = try_connect(
@@ -348,9 +419,21 @@ pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<L
 
                 thread::sleep(RETRY_CONNECT_MIN_INTERVAL);
             }
-        } else {
-            thread::sleep(RETRY_CONNECT_MIN_INTERVAL);
         }
+
+        let internet_config = SESSION_MANAGER
+            .read()
+            .settings()
+            .connection
+            .internet_streaming
+            .clone();
+        if let Switch::Enabled(config) = internet_config {
+            dbg_connection!("handshake_loop: Try connect through relay");
+
+            try_connect_through_relay(&ctx, &lifecycle_state, &config.rendezvous_server);
+        }
+
+        thread::sleep(RETRY_CONNECT_MIN_INTERVAL);
     }
 
     alvr_common::dbg_connection!("handshake_loop: Joining connection threads");
@@ -379,17 +462,116 @@ fn try_connect(
         con_bail!("unreachable");
     };
 
-    dbg_connection!("try_connect: Pushing new client connection thread");
+    spawn_connection_pipeline(
+        ctx,
+        lifecycle_state,
+        proto_socket,
+        client_hostname,
+        client_ip,
+        None,
+    );
+
+    Ok(())
+}
+
+// Clients on other networks wait on the relay for the streamer. The relay replies immediately if
+// the client is not waiting, so all trusted clients can be tried in turn.
+fn try_connect_through_relay(
+    ctx: &Arc<ConnectionContext>,
+    lifecycle_state: &Arc<RwLock<LifecycleState>>,
+    rendezvous_server: &str,
+) {
+    let relay_address = match alvr_sockets::resolve_rendezvous_address(rendezvous_server) {
+        Ok(address) => address,
+        Err(e) => {
+            warn!("Failed to resolve rendezvous server address: {e:?}");
+            return;
+        }
+    };
+
+    let clients = SESSION_MANAGER
+        .read()
+        .client_list()
+        .iter()
+        .filter(|(_, info)| info.trusted && info.connection_state == ConnectionState::Disconnected)
+        .filter_map(|(hostname, info)| Some((hostname.clone(), info.relay_pairing_token.clone()?)))
+        .collect::<Vec<_>>();
+
+    for (client_hostname, pairing_token) in clients {
+        if let Ok((proto_socket, relay_ip)) = ProtoControlSocket::connect_to(
+            HANDSHAKE_ACTION_TIMEOUT,
+            PeerType::ClientThroughRelay {
+                relay_address,
+                pairing_token: &pairing_token,
+            },
+        ) {
+            dbg_connection!("try_connect_through_relay: Connected to {client_hostname}");
+
+            spawn_connection_pipeline(
+                Arc::clone(ctx),
+                Arc::clone(lifecycle_state),
+                proto_socket,
+                client_hostname,
+                relay_ip,
+                Some(relay_address),
+            );
+        }
+    }
+}
+
+fn version_rejection(reason: String) -> ConnectionRejection {
+    let server_version = ALVR_VERSION.to_string();
+
+    let download_hint = if alvr_common::is_stable() {
+        format!(
+            "Download the client from \
+            https://github.com/alvr-org/ALVR/releases/tag/v{server_version}"
+        )
+    } else if alvr_common::is_nightly() {
+        "Download the client from https://github.com/alvr-org/ALVR-nightly/releases".into()
+    } else {
+        "Build the client from the same source as the streamer".into()
+    };
+
+    ConnectionRejection {
+        reason,
+        suggested_client_version: server_version.clone(),
+        server_version,
+        download_hint,
+    }
+}
+
+fn spawn_connection_pipeline(
+    ctx: Arc<ConnectionContext>,
+    lifecycle_state: Arc<RwLock<LifecycleState>>,
+    proto_socket: ProtoControlSocket,
+    client_hostname: String,
+    client_ip: IpAddr,
+    relay_address: Option<SocketAddr>,
+) {
+    dbg_connection!("spawn_connection_pipeline: Pushing new client connection thread");
 
     ctx.connection_threads.lock().push(thread::spawn({
         let ctx = Arc::clone(&ctx);
         move || {
+            // The client state belongs to the stream being resumed
+            if ctx.resumable_sessions.lock().contains_key(&client_hostname) {
+                if let Err(e) =
+                    hand_over_to_resumable_session(&ctx, proto_socket, &client_hostname, client_ip)
+                {
+                    error!("Session resume error for {client_hostname}: {e}");
+                }
+
+                return;
+            }
+
             if let Err(e) = connection_pipeline(
                 Arc::clone(&ctx),
                 lifecycle_state,
                 proto_socket,
                 client_hostname.clone(),
                 client_ip,
+                relay_address,
             ) {
                 error!("Handshake error for {client_hostname}: {e}");
             }
@@ -408,16 +590,114 @@ fn try_connect(
                 .update_client_list(client_hostname, action);
         }
     }));
+}
+
+fn hand_over_to_resumable_session(
+    ctx: &ConnectionContext,
+    mut proto_socket: ProtoControlSocket,
+    client_hostname: &str,
+    client_ip: IpAddr,
+) -> ConResult {
+    let ClientConnectionResult::SessionResume { token } =
+        proto_socket.recv(HANDSHAKE_ACTION_TIMEOUT)?
+    else {
+        con_bail!("The client started a new session");
+    };
+
+    // The session is removed only with the right token, so that other peers announcing the same
+    // hostname cannot end the stream
+    let session = {
+        let mut resumable_sessions = ctx.resumable_sessions.lock();
+        match resumable_sessions.get(client_hostname) {
+            Some(session) if session.token == token => {
+                resumable_sessions.remove(client_hostname).unwrap()
+            }
+            Some(_) => con_bail!("Invalid resume token"),
+            None => con_bail!("The stream cannot be resumed anymore"),
+        }
+    };
+
+    if session
+        .socket_sender
+        .send((proto_socket, client_ip))
+        .is_err()
+    {
+        con_bail!("The stream cannot be resumed anymore");
+    }
 
     Ok(())
 }
 
+// Waits for the client to reconnect within the grace period, then moves the running stream to the
+// new sockets. Returns the new client IP.
+#[allow(clippy::too_many_arguments)]
+fn resume_stream(
+    ctx: &ConnectionContext,
+    client_hostname: &str,
+    token: u64,
+    resume_config: &SessionResumeConfig,
+    connection_config: &ConnectionConfig,
+    control_sender: &Mutex<ControlSocketSender<ServerControlPacket>>,
+    control_receiver: &Mutex<ControlSocketReceiver<ClientControlPacket>>,
+    stream_socket: &Mutex<StreamSocket>,
+) -> ConResult<IpAddr> {
+    // Free the stream port for the new connection
+    stream_socket.lock().detach();
+
+    let (socket_sender, socket_receiver) = mpsc::channel();
+    ctx.resumable_sessions.lock().insert(
+        client_hostname.to_owned(),
+        ResumableSession {
+            token,
+            socket_sender,
+        },
+    );
+    let maybe_socket =
+        socket_receiver.recv_timeout(Duration::from_secs_f32(resume_config.grace_period_s));
+    ctx.resumable_sessions.lock().remove(client_hostname);
+
+    let Ok((proto_socket, client_ip)) = maybe_socket else {
+        con_bail!("The client did not come back in time");
+    };
+
+    let (mut new_control_sender, mut new_control_receiver) =
+        proto_socket.split(STREAMING_RECV_TIMEOUT).to_con()?;
+
+    new_control_sender
+        .send(&ServerControlPacket::StartStream)
+        .to_con()?;
+    if !matches!(
+        new_control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?,
+        ClientControlPacket::StreamReady
+    ) {
+        con_bail!("Got unexpected packet waiting for stream ack");
+    }
+
+    let new_stream_socket = StreamSocketBuilder::connect_to_client(
+        HANDSHAKE_ACTION_TIMEOUT,
+        client_ip,
+        connection_config.stream_port,
+        connection_config.stream_protocol.clone(),
+        connection_config.dscp.clone(),
+        connection_config.server_send_buffer_bytes.clone(),
+        connection_config.server_recv_buffer_bytes.clone(),
+        connection_config.packet_size as _,
+    )?;
+
+    stream_socket.lock().reattach(new_stream_socket);
+    *control_sender.lock() = new_control_sender;
+    *control_receiver.lock() = new_control_receiver;
+
+    Ok(client_ip)
+}
+
 fn connection_pipeline(
     ctx: Arc<ConnectionContext>,
     lifecycle_state: Arc<RwLock<LifecycleState>>,
     mut proto_socket: ProtoControlSocket,
     client_hostname: String,
     client_ip: IpAddr,
+    relay_address: Option<SocketAddr>,
 ) -> ConResult {
     dbg_connection!("connection_pipeline: Begin");
 
@@ -468,9 +748,33 @@ fn connection_pipeline(
                 client_protocol_id,
             );
 
+            // Prefer the more detailed reason found during discovery
+            let reason = session_manager_lock
+                .client_list()
+                .get(&client_hostname)
+                .and_then(|c| c.incompatibility.clone())
+                .unwrap_or_else(|| "The client and streamer versions do not match.".into());
+            session_manager_lock.update_client_list(
+                client_hostname.clone(),
+                ClientListAction::SetIncompatibility(Some(reason.clone())),
+            );
+
+            // Let the headset explain the problem instead of searching for the streamer forever
+            proto_socket
+                .send(
+                    &alvr_packets::encode_connection_rejection(&version_rejection(reason))
+                        .to_con()?,
+                )
+                .to_con()?;
+
             return Ok(());
         }
 
+        session_manager_lock.update_client_list(
+            client_hostname.clone(),
+            ClientListAction::SetIncompatibility(None),
+        );
+
         streaming_capabilities
     } else {
         debug!("Found client in standby. Retrying");
@@ -483,9 +787,65 @@ fn connection_pipeline(
         con_bail!("Only streaming clients are supported for now");
     };
 
+    let hooks_config = session_manager_lock
+        .settings()
+        .connection
+        .event_hooks
+        .clone();
+    event_hooks::trigger(
+        &hooks_config,
+        HookEvent::ClientConnecting,
+        HookEventData {
+            hostname: Some(client_hostname.clone()),
+            ip: Some(client_ip),
+            ..Default::default()
+        },
+    );
+
+    if session_manager_lock
+        .client_list()
+        .get(&client_hostname)
+        .map(|c| c.spectator)
+        .unwrap_or(false)
+    {
+        if relay_address.is_some() {
+            con_bail!("Spectators are not supported with internet streaming");
+        }
+
+        return spectator::spectator_pipeline(
+            ctx,
+            lifecycle_state,
+            proto_socket,
+            client_hostname,
+            client_ip,
+            streaming_caps,
+            session_manager_lock,
+        );
+    }
+
     dbg_connection!("connection_pipeline: setting up negotiated streaming config");
 
-    let settings = session_manager_lock.settings().clone();
+    let mut settings = session_manager_lock.settings().clone();
+
+    // ADB can forward only TCP connections
+    let cabled = session_manager_lock
+        .client_list()
+        .get(&client_hostname)
+        .map(|c| c.cabled)
+        .unwrap_or(false);
+    let relay_pairing_token = session_manager_lock
+        .client_list()
+        .get(&client_hostname)
+        .and_then(|c| c.relay_pairing_token.clone());
+    let mut session_for_client = session_manager_lock.session().clone();
+    if cabled {
+        settings.connection.stream_protocol = SocketProtocol::Tcp;
+        session_for_client
+            .session_settings
+            .connection
+            .stream_protocol
+            .variant = SocketProtocolDefaultVariant::Tcp;
+    }
 
     fn get_view_res(config: FrameSize, default_res: UVec2) -> UVec2 {
         let res = match config {
@@ -515,11 +875,20 @@ fn connection_pipeline(
         streaming_caps.default_view_resolution,
     );
 
+    // The preferences chosen on the headset take precedence over the session settings
+    let overrides = &streaming_caps.streaming_overrides;
+    let preferred_fps = overrides
+        .refresh_rate
+        .unwrap_or(settings.video.preferred_fps);
+    let preferred_codec = overrides
+        .preferred_codec
+        .unwrap_or(settings.video.preferred_codec);
+
     let fps = {
         let mut best_match = 0_f32;
         let mut min_diff = f32::MAX;
         for rate in &streaming_caps.supported_refresh_rates {
-            let diff = (*rate - settings.video.preferred_fps).abs();
+            let diff = (*rate - preferred_fps).abs();
             if diff < min_diff {
                 best_match = *rate;
                 min_diff = diff;
@@ -530,7 +899,7 @@ fn connection_pipeline(
 
     if !streaming_caps
         .supported_refresh_rates
-        .contains(&settings.video.preferred_fps)
+        .contains(&preferred_fps)
     {
         warn!("Chosen refresh rate not supported. Using {fps}Hz");
     }
@@ -576,7 +945,7 @@ fn connection_pipeline(
         false
     };
 
-    let codec = if settings.video.preferred_codec == CodecType::AV1 {
+    let codec = if preferred_codec == CodecType::AV1 {
         let codec = if streaming_caps.encoder_av1 {
             CodecType::AV1
         } else {
@@ -589,9 +958,22 @@ fn connection_pipeline(
 
         codec
     } else {
-        settings.video.preferred_codec
+        preferred_codec
     };
 
+    let client_max_bitrate_mbps =
+        bitrate::applicable_client_max_bitrate(&settings.video.bitrate, overrides.max_bitrate_mbps);
+
+    // Only the overrides that took effect are shown in the dashboard
+    session_manager_lock.update_client_list(
+        client_hostname.clone(),
+        ClientListAction::SetStreamingOverrides(ClientStreamingOverrides {
+            preferred_codec: overrides.preferred_codec.filter(|c| *c == codec),
+            max_bitrate_mbps: client_max_bitrate_mbps,
+            refresh_rate: overrides.refresh_rate.filter(|rate| *rate == fps),
+        }),
+    );
+
     let game_audio_sample_rate =
         if let Switch::Enabled(game_audio_config) = &settings.audio.game_audio {
             #[cfg(not(target_os = "linux"))]
@@ -629,7 +1011,7 @@ fn connection_pipeline(
 
     dbg_connection!("connection_pipeline: send streaming config");
     let stream_config_packet = alvr_packets::encode_stream_config(
-        session_manager_lock.session(),
+        &session_for_client,
         &NegotiatedStreamingConfig {
             view_resolution: stream_view_resolution,
             refresh_rate_hint: fps,
@@ -643,23 +1025,104 @@ fn connection_pipeline(
     let (mut control_sender, mut control_receiver) =
         proto_socket.split(STREAMING_RECV_TIMEOUT).to_con()?;
 
+    if ctx.clients_to_diagnose.lock().remove(&client_hostname) {
+        if relay_address.is_some() {
+            con_bail!("Connection diagnostics are not supported with internet streaming");
+        }
+
+        // Other clients can connect in the meantime, the diagnostics do not touch the session
+        drop(session_manager_lock);
+
+        info!("Running connection diagnostics for {client_hostname}");
+        let report = diagnostics::run_diagnostics(
+            client_hostname,
+            client_ip,
+            &settings.connection,
+            &mut control_sender,
+            &mut control_receiver,
+        )?;
+        info!("Connection diagnostics report: {report:?}");
+
+        alvr_events::send_event(EventType::ConnectionDiagnostics(Box::new(report)));
+
+        return Ok(());
+    }
+
     let mut new_openvr_config = contruct_openvr_config(session_manager_lock.session());
-    new_openvr_config.eye_resolution_width = stream_view_resolution.x;
-    new_openvr_config.eye_resolution_height = stream_view_resolution.y;
-    new_openvr_config.target_eye_resolution_width = target_view_resolution.x;
-    new_openvr_config.target_eye_resolution_height = target_view_resolution.y;
-    new_openvr_config.refresh_rate = fps as _;
-    new_openvr_config.enable_foveated_encoding = enable_foveated_encoding;
-    new_openvr_config.h264_profile = encoder_profile as _;
-    new_openvr_config.use_10bit_encoder = enable_10_bits_encoding;
-    new_openvr_config.codec = codec as _;
-
-    if session_manager_lock.session().openvr_config != new_openvr_config {
+    new_openvr_config.boot.eye_resolution_width = stream_view_resolution.x;
+    new_openvr_config.boot.eye_resolution_height = stream_view_resolution.y;
+    new_openvr_config.boot.target_eye_resolution_width = target_view_resolution.x;
+    new_openvr_config.boot.target_eye_resolution_height = target_view_resolution.y;
+    new_openvr_config.boot.refresh_rate = fps as _;
+    new_openvr_config.stream.enable_foveated_encoding = enable_foveated_encoding;
+    new_openvr_config.stream.h264_profile = encoder_profile as _;
+    new_openvr_config.stream.use_10bit_encoder = enable_10_bits_encoding;
+    new_openvr_config.stream.codec = codec as _;
+
+    let old_openvr_config = session_manager_lock.session().openvr_config.clone();
+    if old_openvr_config.boot != new_openvr_config.boot {
         session_manager_lock.session_mut().openvr_config = new_openvr_config;
 
         control_sender.send(&ServerControlPacket::Restarting).ok();
 
         crate::notify_restart_driver();
+    } else if old_openvr_config.stream != new_openvr_config.stream {
+        info!("Applying new encoder configuration without restarting SteamVR");
+
+        // The driver reads the session again when the stream starts, it must be saved first
+        let stream_config = new_openvr_config.stream.clone();
+        session_manager_lock.session_mut().openvr_config = new_openvr_config;
+
+        ctx.events_sender
+            .send(ServerCoreEvent::UpdateStreamConfig(Box::new(stream_config)))
+            .ok();
+    }
+
+    let internet_stream = if let Some(rendezvous_address) = relay_address {
+        let Some(config) = settings.connection.internet_streaming.as_option().cloned() else {
+            con_bail!("Internet streaming has been disabled");
+        };
+
+        dbg_connection!("connection_pipeline: Query public stream address");
+        let builder = InternetSocketBuilder::bind(
+            rendezvous_address,
+            settings.connection.stream_port,
+            settings.connection.dscp.clone(),
+            settings.connection.server_send_buffer_bytes.clone(),
+            settings.connection.server_recv_buffer_bytes.clone(),
+            HANDSHAKE_ACTION_TIMEOUT,
+        )
+        .to_con()?;
+        control_sender
+            .send(&ServerControlPacket::InternetEndpoint(
+                builder.public_address,
+            ))
+            .to_con()?;
+
+        Some((builder, config))
+    } else {
+        None
+    };
+
+    // Resuming is not supported through the relay, since the relay session cannot be reused
+    let resume_config = settings
+        .connection
+        .session_resume
+        .as_option()
+        .filter(|_| relay_address.is_none())
+        .cloned();
+    let resume_token = rand::random();
+    if resume_config.is_some() {
+        control_sender
+            .send(&ServerControlPacket::ResumeToken(resume_token))
+            .to_con()?;
+    }
+
+    // Lets the client wait for this streamer on the relay later
+    if let Some(token) = relay_pairing_token {
+        control_sender
+            .send(&ServerControlPacket::RelayPairingToken(token))
+            .to_con()?;
     }
 
     dbg_connection!("connection_pipeline: Send StartStream packet");
@@ -667,6 +1130,24 @@ fn connection_pipeline(
         .send(&ServerControlPacket::StartStream)
         .to_con()?;
 
+    let client_public_address = if internet_stream.is_some() {
+        let ClientControlPacket::InternetEndpoint(address) =
+            control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?
+        else {
+            con_bail!("Got unexpected packet waiting for client public address");
+        };
+
+        // The client IP seen by the streamer was the one of the relay
+        session_manager_lock.update_client_list(
+            client_hostname.clone(),
+            ClientListAction::UpdateCurrentIp(Some(address.ip())),
+        );
+
+        address
+    } else {
+        SocketAddr::new(client_ip, settings.connection.stream_port)
+    };
+
     let signal = control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?;
     if !matches!(signal, ClientControlPacket::StreamReady) {
         con_bail!("Got unexpected packet waiting for stream ack");
@@ -684,18 +1165,39 @@ fn connection_pipeline(
     ));
 
     *ctx.bitrate_manager.lock() = BitrateManager::new(settings.video.bitrate.history_size, fps);
+    ctx.bitrate_manager
+        .lock()
+        .set_client_max_bitrate(client_max_bitrate_mbps);
+
+    let mut stream_socket = if let Some((builder, config)) = internet_stream {
+        dbg_connection!("connection_pipeline: StreamSocket through NAT");
+        let (stream_socket, path, round_trip_time) = internet::connect_stream_socket(
+            builder,
+            client_public_address,
+            &config,
+            settings.connection.packet_size as _,
+            &mut control_sender,
+            &mut control_receiver,
+        )?;
+
+        if let Some(stats) = &mut *ctx.statistics_manager.lock() {
+            stats.report_connection_path(path, round_trip_time);
+        }
 
-    dbg_connection!("connection_pipeline: StreamSocket connect_to_client");
-    let mut stream_socket = StreamSocketBuilder::connect_to_client(
-        HANDSHAKE_ACTION_TIMEOUT,
-        client_ip,
-        settings.connection.stream_port,
-        settings.connection.stream_protocol,
-        settings.connection.dscp,
-        settings.connection.server_send_buffer_bytes,
-        settings.connection.server_recv_buffer_bytes,
-        settings.connection.packet_size as _,
-    )?;
+        stream_socket
+    } else {
+        dbg_connection!("connection_pipeline: StreamSocket connect_to_client");
+        StreamSocketBuilder::connect_to_client(
+            HANDSHAKE_ACTION_TIMEOUT,
+            client_ip,
+            settings.connection.stream_port,
+            settings.connection.stream_protocol.clone(),
+            settings.connection.dscp.clone(),
+            settings.connection.server_send_buffer_bytes.clone(),
+            settings.connection.server_recv_buffer_bytes.clone(),
+            settings.connection.packet_size as _,
+        )?
+    };
 
     let mut video_sender = stream_socket.request_stream(VIDEO);
     let game_audio_sender: alvr_sockets::StreamSender<()> = stream_socket.request_stream(AUDIO);
@@ -707,6 +1209,11 @@ fn connection_pipeline(
     let mut statics_receiver =
         stream_socket.subscribe_to_stream::<ClientStatistics>(STATISTICS, MAX_UNREAD_PACKETS);
 
+    // The sockets are replaced when the stream is resumed
+    let stream_socket = Arc::new(Mutex::new(stream_socket));
+    let control_receiver = Arc::new(Mutex::new(control_receiver));
+    let connection_lost = Arc::new(RelaxedAtomic::new(false));
+
     let (video_channel_sender, video_channel_receiver) =
         std::sync::mpsc::sync_channel(settings.connection.max_queued_server_video_frames);
     *ctx.video_channel_sender.lock() = Some(video_channel_sender);
@@ -893,6 +1400,12 @@ fn connection_pipeline(
                         BodyTrackingSink::new(config.sink, settings.connection.osc_local_port).ok()
                     });
 
+            let mut osc_output_sink = settings
+                .headset
+                .osc_output
+                .as_option()
+                .and_then(|config| OscOutputSink::new(config).ok());
+
             while is_streaming(&client_hostname) {
                 let data = match tracking_receiver.recv(STREAMING_RECV_TIMEOUT) {
                     Ok(tracking) => tracking,
@@ -968,9 +1481,36 @@ fn connection_pipeline(
                     let mut face_data = tracking.face_data.clone();
                     face_data.eye_gazes = local_eye_gazes;
 
+                    if let Switch::Enabled(config) =
+                        &SESSION_MANAGER.read().settings().headset.face_tracking
+                    {
+                        face_expressions::convert_face_data(
+                            &mut face_data,
+                            config.expressions_format,
+                        );
+                    }
+
                     sink.send_tracking(face_data);
                 }
 
+                if let Some(sink) = &mut osc_output_sink {
+                    let session_manager_lock = SESSION_MANAGER.read();
+                    if let Some(config) = session_manager_lock
+                        .settings()
+                        .headset
+                        .osc_output
+                        .as_option()
+                    {
+                        sink.send_tracking(
+                            config,
+                            &motions,
+                            hand_skeletons,
+                            local_eye_gazes,
+                            &tracking.face_data,
+                        );
+                    }
+                }
+
                 let track_body = {
                     let session_manager_lock = SESSION_MANAGER.read();
                     matches!(
@@ -1042,7 +1582,11 @@ fn connection_pipeline(
                                 } else {
                                     [None, None]
                                 },
-                                face_data: tracking.face_data,
+                                // Note: eye gazes are relative to the head
+                                face_data: FaceData {
+                                    eye_gazes: local_eye_gazes,
+                                    ..tracking.face_data
+                                },
                             }),
                             controllers_pose_time_offset: stats.tracker_pose_time_offset(),
                         })
@@ -1092,15 +1636,17 @@ fn connection_pipeline(
     let keepalive_thread = thread::spawn({
         let control_sender = Arc::clone(&control_sender);
         let disconnect_notif = Arc::clone(&disconnect_notif);
+        let connection_lost = Arc::clone(&connection_lost);
         let client_hostname = client_hostname.clone();
         move || {
             while is_streaming(&client_hostname) {
                 if let Err(e) = control_sender.lock().send(&ServerControlPacket::KeepAlive) {
-                    info!("Client disconnected. Cause: {e:?}");
+                    if !connection_lost.value() {
+                        info!("Client disconnected. Cause: {e:?}");
+                    }
 
+                    connection_lost.set(true);
                     disconnect_notif.notify_one();
-
-                    return;
                 }
 
                 thread::sleep(KEEPALIVE_INTERVAL);
@@ -1111,47 +1657,79 @@ fn connection_pipeline(
     let control_receive_thread = thread::spawn({
         let ctx = Arc::clone(&ctx);
 
-        let controllers_config = session_manager_lock
+        // Note: SESSION_MANAGER is write-locked here, the active profile is read from the context
+        let mut controller_button_set = CONTROLLER_PROFILE_INFO
+            .get(&alvr_common::hash_string(QUEST_CONTROLLER_PROFILE_PATH))
+            .map(|info| info.button_set.clone());
+        let mut active_mapping_profile = ctx.active_button_mapping_profile.lock().clone();
+        let mut controller_button_mapping_manager = session_manager_lock
             .settings()
             .headset
             .controllers
-            .as_option();
-        let mut controller_button_mapping_manager = controllers_config.map(|config| {
-            if let Some(mappings) = &config.button_mappings {
-                ButtonMappingManager::new_manual(mappings)
-            } else {
-                ButtonMappingManager::new_automatic(
-                    &CONTROLLER_PROFILE_INFO
-                        .get(&alvr_common::hash_string(QUEST_CONTROLLER_PROFILE_PATH))
-                        .unwrap()
-                        .button_set,
-                    &config.emulation_mode,
-                    &config.button_mapping_config,
+            .as_option()
+            .and_then(|config| {
+                input_mapping::controller_button_mapping_manager(
+                    config,
+                    controller_button_set.as_ref(),
+                    active_mapping_profile.as_ref(),
                 )
-            }
-        });
-        let controllers_emulation_mode =
-            controllers_config.map(|config| config.emulation_mode.clone());
+            });
 
         let disconnect_notif = Arc::clone(&disconnect_notif);
         let control_sender = Arc::clone(&control_sender);
+        let control_receiver = Arc::clone(&control_receiver);
+        let connection_lost = Arc::clone(&connection_lost);
         let client_hostname = client_hostname.clone();
         move || {
+            let mut low_battery_devices = HashSet::new();
             let mut disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
             while is_streaming(&client_hostname) {
-                let packet = match control_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                let profile_changed =
+                    *ctx.active_button_mapping_profile.lock() != active_mapping_profile;
+                if profile_changed {
+                    active_mapping_profile = ctx.active_button_mapping_profile.lock().clone();
+                    controller_button_mapping_manager = SESSION_MANAGER
+                        .read()
+                        .settings()
+                        .headset
+                        .controllers
+                        .as_option()
+                        .and_then(|config| {
+                            input_mapping::controller_button_mapping_manager(
+                                config,
+                                controller_button_set.as_ref(),
+                                active_mapping_profile.as_ref(),
+                            )
+                        });
+                }
+
+                let maybe_packet = control_receiver.lock().recv(STREAMING_RECV_TIMEOUT);
+                let packet = match maybe_packet {
                     Ok(packet) => packet,
                     Err(ConnectionError::TryAgain(_)) => {
                         if Instant::now() > disconnection_deadline {
-                            info!("Client disconnected. Timeout");
-                            break;
-                        } else {
-                            continue;
+                            if !connection_lost.value() {
+                                info!("Client disconnected. Timeout");
+                            }
+
+                            connection_lost.set(true);
+                            disconnect_notif.notify_one();
+
+                            disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
                         }
+
+                        continue;
                     }
                     Err(e) => {
-                        info!("Client disconnected. Cause: {e}");
-                        break;
+                        if !connection_lost.value() {
+                            info!("Client disconnected. Cause: {e}");
+                        }
+
+                        connection_lost.set(true);
+                        disconnect_notif.notify_one();
+
+                        thread::sleep(STREAMING_RECV_TIMEOUT);
+                        continue;
                     }
                 };
 
@@ -1229,6 +1807,29 @@ fn connection_pipeline(
                                 packet.is_plugged,
                             );
                         }
+
+                        // Trigger only once when the level crosses the threshold
+                        let session_manager_lock = SESSION_MANAGER.read();
+                        let hooks_config = &session_manager_lock.settings().connection.event_hooks;
+                        let is_low = !packet.is_plugged
+                            && packet.gauge_value < hooks_config.battery_low_threshold;
+                        if is_low && low_battery_devices.insert(packet.device_id) {
+                            event_hooks::trigger(
+                                hooks_config,
+                                HookEvent::BatteryLow,
+                                HookEventData {
+                                    hostname: Some(client_hostname.clone()),
+                                    ip: Some(client_ip),
+                                    reason: DEVICE_ID_TO_PATH
+                                        .get(&packet.device_id)
+                                        .map(|path| (*path).to_owned()),
+                                    battery_gauge: Some(packet.gauge_value),
+                                    ..Default::default()
+                                },
+                            );
+                        } else if !is_low {
+                            low_battery_devices.remove(&packet.device_id);
+                        }
                     }
                     ClientControlPacket::Buttons(entries) => {
                         {
@@ -1270,30 +1871,35 @@ fn connection_pipeline(
                         };
                     }
                     ClientControlPacket::ActiveInteractionProfile { profile_id, .. } => {
-                        controller_button_mapping_manager = if let Switch::Enabled(config) =
-                            &SESSION_MANAGER.read().settings().headset.controllers
-                        {
-                            if let Some(mappings) = &config.button_mappings {
-                                Some(ButtonMappingManager::new_manual(mappings))
-                            } else if let (Some(profile_info), Some(emulation_mode)) = (
-                                CONTROLLER_PROFILE_INFO.get(&profile_id),
-                                &controllers_emulation_mode,
-                            ) {
-                                Some(ButtonMappingManager::new_automatic(
-                                    &profile_info.button_set,
-                                    emulation_mode,
-                                    &config.button_mapping_config,
-                                ))
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        };
-                    }
-                    ClientControlPacket::Log { level, message } => {
-                        info!("Client {client_hostname}: [{level:?}] {message}")
+                        controller_button_set = CONTROLLER_PROFILE_INFO
+                            .get(&profile_id)
+                            .map(|info| info.button_set.clone());
+                        controller_button_mapping_manager = SESSION_MANAGER
+                            .read()
+                            .settings()
+                            .headset
+                            .controllers
+                            .as_option()
+                            .and_then(|config| {
+                                input_mapping::controller_button_mapping_manager(
+                                    config,
+                                    controller_button_set.as_ref(),
+                                    active_mapping_profile.as_ref(),
+                                )
+                            });
                     }
+                    ClientControlPacket::Log {
+                        level,
+                        message,
+                        debug_group,
+                    } => alvr_common::log::log!(
+                        target: &alvr_common::client_log_target(
+                            &client_hostname,
+                            debug_group.as_deref()
+                        ),
+                        level.into_log_level(),
+                        "{message}"
+                    ),
                     ClientControlPacket::Reserved(json_string) => {
                         let reserved: ReservedClientControlPacket =
                             match serde_json::from_str(&json_string) {
@@ -1311,23 +1917,20 @@ fn connection_pipeline(
                                 input_ids,
                                 ..
                             } => {
-                                controller_button_mapping_manager = if let Switch::Enabled(config) =
-                                    &SESSION_MANAGER.read().settings().headset.controllers
-                                {
-                                    if let Some(mappings) = &config.button_mappings {
-                                        Some(ButtonMappingManager::new_manual(mappings))
-                                    } else {
-                                        controllers_emulation_mode.as_ref().map(|emulation_mode| {
-                                            ButtonMappingManager::new_automatic(
-                                                &input_ids,
-                                                emulation_mode,
-                                                &config.button_mapping_config,
-                                            )
-                                        })
-                                    }
-                                } else {
-                                    None
-                                };
+                                controller_button_set = Some(input_ids);
+                                controller_button_mapping_manager = SESSION_MANAGER
+                                    .read()
+                                    .settings()
+                                    .headset
+                                    .controllers
+                                    .as_option()
+                                    .and_then(|config| {
+                                        input_mapping::controller_button_mapping_manager(
+                                            config,
+                                            controller_button_set.as_ref(),
+                                            active_mapping_profile.as_ref(),
+                                        )
+                                    });
                             }
                         }
                     }
@@ -1342,19 +1945,25 @@ fn connection_pipeline(
     });
 
     let stream_receive_thread = thread::spawn({
+        let stream_socket = Arc::clone(&stream_socket);
         let disconnect_notif = Arc::clone(&disconnect_notif);
+        let connection_lost = Arc::clone(&connection_lost);
         let client_hostname = client_hostname.clone();
         move || {
             while is_streaming(&client_hostname) {
-                match stream_socket.recv() {
+                let res = stream_socket.lock().recv();
+                match res {
                     Ok(()) => (),
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(e) => {
-                        info!("Client disconnected. Cause: {e}");
+                        if !connection_lost.value() {
+                            info!("Client disconnected. Cause: {e}");
+                        }
 
+                        connection_lost.set(true);
                         disconnect_notif.notify_one();
 
-                        return;
+                        thread::sleep(STREAMING_RECV_TIMEOUT);
                     }
                 }
             }
@@ -1362,6 +1971,7 @@ fn connection_pipeline(
     });
 
     let lifecycle_check_thread = thread::spawn({
+        let lifecycle_state = Arc::clone(&lifecycle_state);
         let disconnect_notif = Arc::clone(&disconnect_notif);
         let client_hostname = client_hostname.clone();
         move || {
@@ -1380,19 +1990,18 @@ fn connection_pipeline(
         }
     });
 
-    {
-        let on_connect_script = settings.connection.on_connect_script;
-
-        if !on_connect_script.is_empty() {
-            info!("Running on connect script (connect): {on_connect_script}");
-            if let Err(e) = Command::new(&on_connect_script)
-                .env("ACTION", "connect")
-                .spawn()
-            {
-                warn!("Failed to run connect script: {e}");
-            }
-        }
-    }
+    event_hooks::trigger(
+        &settings.connection.event_hooks,
+        HookEvent::StreamingStarted,
+        HookEventData {
+            hostname: Some(client_hostname.clone()),
+            ip: Some(client_ip),
+            view_resolution: Some(stream_view_resolution),
+            fps: Some(fps),
+            codec: Some(codec),
+            ..Default::default()
+        },
+    );
 
     if settings.extra.capture.startup_video_recording {
         info!("Creating recording file");
@@ -1409,7 +2018,53 @@ fn connection_pipeline(
         .ok();
 
     dbg_connection!("connection_pipeline: handshake finished; unlocking streams");
-    alvr_common::wait_rwlock(&disconnect_notif, &mut session_manager_lock);
+    loop {
+        alvr_common::wait_rwlock(&disconnect_notif, &mut session_manager_lock);
+
+        let still_streaming = is_client_streaming(&session_manager_lock, &client_hostname)
+            && *lifecycle_state.read() == LifecycleState::Resumed;
+        let Some(resume_config) = resume_config
+            .as_ref()
+            .filter(|_| still_streaming && connection_lost.value())
+        else {
+            break;
+        };
+
+        info!("Waiting for {client_hostname} to resume the stream");
+        let res = RwLockWriteGuard::unlocked(&mut session_manager_lock, || {
+            resume_stream(
+                &ctx,
+                &client_hostname,
+                resume_token,
+                resume_config,
+                &settings.connection,
+                &control_sender,
+                &control_receiver,
+                &stream_socket,
+            )
+        });
+        match res {
+            Ok(new_client_ip) => {
+                info!("Stream resumed");
+
+                session_manager_lock.update_client_list(
+                    client_hostname.clone(),
+                    ClientListAction::UpdateCurrentIp(Some(new_client_ip)),
+                );
+                connection_lost.set(false);
+                ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+
+                // The threads may have stopped while unlocked, then the notification was missed
+                if !is_client_streaming(&session_manager_lock, &client_hostname) {
+                    break;
+                }
+            }
+            Err(e) => {
+                info!("Could not resume the stream: {e}");
+                break;
+            }
+        }
+    }
     dbg_connection!("connection_pipeline: Begin connection shutdown");
 
     // This requests shutdown from threads
@@ -1418,25 +2073,30 @@ fn connection_pipeline(
 
     *ctx.video_recording_file.lock() = None;
 
+    // If the client is still marked as streaming, the disconnection was not requested
+    let disconnect_reason = if *lifecycle_state.read() != LifecycleState::Resumed {
+        "server_shutdown"
+    } else if is_client_streaming(&session_manager_lock, &client_hostname) {
+        "connection_lost"
+    } else {
+        "requested"
+    };
+
     session_manager_lock.update_client_list(
         client_hostname.clone(),
         ClientListAction::SetConnectionState(ConnectionState::Disconnecting),
     );
 
-    let on_disconnect_script = session_manager_lock
-        .settings()
-        .connection
-        .on_disconnect_script
-        .clone();
-    if !on_disconnect_script.is_empty() {
-        info!("Running on disconnect script (disconnect): {on_disconnect_script}");
-        if let Err(e) = Command::new(&on_disconnect_script)
-            .env("ACTION", "disconnect")
-            .spawn()
-        {
-            warn!("Failed to run disconnect script: {e}");
-        }
-    }
+    event_hooks::trigger(
+        &session_manager_lock.settings().connection.event_hooks,
+        HookEvent::ClientDisconnected,
+        HookEventData {
+            hostname: Some(client_hostname.clone()),
+            ip: Some(client_ip),
+            reason: Some(disconnect_reason.into()),
+            ..Default::default()
+        },
+    );
 
     // Allow threads to shutdown correctly
     drop(session_manager_lock);
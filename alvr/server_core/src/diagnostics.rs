@@ -0,0 +1,374 @@
+use crate::connection::HANDSHAKE_ACTION_TIMEOUT;
+use alvr_common::{con_bail, AnyhowToCon, ConResult, ConnectionError, RelaxedAtomic};
+use alvr_packets::{
+    ClientControlPacket, ClientDiagnosticsResult, DiagnosticsPhase, DiagnosticsReport,
+    PacketSizeDiagnostics, ProtocolDiagnostics, ServerControlPacket, DIAGNOSTICS_DATA,
+    DIAGNOSTICS_PING,
+};
+use alvr_session::{ConnectionConfig, DscpTos, SocketBufferSize, SocketProtocol};
+use alvr_sockets::{
+    ControlSocketReceiver, ControlSocketSender, ProbeSender, StreamSocketBuilder, PROBES_PER_SIZE,
+    PROBE_PACKET_SIZES,
+};
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const PING_COUNT: u32 = 20;
+const PING_INTERVAL: Duration = Duration::from_millis(20);
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+const THROUGHPUT_TEST_DURATION: Duration = Duration::from_secs(2);
+const THROUGHPUT_PACKET_SIZE: usize = 65536;
+const RESULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Leave headroom for the video bitrate spikes and the other streams
+const BITRATE_HEADROOM: f32 = 0.7;
+const MAX_UDP_PACKET_LOSS: f32 = 0.02;
+const MAX_JITTER_MS: f32 = 5.0;
+const MAX_ROUND_TRIP_TIME_MS: f32 = 20.0;
+// Typical OS default for the socket buffers
+const DEFAULT_SOCKET_BUFFER_BYTES: u64 = 212_992;
+const UDP_SHARD_PREFIX_SIZE: u32 = 4;
+
+fn wait_for_client(control_receiver: &mut ControlSocketReceiver<ClientControlPacket>) -> ConResult {
+    if !matches!(
+        control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?,
+        ClientControlPacket::DiagnosticsReady
+    ) {
+        con_bail!("Got unexpected packet waiting for diagnostics ready");
+    }
+
+    Ok(())
+}
+
+fn end_phase(
+    control_sender: &mut ControlSocketSender<ServerControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ClientControlPacket>,
+) -> ConResult<ClientDiagnosticsResult> {
+    control_sender
+        .send(&ServerControlPacket::DiagnosticsPhaseEnd)
+        .to_con()?;
+
+    match control_receiver.recv(RESULT_TIMEOUT)? {
+        ClientControlPacket::DiagnosticsResult(result) => Ok(result),
+        _ => con_bail!("Got unexpected packet waiting for diagnostics result"),
+    }
+}
+
+fn measure_protocol(
+    protocol: SocketProtocol,
+    client_ip: IpAddr,
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ServerControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ClientControlPacket>,
+) -> ConResult<ProtocolDiagnostics> {
+    control_sender
+        .send(&ServerControlPacket::DiagnosticsPhase(
+            DiagnosticsPhase::Stream(protocol.clone()),
+        ))
+        .to_con()?;
+    wait_for_client(control_receiver)?;
+
+    let mut stream_socket = StreamSocketBuilder::connect_to_client(
+        HANDSHAKE_ACTION_TIMEOUT,
+        client_ip,
+        config.stream_port,
+        protocol.clone(),
+        config.dscp.clone(),
+        config.server_send_buffer_bytes.clone(),
+        config.server_recv_buffer_bytes.clone(),
+        config.packet_size as _,
+    )?;
+
+    let mut ping_sender = stream_socket.request_stream::<u32>(DIAGNOSTICS_PING);
+    let mut pong_receiver =
+        stream_socket.subscribe_to_stream::<u32>(DIAGNOSTICS_PING, PING_COUNT as usize);
+    let mut data_sender = stream_socket.request_stream::<u32>(DIAGNOSTICS_DATA);
+
+    let running = Arc::new(RelaxedAtomic::new(true));
+    let receive_thread = thread::spawn({
+        let running = Arc::clone(&running);
+        move || {
+            while running.value() {
+                if let Err(ConnectionError::Other(_)) = stream_socket.recv() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut round_trip_times = vec![];
+    for index in 0..PING_COUNT {
+        let send_instant = Instant::now();
+        ping_sender.send_header(&index).ok();
+
+        // Late replies to previous pings are skipped
+        while let Ok(data) = pong_receiver.recv(PING_TIMEOUT.saturating_sub(send_instant.elapsed()))
+        {
+            if matches!(data.get_header(), Ok(reply_index) if reply_index == index) {
+                round_trip_times.push(send_instant.elapsed().as_secs_f32() * 1000.0);
+                break;
+            }
+        }
+
+        thread::sleep(PING_INTERVAL);
+    }
+
+    let mut sent_packets = 0;
+    let start_instant = Instant::now();
+    while start_instant.elapsed() < THROUGHPUT_TEST_DURATION {
+        let Ok(mut buffer) = data_sender.get_buffer(&sent_packets) else {
+            break;
+        };
+        buffer.get_range_mut(0, THROUGHPUT_PACKET_SIZE);
+        if data_sender.send(buffer).is_ok() {
+            sent_packets += 1;
+        }
+    }
+
+    let result = end_phase(control_sender, control_receiver);
+
+    running.set(false);
+    receive_thread.join().ok();
+
+    let result = result?;
+
+    let round_trip_time_ms = if round_trip_times.is_empty() {
+        f32::MAX
+    } else {
+        round_trip_times.iter().sum::<f32>() / round_trip_times.len() as f32
+    };
+    let jitter_ms = if round_trip_times.len() > 1 {
+        round_trip_times
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .sum::<f32>()
+            / (round_trip_times.len() - 1) as f32
+    } else {
+        0.0
+    };
+    let throughput_mbps = if result.receive_duration > Duration::ZERO {
+        result.received_bytes as f32 * 8.0 / result.receive_duration.as_secs_f32() / 1e6
+    } else {
+        0.0
+    };
+
+    // The throughput test saturates the link, so the loss is measured on the pings
+    let packet_loss = 1.0 - round_trip_times.len() as f32 / PING_COUNT as f32;
+
+    Ok(ProtocolDiagnostics {
+        protocol,
+        round_trip_time_ms,
+        jitter_ms,
+        throughput_mbps,
+        packet_loss,
+    })
+}
+
+fn measure_packet_sizes(
+    client_ip: IpAddr,
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ServerControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ClientControlPacket>,
+) -> ConResult<(Vec<PacketSizeDiagnostics>, Option<bool>)> {
+    control_sender
+        .send(&ServerControlPacket::DiagnosticsPhase(
+            DiagnosticsPhase::PacketSizes,
+        ))
+        .to_con()?;
+    wait_for_client(control_receiver)?;
+
+    // Without a configured marking, test the one that would be most likely picked
+    let dscp = config.dscp.clone().unwrap_or(DscpTos::ExpeditedForwarding);
+
+    ProbeSender::connect(client_ip, config.stream_port, Some(dscp.clone()))
+        .to_con()?
+        .send_probes();
+
+    let result = end_phase(control_sender, control_receiver)?;
+
+    let packet_sizes = PROBE_PACKET_SIZES
+        .iter()
+        .map(|&size| {
+            let received = result
+                .received_probes
+                .iter()
+                .find(|(probe_size, _)| *probe_size == size)
+                .map(|(_, count)| *count)
+                .unwrap_or(0);
+
+            PacketSizeDiagnostics {
+                size,
+                delivery_ratio: received as f32 / PROBES_PER_SIZE as f32,
+            }
+        })
+        .collect();
+
+    // The two lower bits are used by ECN and can be changed by the routers
+    let dscp_preserved = result
+        .probe_tos
+        .map(|tos| tos >> 2 == alvr_sockets::dscp_value(dscp));
+
+    Ok((packet_sizes, dscp_preserved))
+}
+
+fn recommended_buffer_bytes(configured: &SocketBufferSize, required: u64) -> Option<String> {
+    let current = match configured {
+        SocketBufferSize::Default => DEFAULT_SOCKET_BUFFER_BYTES,
+        SocketBufferSize::Maximum => return None,
+        SocketBufferSize::Custom(bytes) => *bytes as u64,
+    };
+
+    (current < required).then(|| format!("{} KB", required.div_ceil(1000)))
+}
+
+fn make_recommendations(report: &DiagnosticsReport, config: &ConnectionConfig) -> Vec<String> {
+    let mut recommendations = vec![];
+
+    let udp = report
+        .protocols
+        .iter()
+        .find(|p| matches!(p.protocol, SocketProtocol::Udp));
+    let tcp = report
+        .protocols
+        .iter()
+        .find(|p| matches!(p.protocol, SocketProtocol::Tcp));
+
+    let best = match (udp, tcp) {
+        (Some(udp), Some(tcp)) if udp.packet_loss > MAX_UDP_PACKET_LOSS => {
+            recommendations.push(format!(
+                "Use the TCP stream protocol: UDP lost {:.0}% of the packets",
+                udp.packet_loss * 100.0
+            ));
+            Some(tcp)
+        }
+        (Some(udp), _) => {
+            if matches!(config.stream_protocol, SocketProtocol::Tcp) {
+                recommendations.push(
+                    "Use the UDP stream protocol: the network is reliable enough and UDP has \
+                    lower latency"
+                        .into(),
+                );
+            }
+            Some(udp)
+        }
+        (None, tcp) => tcp,
+    };
+
+    if let Some(best) = best {
+        let bitrate_cap_mbps = best.throughput_mbps * BITRATE_HEADROOM;
+        recommendations.push(format!(
+            "Cap the bitrate to {bitrate_cap_mbps:.0} Mbps ({:.0}% of the measured throughput)",
+            BITRATE_HEADROOM * 100.0
+        ));
+
+        if best.round_trip_time_ms > MAX_ROUND_TRIP_TIME_MS {
+            recommendations.push(format!(
+                "High round trip time ({:.1} ms): connect the PC with a cable and the headset to \
+                a 5 GHz or 6 GHz Wi-Fi network",
+                best.round_trip_time_ms
+            ));
+        }
+
+        if best.jitter_ms > MAX_JITTER_MS {
+            recommendations.push(format!(
+                "High jitter ({:.1} ms): increase the client buffering frames, or move away from \
+                crowded Wi-Fi channels",
+                best.jitter_ms
+            ));
+        }
+
+        // The buffers should hold a few round trips worth of data at the capped bitrate
+        let bandwidth_delay_bytes =
+            (bitrate_cap_mbps * 1e6 / 8.0 * best.round_trip_time_ms / 1000.0) as u64;
+        let required_bytes = bandwidth_delay_bytes * 4;
+        if let Some(size) =
+            recommended_buffer_bytes(&config.server_send_buffer_bytes, required_bytes)
+        {
+            recommendations.push(format!(
+                "Set the server send buffer size to at least {size}"
+            ));
+        }
+        if let Some(size) =
+            recommended_buffer_bytes(&config.client_recv_buffer_bytes, required_bytes)
+        {
+            recommendations.push(format!(
+                "Set the client receive buffer size to at least {size}"
+            ));
+        }
+    }
+
+    let largest_reliable_size = report
+        .packet_sizes
+        .iter()
+        .take_while(|p| p.delivery_ratio >= 0.9)
+        .map(|p| p.size)
+        .last();
+    match largest_reliable_size {
+        Some(size) if (config.packet_size as u32 + UDP_SHARD_PREFIX_SIZE) > size => {
+            recommendations.push(format!(
+                "Lower the packet size to {}: larger packets are dropped or fragmented by the \
+                network",
+                size - UDP_SHARD_PREFIX_SIZE
+            ));
+        }
+        None => recommendations
+            .push("Even small packets are dropped: check the firewall on both devices".into()),
+        _ => (),
+    }
+
+    match (report.dscp_preserved, &config.dscp) {
+        (Some(false), Some(_)) => recommendations
+            .push("The network clears the DSCP marking: the DSCP setting has no effect".into()),
+        (Some(true), None) => recommendations.push(
+            "The network keeps the DSCP marking: enabling DSCP may reduce latency under load"
+                .into(),
+        ),
+        _ => (),
+    }
+
+    recommendations
+}
+
+// Runs in place of the stream, while the client is waiting for the stream to start
+pub fn run_diagnostics(
+    hostname: String,
+    client_ip: IpAddr,
+    config: &ConnectionConfig,
+    control_sender: &mut ControlSocketSender<ServerControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ClientControlPacket>,
+) -> ConResult<DiagnosticsReport> {
+    let mut protocols = vec![];
+    for protocol in [SocketProtocol::Udp, SocketProtocol::Tcp] {
+        protocols.push(measure_protocol(
+            protocol,
+            client_ip,
+            config,
+            control_sender,
+            control_receiver,
+        )?);
+    }
+
+    let (packet_sizes, dscp_preserved) =
+        measure_packet_sizes(client_ip, config, control_sender, control_receiver)?;
+
+    control_sender
+        .send(&ServerControlPacket::DiagnosticsPhase(
+            DiagnosticsPhase::Finished,
+        ))
+        .to_con()?;
+
+    let mut report = DiagnosticsReport {
+        hostname,
+        protocols,
+        packet_sizes,
+        dscp_preserved,
+        recommendations: vec![],
+    };
+    report.recommendations = make_recommendations(&report, config);
+
+    Ok(report)
+}
@@ -0,0 +1,149 @@
+use alvr_common::{glam::UVec2, info, warn};
+use alvr_session::{CodecType, EventHooksConfig, HookAction, HookEvent};
+use serde::Serialize;
+use std::{net::IpAddr, process::Command, thread, time::Duration};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Clone, Default)]
+pub struct HookEventData {
+    pub hostname: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub view_resolution: Option<UVec2>,
+    pub fps: Option<f32>,
+    pub codec: Option<CodecType>,
+    pub reason: Option<String>,
+    pub battery_gauge: Option<f32>,
+    pub bitrate_mbps: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct WebhookBody<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    data: &'a HookEventData,
+}
+
+fn event_name(event: HookEvent) -> &'static str {
+    match event {
+        HookEvent::ClientDiscovered => "client_discovered",
+        HookEvent::ClientTrusted => "client_trusted",
+        HookEvent::ClientConnecting => "client_connecting",
+        HookEvent::StreamingStarted => "streaming_started",
+        HookEvent::ClientDisconnected => "client_disconnected",
+        HookEvent::BatteryLow => "battery_low",
+        HookEvent::BitrateDropped => "bitrate_dropped",
+        HookEvent::SteamvrRestarting => "steamvr_restarting",
+    }
+}
+
+// ACTION is kept for compatibility with the old connect and disconnect scripts, which were
+// migrated to the streaming started and client disconnected events
+fn legacy_action(event: HookEvent) -> &'static str {
+    match event {
+        HookEvent::StreamingStarted => "connect",
+        HookEvent::ClientDisconnected => "disconnect",
+        _ => event_name(event),
+    }
+}
+
+fn run_command(path: &str, event: HookEvent, data: &HookEventData) {
+    let mut command = Command::new(path);
+    command
+        .env("ACTION", legacy_action(event))
+        .env("ALVR_EVENT", event_name(event));
+    if let Some(hostname) = &data.hostname {
+        command.env("ALVR_HOSTNAME", hostname);
+    }
+    if let Some(ip) = data.ip {
+        command.env("ALVR_IP", ip.to_string());
+    }
+    if let Some(resolution) = data.view_resolution {
+        command.env(
+            "ALVR_RESOLUTION",
+            format!("{}x{}", resolution.x, resolution.y),
+        );
+    }
+    if let Some(fps) = data.fps {
+        command.env("ALVR_FPS", fps.to_string());
+    }
+    if let Some(codec) = data.codec {
+        command.env("ALVR_CODEC", format!("{codec:?}"));
+    }
+    if let Some(reason) = &data.reason {
+        command.env("ALVR_REASON", reason);
+    }
+    if let Some(gauge) = data.battery_gauge {
+        command.env("ALVR_BATTERY", gauge.to_string());
+    }
+    if let Some(bitrate) = data.bitrate_mbps {
+        command.env("ALVR_BITRATE_MBPS", bitrate.to_string());
+    }
+
+    if let Err(e) = command.spawn() {
+        warn!("Failed to run hook command {path}: {e}");
+    }
+}
+
+fn send_webhook(url: String, event: HookEvent, data: HookEventData) {
+    let body = match serde_json::to_string(&WebhookBody {
+        event: event_name(event),
+        data: &data,
+    }) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to serialize webhook body: {e}");
+            return;
+        }
+    };
+
+    // Webhooks run on their own thread, so that a slow endpoint cannot stall the connection
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                warn!("Failed to create webhook runtime: {e}");
+                return;
+            }
+        };
+
+        let result = runtime.block_on(
+            reqwest::Client::new()
+                .post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send(),
+        );
+        match result {
+            Ok(response) if !response.status().is_success() => {
+                warn!("Webhook {url} returned status {}", response.status())
+            }
+            Ok(_) => (),
+            Err(e) => warn!("Failed to send webhook {url}: {e}"),
+        }
+    });
+}
+
+// Settings are passed by the caller since SESSION_MANAGER is often already locked
+pub fn trigger(config: &EventHooksConfig, event: HookEvent, data: HookEventData) {
+    for hook in config.hooks.iter().filter(|hook| hook.event == event) {
+        match &hook.action {
+            HookAction::Command(path) => {
+                if !path.is_empty() {
+                    info!("Running {} hook: {path}", event_name(event));
+                    run_command(path, event, &data);
+                }
+            }
+            HookAction::Webhook(url) => {
+                if !url.is_empty() {
+                    info!("Sending {} webhook: {url}", event_name(event));
+                    send_webhook(url.clone(), event, data.clone());
+                }
+            }
+        }
+    }
+}
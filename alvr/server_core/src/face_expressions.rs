@@ -0,0 +1,756 @@
+// Typed face expression model. ARKit blendshapes are used as the common representation, every
+// source format (FB, HTC eye and HTC lip) can be converted to and from it.
+
+use alvr_packets::FaceData;
+use alvr_session::FaceExpressionsFormat;
+
+pub const FB_EXPRESSION_COUNT: usize = 70;
+pub const HTC_EYE_EXPRESSION_COUNT: usize = 14;
+pub const HTC_LIP_EXPRESSION_COUNT: usize = 37;
+pub const ARKIT_BLENDSHAPE_COUNT: usize = 52;
+// Eye blendshapes come first in ArkitBlendshape
+const ARKIT_EYE_BLENDSHAPE_COUNT: usize = 14;
+
+// XR_FB_face_tracking2 order. The first 63 entries match XR_FB_face_tracking.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FbExpression {
+    BrowLowererL,
+    BrowLowererR,
+    CheekPuffL,
+    CheekPuffR,
+    CheekRaiserL,
+    CheekRaiserR,
+    CheekSuckL,
+    CheekSuckR,
+    ChinRaiserB,
+    ChinRaiserT,
+    DimplerL,
+    DimplerR,
+    EyesClosedL,
+    EyesClosedR,
+    EyesLookDownL,
+    EyesLookDownR,
+    EyesLookLeftL,
+    EyesLookLeftR,
+    EyesLookRightL,
+    EyesLookRightR,
+    EyesLookUpL,
+    EyesLookUpR,
+    InnerBrowRaiserL,
+    InnerBrowRaiserR,
+    JawDrop,
+    JawSidewaysLeft,
+    JawSidewaysRight,
+    JawThrust,
+    LidTightenerL,
+    LidTightenerR,
+    LipCornerDepressorL,
+    LipCornerDepressorR,
+    LipCornerPullerL,
+    LipCornerPullerR,
+    LipFunnelerLB,
+    LipFunnelerLT,
+    LipFunnelerRB,
+    LipFunnelerRT,
+    LipPressorL,
+    LipPressorR,
+    LipPuckerL,
+    LipPuckerR,
+    LipStretcherL,
+    LipStretcherR,
+    LipSuckLB,
+    LipSuckLT,
+    LipSuckRB,
+    LipSuckRT,
+    LipTightenerL,
+    LipTightenerR,
+    LipsToward,
+    LowerLipDepressorL,
+    LowerLipDepressorR,
+    MouthLeft,
+    MouthRight,
+    NoseWrinklerL,
+    NoseWrinklerR,
+    OuterBrowRaiserL,
+    OuterBrowRaiserR,
+    UpperLidRaiserL,
+    UpperLidRaiserR,
+    UpperLipRaiserL,
+    UpperLipRaiserR,
+    TongueTipInterdental,
+    TongueTipAlveolar,
+    TongueFrontDorsalPalate,
+    TongueMidDorsalPalate,
+    TongueBackDorsalVelar,
+    TongueOut,
+    TongueRetreat,
+}
+
+// XR_HTC_facial_tracking eye expressions order
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HtcEyeExpression {
+    LeftBlink,
+    LeftWide,
+    RightBlink,
+    RightWide,
+    LeftSqueeze,
+    RightSqueeze,
+    LeftDown,
+    RightDown,
+    LeftOut,
+    RightIn,
+    LeftIn,
+    RightOut,
+    LeftUp,
+    RightUp,
+}
+
+// XR_HTC_facial_tracking lip expressions order
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HtcLipExpression {
+    JawRight,
+    JawLeft,
+    JawForward,
+    JawOpen,
+    MouthApeShape,
+    MouthUpperRight,
+    MouthUpperLeft,
+    MouthLowerRight,
+    MouthLowerLeft,
+    MouthUpperOverturn,
+    MouthLowerOverturn,
+    MouthPout,
+    MouthSmileRight,
+    MouthSmileLeft,
+    MouthSadRight,
+    MouthSadLeft,
+    CheekPuffRight,
+    CheekPuffLeft,
+    CheekSuck,
+    MouthUpperUpright,
+    MouthUpperUpleft,
+    MouthLowerDownright,
+    MouthLowerDownleft,
+    MouthUpperInside,
+    MouthLowerInside,
+    MouthLowerOverlay,
+    TongueLongstep1,
+    TongueLeft,
+    TongueRight,
+    TongueUp,
+    TongueDown,
+    TongueRoll,
+    TongueLongstep2,
+    TongueUprightMorph,
+    TongueUpleftMorph,
+    TongueDownrightMorph,
+    TongueDownleftMorph,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArkitBlendshape {
+    EyeBlinkLeft,
+    EyeLookDownLeft,
+    EyeLookInLeft,
+    EyeLookOutLeft,
+    EyeLookUpLeft,
+    EyeSquintLeft,
+    EyeWideLeft,
+    EyeBlinkRight,
+    EyeLookDownRight,
+    EyeLookInRight,
+    EyeLookOutRight,
+    EyeLookUpRight,
+    EyeSquintRight,
+    EyeWideRight,
+    JawForward,
+    JawLeft,
+    JawRight,
+    JawOpen,
+    MouthClose,
+    MouthFunnel,
+    MouthPucker,
+    MouthLeft,
+    MouthRight,
+    MouthSmileLeft,
+    MouthSmileRight,
+    MouthFrownLeft,
+    MouthFrownRight,
+    MouthDimpleLeft,
+    MouthDimpleRight,
+    MouthStretchLeft,
+    MouthStretchRight,
+    MouthRollLower,
+    MouthRollUpper,
+    MouthShrugLower,
+    MouthShrugUpper,
+    MouthPressLeft,
+    MouthPressRight,
+    MouthLowerDownLeft,
+    MouthLowerDownRight,
+    MouthUpperUpLeft,
+    MouthUpperUpRight,
+    BrowDownLeft,
+    BrowDownRight,
+    BrowInnerUp,
+    BrowOuterUpLeft,
+    BrowOuterUpRight,
+    CheekPuff,
+    CheekSquintLeft,
+    CheekSquintRight,
+    NoseSneerLeft,
+    NoseSneerRight,
+    TongueOut,
+}
+
+pub const ARKIT_BLENDSHAPE_NAMES: [&str; ARKIT_BLENDSHAPE_COUNT] = [
+    "eyeBlinkLeft",
+    "eyeLookDownLeft",
+    "eyeLookInLeft",
+    "eyeLookOutLeft",
+    "eyeLookUpLeft",
+    "eyeSquintLeft",
+    "eyeWideLeft",
+    "eyeBlinkRight",
+    "eyeLookDownRight",
+    "eyeLookInRight",
+    "eyeLookOutRight",
+    "eyeLookUpRight",
+    "eyeSquintRight",
+    "eyeWideRight",
+    "jawForward",
+    "jawLeft",
+    "jawRight",
+    "jawOpen",
+    "mouthClose",
+    "mouthFunnel",
+    "mouthPucker",
+    "mouthLeft",
+    "mouthRight",
+    "mouthSmileLeft",
+    "mouthSmileRight",
+    "mouthFrownLeft",
+    "mouthFrownRight",
+    "mouthDimpleLeft",
+    "mouthDimpleRight",
+    "mouthStretchLeft",
+    "mouthStretchRight",
+    "mouthRollLower",
+    "mouthRollUpper",
+    "mouthShrugLower",
+    "mouthShrugUpper",
+    "mouthPressLeft",
+    "mouthPressRight",
+    "mouthLowerDownLeft",
+    "mouthLowerDownRight",
+    "mouthUpperUpLeft",
+    "mouthUpperUpRight",
+    "browDownLeft",
+    "browDownRight",
+    "browInnerUp",
+    "browOuterUpLeft",
+    "browOuterUpRight",
+    "cheekPuff",
+    "cheekSquintLeft",
+    "cheekSquintRight",
+    "noseSneerLeft",
+    "noseSneerRight",
+    "tongueOut",
+];
+
+// Each blendshape is the average of the listed source expressions. When converting back, every
+// listed source expression receives the blendshape weight.
+const FB_TO_ARKIT: &[(ArkitBlendshape, &[FbExpression])] = {
+    use ArkitBlendshape as A;
+    use FbExpression as F;
+    &[
+        (A::EyeBlinkLeft, &[F::EyesClosedL]),
+        (A::EyeLookDownLeft, &[F::EyesLookDownL]),
+        (A::EyeLookInLeft, &[F::EyesLookRightL]),
+        (A::EyeLookOutLeft, &[F::EyesLookLeftL]),
+        (A::EyeLookUpLeft, &[F::EyesLookUpL]),
+        (A::EyeSquintLeft, &[F::LidTightenerL]),
+        (A::EyeWideLeft, &[F::UpperLidRaiserL]),
+        (A::EyeBlinkRight, &[F::EyesClosedR]),
+        (A::EyeLookDownRight, &[F::EyesLookDownR]),
+        (A::EyeLookInRight, &[F::EyesLookLeftR]),
+        (A::EyeLookOutRight, &[F::EyesLookRightR]),
+        (A::EyeLookUpRight, &[F::EyesLookUpR]),
+        (A::EyeSquintRight, &[F::LidTightenerR]),
+        (A::EyeWideRight, &[F::UpperLidRaiserR]),
+        (A::JawForward, &[F::JawThrust]),
+        (A::JawLeft, &[F::JawSidewaysLeft]),
+        (A::JawRight, &[F::JawSidewaysRight]),
+        (A::JawOpen, &[F::JawDrop]),
+        (A::MouthClose, &[F::LipsToward]),
+        (
+            A::MouthFunnel,
+            &[
+                F::LipFunnelerLB,
+                F::LipFunnelerLT,
+                F::LipFunnelerRB,
+                F::LipFunnelerRT,
+            ],
+        ),
+        (A::MouthPucker, &[F::LipPuckerL, F::LipPuckerR]),
+        (A::MouthLeft, &[F::MouthLeft]),
+        (A::MouthRight, &[F::MouthRight]),
+        (A::MouthSmileLeft, &[F::LipCornerPullerL]),
+        (A::MouthSmileRight, &[F::LipCornerPullerR]),
+        (A::MouthFrownLeft, &[F::LipCornerDepressorL]),
+        (A::MouthFrownRight, &[F::LipCornerDepressorR]),
+        (A::MouthDimpleLeft, &[F::DimplerL]),
+        (A::MouthDimpleRight, &[F::DimplerR]),
+        (A::MouthStretchLeft, &[F::LipStretcherL]),
+        (A::MouthStretchRight, &[F::LipStretcherR]),
+        (A::MouthRollLower, &[F::LipSuckLB, F::LipSuckRB]),
+        (A::MouthRollUpper, &[F::LipSuckLT, F::LipSuckRT]),
+        (A::MouthShrugLower, &[F::ChinRaiserB]),
+        (A::MouthShrugUpper, &[F::ChinRaiserT]),
+        (A::MouthPressLeft, &[F::LipPressorL]),
+        (A::MouthPressRight, &[F::LipPressorR]),
+        (A::MouthLowerDownLeft, &[F::LowerLipDepressorL]),
+        (A::MouthLowerDownRight, &[F::LowerLipDepressorR]),
+        (A::MouthUpperUpLeft, &[F::UpperLipRaiserL]),
+        (A::MouthUpperUpRight, &[F::UpperLipRaiserR]),
+        (A::BrowDownLeft, &[F::BrowLowererL]),
+        (A::BrowDownRight, &[F::BrowLowererR]),
+        (A::BrowInnerUp, &[F::InnerBrowRaiserL, F::InnerBrowRaiserR]),
+        (A::BrowOuterUpLeft, &[F::OuterBrowRaiserL]),
+        (A::BrowOuterUpRight, &[F::OuterBrowRaiserR]),
+        (A::CheekPuff, &[F::CheekPuffL, F::CheekPuffR]),
+        (A::CheekSquintLeft, &[F::CheekRaiserL]),
+        (A::CheekSquintRight, &[F::CheekRaiserR]),
+        (A::NoseSneerLeft, &[F::NoseWrinklerL]),
+        (A::NoseSneerRight, &[F::NoseWrinklerR]),
+        (A::TongueOut, &[F::TongueOut]),
+    ]
+};
+
+const HTC_EYE_TO_ARKIT: &[(ArkitBlendshape, &[HtcEyeExpression])] = {
+    use ArkitBlendshape as A;
+    use HtcEyeExpression as H;
+    &[
+        (A::EyeBlinkLeft, &[H::LeftBlink]),
+        (A::EyeLookDownLeft, &[H::LeftDown]),
+        (A::EyeLookInLeft, &[H::LeftIn]),
+        (A::EyeLookOutLeft, &[H::LeftOut]),
+        (A::EyeLookUpLeft, &[H::LeftUp]),
+        (A::EyeSquintLeft, &[H::LeftSqueeze]),
+        (A::EyeWideLeft, &[H::LeftWide]),
+        (A::EyeBlinkRight, &[H::RightBlink]),
+        (A::EyeLookDownRight, &[H::RightDown]),
+        (A::EyeLookInRight, &[H::RightIn]),
+        (A::EyeLookOutRight, &[H::RightOut]),
+        (A::EyeLookUpRight, &[H::RightUp]),
+        (A::EyeSquintRight, &[H::RightSqueeze]),
+        (A::EyeWideRight, &[H::RightWide]),
+    ]
+};
+
+const HTC_LIP_TO_ARKIT: &[(ArkitBlendshape, &[HtcLipExpression])] = {
+    use ArkitBlendshape as A;
+    use HtcLipExpression as H;
+    &[
+        (A::JawForward, &[H::JawForward]),
+        (A::JawLeft, &[H::JawLeft]),
+        (A::JawRight, &[H::JawRight]),
+        (A::JawOpen, &[H::JawOpen]),
+        (A::MouthClose, &[H::MouthApeShape]),
+        (
+            A::MouthFunnel,
+            &[H::MouthUpperOverturn, H::MouthLowerOverturn],
+        ),
+        (A::MouthPucker, &[H::MouthPout]),
+        (A::MouthLeft, &[H::MouthUpperLeft, H::MouthLowerLeft]),
+        (A::MouthRight, &[H::MouthUpperRight, H::MouthLowerRight]),
+        (A::MouthSmileLeft, &[H::MouthSmileLeft]),
+        (A::MouthSmileRight, &[H::MouthSmileRight]),
+        (A::MouthFrownLeft, &[H::MouthSadLeft]),
+        (A::MouthFrownRight, &[H::MouthSadRight]),
+        (A::MouthRollLower, &[H::MouthLowerInside]),
+        (A::MouthRollUpper, &[H::MouthUpperInside]),
+        (A::MouthShrugLower, &[H::MouthLowerOverlay]),
+        (A::MouthLowerDownLeft, &[H::MouthLowerDownleft]),
+        (A::MouthLowerDownRight, &[H::MouthLowerDownright]),
+        (A::MouthUpperUpLeft, &[H::MouthUpperUpleft]),
+        (A::MouthUpperUpRight, &[H::MouthUpperUpright]),
+        (A::CheekPuff, &[H::CheekPuffLeft, H::CheekPuffRight]),
+        (A::TongueOut, &[H::TongueLongstep1]),
+    ]
+};
+
+fn to_arkit<T: Copy + Into<usize>>(
+    table: &[(ArkitBlendshape, &[T])],
+    weights: &[f32],
+    blendshapes: &mut [f32; ARKIT_BLENDSHAPE_COUNT],
+) {
+    for (blendshape, sources) in table {
+        let values = sources
+            .iter()
+            .filter_map(|s| weights.get((*s).into()))
+            .collect::<Vec<_>>();
+
+        if !values.is_empty() {
+            blendshapes[*blendshape as usize] =
+                values.iter().copied().sum::<f32>() / values.len() as f32;
+        }
+    }
+}
+
+fn from_arkit<T: Copy + Into<usize>>(
+    table: &[(ArkitBlendshape, &[T])],
+    blendshapes: &[f32; ARKIT_BLENDSHAPE_COUNT],
+    count: usize,
+) -> Vec<f32> {
+    let mut weights = vec![0.0; count];
+    for (blendshape, targets) in table {
+        for target in *targets {
+            weights[(*target).into()] = blendshapes[*blendshape as usize];
+        }
+    }
+
+    weights
+}
+
+impl From<FbExpression> for usize {
+    fn from(value: FbExpression) -> Self {
+        value as usize
+    }
+}
+
+impl From<HtcEyeExpression> for usize {
+    fn from(value: HtcEyeExpression) -> Self {
+        value as usize
+    }
+}
+
+impl From<HtcLipExpression> for usize {
+    fn from(value: HtcLipExpression) -> Self {
+        value as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FaceExpressions {
+    pub blendshapes: [f32; ARKIT_BLENDSHAPE_COUNT],
+    pub has_eyes: bool,
+    pub has_lower_face: bool,
+}
+
+impl FaceExpressions {
+    // FB expressions are preferred over HTC ones if both are available
+    pub fn from_face_data(face_data: &FaceData) -> Option<Self> {
+        let mut blendshapes = [0.0; ARKIT_BLENDSHAPE_COUNT];
+
+        if let Some(weights) = &face_data.fb_face_expression {
+            to_arkit(FB_TO_ARKIT, weights, &mut blendshapes);
+
+            return Some(Self {
+                blendshapes,
+                has_eyes: true,
+                has_lower_face: true,
+            });
+        }
+
+        if let Some(weights) = &face_data.htc_eye_expression {
+            to_arkit(HTC_EYE_TO_ARKIT, weights, &mut blendshapes);
+        }
+        if let Some(weights) = &face_data.htc_lip_expression {
+            to_arkit(HTC_LIP_TO_ARKIT, weights, &mut blendshapes);
+        }
+
+        let has_eyes = face_data.htc_eye_expression.is_some();
+        let has_lower_face = face_data.htc_lip_expression.is_some();
+
+        (has_eyes || has_lower_face).then_some(Self {
+            blendshapes,
+            has_eyes,
+            has_lower_face,
+        })
+    }
+
+    pub fn get(&self, blendshape: ArkitBlendshape) -> f32 {
+        self.blendshapes[blendshape as usize]
+    }
+
+    pub fn to_fb(&self) -> Vec<f32> {
+        from_arkit(FB_TO_ARKIT, &self.blendshapes, FB_EXPRESSION_COUNT)
+    }
+
+    pub fn to_htc_eye(&self) -> Option<Vec<f32>> {
+        self.has_eyes.then(|| {
+            from_arkit(
+                HTC_EYE_TO_ARKIT,
+                &self.blendshapes,
+                HTC_EYE_EXPRESSION_COUNT,
+            )
+        })
+    }
+
+    pub fn to_htc_lip(&self) -> Option<Vec<f32>> {
+        self.has_lower_face.then(|| {
+            from_arkit(
+                HTC_LIP_TO_ARKIT,
+                &self.blendshapes,
+                HTC_LIP_EXPRESSION_COUNT,
+            )
+        })
+    }
+
+    pub fn named_blendshapes(&self) -> impl Iterator<Item = (&'static str, f32)> + '_ {
+        ARKIT_BLENDSHAPE_NAMES
+            .iter()
+            .copied()
+            .zip(self.blendshapes.iter().copied())
+    }
+
+    // Like named_blendshapes(), but skips the eye or lower face blendshapes if the source doesn't
+    // provide them
+    pub fn available_named_blendshapes(&self) -> impl Iterator<Item = (&'static str, f32)> + '_ {
+        self.named_blendshapes()
+            .enumerate()
+            .filter(|(index, _)| {
+                if *index < ARKIT_EYE_BLENDSHAPE_COUNT {
+                    self.has_eyes
+                } else {
+                    self.has_lower_face
+                }
+            })
+            .map(|(_, named_blendshape)| named_blendshape)
+    }
+}
+
+// Replace the expressions of the face data with the requested format. Eye gazes are untouched.
+pub fn convert_face_data(face_data: &mut FaceData, format: FaceExpressionsFormat) {
+    let expressions = match format {
+        FaceExpressionsFormat::Native => return,
+        FaceExpressionsFormat::Fb | FaceExpressionsFormat::Htc => {
+            FaceExpressions::from_face_data(face_data)
+        }
+    };
+
+    let Some(expressions) = expressions else {
+        return;
+    };
+
+    if matches!(format, FaceExpressionsFormat::Fb) {
+        face_data.fb_face_expression = Some(expressions.to_fb());
+        face_data.htc_eye_expression = None;
+        face_data.htc_lip_expression = None;
+    } else {
+        face_data.fb_face_expression = None;
+        face_data.htc_eye_expression = expressions.to_htc_eye();
+        face_data.htc_lip_expression = expressions.to_htc_lip();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fb_weights(count: usize, values: &[(FbExpression, f32)]) -> Vec<f32> {
+        let mut weights = vec![0.0; count];
+        for (expression, value) in values {
+            weights[*expression as usize] = *value;
+        }
+
+        weights
+    }
+
+    fn fb_face_data(weights: Vec<f32>) -> FaceData {
+        FaceData {
+            fb_face_expression: Some(weights),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_blendshape_names_match_enum() {
+        assert_eq!(
+            ARKIT_BLENDSHAPE_NAMES[ArkitBlendshape::EyeBlinkLeft as usize],
+            "eyeBlinkLeft"
+        );
+        assert_eq!(
+            ARKIT_BLENDSHAPE_NAMES[ArkitBlendshape::JawOpen as usize],
+            "jawOpen"
+        );
+        assert_eq!(
+            ARKIT_BLENDSHAPE_NAMES[ArkitBlendshape::TongueOut as usize],
+            "tongueOut"
+        );
+        assert_eq!(
+            ArkitBlendshape::TongueOut as usize,
+            ARKIT_BLENDSHAPE_COUNT - 1
+        );
+        assert_eq!(
+            FbExpression::TongueRetreat as usize,
+            FB_EXPRESSION_COUNT - 1
+        );
+        assert_eq!(
+            HtcEyeExpression::RightUp as usize,
+            HTC_EYE_EXPRESSION_COUNT - 1
+        );
+        assert_eq!(
+            HtcLipExpression::TongueDownleftMorph as usize,
+            HTC_LIP_EXPRESSION_COUNT - 1
+        );
+    }
+
+    #[test]
+    fn test_eye_blendshapes_come_first() {
+        for (index, name) in ARKIT_BLENDSHAPE_NAMES.iter().enumerate() {
+            assert_eq!(name.starts_with("eye"), index < ARKIT_EYE_BLENDSHAPE_COUNT);
+        }
+    }
+
+    #[test]
+    fn test_fb_known_mappings() {
+        let face_data = fb_face_data(fb_weights(
+            FB_EXPRESSION_COUNT,
+            &[
+                (FbExpression::EyesClosedL, 0.9),
+                (FbExpression::JawDrop, 0.5),
+                (FbExpression::LipPuckerL, 0.2),
+                (FbExpression::LipPuckerR, 0.6),
+                (FbExpression::TongueOut, 0.3),
+            ],
+        ));
+
+        let expressions = FaceExpressions::from_face_data(&face_data).unwrap();
+
+        assert!(expressions.has_eyes && expressions.has_lower_face);
+        assert_eq!(expressions.get(ArkitBlendshape::EyeBlinkLeft), 0.9);
+        assert_eq!(expressions.get(ArkitBlendshape::EyeBlinkRight), 0.0);
+        assert_eq!(expressions.get(ArkitBlendshape::JawOpen), 0.5);
+        // Multiple source expressions are averaged
+        assert!((expressions.get(ArkitBlendshape::MouthPucker) - 0.4).abs() < 1e-6);
+        assert_eq!(expressions.get(ArkitBlendshape::TongueOut), 0.3);
+    }
+
+    #[test]
+    fn test_fb_roundtrip() {
+        let weights = fb_weights(
+            FB_EXPRESSION_COUNT,
+            &[
+                (FbExpression::JawDrop, 0.5),
+                (FbExpression::CheekPuffL, 0.7),
+                (FbExpression::CheekPuffR, 0.7),
+            ],
+        );
+
+        let fb = FaceExpressions::from_face_data(&fb_face_data(weights.clone()))
+            .unwrap()
+            .to_fb();
+
+        assert_eq!(fb, weights);
+    }
+
+    // XR_FB_face_tracking (v1) reports 63 expressions, without the tongue
+    #[test]
+    fn test_fb_v1_vector() {
+        let face_data = fb_face_data(fb_weights(63, &[(FbExpression::JawDrop, 1.0)]));
+
+        let expressions = FaceExpressions::from_face_data(&face_data).unwrap();
+
+        assert_eq!(expressions.get(ArkitBlendshape::JawOpen), 1.0);
+        assert_eq!(expressions.get(ArkitBlendshape::TongueOut), 0.0);
+        assert_eq!(expressions.to_fb().len(), FB_EXPRESSION_COUNT);
+    }
+
+    #[test]
+    fn test_fb_short_vector() {
+        let face_data = fb_face_data(fb_weights(13, &[(FbExpression::EyesClosedL, 0.8)]));
+
+        let expressions = FaceExpressions::from_face_data(&face_data).unwrap();
+
+        assert_eq!(expressions.get(ArkitBlendshape::EyeBlinkLeft), 0.8);
+        assert_eq!(expressions.get(ArkitBlendshape::EyeBlinkRight), 0.0);
+        assert_eq!(expressions.get(ArkitBlendshape::JawOpen), 0.0);
+
+        let empty = FaceExpressions::from_face_data(&fb_face_data(vec![])).unwrap();
+        assert!(empty.blendshapes.iter().all(|w| *w == 0.0));
+    }
+
+    #[test]
+    fn test_htc_to_fb() {
+        let mut eye = vec![0.0; HTC_EYE_EXPRESSION_COUNT];
+        eye[HtcEyeExpression::LeftBlink as usize] = 1.0;
+        eye[HtcEyeExpression::RightWide as usize] = 0.4;
+        let mut lip = vec![0.0; HTC_LIP_EXPRESSION_COUNT];
+        lip[HtcLipExpression::JawOpen as usize] = 0.7;
+        lip[HtcLipExpression::MouthSmileRight as usize] = 0.6;
+        let mut face_data = FaceData {
+            htc_eye_expression: Some(eye),
+            htc_lip_expression: Some(lip),
+            ..Default::default()
+        };
+
+        convert_face_data(&mut face_data, FaceExpressionsFormat::Fb);
+
+        assert!(face_data.htc_eye_expression.is_none());
+        assert!(face_data.htc_lip_expression.is_none());
+        let fb = face_data.fb_face_expression.unwrap();
+        assert_eq!(fb.len(), FB_EXPRESSION_COUNT);
+        assert_eq!(fb[FbExpression::EyesClosedL as usize], 1.0);
+        assert_eq!(fb[FbExpression::EyesClosedR as usize], 0.0);
+        assert_eq!(fb[FbExpression::UpperLidRaiserR as usize], 0.4);
+        assert_eq!(fb[FbExpression::JawDrop as usize], 0.7);
+        assert_eq!(fb[FbExpression::LipCornerPullerR as usize], 0.6);
+    }
+
+    #[test]
+    fn test_htc_eye_only() {
+        let mut eye = vec![0.0; HTC_EYE_EXPRESSION_COUNT];
+        eye[HtcEyeExpression::RightBlink as usize] = 0.5;
+        let face_data = FaceData {
+            htc_eye_expression: Some(eye.clone()),
+            ..Default::default()
+        };
+
+        let expressions = FaceExpressions::from_face_data(&face_data).unwrap();
+
+        assert!(expressions.has_eyes && !expressions.has_lower_face);
+        assert_eq!(expressions.get(ArkitBlendshape::EyeBlinkRight), 0.5);
+        assert_eq!(expressions.to_htc_eye(), Some(eye));
+        assert_eq!(expressions.to_htc_lip(), None);
+        assert_eq!(
+            expressions.available_named_blendshapes().count(),
+            ARKIT_EYE_BLENDSHAPE_COUNT
+        );
+    }
+
+    #[test]
+    fn test_fb_to_htc() {
+        let mut face_data = fb_face_data(fb_weights(
+            FB_EXPRESSION_COUNT,
+            &[
+                (FbExpression::EyesClosedR, 0.3),
+                (FbExpression::JawDrop, 0.9),
+            ],
+        ));
+
+        convert_face_data(&mut face_data, FaceExpressionsFormat::Htc);
+
+        assert!(face_data.fb_face_expression.is_none());
+        let eye = face_data.htc_eye_expression.unwrap();
+        let lip = face_data.htc_lip_expression.unwrap();
+        assert_eq!(eye.len(), HTC_EYE_EXPRESSION_COUNT);
+        assert_eq!(lip.len(), HTC_LIP_EXPRESSION_COUNT);
+        assert_eq!(eye[HtcEyeExpression::RightBlink as usize], 0.3);
+        assert_eq!(lip[HtcLipExpression::JawOpen as usize], 0.9);
+    }
+
+    #[test]
+    fn test_native_and_empty_are_untouched() {
+        let weights = fb_weights(FB_EXPRESSION_COUNT, &[(FbExpression::JawDrop, 0.5)]);
+        let mut face_data = fb_face_data(weights.clone());
+        convert_face_data(&mut face_data, FaceExpressionsFormat::Native);
+        assert_eq!(face_data.fb_face_expression, Some(weights));
+
+        assert!(FaceExpressions::from_face_data(&FaceData::default()).is_none());
+        let mut face_data = FaceData::default();
+        convert_face_data(&mut face_data, FaceExpressionsFormat::Htc);
+        assert!(face_data.htc_eye_expression.is_none());
+    }
+}
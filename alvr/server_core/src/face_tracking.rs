@@ -1,7 +1,11 @@
+use crate::{
+    face_expressions::{ArkitBlendshape, FaceExpressions},
+    osc_output::OSC_TIME_IMMEDIATELY,
+};
 use alvr_common::{anyhow::Result, glam::EulerRot};
 use alvr_packets::FaceData;
 use alvr_session::FaceTrackingSinkConfig;
-use rosc::{OscMessage, OscPacket, OscType};
+use rosc::{OscBundle, OscMessage, OscPacket, OscType};
 use std::{f32::consts::PI, mem, net::UdpSocket};
 
 const RAD_TO_DEG: f32 = 180.0 / PI;
@@ -20,6 +24,7 @@ impl FaceTrackingSink {
         let port = match config {
             FaceTrackingSinkConfig::VrchatEyeOsc { port } => port,
             FaceTrackingSinkConfig::VrcFaceTracking => VRCFT_PORT,
+            FaceTrackingSinkConfig::ArkitOsc { port } => port,
         };
 
         let socket = UdpSocket::bind(format!("127.0.0.1:{local_osc_port}"))?;
@@ -89,26 +94,16 @@ impl FaceTrackingSink {
                     );
                 }
 
-                let left_eye_blink = face_data
-                    .fb_face_expression
-                    .as_ref()
-                    .map(|v| v[12])
-                    .or_else(|| face_data.htc_eye_expression.as_ref().map(|v| v[0]));
-                let right_eye_blink = face_data
-                    .fb_face_expression
-                    .map(|v| v[13])
-                    .or_else(|| face_data.htc_eye_expression.map(|v| v[2]));
-
-                if let (Some(left), Some(right)) = (left_eye_blink, right_eye_blink) {
+                if let Some(expressions) = FaceExpressions::from_face_data(&face_data)
+                    .filter(|expressions| expressions.has_eyes)
+                {
+                    let left = expressions.get(ArkitBlendshape::EyeBlinkLeft);
+                    let right = expressions.get(ArkitBlendshape::EyeBlinkRight);
+
                     self.send_osc_message(
                         "/tracking/eye/EyesClosedAmount",
                         vec![OscType::Float((left + right) / 2.0)],
                     );
-                } else if let Some(blink) = left_eye_blink.or(right_eye_blink) {
-                    self.send_osc_message(
-                        "/tracking/eye/EyesClosedAmount",
-                        vec![OscType::Float(blink)],
-                    );
                 }
             }
             FaceTrackingSinkConfig::VrcFaceTracking { .. } => {
@@ -144,6 +139,26 @@ impl FaceTrackingSink {
 
                 self.socket.send(&self.packet_buffer).ok();
             }
+            FaceTrackingSinkConfig::ArkitOsc { .. } => {
+                if let Some(expressions) = FaceExpressions::from_face_data(&face_data) {
+                    let bundle = OscPacket::Bundle(OscBundle {
+                        timetag: OSC_TIME_IMMEDIATELY,
+                        content: expressions
+                            .available_named_blendshapes()
+                            .map(|(name, weight)| {
+                                OscPacket::Message(OscMessage {
+                                    addr: format!("/arkit/{name}"),
+                                    args: vec![OscType::Float(weight)],
+                                })
+                            })
+                            .collect(),
+                    });
+
+                    if let Ok(buffer) = rosc::encoder::encode(&bundle) {
+                        self.socket.send(&buffer).ok();
+                    }
+                }
+            }
         }
     }
 }
@@ -1,15 +1,284 @@
-use alvr_packets::Haptics;
-use alvr_session::HapticsConfig;
+use alvr_common::HAND_LEFT_ID;
+use alvr_packets::{Haptics, HapticsEnvelope, HapticsPacket, PcmHaptics};
+use alvr_session::{HapticsConfig, HapticsFrequencyResponsePoint};
 use std::time::Duration;
 
-pub fn map_haptics(config: &HapticsConfig, haptics: Haptics) -> Haptics {
-    Haptics {
-        duration: Duration::max(
-            haptics.duration,
-            Duration::from_secs_f32(config.min_duration_s),
-        ),
-        amplitude: config.intensity_multiplier
-            * f32::powf(haptics.amplitude, config.amplitude_curve),
-        ..haptics
+// Avoid huge amplitudes when compensating frequencies the actuator barely reproduces
+const MIN_FREQUENCY_RESPONSE_GAIN: f32 = 0.1;
+
+// Piecewise linear interpolation of the response curve, constant outside of its range
+fn frequency_response_gain(points: &[HapticsFrequencyResponsePoint], frequency: f32) -> f32 {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+
+    let gain = match points.iter().position(|p| p.frequency >= frequency) {
+        None => points.last().map(|p| p.gain).unwrap_or(1.0),
+        Some(0) => points[0].gain,
+        Some(idx) => {
+            let low = &points[idx - 1];
+            let high = &points[idx];
+            let t = (frequency - low.frequency) / (high.frequency - low.frequency);
+
+            low.gain + (high.gain - low.gain) * t
+        }
+    };
+
+    f32::max(gain, MIN_FREQUENCY_RESPONSE_GAIN)
+}
+
+fn map_amplitude(config: &HapticsConfig, amplitude: f32) -> f32 {
+    config.intensity_multiplier * f32::powf(amplitude.clamp(0.0, 1.0), config.amplitude_curve)
+}
+
+pub fn map_haptics(config: &HapticsConfig, haptics: Haptics) -> HapticsPacket {
+    let frequency_response = if haptics.device_id == *HAND_LEFT_ID {
+        &config.left_frequency_response
+    } else {
+        &config.right_frequency_response
+    };
+
+    HapticsPacket::Vibration {
+        haptics: Haptics {
+            duration: Duration::max(
+                haptics.duration,
+                Duration::from_secs_f32(config.min_duration_s),
+            ),
+            amplitude: (map_amplitude(config, haptics.amplitude)
+                / frequency_response_gain(frequency_response, haptics.frequency))
+            .clamp(0.0, 1.0),
+            ..haptics
+        },
+        envelope: HapticsEnvelope {
+            attack: Duration::from_secs_f32(config.envelope_attack_s.max(0.0)),
+            release: Duration::from_secs_f32(config.envelope_release_s.max(0.0)),
+        },
+    }
+}
+
+// Long buffers are split in chunks so that they fit in a few shards and the client can start
+// playback early. All chunks except the first are queued.
+pub fn map_pcm_haptics(config: &HapticsConfig, haptics: PcmHaptics) -> Vec<HapticsPacket> {
+    let samples = haptics
+        .samples
+        .iter()
+        .map(|s| map_amplitude(config, *s).clamp(0.0, 1.0))
+        .collect::<Vec<_>>();
+
+    let chunk_size = usize::max(
+        (config.pcm_chunk_duration_s * haptics.sample_rate) as usize,
+        1,
+    );
+
+    samples
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            HapticsPacket::Pcm(PcmHaptics {
+                device_id: haptics.device_id,
+                sample_rate: haptics.sample_rate,
+                samples: chunk.to_vec(),
+                append: haptics.append || idx > 0,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(frequency: f32, gain: f32) -> HapticsFrequencyResponsePoint {
+        HapticsFrequencyResponsePoint { frequency, gain }
+    }
+
+    fn test_config() -> HapticsConfig {
+        HapticsConfig {
+            intensity_multiplier: 1.0,
+            amplitude_curve: 1.0,
+            min_duration_s: 0.01,
+            envelope_attack_s: 0.0,
+            envelope_release_s: 0.0,
+            left_frequency_response: vec![],
+            right_frequency_response: vec![],
+            pcm_chunk_duration_s: 0.1,
+        }
+    }
+
+    fn pcm(samples: Vec<f32>, sample_rate: f32, append: bool) -> PcmHaptics {
+        PcmHaptics {
+            device_id: *HAND_LEFT_ID,
+            sample_rate,
+            samples,
+            append,
+        }
+    }
+
+    fn pcm_packets(packets: Vec<HapticsPacket>) -> Vec<PcmHaptics> {
+        packets
+            .into_iter()
+            .map(|packet| match packet {
+                HapticsPacket::Pcm(haptics) => haptics,
+                HapticsPacket::Vibration { .. } => panic!("Expected a PCM packet"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frequency_response_flat_when_empty() {
+        assert_eq!(frequency_response_gain(&[], 160.0), 1.0);
+    }
+
+    #[test]
+    fn test_frequency_response_interpolation() {
+        // Unsorted on purpose
+        let points = [point(200.0, 0.5), point(100.0, 1.0), point(300.0, 0.7)];
+
+        assert_eq!(frequency_response_gain(&points, 100.0), 1.0);
+        assert_eq!(frequency_response_gain(&points, 150.0), 0.75);
+        assert_eq!(frequency_response_gain(&points, 200.0), 0.5);
+        assert!((frequency_response_gain(&points, 250.0) - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_frequency_response_constant_outside_range() {
+        let points = [point(100.0, 0.8), point(200.0, 0.4)];
+
+        assert_eq!(frequency_response_gain(&points, 0.0), 0.8);
+        assert_eq!(frequency_response_gain(&points, 1000.0), 0.4);
+    }
+
+    #[test]
+    fn test_frequency_response_minimum_gain() {
+        let points = [point(100.0, 0.0), point(200.0, 1.0)];
+
+        assert_eq!(
+            frequency_response_gain(&points, 100.0),
+            MIN_FREQUENCY_RESPONSE_GAIN
+        );
+    }
+
+    #[test]
+    fn test_map_haptics_compensates_frequency_response() {
+        let config = HapticsConfig {
+            left_frequency_response: vec![point(100.0, 0.5)],
+            ..test_config()
+        };
+
+        let packet = map_haptics(
+            &config,
+            Haptics {
+                device_id: *HAND_LEFT_ID,
+                duration: Duration::ZERO,
+                frequency: 100.0,
+                amplitude: 0.4,
+            },
+        );
+
+        let HapticsPacket::Vibration { haptics, .. } = packet else {
+            panic!("Expected a vibration packet");
+        };
+        assert_eq!(haptics.amplitude, 0.8);
+        assert_eq!(haptics.duration, Duration::from_secs_f32(0.01));
+    }
+
+    #[test]
+    fn test_map_haptics_clamps_amplitude() {
+        let config = HapticsConfig {
+            intensity_multiplier: 1.5,
+            left_frequency_response: vec![point(100.0, 0.5)],
+            ..test_config()
+        };
+
+        let amplitude = |amplitude| {
+            let HapticsPacket::Vibration { haptics, .. } = map_haptics(
+                &config,
+                Haptics {
+                    device_id: *HAND_LEFT_ID,
+                    duration: Duration::ZERO,
+                    frequency: 100.0,
+                    amplitude,
+                },
+            ) else {
+                panic!("Expected a vibration packet");
+            };
+
+            haptics.amplitude
+        };
+
+        assert_eq!(amplitude(0.8), 1.0);
+        assert_eq!(amplitude(-0.5), 0.0);
+    }
+
+    #[test]
+    fn test_map_pcm_haptics_chunks() {
+        // 0.1s chunks at 100Hz are 10 samples long
+        let samples = (0..25).map(|i| i as f32 / 25.0).collect::<Vec<_>>();
+
+        let packets = pcm_packets(map_pcm_haptics(
+            &test_config(),
+            pcm(samples.clone(), 100.0, false),
+        ));
+
+        assert_eq!(
+            packets.iter().map(|p| p.samples.len()).collect::<Vec<_>>(),
+            [10, 10, 5]
+        );
+        // Only the first chunk restarts playback
+        assert_eq!(
+            packets.iter().map(|p| p.append).collect::<Vec<_>>(),
+            [false, true, true]
+        );
+        assert_eq!(
+            packets
+                .iter()
+                .flat_map(|p| p.samples.iter().copied())
+                .collect::<Vec<_>>(),
+            samples
+        );
+        assert!(packets
+            .iter()
+            .all(|p| p.device_id == *HAND_LEFT_ID && p.sample_rate == 100.0));
+    }
+
+    #[test]
+    fn test_map_pcm_haptics_append() {
+        let packets = pcm_packets(map_pcm_haptics(
+            &test_config(),
+            pcm(vec![0.5; 5], 100.0, true),
+        ));
+
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].append);
+    }
+
+    #[test]
+    fn test_map_pcm_haptics_amplitude() {
+        let config = HapticsConfig {
+            intensity_multiplier: 2.0,
+            amplitude_curve: 2.0,
+            ..test_config()
+        };
+
+        let packets = pcm_packets(map_pcm_haptics(
+            &config,
+            pcm(vec![-1.0, 0.5, 2.0], 100.0, false),
+        ));
+
+        // Samples are clamped to [0, 1] before and after mapping
+        assert_eq!(packets[0].samples, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_map_pcm_haptics_tiny_chunks() {
+        // The chunk size is at least one sample
+        let config = HapticsConfig {
+            pcm_chunk_duration_s: 0.0,
+            ..test_config()
+        };
+
+        let packets = pcm_packets(map_pcm_haptics(&config, pcm(vec![0.1; 3], 100.0, false)));
+
+        assert_eq!(packets.len(), 3);
+        assert!(pcm_packets(map_pcm_haptics(&config, pcm(vec![], 100.0, false))).is_empty());
     }
 }
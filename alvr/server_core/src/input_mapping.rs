@@ -1,8 +1,8 @@
 use alvr_common::*;
 use alvr_packets::{ButtonEntry, ButtonValue};
 use alvr_session::{
-    AutomaticButtonMappingConfig, BinaryToScalarStates, ButtonBindingTarget, ButtonMappingType,
-    ControllersEmulationMode, HysteresisThreshold, Range,
+    AutomaticButtonMappingConfig, BinaryToScalarStates, ButtonBindingTarget, ButtonMappingProfile,
+    ButtonMappingType, ControllersConfig, ControllersEmulationMode, HysteresisThreshold, Range,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -657,3 +657,25 @@ impl ButtonMappingManager {
         destination_buttons
     }
 }
+
+// Mappings from the active per-game profile take precedence over the manual mappings in settings.
+// Automatic mappings are used only if neither is available and the source button set is known.
+pub fn controller_button_mapping_manager(
+    config: &ControllersConfig,
+    source_button_set: Option<&HashSet<u64>>,
+    mapping_profile: Option<&ButtonMappingProfile>,
+) -> Option<ButtonMappingManager> {
+    if let Some(profile) = mapping_profile {
+        Some(ButtonMappingManager::new_manual(&profile.button_mappings))
+    } else if let Some(mappings) = &config.button_mappings {
+        Some(ButtonMappingManager::new_manual(mappings))
+    } else {
+        source_button_set.map(|button_set| {
+            ButtonMappingManager::new_automatic(
+                button_set,
+                &config.emulation_mode,
+                &config.button_mapping_config,
+            )
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use crate::connection::HANDSHAKE_ACTION_TIMEOUT;
+use alvr_common::{con_bail, info, AnyhowToCon, ConResult};
+use alvr_packets::{ClientControlPacket, ConnectionPath, ServerControlPacket};
+use alvr_session::InternetStreamingConfig;
+use alvr_sockets::{
+    ControlSocketReceiver, ControlSocketSender, InternetSocketBuilder, StreamSocket,
+};
+use std::{net::SocketAddr, time::Duration};
+
+// Called after the client sent its public address. The streamer decides the connection path, since
+// hole punching must have succeeded on both sides to be usable.
+// Returns the stream socket, the connection path and its round trip time
+pub fn connect_stream_socket(
+    builder: InternetSocketBuilder,
+    client_address: SocketAddr,
+    config: &InternetStreamingConfig,
+    max_packet_size: usize,
+    control_sender: &mut ControlSocketSender<ServerControlPacket>,
+    control_receiver: &mut ControlSocketReceiver<ClientControlPacket>,
+) -> ConResult<(StreamSocket, ConnectionPath, Duration)> {
+    let punch_result = if config.hole_punching {
+        builder
+            .punch_hole(
+                client_address,
+                Duration::from_secs_f32(config.hole_punching_timeout_s),
+            )
+            .to_con()?
+    } else {
+        None
+    };
+
+    let (client_punched, client_rendezvous_rtt) =
+        match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)? {
+            ClientControlPacket::HolePunchResult {
+                success,
+                rendezvous_rtt,
+            } => (success, rendezvous_rtt),
+            _ => con_bail!("Got unexpected packet waiting for hole punch result"),
+        };
+
+    let relay_session = rand::random();
+
+    match punch_result {
+        Some((round_trip_time, client_address)) if client_punched => {
+            info!("Hole punching succeeded, streaming directly");
+
+            control_sender
+                .send(&ServerControlPacket::InternetPath {
+                    path: ConnectionPath::HolePunched,
+                    relay_session,
+                })
+                .to_con()?;
+
+            let socket = builder.connect_direct(
+                client_address,
+                max_packet_size,
+                HANDSHAKE_ACTION_TIMEOUT,
+            )?;
+
+            Ok((socket, ConnectionPath::HolePunched, round_trip_time))
+        }
+        _ if config.relay_fallback => {
+            info!("Streaming through the relay");
+
+            control_sender
+                .send(&ServerControlPacket::InternetPath {
+                    path: ConnectionPath::Relayed,
+                    relay_session,
+                })
+                .to_con()?;
+
+            // Both peers talk to the relay, so the round trip is the sum of the two
+            let round_trip_time = builder.rendezvous_rtt + client_rendezvous_rtt;
+            let socket = builder.connect_relayed(
+                relay_session,
+                max_packet_size,
+                HANDSHAKE_ACTION_TIMEOUT,
+            )?;
+
+            Ok((socket, ConnectionPath::Relayed, round_trip_time))
+        }
+        _ => con_bail!("Hole punching failed and the relay fallback is disabled"),
+    }
+}
@@ -2,24 +2,37 @@ mod bitrate;
 mod body_tracking;
 mod c_api;
 mod connection;
+mod diagnostics;
+mod event_hooks;
+mod face_expressions;
 mod face_tracking;
 mod hand_gestures;
 mod haptics;
 mod input_mapping;
+mod internet;
 mod logging_backend;
+mod osc_output;
 mod sockets;
+mod spectator;
 mod statistics;
 mod tracking;
+mod usb;
 mod web_server;
 
 pub use c_api::*;
+pub use face_expressions::{ArkitBlendshape, FaceExpressions};
 pub use logging_backend::init_logging;
 pub use tracking::get_hand_skeleton_offsets;
 
-use crate::connection::VideoPacket;
+use crate::{
+    connection::{ResumableSession, VideoPacket},
+    event_hooks::HookEventData,
+    spectator::SpectatorSender,
+};
 use alvr_common::{
     dbg_server_core, error,
     glam::Vec2,
+    info,
     once_cell::sync::Lazy,
     parking_lot::{Mutex, RwLock},
     settings_schema::Switch,
@@ -28,16 +41,18 @@ use alvr_common::{
 use alvr_events::{EventType, HapticsEvent};
 use alvr_filesystem as afs;
 use alvr_packets::{
-    BatteryInfo, ButtonEntry, ClientListAction, DecoderInitializationConfig, Haptics, Tracking,
-    VideoPacketHeader,
+    BatteryInfo, ButtonEntry, ClientListAction, DecoderInitializationConfig, Haptics,
+    HapticsPacket, PcmHaptics, Tracking, VideoPacketHeader,
 };
 use alvr_server_io::ServerSessionManager;
-use alvr_session::{CodecType, OpenvrProperty, Settings};
+use alvr_session::{
+    ButtonMappingProfile, CodecType, HookEvent, OpenvrProperty, OpenvrStreamConfig, Settings,
+};
 use alvr_sockets::StreamSender;
 use bitrate::{BitrateManager, DynamicEncoderParams};
 use statistics::StatisticsManager;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     env,
     fs::File,
     io::Write,
@@ -80,6 +95,8 @@ pub enum ServerCoreEvent {
         device_id: u64,
         prop: OpenvrProperty,
     },
+    // Sent before ClientConnected when only stream-time properties changed
+    UpdateStreamConfig(Box<OpenvrStreamConfig>),
     ClientConnected,
     ClientDisconnected,
     Battery(BatteryInfo),
@@ -106,8 +123,13 @@ pub struct ConnectionContext {
     video_recording_file: Mutex<Option<File>>,
     connection_threads: Mutex<Vec<JoinHandle<()>>>,
     clients_to_be_removed: Mutex<HashSet<String>>,
+    clients_to_diagnose: Mutex<HashSet<String>>,
     video_channel_sender: Mutex<Option<SyncSender<VideoPacket>>>,
-    haptics_sender: Mutex<Option<StreamSender<Haptics>>>,
+    spectators: Mutex<HashMap<String, SpectatorSender>>,
+    haptics_sender: Mutex<Option<StreamSender<HapticsPacket>>>,
+    steamvr_app_key: Mutex<Option<String>>,
+    active_button_mapping_profile: Mutex<Option<ButtonMappingProfile>>,
+    resumable_sessions: Mutex<HashMap<String, ResumableSession>>,
 }
 
 pub fn create_recording_file(connection_context: &ConnectionContext, settings: &Settings) {
@@ -142,6 +164,44 @@ pub fn create_recording_file(connection_context: &ConnectionContext, settings: &
     }
 }
 
+// Called when the SteamVR application in focus or the profiles library changes. Profiles are compared
+// by content, so that an edited profile with the same name is applied too.
+pub fn update_active_button_mapping_profile(connection_context: &ConnectionContext) {
+    let per_game_profiles_enabled = SESSION_MANAGER
+        .read()
+        .settings()
+        .headset
+        .controllers
+        .as_option()
+        .map(|config| config.per_game_button_mapping_profiles)
+        .unwrap_or(false);
+
+    let app_key = connection_context.steamvr_app_key.lock().clone();
+    let profile = if let (true, Some(app_key)) = (per_game_profiles_enabled, app_key) {
+        alvr_server_io::find_button_mapping_profile(
+            alvr_server_io::get_button_mapping_profiles(
+                &FILESYSTEM_LAYOUT
+                    .get()
+                    .unwrap()
+                    .button_mapping_profiles_dir(),
+            ),
+            &app_key,
+        )
+    } else {
+        None
+    };
+
+    let mut active_profile_lock = connection_context.active_button_mapping_profile.lock();
+    if *active_profile_lock != profile {
+        if let Some(profile) = &profile {
+            info!("Activating button mapping profile \"{}\"", profile.name);
+        } else if let Some(profile) = &*active_profile_lock {
+            info!("Deactivating button mapping profile \"{}\"", profile.name);
+        }
+    }
+    *active_profile_lock = profile;
+}
+
 pub fn notify_restart_driver() {
     let mut system = sysinfo::System::new_with_specifics(
         RefreshKind::new().with_processes(ProcessRefreshKind::everything()),
@@ -207,8 +267,13 @@ impl ServerCoreContext {
             video_recording_file: Mutex::new(None),
             connection_threads: Mutex::new(Vec::new()),
             clients_to_be_removed: Mutex::new(HashSet::new()),
+            clients_to_diagnose: Mutex::new(HashSet::new()),
             video_channel_sender: Mutex::new(None),
+            spectators: Mutex::new(HashMap::new()),
             haptics_sender: Mutex::new(None),
+            steamvr_app_key: Mutex::new(None),
+            active_button_mapping_profile: Mutex::new(None),
+            resumable_sessions: Mutex::new(HashMap::new()),
         });
 
         let webserver_runtime = Runtime::new().unwrap();
@@ -279,6 +344,36 @@ impl ServerCoreContext {
         }
     }
 
+    pub fn send_haptics_pcm(&self, haptics: PcmHaptics) {
+        dbg_server_core!("send_haptics_pcm");
+
+        let haptics_config = SESSION_MANAGER
+            .read()
+            .settings()
+            .headset
+            .controllers
+            .as_option()
+            .and_then(|c| c.haptics.as_option().cloned());
+
+        if let (Some(config), Some(sender)) = (
+            haptics_config,
+            &mut *self.connection_context.haptics_sender.lock(),
+        ) {
+            for packet in haptics::map_pcm_haptics(&config, haptics) {
+                sender.send_header(&packet).ok();
+            }
+        }
+    }
+
+    // Select the button mapping profile associated with the SteamVR application in focus
+    pub fn report_steamvr_app_key(&self, app_key: Option<String>) {
+        dbg_server_core!("report_steamvr_app_key");
+
+        *self.connection_context.steamvr_app_key.lock() = app_key;
+
+        update_active_button_mapping_profile(&self.connection_context);
+    }
+
     pub fn set_video_config_nals(&self, config_buffer: Vec<u8>, codec: CodecType) {
         dbg_server_core!("set_video_config_nals");
 
@@ -350,6 +445,24 @@ impl ServerCoreContext {
                     file.write_all(&nal_buffer).ok();
                 }
 
+                let mut spectator_requested_idr = false;
+                for (hostname, spectator) in &mut *self.connection_context.spectators.lock() {
+                    let header = VideoPacketHeader {
+                        timestamp: target_timestamp,
+                        is_idr,
+                    };
+                    if spectator.send_video(header, nal_buffer.clone()) {
+                        warn!("Spectator {hostname} fell behind, requesting IDR");
+                        spectator_requested_idr = true;
+                    }
+                }
+                if spectator_requested_idr {
+                    self.connection_context
+                        .events_sender
+                        .send(ServerCoreEvent::RequestIDR)
+                        .ok();
+                }
+
                 if matches!(
                     sender.try_send(VideoPacket {
                         header: VideoPacketHeader {
@@ -385,15 +498,42 @@ impl ServerCoreContext {
     pub fn get_dynamic_encoder_params(&self) -> Option<DynamicEncoderParams> {
         dbg_server_core!("get_dynamic_encoder_params");
 
-        let pair = {
+        static BITRATE_DROPPED: AtomicBool = AtomicBool::new(false);
+
+        // The session lock must be released before locking the managers
+        let (bitrate_config, hooks_config) = {
             let session_manager_lock = SESSION_MANAGER.read();
-            self.connection_context
-                .bitrate_manager
-                .lock()
-                .get_encoder_params(&session_manager_lock.settings().video.bitrate)
+            let settings = session_manager_lock.settings();
+
+            (
+                settings.video.bitrate.clone(),
+                settings.connection.event_hooks.clone(),
+            )
         };
 
+        let pair = self
+            .connection_context
+            .bitrate_manager
+            .lock()
+            .get_encoder_params(&bitrate_config);
+
         if let Some((params, stats)) = pair {
+            // Trigger only once when the bitrate crosses the threshold
+            let bitrate_mbps = params.bitrate_bps as f32 / 1e6;
+            let dropped = bitrate_mbps < hooks_config.bitrate_dropped_threshold_mbps;
+            if dropped && !BITRATE_DROPPED.swap(true, Ordering::Relaxed) {
+                event_hooks::trigger(
+                    &hooks_config,
+                    HookEvent::BitrateDropped,
+                    HookEventData {
+                        bitrate_mbps: Some(bitrate_mbps),
+                        ..Default::default()
+                    },
+                );
+            } else if !dropped {
+                BITRATE_DROPPED.store(false, Ordering::Relaxed);
+            }
+
             if let Some(stats_manager) = &mut *self.connection_context.statistics_manager.lock() {
                 stats_manager.report_nominal_bitrate_stats(stats);
             }
@@ -447,6 +587,12 @@ impl ServerCoreContext {
 
         self.is_restarting.set(true);
 
+        event_hooks::trigger(
+            &SESSION_MANAGER.read().settings().connection.event_hooks,
+            HookEvent::SteamvrRestarting,
+            HookEventData::default(),
+        );
+
         // drop is called here for self
     }
 }
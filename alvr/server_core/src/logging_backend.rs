@@ -1,5 +1,9 @@
 use crate::SESSION_MANAGER;
-use alvr_common::{log::LevelFilter, once_cell::sync::Lazy, LogEntry, LogSeverity};
+use alvr_common::{
+    log::{Level, LevelFilter},
+    once_cell::sync::Lazy,
+    LogEntry, LogSeverity, LogSource,
+};
 use alvr_events::{Event, EventType};
 use chrono::Local;
 use fern::Dispatch;
@@ -7,9 +11,15 @@ use std::{fs, path::PathBuf};
 use tokio::sync::broadcast;
 
 static CHANNEL_CAPACITY: usize = 256;
-pub static LOGGING_EVENTS_SENDER: Lazy<broadcast::Sender<Event>> =
+// Events are written to the session log already serialized, under this target
+const EVENT_LOG_TARGET: &str = "alvr_events";
+pub static EVENTS_SENDER: Lazy<broadcast::Sender<Event>> =
     Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
 
+fn timestamp() -> String {
+    Local::now().format("%H:%M:%S.%f").to_string()
+}
+
 pub fn init_logging(session_log_path: Option<PathBuf>, crash_log_path: Option<PathBuf>) {
     let debug_groups_config = SESSION_MANAGER
         .read()
@@ -19,33 +29,47 @@ pub fn init_logging(session_log_path: Option<PathBuf>, crash_log_path: Option<Pa
         .debug_groups
         .clone();
 
+    alvr_events::set_event_listener(|event_type| {
+        let event = Event {
+            timestamp: timestamp(),
+            event_type,
+        };
+
+        // Keep the events in the session log too, it is packaged in the support bundles
+        if let Ok(event_json) = serde_json::to_string(&event) {
+            alvr_common::log::info!(target: EVENT_LOG_TARGET, "{event_json}");
+        }
+
+        EVENTS_SENDER.send(event).ok();
+    });
+
     let mut log_dispatch = Dispatch::new()
         // Note: meta::target() is in the format <crate>::<module>
-        .filter(|meta| !meta.target().starts_with("mdns_sd"))
+        .filter(move |meta| {
+            !meta.target().starts_with("mdns_sd")
+                && (meta.level() <= Level::Info
+                    || alvr_common::filter_debug_groups(meta.target(), &debug_groups_config))
+        })
         .format(move |out, message, record| {
-            let maybe_event = format!("{message}");
-            let event_type = if maybe_event.starts_with('{') && maybe_event.ends_with('}') {
-                serde_json::from_str(&maybe_event).unwrap()
-            } else {
-                let log_level = record.level();
-                if log_level <= LevelFilter::Info
-                    || alvr_common::filter_debug_groups(&maybe_event, &debug_groups_config)
-                {
-                    EventType::Log(LogEntry {
-                        severity: LogSeverity::from_log_level(record.level()),
-                        content: message.to_string(),
-                    })
-                } else {
-                    return;
-                }
-            };
+            // Already published by the event listener
+            if record.target() == EVENT_LOG_TARGET {
+                out.finish(*message);
+
+                return;
+            }
+
             let event = Event {
-                timestamp: Local::now().format("%H:%M:%S.%f").to_string(),
-                event_type,
+                timestamp: timestamp(),
+                event_type: EventType::Log(LogEntry::new(
+                    LogSeverity::from_log_level(record.level()),
+                    message.to_string(),
+                    LogSource::from_log_target(record.target()),
+                    alvr_common::debug_group_from_target(record.target()).map(String::from),
+                )),
             };
             out.finish(format_args!("{}", serde_json::to_string(&event).unwrap()));
 
-            LOGGING_EVENTS_SENDER.send(event).ok();
+            EVENTS_SENDER.send(event).ok();
         });
 
     if cfg!(debug_assertions) {
@@ -0,0 +1,349 @@
+use crate::face_expressions::FaceExpressions;
+use alvr_common::{
+    anyhow::Result,
+    glam::{EulerRot, Quat, Vec3},
+    DeviceMotion, Pose, DEVICE_ID_TO_PATH,
+};
+use alvr_packets::FaceData;
+use alvr_session::{OscCoordinateConvention, OscOutputConfig, OscRotationFormat};
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use std::{
+    f32::consts::PI,
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+const RAD_TO_DEG: f32 = 180.0 / PI;
+
+// Special time tag value meaning "process immediately"
+pub(crate) const OSC_TIME_IMMEDIATELY: OscTime = OscTime {
+    seconds: 0,
+    fractional: 1,
+};
+
+fn fill_template(template: &str, substitutions: &[(&str, &str)]) -> String {
+    let mut address = template.to_owned();
+    for (key, value) in substitutions {
+        address = address.replace(&format!("{{{key}}}"), value);
+    }
+
+    address
+}
+
+// Input poses are in the OpenXR convention: right-handed, Y up, -Z forward, meters
+pub fn convert_pose(pose: Pose, convention: OscCoordinateConvention) -> Pose {
+    let p = pose.position;
+    let q = pose.orientation;
+
+    // A change of handedness mirrors the position, while the quaternion vector part (a
+    // pseudovector) gets mirrored and negated
+    match convention {
+        OscCoordinateConvention::OpenXr => pose,
+        OscCoordinateConvention::Unity => Pose {
+            orientation: Quat::from_xyzw(-q.x, -q.y, q.z, q.w),
+            position: Vec3::new(p.x, p.y, -p.z),
+        },
+        OscCoordinateConvention::Unreal => Pose {
+            orientation: Quat::from_xyzw(q.z, -q.x, -q.y, q.w),
+            position: Vec3::new(-p.z, p.x, p.y) * 100.0,
+        },
+    }
+}
+
+fn rotation_args(orientation: Quat, format: OscRotationFormat) -> Vec<OscType> {
+    match format {
+        OscRotationFormat::Quaternion => orientation
+            .to_array()
+            .into_iter()
+            .map(OscType::Float)
+            .collect(),
+        OscRotationFormat::EulerDegrees => {
+            let (x, y, z) = orientation.to_euler(EulerRot::XYZ);
+            vec![
+                OscType::Float(x * RAD_TO_DEG),
+                OscType::Float(y * RAD_TO_DEG),
+                OscType::Float(z * RAD_TO_DEG),
+            ]
+        }
+    }
+}
+
+fn pose_args(pose: Pose, config: &OscOutputConfig) -> Vec<OscType> {
+    let pose = convert_pose(pose, config.coordinate_convention);
+
+    let mut args = pose
+        .position
+        .to_array()
+        .into_iter()
+        .map(OscType::Float)
+        .collect::<Vec<_>>();
+    args.extend(rotation_args(pose.orientation, config.rotation_format));
+
+    args
+}
+
+// Expressions are sent as ARKit blendshapes, so addresses don't depend on the source extension
+fn face_expression_messages(template: &str, face_data: &FaceData) -> Vec<OscMessage> {
+    let Some(expressions) = FaceExpressions::from_face_data(face_data) else {
+        return vec![];
+    };
+
+    expressions
+        .available_named_blendshapes()
+        .map(|(name, weight)| OscMessage {
+            addr: fill_template(template, &[("expression", name)]),
+            args: vec![OscType::Float(weight)],
+        })
+        .collect()
+}
+
+pub struct OscOutputSink {
+    socket: UdpSocket,
+    last_send_instant: Option<Instant>,
+}
+
+impl OscOutputSink {
+    pub fn new(config: &OscOutputConfig) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(format!("{}:{}", config.host, config.port))?;
+
+        Ok(Self {
+            socket,
+            last_send_instant: None,
+        })
+    }
+
+    // Motions and hand skeletons should be already recentered. Eye gazes are relative to the head.
+    pub fn send_tracking(
+        &mut self,
+        config: &OscOutputConfig,
+        device_motions: &[(u64, DeviceMotion)],
+        hand_skeletons: [Option<[Pose; 26]>; 2],
+        local_eye_gazes: [Option<Pose>; 2],
+        face_data: &FaceData,
+    ) {
+        let now = Instant::now();
+        if let Some(last_instant) = self.last_send_instant {
+            if now < last_instant + Duration::from_secs_f32(1.0 / config.max_rate.max(1.0)) {
+                return;
+            }
+        }
+        self.last_send_instant = Some(now);
+
+        let mut messages = vec![];
+
+        if let Some(template) = config.device_motion_address.as_option() {
+            for (id, motion) in device_motions {
+                if let Some(path) = DEVICE_ID_TO_PATH.get(id) {
+                    let device = path.trim_start_matches("/user/");
+                    messages.push(OscMessage {
+                        addr: fill_template(template, &[("device", device)]),
+                        args: pose_args(motion.pose, config),
+                    });
+                }
+            }
+        }
+
+        if let Some(template) = config.hand_joint_address.as_option() {
+            for (hand, skeleton) in ["left", "right"].into_iter().zip(hand_skeletons) {
+                if let Some(skeleton) = skeleton {
+                    for (joint, pose) in skeleton.into_iter().enumerate() {
+                        messages.push(OscMessage {
+                            addr: fill_template(
+                                template,
+                                &[("hand", hand), ("joint", &joint.to_string())],
+                            ),
+                            args: pose_args(pose, config),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(template) = config.eye_gaze_address.as_option() {
+            for (eye, gaze) in ["left", "right"].into_iter().zip(local_eye_gazes) {
+                if let Some(gaze) = gaze {
+                    let orientation = convert_pose(gaze, config.coordinate_convention).orientation;
+                    messages.push(OscMessage {
+                        addr: fill_template(template, &[("eye", eye)]),
+                        args: rotation_args(orientation, config.rotation_format),
+                    });
+                }
+            }
+        }
+
+        if let Some(template) = config.face_expression_address.as_option() {
+            messages.extend(face_expression_messages(template, face_data));
+        }
+
+        if messages.is_empty() {
+            return;
+        }
+
+        if config.use_bundles {
+            let bundle = OscPacket::Bundle(OscBundle {
+                timetag: OSC_TIME_IMMEDIATELY,
+                content: messages.into_iter().map(OscPacket::Message).collect(),
+            });
+            if let Ok(buffer) = rosc::encoder::encode(&bundle) {
+                self.socket.send(&buffer).ok();
+            }
+        } else {
+            for message in messages {
+                if let Ok(buffer) = rosc::encoder::encode(&OscPacket::Message(message)) {
+                    self.socket.send(&buffer).ok();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face_expressions::{FbExpression, FB_EXPRESSION_COUNT};
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    // Direction vectors are mapped like positions, without the unit scale
+    fn convert_direction(v: Vec3, convention: OscCoordinateConvention) -> Vec3 {
+        match convention {
+            OscCoordinateConvention::OpenXr => v,
+            OscCoordinateConvention::Unity => Vec3::new(v.x, v.y, -v.z),
+            OscCoordinateConvention::Unreal => Vec3::new(-v.z, v.x, v.y),
+        }
+    }
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(
+            fill_template(
+                "/alvr/hand/{hand}/joint/{joint}",
+                &[("hand", "left"), ("joint", "3")]
+            ),
+            "/alvr/hand/left/joint/3"
+        );
+        assert_eq!(
+            fill_template("/{eye}/{eye}", &[("eye", "right")]),
+            "/right/right"
+        );
+        // Unknown placeholders are left untouched
+        assert_eq!(
+            fill_template("/alvr/{device}/{other}", &[("device", "head")]),
+            "/alvr/head/{other}"
+        );
+    }
+
+    #[test]
+    fn test_convert_pose_position() {
+        let pose = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::new(1.0, 2.0, 3.0),
+        };
+
+        assert_vec_eq(
+            convert_pose(pose, OscCoordinateConvention::OpenXr).position,
+            Vec3::new(1.0, 2.0, 3.0),
+        );
+        // Unity: left-handed, Y up, +Z forward, meters
+        assert_vec_eq(
+            convert_pose(pose, OscCoordinateConvention::Unity).position,
+            Vec3::new(1.0, 2.0, -3.0),
+        );
+        // Unreal: left-handed, X forward, Y right, Z up, centimeters
+        assert_vec_eq(
+            convert_pose(pose, OscCoordinateConvention::Unreal).position,
+            Vec3::new(-300.0, 100.0, 200.0),
+        );
+    }
+
+    #[test]
+    fn test_convert_pose_forward() {
+        let forward = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::NEG_Z,
+        };
+
+        assert_vec_eq(
+            convert_pose(forward, OscCoordinateConvention::Unity).position,
+            Vec3::Z,
+        );
+        assert_vec_eq(
+            convert_pose(forward, OscCoordinateConvention::Unreal).position,
+            Vec3::X * 100.0,
+        );
+    }
+
+    // Rotating a vector and then converting it must match rotating the converted vector with the
+    // converted orientation
+    #[test]
+    fn test_convert_pose_orientation() {
+        let orientations = [
+            Quat::from_rotation_x(0.3),
+            Quat::from_rotation_y(-1.2),
+            Quat::from_rotation_z(2.5),
+            Quat::from_euler(EulerRot::YXZ, 0.7, -0.4, 1.1),
+        ];
+        let vectors = [Vec3::X, Vec3::Y, Vec3::NEG_Z, Vec3::new(0.3, -0.5, 0.8)];
+
+        for convention in [
+            OscCoordinateConvention::OpenXr,
+            OscCoordinateConvention::Unity,
+            OscCoordinateConvention::Unreal,
+        ] {
+            for orientation in orientations {
+                let converted = convert_pose(
+                    Pose {
+                        orientation,
+                        position: Vec3::ZERO,
+                    },
+                    convention,
+                )
+                .orientation;
+                assert!(converted.is_normalized());
+
+                for v in vectors {
+                    assert_vec_eq(
+                        converted * convert_direction(v, convention),
+                        convert_direction(orientation * v, convention),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_face_expression_messages_use_blendshape_names() {
+        let mut weights = vec![0.0; FB_EXPRESSION_COUNT];
+        weights[FbExpression::JawDrop as usize] = 0.5;
+        let face_data = FaceData {
+            fb_face_expression: Some(weights),
+            ..Default::default()
+        };
+
+        let messages = face_expression_messages("/alvr/face/{expression}", &face_data);
+
+        let jaw_open = messages
+            .iter()
+            .find(|m| m.addr == "/alvr/face/jawOpen")
+            .unwrap();
+        assert_eq!(jaw_open.args, [OscType::Float(0.5)]);
+        assert_eq!(messages.len(), 52);
+    }
+
+    #[test]
+    fn test_face_expression_messages_skip_missing_parts() {
+        let face_data = FaceData {
+            htc_eye_expression: Some(vec![0.0; 14]),
+            ..Default::default()
+        };
+
+        let messages = face_expression_messages("/{expression}", &face_data);
+
+        assert_eq!(messages.len(), 14);
+        assert!(messages.iter().all(|m| m.addr.starts_with("/eye")));
+        assert!(face_expression_messages("/{expression}", &FaceData::default()).is_empty());
+    }
+}
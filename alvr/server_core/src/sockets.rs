@@ -2,64 +2,205 @@ use alvr_common::{
     anyhow::{bail, Result},
     warn, ToAny,
 };
+use alvr_session::DiscoveryConfig;
+use alvr_sockets::DiscoveryPacket;
 use flume::TryRecvError;
 use mdns_sd::{Receiver, ServiceDaemon, ServiceEvent};
-use std::{collections::HashMap, net::IpAddr};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, UdpSocket},
+};
+
+pub struct DiscoveredClient {
+    pub ip: IpAddr,
+    // Set if the client protocol does not match
+    pub incompatibility: Option<String>,
+}
+
+// Returns the reason of the incompatibility, if any
+fn check_client_protocol(hostname: &str, client_protocol: &str) -> Option<String> {
+    let server_protocol = alvr_common::protocol_id();
+    let client_is_dev = client_protocol.contains("-dev");
+    let server_is_dev = server_protocol.contains("-dev");
+
+    if client_protocol != server_protocol {
+        let reason = if client_is_dev && server_is_dev {
+            "Please use matching nightly versions."
+        } else if client_is_dev {
+            "Please use nightly server or stable client."
+        } else if server_is_dev {
+            "Please use stable server or nightly client."
+        } else {
+            "Please use matching stable versions."
+        };
+        let protocols = format!("Protocols: server={server_protocol}, client={client_protocol}");
+        warn!("Found incompatible client {hostname}! {reason}\n{protocols}");
+
+        Some(format!("{reason} {protocols}"))
+    } else {
+        None
+    }
+}
 
 pub struct WelcomeSocket {
-    mdns_receiver: Receiver<ServiceEvent>,
+    mdns_receiver: Option<Receiver<ServiceEvent>>,
+    broadcast_socket: Option<UdpSocket>,
+    rendezvous_socket: Option<UdpSocket>,
 }
 
 impl WelcomeSocket {
-    pub fn new() -> Result<Self> {
-        let mdns_receiver = ServiceDaemon::new()?.browse(alvr_sockets::MDNS_SERVICE_TYPE)?;
+    // mDNS failures are not fatal, since the other transports may work
+    pub fn new() -> Self {
+        let mdns_receiver = match ServiceDaemon::new()
+            .and_then(|daemon| daemon.browse(alvr_sockets::MDNS_SERVICE_TYPE))
+        {
+            Ok(receiver) => Some(receiver),
+            Err(e) => {
+                warn!("Failed to create mDNS discovery socket: {e}");
+                None
+            }
+        };
 
-        Ok(Self { mdns_receiver })
+        Self {
+            mdns_receiver,
+            broadcast_socket: None,
+            rendezvous_socket: None,
+        }
     }
 
-    // Returns: client IP, client hostname
-    pub fn recv_all(&mut self) -> Result<HashMap<String, IpAddr>> {
-        let mut clients = HashMap::new();
+    fn recv_mdns(&mut self, clients: &mut HashMap<String, DiscoveredClient>) -> Result<()> {
+        let Some(receiver) = &self.mdns_receiver else {
+            return Ok(());
+        };
 
         loop {
-            match self.mdns_receiver.try_recv() {
+            match receiver.try_recv() {
                 Ok(event) => {
                     if let ServiceEvent::ServiceResolved(info) = event {
                         let hostname = info
                             .get_property_val_str(alvr_sockets::MDNS_DEVICE_ID_KEY)
                             .unwrap_or_else(|| info.get_hostname());
-                        let address = *info.get_addresses().iter().next().to_any()?;
+                        let ip = *info.get_addresses().iter().next().to_any()?;
 
                         let client_protocol = info
                             .get_property_val_str(alvr_sockets::MDNS_PROTOCOL_KEY)
                             .to_any()?;
-                        let server_protocol = alvr_common::protocol_id();
-                        let client_is_dev = client_protocol.contains("-dev");
-                        let server_is_dev = server_protocol.contains("-dev");
-
-                        if client_protocol != server_protocol {
-                            let reason = if client_is_dev && server_is_dev {
-                                "Please use matching nightly versions."
-                            } else if client_is_dev {
-                                "Please use nightly server or stable client."
-                            } else if server_is_dev {
-                                "Please use stable server or nightly client."
-                            } else {
-                                "Please use matching stable versions."
-                            };
-                            let protocols = format!(
-                                "Protocols: server={server_protocol}, client={client_protocol}"
-                            );
-                            warn!("Found incompatible client {hostname}! {reason}\n{protocols}");
-                        }
-
-                        clients.insert(hostname.into(), address);
+                        let incompatibility = check_client_protocol(hostname, client_protocol);
+
+                        clients.insert(
+                            hostname.into(),
+                            DiscoveredClient {
+                                ip,
+                                incompatibility,
+                            },
+                        );
                     }
                 }
-                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Empty) => return Ok(()),
                 Err(e) => bail!(e),
             }
         }
+    }
+
+    fn recv_broadcast(&mut self, clients: &mut HashMap<String, DiscoveredClient>) -> Result<()> {
+        let socket = match &self.broadcast_socket {
+            Some(socket) => socket,
+            None => {
+                let socket = UdpSocket::bind((
+                    Ipv4Addr::UNSPECIFIED,
+                    alvr_sockets::DISCOVERY_BROADCAST_PORT,
+                ))?;
+                socket.set_nonblocking(true)?;
+
+                self.broadcast_socket.insert(socket)
+            }
+        };
+
+        while let Some((packet, address)) = alvr_sockets::recv_discovery_packet(socket)? {
+            if let DiscoveryPacket::ClientAnnouncement {
+                hostname,
+                protocol_id,
+            } = packet
+            {
+                let incompatibility = check_client_protocol(&hostname, &protocol_id);
+                clients.insert(
+                    hostname,
+                    DiscoveredClient {
+                        ip: address.ip(),
+                        incompatibility,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // The reply to the query is received on the next call
+    fn recv_rendezvous(
+        &mut self,
+        server_address: &str,
+        clients: &mut HashMap<String, DiscoveredClient>,
+    ) -> Result<()> {
+        let socket = match &self.rendezvous_socket {
+            Some(socket) => socket,
+            None => {
+                let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+                socket.set_nonblocking(true)?;
+
+                self.rendezvous_socket.insert(socket)
+            }
+        };
+
+        while let Some((packet, _)) = alvr_sockets::recv_discovery_packet(socket)? {
+            if let DiscoveryPacket::ClientList(list) = packet {
+                for client in list {
+                    let incompatibility =
+                        check_client_protocol(&client.hostname, &client.protocol_id);
+                    clients.insert(
+                        client.hostname,
+                        DiscoveredClient {
+                            ip: client.ip,
+                            incompatibility,
+                        },
+                    );
+                }
+            }
+        }
+
+        alvr_sockets::send_discovery_packet(
+            socket,
+            &DiscoveryPacket::QueryClients,
+            alvr_sockets::resolve_rendezvous_address(server_address)?,
+        )
+    }
+
+    // The hashmap key is the client hostname
+    pub fn recv_all(
+        &mut self,
+        config: &DiscoveryConfig,
+    ) -> Result<HashMap<String, DiscoveredClient>> {
+        let mut clients = HashMap::new();
+
+        self.recv_mdns(&mut clients)?;
+
+        if config.broadcast {
+            if let Err(e) = self.recv_broadcast(&mut clients) {
+                warn!("Broadcast discovery error: {e:?}");
+                self.broadcast_socket = None;
+            }
+        } else {
+            self.broadcast_socket = None;
+        }
+
+        if let Some(address) = config.rendezvous_server.as_option() {
+            if let Err(e) = self.recv_rendezvous(address, &mut clients) {
+                warn!("Rendezvous discovery error: {e:?}");
+                self.rendezvous_socket = None;
+            }
+        } else {
+            self.rendezvous_socket = None;
+        }
 
         Ok(clients)
     }
@@ -0,0 +1,618 @@
+use crate::{
+    connection::{
+        is_streaming, VideoPacket, HANDSHAKE_ACTION_TIMEOUT, MAX_UNREAD_PACKETS,
+        STREAMING_RECV_TIMEOUT,
+    },
+    statistics::SpectatorStatisticsManager,
+    ConnectionContext, ServerCoreEvent,
+};
+use alvr_common::{
+    con_bail, dbg_connection,
+    glam::UVec2,
+    info,
+    parking_lot::{Condvar, Mutex, RwLock, RwLockWriteGuard},
+    settings_schema::Switch,
+    warn, AnyhowToCon, ConResult, ConnectionError, ConnectionState, LifecycleState,
+};
+use alvr_packets::{
+    ClientControlPacket, ClientListAction, ClientStatistics, NegotiatedStreamingConfig,
+    ServerControlPacket, Tracking, VideoPacketHeader, VideoStreamingCapabilities, AUDIO,
+    STATISTICS, TRACKING, VIDEO,
+};
+use alvr_server_io::ServerSessionManager;
+use alvr_session::{CodecType, H264Profile};
+use alvr_sockets::{
+    ProtoControlSocket, StreamReceiver, StreamSender, StreamSocketBuilder, KEEPALIVE_INTERVAL,
+    KEEPALIVE_TIMEOUT,
+};
+use std::{
+    net::IpAddr,
+    sync::{
+        mpsc::{self, RecvTimeoutError, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Keyframes are expensive for the main client too, a spectator that keeps falling behind must not
+// request them more often than this
+const IDR_REQUEST_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct SpectatorSender {
+    stream_port: u16,
+    video_sender: SyncSender<VideoPacket>,
+    pub statistics: SpectatorStatisticsManager,
+    // After a dropped frame the stream cannot be decoded until the next IDR
+    waiting_for_idr: bool,
+    last_idr_request: Instant,
+}
+
+impl SpectatorSender {
+    // Returns true if a new IDR should be requested
+    pub fn send_video(&mut self, header: VideoPacketHeader, payload: Vec<u8>) -> bool {
+        if self.waiting_for_idr && !header.is_idr {
+            self.statistics.report_video_packet_dropped();
+
+            return self.should_request_idr();
+        }
+
+        let buffer_size = payload.len();
+        match self.video_sender.try_send(VideoPacket { header, payload }) {
+            Ok(()) => {
+                self.waiting_for_idr = false;
+                self.statistics.report_video_packet(buffer_size);
+
+                false
+            }
+            Err(TrySendError::Full(_)) => {
+                self.statistics.report_video_packet_dropped();
+                self.waiting_for_idr = true;
+
+                self.should_request_idr()
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    fn should_request_idr(&mut self) -> bool {
+        if self.last_idr_request.elapsed() > IDR_REQUEST_INTERVAL {
+            self.last_idr_request = Instant::now();
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Spectators cannot share the stream port of the main client, each one gets the first free port
+// starting from first_port
+fn spectator_stream_port(
+    first_port: u16,
+    main_stream_port: u16,
+    used_ports: &[u16],
+) -> Option<u16> {
+    (first_port..=u16::MAX).find(|port| *port != main_stream_port && !used_ports.contains(port))
+}
+
+// Spectators receive the same encoded video (and optionally game audio) as the main client. The
+// streaming parameters are not negotiated, and the spectator tracking and inputs are discarded.
+// The caller must have checked the client protocol and obtained the streaming capabilities.
+pub fn spectator_pipeline(
+    ctx: Arc<ConnectionContext>,
+    lifecycle_state: Arc<RwLock<LifecycleState>>,
+    mut proto_socket: ProtoControlSocket,
+    client_hostname: String,
+    client_ip: IpAddr,
+    streaming_caps: VideoStreamingCapabilities,
+    mut session_manager_lock: RwLockWriteGuard<'static, ServerSessionManager>,
+) -> ConResult {
+    dbg_connection!("spectator_pipeline: Begin");
+
+    let settings = session_manager_lock.settings().clone();
+
+    let Switch::Enabled(config) = settings.connection.spectators.clone() else {
+        con_bail!("Client is marked as spectator but spectators are disabled");
+    };
+
+    let openvr_config = session_manager_lock.session().openvr_config.clone();
+
+    if openvr_config.stream.codec == CodecType::AV1 as u8 && !streaming_caps.encoder_av1 {
+        con_bail!("AV1 decoding is not supported by the spectator");
+    }
+    if openvr_config.stream.codec == CodecType::H264 as u8
+        && openvr_config.stream.h264_profile == H264Profile::High as u32
+        && !streaming_caps.encoder_high_profile
+    {
+        con_bail!("H264 high profile decoding is not supported by the spectator");
+    }
+    if openvr_config.stream.use_10bit_encoder && !streaming_caps.encoder_10_bits {
+        con_bail!("10 bits decoding is not supported by the spectator");
+    }
+    if openvr_config.stream.enable_foveated_encoding && !streaming_caps.supports_foveated_encoding {
+        warn!("Foveated encoding is not supported by the spectator {client_hostname}.");
+    }
+
+    let game_audio_config = settings
+        .audio
+        .game_audio
+        .as_option()
+        .filter(|_| config.game_audio)
+        .cloned();
+
+    #[cfg(not(target_os = "linux"))]
+    let game_audio_sample_rate = if let Some(game_audio_config) = &game_audio_config {
+        alvr_audio::AudioDevice::new_output(game_audio_config.device.as_ref())
+            .to_con()?
+            .input_sample_rate()
+            .to_con()?
+    } else {
+        0
+    };
+    // On Linux, the game audio is captured with a dedicated virtual sink, which cannot be shared
+    #[cfg(target_os = "linux")]
+    let game_audio_sample_rate = {
+        if game_audio_config.is_some() {
+            warn!("Game audio is not supported for spectators on Linux");
+        }
+
+        0
+    };
+
+    // Spectators connect one at a time with the session locked, so the port cannot be taken by
+    // another spectator before this one is registered
+    let used_ports = ctx
+        .spectators
+        .lock()
+        .values()
+        .map(|spectator| spectator.stream_port)
+        .collect::<Vec<_>>();
+    let Some(stream_port) = spectator_stream_port(
+        config.first_stream_port,
+        settings.connection.stream_port,
+        &used_ports,
+    ) else {
+        con_bail!("No free stream port for the spectator");
+    };
+
+    // The client reads the stream port from the session
+    let mut session = session_manager_lock.session().clone();
+    session.session_settings.connection.stream_port = stream_port;
+
+    dbg_connection!("spectator_pipeline: send streaming config");
+    let stream_config_packet = alvr_packets::encode_stream_config(
+        &session,
+        &NegotiatedStreamingConfig {
+            view_resolution: UVec2::new(
+                openvr_config.boot.eye_resolution_width,
+                openvr_config.boot.eye_resolution_height,
+            ),
+            refresh_rate_hint: openvr_config.boot.refresh_rate as f32,
+            game_audio_sample_rate,
+            enable_foveated_encoding: openvr_config.stream.enable_foveated_encoding,
+        },
+    )
+    .to_con()?;
+    proto_socket.send(&stream_config_packet).to_con()?;
+
+    let (mut control_sender, mut control_receiver) =
+        proto_socket.split(STREAMING_RECV_TIMEOUT).to_con()?;
+
+    control_sender
+        .send(&ServerControlPacket::StartStream)
+        .to_con()?;
+
+    let signal = control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?;
+    if !matches!(signal, ClientControlPacket::StreamReady) {
+        con_bail!("Got unexpected packet waiting for stream ack");
+    }
+    dbg_connection!("spectator_pipeline: Got StreamReady packet");
+
+    if let Some(decoder_config) = ctx.decoder_config.lock().clone() {
+        control_sender
+            .send(&ServerControlPacket::DecoderConfig(decoder_config))
+            .to_con()?;
+    }
+
+    let mut stream_socket = StreamSocketBuilder::connect_to_client(
+        HANDSHAKE_ACTION_TIMEOUT,
+        client_ip,
+        stream_port,
+        settings.connection.stream_protocol,
+        settings.connection.dscp,
+        settings.connection.server_send_buffer_bytes,
+        settings.connection.server_recv_buffer_bytes,
+        settings.connection.packet_size as _,
+    )?;
+
+    let mut video_sender = stream_socket.request_stream(VIDEO);
+    #[cfg_attr(target_os = "linux", allow(unused_variables))]
+    let game_audio_sender: StreamSender<()> = stream_socket.request_stream(AUDIO);
+    // Streams sent by the spectator are subscribed only to discard their packets
+    let _microphone_receiver: StreamReceiver<()> =
+        stream_socket.subscribe_to_stream(AUDIO, MAX_UNREAD_PACKETS);
+    let _tracking_receiver: StreamReceiver<Tracking> =
+        stream_socket.subscribe_to_stream(TRACKING, MAX_UNREAD_PACKETS);
+    let mut statistics_receiver =
+        stream_socket.subscribe_to_stream::<ClientStatistics>(STATISTICS, MAX_UNREAD_PACKETS);
+
+    let (video_channel_sender, video_channel_receiver) =
+        mpsc::sync_channel(config.max_queued_video_frames);
+    ctx.spectators.lock().insert(
+        client_hostname.clone(),
+        SpectatorSender {
+            stream_port,
+            video_sender: video_channel_sender,
+            statistics: SpectatorStatisticsManager::new(client_hostname.clone()),
+            waiting_for_idr: true,
+            last_idr_request: Instant::now(),
+        },
+    );
+
+    let disconnect_notif = Arc::new(Condvar::new());
+
+    let video_send_thread = thread::spawn({
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                let VideoPacket { header, payload } =
+                    match video_channel_receiver.recv_timeout(STREAMING_RECV_TIMEOUT) {
+                        Ok(packet) => packet,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    };
+
+                let mut buffer = video_sender.get_buffer(&header).unwrap();
+                buffer
+                    .get_range_mut(0, payload.len())
+                    .copy_from_slice(&payload);
+                video_sender.send(buffer).ok();
+            }
+        }
+    });
+
+    #[cfg(not(target_os = "linux"))]
+    let game_audio_thread = if let Some(game_audio_config) = game_audio_config {
+        let client_hostname = client_hostname.clone();
+        thread::spawn(move || {
+            while is_streaming(&client_hostname) {
+                let device =
+                    match alvr_audio::AudioDevice::new_output(game_audio_config.device.as_ref()) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            warn!("New audio device failed: {e:?}");
+                            thread::sleep(STREAMING_RECV_TIMEOUT);
+                            continue;
+                        }
+                    };
+
+                // Muting is left to the main client connection
+                if let Err(e) = alvr_audio::record_audio_blocking(
+                    Arc::new({
+                        let client_hostname = client_hostname.clone();
+                        move || is_streaming(&client_hostname)
+                    }),
+                    game_audio_sender.clone(),
+                    &device,
+                    2,
+                    false,
+                ) {
+                    alvr_common::error!("Spectator audio record error: {e:?}");
+                }
+            }
+        })
+    } else {
+        thread::spawn(|| ())
+    };
+    #[cfg(target_os = "linux")]
+    let game_audio_thread = thread::spawn(|| ());
+
+    let statistics_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                let data = match statistics_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                    Ok(stats) => stats,
+                    Err(ConnectionError::TryAgain(_)) => continue,
+                    Err(ConnectionError::Other(_)) => return,
+                };
+                let Ok(client_stats) = data.get_header() else {
+                    return;
+                };
+
+                if let Some(spectator) = ctx.spectators.lock().get_mut(&client_hostname) {
+                    spectator.statistics.report_statistics(client_stats);
+                }
+            }
+        }
+    });
+
+    let control_sender = Arc::new(Mutex::new(control_sender));
+
+    let keepalive_thread = thread::spawn({
+        let control_sender = Arc::clone(&control_sender);
+        let disconnect_notif = Arc::clone(&disconnect_notif);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                if let Err(e) = control_sender.lock().send(&ServerControlPacket::KeepAlive) {
+                    info!("Spectator disconnected. Cause: {e:?}");
+
+                    disconnect_notif.notify_one();
+
+                    return;
+                }
+
+                thread::sleep(KEEPALIVE_INTERVAL);
+            }
+        }
+    });
+
+    let control_receive_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let control_sender = Arc::clone(&control_sender);
+        let disconnect_notif = Arc::clone(&disconnect_notif);
+        let client_hostname = client_hostname.clone();
+        move || {
+            let mut disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+            while is_streaming(&client_hostname) {
+                let packet = match control_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                    Ok(packet) => packet,
+                    Err(ConnectionError::TryAgain(_)) => {
+                        if Instant::now() > disconnection_deadline {
+                            info!("Spectator disconnected. Timeout");
+                            break;
+                        } else {
+                            continue;
+                        }
+                    }
+                    Err(e) => {
+                        info!("Spectator disconnected. Cause: {e}");
+                        break;
+                    }
+                };
+
+                // Everything that would affect SteamVR is ignored
+                match packet {
+                    ClientControlPacket::RequestIdr => {
+                        if let Some(config) = ctx.decoder_config.lock().clone() {
+                            control_sender
+                                .lock()
+                                .send(&ServerControlPacket::DecoderConfig(config))
+                                .ok();
+                        }
+                        ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+                    }
+                    ClientControlPacket::VideoErrorReport => {
+                        ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+                    }
+                    ClientControlPacket::Log { level, message, .. } => {
+                        info!("Spectator {client_hostname}: [{level:?}] {message}")
+                    }
+                    _ => (),
+                }
+
+                disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+            }
+
+            disconnect_notif.notify_one()
+        }
+    });
+
+    let stream_receive_thread = thread::spawn({
+        let disconnect_notif = Arc::clone(&disconnect_notif);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                match stream_socket.recv() {
+                    Ok(()) => (),
+                    Err(ConnectionError::TryAgain(_)) => continue,
+                    Err(e) => {
+                        info!("Spectator disconnected. Cause: {e}");
+
+                        disconnect_notif.notify_one();
+
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    let lifecycle_check_thread = thread::spawn({
+        let disconnect_notif = Arc::clone(&disconnect_notif);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname)
+                && *lifecycle_state.read() == LifecycleState::Resumed
+            {
+                thread::sleep(STREAMING_RECV_TIMEOUT);
+            }
+
+            disconnect_notif.notify_one()
+        }
+    });
+
+    session_manager_lock.update_client_list(
+        client_hostname.clone(),
+        ClientListAction::SetConnectionState(ConnectionState::Streaming),
+    );
+
+    // The spectator can start decoding only from an IDR frame
+    ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+
+    info!("Spectator {client_hostname} connected on port {stream_port}");
+
+    dbg_connection!("spectator_pipeline: handshake finished; unlocking streams");
+    alvr_common::wait_rwlock(&disconnect_notif, &mut session_manager_lock);
+    dbg_connection!("spectator_pipeline: Begin connection shutdown");
+
+    // This requests shutdown from the video thread
+    ctx.spectators.lock().remove(&client_hostname);
+
+    session_manager_lock.update_client_list(
+        client_hostname.clone(),
+        ClientListAction::SetConnectionState(ConnectionState::Disconnecting),
+    );
+
+    // Allow threads to shutdown correctly
+    drop(session_manager_lock);
+
+    dbg_connection!("spectator_pipeline: Shutdown threads");
+    video_send_thread.join().ok();
+    game_audio_thread.join().ok();
+    statistics_thread.join().ok();
+    control_receive_thread.join().ok();
+    stream_receive_thread.join().ok();
+    keepalive_thread.join().ok();
+    lifecycle_check_thread.join().ok();
+
+    dbg_connection!("spectator_pipeline: End");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_session::{SocketBufferSize, SocketProtocol};
+    use std::net::Ipv4Addr;
+
+    fn test_spectator(
+        max_queued_video_frames: usize,
+    ) -> (SpectatorSender, mpsc::Receiver<VideoPacket>) {
+        let (video_sender, video_receiver) = mpsc::sync_channel(max_queued_video_frames);
+
+        let spectator = SpectatorSender {
+            stream_port: 0,
+            video_sender,
+            statistics: SpectatorStatisticsManager::new("spectator".into()),
+            waiting_for_idr: true,
+            last_idr_request: Instant::now(),
+        };
+
+        (spectator, video_receiver)
+    }
+
+    fn header(timestamp_ms: u64, is_idr: bool) -> VideoPacketHeader {
+        VideoPacketHeader {
+            timestamp: Duration::from_millis(timestamp_ms),
+            is_idr,
+        }
+    }
+
+    #[test]
+    fn test_spectator_stream_port() {
+        assert_eq!(spectator_stream_port(9950, 9944, &[]), Some(9950));
+        assert_eq!(spectator_stream_port(9950, 9944, &[9950, 9952]), Some(9951));
+        assert_eq!(spectator_stream_port(9944, 9944, &[]), Some(9945));
+        assert_eq!(spectator_stream_port(u16::MAX, 9944, &[u16::MAX]), None);
+    }
+
+    // Both sockets would be bound to the same local port if spectators used the main stream port
+    #[test]
+    fn test_main_client_and_spectator_stream_together() {
+        const MAIN_STREAM_PORT: u16 = 29944;
+        const FIRST_SPECTATOR_PORT: u16 = 29950;
+
+        let connect = |port| {
+            StreamSocketBuilder::connect_to_client(
+                Duration::from_millis(100),
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                port,
+                SocketProtocol::Udp,
+                None,
+                SocketBufferSize::Default,
+                SocketBufferSize::Default,
+                1400,
+            )
+            .unwrap()
+        };
+
+        let spectator_port =
+            spectator_stream_port(FIRST_SPECTATOR_PORT, MAIN_STREAM_PORT, &[]).unwrap();
+
+        let mut main_socket = connect(MAIN_STREAM_PORT);
+        let mut spectator_socket = connect(spectator_port);
+
+        // The sockets are connected to their own address, every packet comes back to the sender
+        for (socket, marker) in [(&mut main_socket, 1), (&mut spectator_socket, 2)] {
+            let mut sender = socket.request_stream::<u32>(VIDEO);
+            let mut receiver = socket.subscribe_to_stream::<u32>(VIDEO, MAX_UNREAD_PACKETS);
+
+            let mut buffer = sender.get_buffer(&marker).unwrap();
+            buffer
+                .get_range_mut(0, 4)
+                .copy_from_slice(&[marker as u8; 4]);
+            sender.send(buffer).unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(1);
+            let (header, payload) = loop {
+                assert!(Instant::now() < deadline, "Packet not received");
+
+                socket.recv().ok();
+                if let Ok(data) = receiver.recv(Duration::ZERO) {
+                    let (header, payload) = data.get().unwrap();
+                    break (header, payload.to_vec());
+                }
+            };
+
+            assert_eq!(header, marker);
+            assert_eq!(payload, [marker as u8; 4]);
+        }
+    }
+
+    #[test]
+    fn test_spectator_skips_frames_until_idr() {
+        let (mut spectator, video_receiver) = test_spectator(4);
+
+        // A new spectator waits for the IDR requested when it connected
+        assert!(!spectator.send_video(header(0, false), vec![0]));
+        assert!(video_receiver.try_recv().is_err());
+
+        assert!(!spectator.send_video(header(1, true), vec![1]));
+        assert!(!spectator.send_video(header(2, false), vec![2]));
+
+        let timestamps = video_receiver
+            .try_iter()
+            .map(|packet| packet.header.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timestamps,
+            [Duration::from_millis(1), Duration::from_millis(2)]
+        );
+    }
+
+    #[test]
+    fn test_spectator_idr_requests_are_rate_limited() {
+        let (mut spectator, video_receiver) = test_spectator(1);
+
+        assert!(!spectator.send_video(header(0, true), vec![0]));
+
+        // The queue is full: the frame is dropped and the spectator waits for an IDR
+        spectator.last_idr_request = Instant::now() - IDR_REQUEST_INTERVAL * 2;
+        assert!(spectator.send_video(header(1, false), vec![1]));
+        assert!(spectator.waiting_for_idr);
+
+        video_receiver.try_recv().unwrap();
+
+        // Frames are skipped while waiting, without requesting again
+        for timestamp in 2..100 {
+            assert!(!spectator.send_video(header(timestamp, false), vec![]));
+        }
+        assert!(video_receiver.try_recv().is_err());
+
+        // The request is repeated only after the interval
+        spectator.last_idr_request = Instant::now() - IDR_REQUEST_INTERVAL * 2;
+        assert!(spectator.send_video(header(100, false), vec![]));
+
+        assert!(!spectator.send_video(header(101, true), vec![101]));
+        assert!(!spectator.waiting_for_idr);
+        assert_eq!(
+            video_receiver.try_recv().unwrap().header.timestamp,
+            Duration::from_millis(101)
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use alvr_common::{SlidingWindowAverage, HEAD_ID};
-use alvr_events::{EventType, GraphStatistics, NominalBitrateStats, StatisticsSummary};
-use alvr_packets::ClientStatistics;
+use alvr_events::{
+    EventType, GraphStatistics, NominalBitrateStats, SpectatorStatisticsSummary, StatisticsSummary,
+};
+use alvr_packets::{ClientStatistics, ConnectionPath};
 use std::{
     collections::{HashMap, VecDeque},
     time::{Duration, Instant},
 };
 
 const FULL_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+// Summaries kept for support bundles, one minute worth
+const RECENT_SUMMARIES_COUNT: usize = 120;
 
 pub struct HistoryFrame {
     target_timestamp: Duration,
@@ -57,6 +61,9 @@ pub struct StatisticsManager {
     last_vsync_time: Instant,
     frame_interval: Duration,
     last_nominal_bitrate_stats: NominalBitrateStats,
+    connection_path: ConnectionPath,
+    connection_path_rtt: Duration,
+    recent_summaries: VecDeque<StatisticsSummary>,
 }
 
 impl StatisticsManager {
@@ -89,9 +96,18 @@ impl StatisticsManager {
             last_vsync_time: Instant::now(),
             frame_interval: nominal_server_frame_interval,
             last_nominal_bitrate_stats: NominalBitrateStats::default(),
+            connection_path: ConnectionPath::Lan,
+            connection_path_rtt: Duration::ZERO,
+            recent_summaries: VecDeque::new(),
         }
     }
 
+    // The round trip time is measured while establishing the connection
+    pub fn report_connection_path(&mut self, path: ConnectionPath, round_trip_time: Duration) {
+        self.connection_path = path;
+        self.connection_path_rtt = round_trip_time;
+    }
+
     pub fn report_tracking_received(&mut self, target_timestamp: Duration) {
         if !self
             .history_buffer
@@ -234,7 +250,7 @@ impl StatisticsManager {
 
                 let interval_secs = FULL_REPORT_INTERVAL.as_secs_f32();
 
-                alvr_events::send_event(EventType::StatisticsSummary(StatisticsSummary {
+                let summary = StatisticsSummary {
                     video_packets_total: self.video_packets_total,
                     video_packets_per_sec: (self.video_packets_partial_sum as f32 / interval_secs)
                         as _,
@@ -264,7 +280,16 @@ impl StatisticsManager {
                         .cloned()
                         .unwrap_or_default()
                         .is_plugged,
-                }));
+                    connection_path: self.connection_path,
+                    connection_path_rtt_ms: self.connection_path_rtt.as_secs_f32() * 1000.,
+                };
+
+                self.recent_summaries.push_back(summary.clone());
+                if self.recent_summaries.len() > RECENT_SUMMARIES_COUNT {
+                    self.recent_summaries.pop_front();
+                }
+
+                alvr_events::send_event(EventType::StatisticsSummary(summary));
 
                 self.video_packets_partial_sum = 0;
                 self.video_bytes_partial_sum = 0;
@@ -303,6 +328,10 @@ impl StatisticsManager {
         }
     }
 
+    pub fn recent_summaries(&self) -> Vec<StatisticsSummary> {
+        self.recent_summaries.iter().cloned().collect()
+    }
+
     pub fn video_pipeline_latency_average(&self) -> Duration {
         self.total_pipeline_latency_average.get_average()
     }
@@ -325,3 +354,64 @@ impl StatisticsManager {
         (self.last_vsync_time + self.frame_interval).saturating_duration_since(now)
     }
 }
+
+// Spectators don't contribute to the frame timing history, only the network throughput and the
+// client-side latencies are reported
+pub struct SpectatorStatisticsManager {
+    hostname: String,
+    last_full_report_instant: Instant,
+    video_packets_total: usize,
+    video_packets_partial_sum: usize,
+    video_bytes_partial_sum: usize,
+    video_packets_dropped_total: usize,
+}
+
+impl SpectatorStatisticsManager {
+    pub fn new(hostname: String) -> Self {
+        Self {
+            hostname,
+            last_full_report_instant: Instant::now(),
+            video_packets_total: 0,
+            video_packets_partial_sum: 0,
+            video_bytes_partial_sum: 0,
+            video_packets_dropped_total: 0,
+        }
+    }
+
+    pub fn report_video_packet(&mut self, bytes_count: usize) {
+        self.video_packets_total += 1;
+        self.video_packets_partial_sum += 1;
+        self.video_bytes_partial_sum += bytes_count;
+    }
+
+    pub fn report_video_packet_dropped(&mut self) {
+        self.video_packets_dropped_total += 1;
+    }
+
+    pub fn report_statistics(&mut self, client_stats: ClientStatistics) {
+        if self.last_full_report_instant + FULL_REPORT_INTERVAL < Instant::now() {
+            self.last_full_report_instant += FULL_REPORT_INTERVAL;
+
+            let interval_secs = FULL_REPORT_INTERVAL.as_secs_f32();
+            let client_fps = 1.0
+                / client_stats
+                    .frame_interval
+                    .max(Duration::from_millis(1))
+                    .as_secs_f32();
+
+            alvr_events::send_event(EventType::SpectatorStatistics(SpectatorStatisticsSummary {
+                hostname: self.hostname.clone(),
+                video_packets_total: self.video_packets_total,
+                video_packets_per_sec: (self.video_packets_partial_sum as f32 / interval_secs) as _,
+                video_mbits_per_sec: self.video_bytes_partial_sum as f32 * 8. / 1e6 / interval_secs,
+                video_packets_dropped_total: self.video_packets_dropped_total,
+                total_latency_ms: client_stats.total_pipeline_latency.as_secs_f32() * 1000.,
+                decode_latency_ms: client_stats.video_decode.as_secs_f32() * 1000.,
+                client_fps: client_fps as _,
+            }));
+
+            self.video_packets_partial_sum = 0;
+            self.video_bytes_partial_sum = 0;
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// Wired connection through the ADB server. The ADB host protocol is spoken directly on the server
+// socket, the adb executable is only needed to start the server.
+
+use crate::SESSION_MANAGER;
+use alvr_common::{
+    anyhow::{bail, Result},
+    debug, info, warn,
+};
+use alvr_packets::ClientListAction;
+use alvr_session::WiredConnectionConfig;
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream},
+    process::Command,
+    str,
+    time::Duration,
+};
+
+const ADB_SERVER_PORT: u16 = 5037;
+const ADB_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn read_status(stream: &mut impl Read) -> Result<()> {
+    let mut status = [0; 4];
+    stream.read_exact(&mut status)?;
+
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => bail!("ADB request failed: {}", read_message(stream)?),
+        _ => bail!("Unexpected ADB response"),
+    }
+}
+
+fn read_message(stream: &mut impl Read) -> Result<String> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+    let length = usize::from_str_radix(str::from_utf8(&length)?, 16)?;
+
+    let mut message = vec![0; length];
+    stream.read_exact(&mut message)?;
+
+    Ok(String::from_utf8(message)?)
+}
+
+// Each request uses a new connection, the ADB server closes it after replying
+fn adb_request(service: &str) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect_timeout(
+        &SocketAddr::new(Ipv4Addr::LOCALHOST.into(), ADB_SERVER_PORT),
+        ADB_TIMEOUT,
+    )?;
+    stream.set_read_timeout(Some(ADB_TIMEOUT))?;
+
+    stream.write_all(format!("{:04x}{service}", service.len()).as_bytes())?;
+    read_status(&mut stream)?;
+
+    Ok(stream)
+}
+
+// Returns the serials of the devices that are ready. Each line is "<serial>\t<state>"
+fn parse_devices(devices: &str) -> Vec<String> {
+    devices
+        .lines()
+        .filter_map(|line| {
+            let (serial, state) = line.split_once('\t')?;
+            (state == "device").then(|| serial.to_owned())
+        })
+        .collect()
+}
+
+fn list_devices() -> Result<Vec<String>> {
+    Ok(parse_devices(&read_message(&mut adb_request(
+        "host:devices",
+    )?)?))
+}
+
+// Returns one "<serial> <local> <remote>" entry per line
+fn list_forwards() -> Result<String> {
+    read_message(&mut adb_request("host:list-forward")?)
+}
+
+fn is_port_forwarded(forwards: &str, serial: &str, port: u16) -> bool {
+    let entry = format!("{serial} tcp:{port} tcp:{port}");
+
+    forwards.lines().any(|line| line == entry)
+}
+
+// The server connects to the client, so the ports of the PC are forwarded to the device
+fn forward_port(serial: &str, port: u16) -> Result<()> {
+    let mut stream = adb_request(&format!(
+        "host-serial:{serial}:forward:tcp:{port};tcp:{port}"
+    ))?;
+
+    // The second status is sent once the forward is in place
+    read_status(&mut stream)
+}
+
+fn usb_client_hostname(serial: &str) -> String {
+    format!("{serial}.usb.client.local.")
+}
+
+pub struct UsbConnectionManager {
+    adb_server_start_attempted: bool,
+    forwarded_device: Option<String>,
+}
+
+impl UsbConnectionManager {
+    pub fn new() -> Self {
+        Self {
+            adb_server_start_attempted: false,
+            forwarded_device: None,
+        }
+    }
+
+    // Only one device can be forwarded at a time, since the ports on the PC are fixed
+    pub fn update(&mut self, config: &WiredConnectionConfig, stream_port: u16) {
+        let devices = match list_devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                if config.start_adb_server && !self.adb_server_start_attempted {
+                    info!("Starting the ADB server");
+                    if let Err(e) = Command::new("adb").arg("start-server").spawn() {
+                        warn!("Failed to start the ADB server: {e}");
+                    }
+
+                    self.adb_server_start_attempted = true;
+                } else {
+                    debug!("ADB server not reachable: {e}");
+                }
+
+                return;
+            }
+        };
+
+        let Some(serial) = devices
+            .iter()
+            .find(|serial| Some(*serial) == self.forwarded_device.as_ref())
+            .or(devices.first())
+            .cloned()
+        else {
+            self.forwarded_device = None;
+            return;
+        };
+
+        // Forwards are removed by ADB when the device is unplugged
+        let forwards = list_forwards().unwrap_or_default();
+        for port in [alvr_sockets::CONTROL_PORT, stream_port] {
+            if !is_port_forwarded(&forwards, &serial, port) {
+                if let Err(e) = forward_port(&serial, port) {
+                    warn!("Failed to forward port {port} to USB device {serial}: {e}");
+                    return;
+                }
+            }
+        }
+
+        if self.forwarded_device.as_ref() != Some(&serial) {
+            info!("Forwarded connection ports to USB device {serial}");
+            self.forwarded_device = Some(serial.clone());
+        }
+
+        let hostname = usb_client_hostname(&serial);
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let mut session_manager = SESSION_MANAGER.write();
+
+        // Other USB devices are not reachable anymore through the forwarded ports
+        let stale_clients = session_manager
+            .client_list()
+            .iter()
+            .filter(|(name, c)| c.cabled && **name != hostname && c.manual_ips.contains(&localhost))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for name in stale_clients {
+            session_manager.update_client_list(name, ClientListAction::SetManualIps(vec![]));
+        }
+
+        if session_manager
+            .client_list()
+            .get(&hostname)
+            .map(|c| !c.cabled || !c.manual_ips.contains(&localhost))
+            .unwrap_or(true)
+        {
+            session_manager.update_client_list(
+                hostname.clone(),
+                ClientListAction::AddIfMissing {
+                    trusted: true,
+                    manual_ips: vec![localhost],
+                },
+            );
+            session_manager.update_client_list(
+                hostname.clone(),
+                ClientListAction::SetManualIps(vec![localhost]),
+            );
+            session_manager.update_client_list(hostname, ClientListAction::SetCabled(true));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_message() {
+        let mut stream = &b"000bhello world"[..];
+        assert_eq!(read_message(&mut stream).unwrap(), "hello world");
+        assert!(stream.is_empty());
+
+        // The length prefix is hexadecimal
+        let message = "a".repeat(0x1f);
+        let bytes = format!("001f{message}trailing");
+        let mut stream = bytes.as_bytes();
+        assert_eq!(read_message(&mut stream).unwrap(), message);
+        assert_eq!(stream, b"trailing");
+
+        assert_eq!(read_message(&mut &b"0000"[..]).unwrap(), "");
+        assert!(read_message(&mut &b"000zabc"[..]).is_err());
+        assert!(read_message(&mut &b"0010short"[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_status() {
+        assert!(read_status(&mut &b"OKAY"[..]).is_ok());
+
+        let error = read_status(&mut &b"FAIL000edevice offline"[..]).unwrap_err();
+        assert!(error.to_string().contains("device offline"));
+
+        assert!(read_status(&mut &b"WHAT"[..]).is_err());
+    }
+
+    #[test]
+    fn test_parse_devices() {
+        let devices = "1WMHH000000000\tdevice\n\
+                       2G0YC000000000\tunauthorized\n\
+                       emulator-5554\toffline\n\
+                       malformed line\n\
+                       3A0ZB000000000\tdevice\n";
+
+        assert_eq!(parse_devices(devices), ["1WMHH000000000", "3A0ZB000000000"]);
+        assert!(parse_devices("").is_empty());
+    }
+
+    #[test]
+    fn test_is_port_forwarded() {
+        let forwards = "1WMHH000000000 tcp:9943 tcp:9943\n\
+                        1WMHH000000000 tcp:9944 tcp:9945\n\
+                        2G0YC000000000 tcp:9944 tcp:9944\n";
+
+        assert!(is_port_forwarded(forwards, "1WMHH000000000", 9943));
+        // Both sides of the forward must match
+        assert!(!is_port_forwarded(forwards, "1WMHH000000000", 9944));
+        // The forward must belong to the same device
+        assert!(!is_port_forwarded(forwards, "3A0ZB000000000", 9943));
+        assert!(!is_port_forwarded(forwards, "1WMHH000000000", 994));
+        assert!(!is_port_forwarded("", "1WMHH000000000", 9943));
+    }
+}
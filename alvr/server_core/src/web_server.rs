@@ -1,13 +1,17 @@
 use crate::{
-    logging_backend::LOGGING_EVENTS_SENDER, ConnectionContext, ServerCoreEvent, FILESYSTEM_LAYOUT,
-    SESSION_MANAGER,
+    event_hooks::{self, HookEventData},
+    logging_backend::EVENTS_SENDER,
+    ConnectionContext, ServerCoreEvent, FILESYSTEM_LAYOUT, SESSION_MANAGER,
 };
 use alvr_common::{
     anyhow::{self, Result},
     error, info, log, warn, ConnectionState,
 };
 use alvr_events::{ButtonEvent, EventType};
-use alvr_packets::{ButtonEntry, ClientListAction, ServerRequest};
+use alvr_packets::{
+    ButtonEntry, ClientListAction, ServerRequest, SetValuesRequest, SettingsChangeOrigin,
+};
+use alvr_session::HookEvent;
 use bytes::Buf;
 use futures::SinkExt;
 use headers::HeaderMapExt;
@@ -17,7 +21,7 @@ use hyper::{
 };
 use serde::de::DeserializeOwned;
 use serde_json as json;
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, path::Path, sync::Arc};
 use tokio::sync::broadcast::{self, error::RecvError};
 use tokio_tungstenite::{tungstenite::protocol, WebSocketStream};
 
@@ -33,6 +37,49 @@ async fn from_request_body<T: DeserializeOwned>(request: Request<Body>) -> Resul
     )?)
 }
 
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html",
+        Some("js") => "text/javascript",
+        Some("wasm") => "application/wasm",
+        Some("css") => "text/css",
+        Some("ico") => "image/x-icon",
+        Some("png") => "image/png",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+// Serves the browser build of the dashboard
+async fn dashboard_file(uri_path: &str) -> Result<Response<Body>> {
+    let relative_path = match uri_path.trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+
+    // Don't allow escaping the dashboard directory
+    if relative_path
+        .split('/')
+        .any(|component| matches!(component, "" | "." | "..") || component.contains('\\'))
+    {
+        return reply(StatusCode::NOT_FOUND);
+    }
+
+    let path = FILESYSTEM_LAYOUT
+        .get()
+        .unwrap()
+        .dashboard_dir()
+        .join(relative_path);
+
+    if let Ok(data) = tokio::fs::read(&path).await {
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, content_type(&path))
+            .body(data.into())?)
+    } else {
+        reply(StatusCode::NOT_FOUND)
+    }
+}
+
 async fn websocket<T: Clone + Send + 'static>(
     request: Request<Body>,
     sender: broadcast::Sender<T>,
@@ -105,10 +152,22 @@ async fn http_api(
                         )));
                     }
                     ServerRequest::UpdateSession(session) => {
-                        *SESSION_MANAGER.write().session_mut() = *session
+                        *SESSION_MANAGER
+                            .write()
+                            .session_mut_from(SettingsChangeOrigin::WebApi) = *session
                     }
-                    ServerRequest::SetValues(descs) => {
-                        SESSION_MANAGER.write().set_values(descs).ok();
+                    ServerRequest::SetValues(SetValuesRequest { origin, values }) => {
+                        SESSION_MANAGER.write().set_values(values, origin).ok();
+                    }
+                    ServerRequest::GetSettingsHistory => {
+                        alvr_events::send_event(EventType::SettingsHistory(
+                            SESSION_MANAGER.read().settings_history(),
+                        ));
+                    }
+                    ServerRequest::RollbackSettings(entry_id) => {
+                        if let Err(e) = SESSION_MANAGER.write().rollback_settings(entry_id) {
+                            error!("Failed to roll back settings: {e:?}");
+                        }
                     }
                     ServerRequest::UpdateClientList {
                         hostname,
@@ -130,7 +189,25 @@ async fn http_api(
                             }
                         }
 
-                        session_manager.update_client_list(hostname, action);
+                        let newly_trusted = matches!(action, ClientListAction::Trust)
+                            && session_manager
+                                .client_list()
+                                .get(&hostname)
+                                .map(|c| !c.trusted)
+                                .unwrap_or(false);
+
+                        session_manager.update_client_list(hostname.clone(), action);
+
+                        if newly_trusted {
+                            event_hooks::trigger(
+                                &session_manager.settings().connection.event_hooks,
+                                HookEvent::ClientTrusted,
+                                HookEventData {
+                                    hostname: Some(hostname),
+                                    ..Default::default()
+                                },
+                            );
+                        }
                     }
                     ServerRequest::GetAudioDevices => {
                         if let Ok(list) = crate::SESSION_MANAGER.read().get_audio_devices_list() {
@@ -190,6 +267,47 @@ async fn http_api(
                             alvr_events::send_event(EventType::DriversList(list));
                         }
                     }
+                    ServerRequest::GetButtonMappingProfiles => {
+                        let profiles_dir = FILESYSTEM_LAYOUT
+                            .get()
+                            .unwrap()
+                            .button_mapping_profiles_dir();
+                        alvr_events::send_event(EventType::ButtonMappingProfiles(
+                            alvr_server_io::get_button_mapping_profiles(&profiles_dir),
+                        ));
+                    }
+                    ServerRequest::ImportButtonMappingProfile(content) => {
+                        let profiles_dir = FILESYSTEM_LAYOUT
+                            .get()
+                            .unwrap()
+                            .button_mapping_profiles_dir();
+                        if let Err(e) =
+                            alvr_server_io::import_button_mapping_profile(&profiles_dir, &content)
+                        {
+                            error!("Failed to import button mapping profile: {e}");
+                        }
+                        crate::update_active_button_mapping_profile(connection_context);
+
+                        alvr_events::send_event(EventType::ButtonMappingProfiles(
+                            alvr_server_io::get_button_mapping_profiles(&profiles_dir),
+                        ));
+                    }
+                    ServerRequest::RemoveButtonMappingProfile(name) => {
+                        let profiles_dir = FILESYSTEM_LAYOUT
+                            .get()
+                            .unwrap()
+                            .button_mapping_profiles_dir();
+                        if let Err(e) =
+                            alvr_server_io::remove_button_mapping_profile(&profiles_dir, &name)
+                        {
+                            error!("Failed to remove button mapping profile: {e}");
+                        }
+                        crate::update_active_button_mapping_profile(connection_context);
+
+                        alvr_events::send_event(EventType::ButtonMappingProfiles(
+                            alvr_server_io::get_button_mapping_profiles(&profiles_dir),
+                        ));
+                    }
                     ServerRequest::RestartSteamvr => {
                         connection_context
                             .events_sender
@@ -202,6 +320,51 @@ async fn http_api(
                             .send(ServerCoreEvent::ShutdownPending)
                             .ok();
                     }
+                    ServerRequest::RunConnectionDiagnostics(hostname) => {
+                        let mut session_manager = SESSION_MANAGER.write();
+                        connection_context
+                            .clients_to_diagnose
+                            .lock()
+                            .insert(hostname.clone());
+
+                        // The diagnostics run during the handshake, so a streaming client is
+                        // disconnected first and will run them when reconnecting
+                        if session_manager
+                            .client_list()
+                            .get(&hostname)
+                            .map(|c| c.connection_state != ConnectionState::Disconnected)
+                            .unwrap_or(false)
+                        {
+                            session_manager.update_client_list(
+                                hostname,
+                                ClientListAction::SetConnectionState(
+                                    ConnectionState::Disconnecting,
+                                ),
+                            );
+                        }
+                    }
+                    ServerRequest::CreateSupportBundle { redact } => {
+                        let session = SESSION_MANAGER.read().session().clone();
+                        let recent_statistics = connection_context
+                            .statistics_manager
+                            .lock()
+                            .as_ref()
+                            .map(|stats| stats.recent_summaries())
+                            .unwrap_or_default();
+
+                        match alvr_server_io::create_support_bundle(
+                            FILESYSTEM_LAYOUT.get().unwrap(),
+                            &session,
+                            &recent_statistics,
+                            redact,
+                        ) {
+                            Ok(path) => {
+                                info!("Support bundle saved to {}", path.display());
+                                alvr_events::send_event(EventType::SupportBundleCreated(path));
+                            }
+                            Err(e) => error!("Failed to create the support bundle: {e}"),
+                        }
+                    }
                 }
 
                 reply(StatusCode::OK)?
@@ -210,7 +373,7 @@ async fn http_api(
             }
         }
         "/api/events" => {
-            websocket(request, LOGGING_EVENTS_SENDER.clone(), |e| {
+            websocket(request, EVENTS_SENDER.clone(), |e| {
                 protocol::Message::Text(json::to_string(&e).unwrap())
             })
             .await?
@@ -270,7 +433,14 @@ async fn http_api(
                 .body(latency.to_string().into())?
         }
         "/api/ping" => reply(StatusCode::OK)?,
-        _ => reply(StatusCode::NOT_FOUND)?,
+        _ => {
+            let path = request.uri().path();
+            if path.starts_with("/api/") {
+                reply(StatusCode::NOT_FOUND)?
+            } else {
+                dashboard_file(path).await?
+            }
+        }
     };
 
     response.headers_mut().insert(
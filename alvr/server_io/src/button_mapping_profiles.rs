@@ -0,0 +1,228 @@
+use alvr_common::anyhow::{bail, Result};
+use alvr_session::ButtonMappingProfile;
+use serde_json as json;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Profile names are user provided, keep only characters that are safe for any filesystem
+fn profile_path(profiles_dir: &Path, name: &str) -> PathBuf {
+    let file_stem = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    profiles_dir.join(format!("{file_stem}.json"))
+}
+
+fn parse_profile(content: &str) -> Result<ButtonMappingProfile> {
+    let profile = json::from_str::<ButtonMappingProfile>(content)?;
+
+    if profile.name.is_empty() {
+        bail!("Button mapping profile has no name");
+    }
+
+    Ok(profile)
+}
+
+// Invalid files are skipped. A missing directory is equivalent to an empty profile list.
+pub fn get_button_mapping_profiles(profiles_dir: &Path) -> Vec<ButtonMappingProfile> {
+    let Ok(entries) = fs::read_dir(profiles_dir) else {
+        return vec![];
+    };
+
+    let mut profiles = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "json").then_some(path)
+        })
+        .filter_map(|path| parse_profile(&fs::read_to_string(path).ok()?).ok())
+        .collect::<Vec<_>>();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    profiles
+}
+
+pub fn save_button_mapping_profile(
+    profiles_dir: &Path,
+    profile: &ButtonMappingProfile,
+) -> Result<()> {
+    fs::create_dir_all(profiles_dir)?;
+    fs::write(
+        profile_path(profiles_dir, &profile.name),
+        json::to_string_pretty(profile)?,
+    )?;
+
+    Ok(())
+}
+
+// Add the content of a standalone profile file to the profiles library. A profile with the same
+// name is overwritten.
+pub fn import_button_mapping_profile(profiles_dir: &Path, content: &str) -> Result<()> {
+    save_button_mapping_profile(profiles_dir, &parse_profile(content)?)
+}
+
+pub fn export_button_mapping_profile(
+    profile: &ButtonMappingProfile,
+    destination_path: &Path,
+) -> Result<()> {
+    fs::write(destination_path, json::to_string_pretty(profile)?)?;
+
+    Ok(())
+}
+
+pub fn remove_button_mapping_profile(profiles_dir: &Path, name: &str) -> Result<()> {
+    fs::remove_file(profile_path(profiles_dir, name))?;
+
+    Ok(())
+}
+
+pub fn find_button_mapping_profile(
+    profiles: Vec<ButtonMappingProfile>,
+    steamvr_app_key: &str,
+) -> Option<ButtonMappingProfile> {
+    profiles.into_iter().find(|p| {
+        p.steamvr_app_keys
+            .iter()
+            .any(|key| key.eq_ignore_ascii_case(steamvr_app_key))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_session::{ButtonBindingTarget, ButtonMappingType};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "alvr_button_mapping_profiles_{name}_{}",
+                std::process::id()
+            ));
+            fs::remove_dir_all(&path).ok();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    fn test_profile(name: &str, app_key: &str, destination: &str) -> ButtonMappingProfile {
+        ButtonMappingProfile {
+            name: name.into(),
+            steamvr_app_keys: vec![app_key.into()],
+            button_mappings: vec![(
+                "/user/hand/left/input/x/click".into(),
+                vec![ButtonBindingTarget {
+                    destination: destination.into(),
+                    mapping_type: ButtonMappingType::Passthrough,
+                    binary_conditions: vec![],
+                }],
+            )],
+        }
+    }
+
+    #[test]
+    fn test_profile_path_sanitizes_name() {
+        let path = profile_path(Path::new("profiles"), "../My game: v2");
+
+        assert_eq!(path, Path::new("profiles").join("___My_game__v2.json"));
+    }
+
+    #[test]
+    fn test_missing_dir_has_no_profiles() {
+        let dir = TempDir::new("missing");
+
+        assert!(get_button_mapping_profiles(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_list_sorted() {
+        let dir = TempDir::new("list");
+
+        save_button_mapping_profile(&dir.0, &test_profile("b", "app.b", "/a")).unwrap();
+        save_button_mapping_profile(&dir.0, &test_profile("a", "app.a", "/a")).unwrap();
+        // Invalid files are skipped
+        fs::write(dir.0.join("invalid.json"), "{").unwrap();
+        fs::write(dir.0.join("notes.txt"), "").unwrap();
+
+        let names = get_button_mapping_profiles(&dir.0)
+            .into_iter()
+            .map(|p| p.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_import_overwrites_same_name() {
+        let dir = TempDir::new("import");
+
+        let original = test_profile("game", "app.game", "/original");
+        let edited = test_profile("game", "app.game", "/edited");
+        import_button_mapping_profile(&dir.0, &json::to_string(&original).unwrap()).unwrap();
+        import_button_mapping_profile(&dir.0, &json::to_string(&edited).unwrap()).unwrap();
+
+        assert_eq!(get_button_mapping_profiles(&dir.0), [edited]);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_content() {
+        let dir = TempDir::new("import_invalid");
+
+        assert!(import_button_mapping_profile(&dir.0, "not json").is_err());
+        let unnamed = json::to_string(&test_profile("", "app", "/a")).unwrap();
+        assert!(import_button_mapping_profile(&dir.0, &unnamed).is_err());
+        assert!(get_button_mapping_profiles(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let dir = TempDir::new("export");
+        fs::create_dir_all(&dir.0).unwrap();
+
+        let profile = test_profile("game", "app.game", "/a");
+        let exported_path = dir.0.join("exported.txt");
+        export_button_mapping_profile(&profile, &exported_path).unwrap();
+
+        let library_dir = dir.0.join("library");
+        import_button_mapping_profile(&library_dir, &fs::read_to_string(exported_path).unwrap())
+            .unwrap();
+        assert_eq!(get_button_mapping_profiles(&library_dir), [profile]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let dir = TempDir::new("remove");
+
+        save_button_mapping_profile(&dir.0, &test_profile("game", "app.game", "/a")).unwrap();
+        remove_button_mapping_profile(&dir.0, "game").unwrap();
+
+        assert!(get_button_mapping_profiles(&dir.0).is_empty());
+        assert!(remove_button_mapping_profile(&dir.0, "game").is_err());
+    }
+
+    #[test]
+    fn test_find_by_app_key_ignores_case() {
+        let profiles = vec![
+            test_profile("a", "steam.app.1", "/a"),
+            test_profile("b", "steam.app.2", "/a"),
+        ];
+
+        let found = find_button_mapping_profile(profiles.clone(), "STEAM.APP.2").unwrap();
+        assert_eq!(found.name, "b");
+        assert!(find_button_mapping_profile(profiles, "steam.app.3").is_none());
+    }
+}
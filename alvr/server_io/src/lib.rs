@@ -1,18 +1,31 @@
+mod button_mapping_profiles;
 mod firewall;
+mod logs_archive;
 mod openvr_drivers;
 mod openvrpaths;
+mod settings_history;
+mod support_bundle;
 
+pub use button_mapping_profiles::*;
 pub use firewall::*;
+pub use logs_archive::*;
 pub use openvr_drivers::*;
 pub use openvrpaths::*;
+pub use settings_history::*;
+pub use support_bundle::*;
 
 use alvr_common::{
     anyhow::{bail, Result},
     error, info, ConnectionState,
 };
 use alvr_events::EventType;
-use alvr_packets::{AudioDevicesList, ClientListAction, PathSegment, PathValuePair};
-use alvr_session::{ClientConnectionConfig, SessionConfig, Settings};
+use alvr_packets::{
+    AudioDevicesList, ClientListAction, PathSegment, PathValuePair, SettingsChangeOrigin,
+    SettingsHistoryEntry,
+};
+use alvr_session::{
+    ClientConnectionConfig, ClientStreamingOverrides, SessionConfig, SessionSettings, Settings,
+};
 use serde_json as json;
 use std::{
     collections::{hash_map::Entry, HashMap},
@@ -22,6 +35,10 @@ use std::{
     path::{Path, PathBuf},
 };
 
+fn new_relay_pairing_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
 fn save_session(session: &SessionConfig, path: &Path) -> Result<()> {
     fs::write(path, json::to_string_pretty(session)?)?;
 
@@ -33,6 +50,9 @@ pub struct SessionLock<'a> {
     session_desc: &'a mut SessionConfig,
     session_path: Option<&'a Path>,
     settings: &'a mut Settings,
+    history: &'a mut SettingsHistory,
+    old_settings: json::Value,
+    origin: SettingsChangeOrigin,
 }
 
 impl Deref for SessionLock<'_> {
@@ -56,6 +76,15 @@ impl Drop for SessionLock<'_> {
 
         *self.settings = self.session_desc.to_settings();
         alvr_events::send_event(EventType::Session(Box::new(self.session_desc.clone())));
+
+        if let Ok(new_settings) = json::to_value(&self.session_desc.session_settings) {
+            if self
+                .history
+                .record(&self.old_settings, &new_settings, self.origin)
+            {
+                alvr_events::send_event(EventType::SettingsHistory(self.history.entries()));
+            }
+        }
     }
 }
 
@@ -68,6 +97,7 @@ pub struct ServerSessionManager {
     session_config: SessionConfig,
     settings: Settings,
     session_path: Option<PathBuf>,
+    settings_history: SettingsHistory,
 }
 
 impl ServerSessionManager {
@@ -80,10 +110,30 @@ impl ServerSessionManager {
             SessionConfig::default()
         };
 
+        // Clients trusted by older versions have no pairing token
+        let mut session_config = session_config;
+        let mut tokens_added = false;
+        for client in session_config.client_connections.values_mut() {
+            if client.trusted && client.relay_pairing_token.is_none() {
+                client.relay_pairing_token = Some(new_relay_pairing_token());
+                tokens_added = true;
+            }
+        }
+        if let (true, Some(session_path)) = (tokens_added, &session_path) {
+            save_session(&session_config, session_path).ok();
+        }
+
+        let settings_history = SettingsHistory::load(
+            session_path
+                .as_deref()
+                .map(SettingsHistory::path_for_session),
+        );
+
         Self {
             session_config: session_config.clone(),
             settings: session_config.to_settings(),
             session_path: session_path.to_owned(),
+            settings_history,
         }
     }
 
@@ -94,7 +144,7 @@ impl ServerSessionManager {
             return SessionConfig::default();
         }
 
-        let session_json = json::from_str::<json::Value>(&session_string)
+        let mut session_json = json::from_str::<json::Value>(&session_string)
             .unwrap_or_else(|e| {
                 error!(
                     "{} {} {}\n{}",
@@ -111,27 +161,37 @@ impl ServerSessionManager {
             return SessionConfig::default();
         }
 
-        json::from_value(session_json.clone()).unwrap_or_else(|_| {
-            fs::write(config_dir.join("session_old.json"), &session_string).ok();
-            let mut session_desc = SessionConfig::default();
-            match session_desc.merge_from_json(&session_json) {
-                Ok(_) => info!(
-                    "{} {}",
-                    "Session extrapolated successfully.",
-                    "Old session.json is stored as session_old.json"
-                ),
-                Err(e) => error!(
-                    "{} {} {}",
-                    "Error while extrapolating session.",
-                    "Old session.json is stored as session_old.json.",
-                    e
-                ),
-            }
-            // not essential, but useful to avoid duplicated errors
-            save_session(&session_desc, session_path).ok();
+        let migrated = alvr_session::migrate_session_json(&mut session_json);
 
-            session_desc
-        })
+        json::from_value::<SessionConfig>(session_json.clone())
+            .inspect(|session_desc| {
+                if migrated {
+                    fs::write(config_dir.join("session_old.json"), &session_string).ok();
+                    info!("Session migrated. Old session.json is stored as session_old.json");
+                    save_session(session_desc, session_path).ok();
+                }
+            })
+            .unwrap_or_else(|_| {
+                fs::write(config_dir.join("session_old.json"), &session_string).ok();
+                let mut session_desc = SessionConfig::default();
+                match session_desc.merge_from_json(&session_json) {
+                    Ok(_) => info!(
+                        "{} {}",
+                        "Session extrapolated successfully.",
+                        "Old session.json is stored as session_old.json"
+                    ),
+                    Err(e) => error!(
+                        "{} {} {}",
+                        "Error while extrapolating session.",
+                        "Old session.json is stored as session_old.json.",
+                        e
+                    ),
+                }
+                // not essential, but useful to avoid duplicated errors
+                save_session(&session_desc, session_path).ok();
+
+                session_desc
+            })
     }
 
     // prefer settings()
@@ -140,10 +200,18 @@ impl ServerSessionManager {
     }
 
     pub fn session_mut(&mut self) -> SessionLock {
+        self.session_mut_from(SettingsChangeOrigin::Server)
+    }
+
+    // Like session_mut(), but the settings changes are recorded in the history with the given origin
+    pub fn session_mut_from(&mut self, origin: SettingsChangeOrigin) -> SessionLock {
         SessionLock {
+            old_settings: json::to_value(&self.session_config.session_settings).unwrap_or_default(),
             session_desc: &mut self.session_config,
             session_path: self.session_path.as_deref(),
             settings: &mut self.settings,
+            history: &mut self.settings_history,
+            origin,
         }
     }
 
@@ -152,8 +220,13 @@ impl ServerSessionManager {
     }
 
     // Note: "value" can be any session subtree, in json format.
-    pub fn set_values(&mut self, descs: Vec<PathValuePair>) -> Result<()> {
+    pub fn set_values(
+        &mut self,
+        descs: Vec<PathValuePair>,
+        origin: SettingsChangeOrigin,
+    ) -> Result<()> {
         let mut session_json = serde_json::to_value(self.session_config.clone()).unwrap();
+        let old_settings = session_json["session_settings"].clone();
 
         for desc in descs {
             let mut session_ref = &mut session_json;
@@ -188,6 +261,48 @@ impl ServerSessionManager {
 
         alvr_events::send_event(EventType::Session(Box::new(self.session_config.clone())));
 
+        self.record_settings_change(&old_settings, origin);
+
+        Ok(())
+    }
+
+    fn record_settings_change(&mut self, old_settings: &json::Value, origin: SettingsChangeOrigin) {
+        if let Ok(new_settings) = json::to_value(&self.session_config.session_settings) {
+            if self
+                .settings_history
+                .record(old_settings, &new_settings, origin)
+            {
+                alvr_events::send_event(EventType::SettingsHistory(
+                    self.settings_history.entries(),
+                ));
+            }
+        }
+    }
+
+    pub fn settings_history(&self) -> Vec<SettingsHistoryEntry> {
+        self.settings_history.entries()
+    }
+
+    // Restores the settings as they were before the given history entry. The rollback itself is
+    // recorded too, so it can be undone
+    pub fn rollback_settings(&mut self, entry_id: u64) -> Result<()> {
+        let old_settings = json::to_value(&self.session_config.session_settings)?;
+        let restored_settings = self
+            .settings_history
+            .settings_before(entry_id, &old_settings)?;
+
+        self.session_config.session_settings =
+            json::from_value::<SessionSettings>(restored_settings)?;
+        self.settings = self.session_config.to_settings();
+
+        if let Some(session_path) = &self.session_path {
+            save_session(&self.session_config, session_path)?;
+        }
+
+        alvr_events::send_event(EventType::Session(Box::new(self.session_config.clone())));
+
+        self.record_settings_change(&old_settings, SettingsChangeOrigin::Rollback);
+
         Ok(())
     }
 
@@ -214,6 +329,10 @@ impl ServerSessionManager {
                         trusted,
                         connection_state: ConnectionState::Disconnected,
                         cabled: false,
+                        spectator: false,
+                        streaming_overrides: ClientStreamingOverrides::default(),
+                        incompatibility: None,
+                        relay_pairing_token: trusted.then(new_relay_pairing_token),
                     };
                     new_entry.insert(client_connection_desc);
 
@@ -230,6 +349,10 @@ impl ServerSessionManager {
             ClientListAction::Trust => {
                 if let Entry::Occupied(mut entry) = maybe_client_entry {
                     entry.get_mut().trusted = true;
+                    entry
+                        .get_mut()
+                        .relay_pairing_token
+                        .get_or_insert_with(new_relay_pairing_token);
 
                     updated = true;
                 }
@@ -262,6 +385,42 @@ impl ServerSessionManager {
                     if entry.get().connection_state != state {
                         entry.get_mut().connection_state = state;
 
+                        updated = true;
+                    }
+                }
+            }
+            ClientListAction::SetStreamingOverrides(overrides) => {
+                if let Entry::Occupied(mut entry) = maybe_client_entry {
+                    if entry.get().streaming_overrides != overrides {
+                        entry.get_mut().streaming_overrides = overrides;
+
+                        updated = true;
+                    }
+                }
+            }
+            ClientListAction::SetIncompatibility(incompatibility) => {
+                if let Entry::Occupied(mut entry) = maybe_client_entry {
+                    if entry.get().incompatibility != incompatibility {
+                        entry.get_mut().incompatibility = incompatibility;
+
+                        updated = true;
+                    }
+                }
+            }
+            ClientListAction::SetCabled(cabled) => {
+                if let Entry::Occupied(mut entry) = maybe_client_entry {
+                    if entry.get().cabled != cabled {
+                        entry.get_mut().cabled = cabled;
+
+                        updated = true;
+                    }
+                }
+            }
+            ClientListAction::SetSpectator(spectator) => {
+                if let Entry::Occupied(mut entry) = maybe_client_entry {
+                    if entry.get().spectator != spectator {
+                        entry.get_mut().spectator = spectator;
+
                         updated = true;
                     }
                 }
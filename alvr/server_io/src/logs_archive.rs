@@ -0,0 +1,38 @@
+use alvr_common::anyhow::Result;
+use alvr_filesystem::Layout;
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+pub(crate) fn write_archive(archive_path: &Path, files: &[(&str, String)]) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(archive_path)?);
+    let options = SimpleFileOptions::default();
+
+    for (name, content) in files {
+        zip.start_file(*name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+// Bundles the given log text together with session.json and the crash log. Missing files are
+// skipped, since a fresh installation might not have them yet.
+pub fn write_logs_archive(archive_path: &Path, layout: &Layout, logs: &str) -> Result<()> {
+    let mut files = vec![("logs.txt", logs.to_owned())];
+    for (name, path) in [
+        ("session.json", layout.session()),
+        ("crash_log.txt", layout.crash_log()),
+    ] {
+        if let Ok(content) = fs::read_to_string(path) {
+            files.push((name, content));
+        }
+    }
+
+    write_archive(archive_path, &files)
+}
@@ -0,0 +1,329 @@
+use alvr_common::anyhow::{bail, Result};
+use alvr_packets::{PathSegment, SettingsChangeOrigin, SettingsHistoryEntry, SettingsValueChange};
+use serde_json as json;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MAX_HISTORY_ENTRIES: usize = 100;
+
+// Collects the changed leaves. Objects with different keys and arrays with different lengths are
+// recorded as a whole
+fn diff_values(
+    path: &mut Vec<PathSegment>,
+    old: &json::Value,
+    new: &json::Value,
+    changes: &mut Vec<SettingsValueChange>,
+) {
+    match (old, new) {
+        (json::Value::Object(old_map), json::Value::Object(new_map))
+            if old_map.len() == new_map.len()
+                && old_map.keys().all(|key| new_map.contains_key(key)) =>
+        {
+            for (key, old_value) in old_map {
+                path.push(PathSegment::Name(key.clone()));
+                diff_values(path, old_value, &new_map[key], changes);
+                path.pop();
+            }
+        }
+        (json::Value::Array(old_array), json::Value::Array(new_array))
+            if old_array.len() == new_array.len() =>
+        {
+            for (index, (old_value, new_value)) in old_array.iter().zip(new_array).enumerate() {
+                path.push(PathSegment::Index(index));
+                diff_values(path, old_value, new_value, changes);
+                path.pop();
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(SettingsValueChange {
+                    path: path.clone(),
+                    old_value: old.clone(),
+                    new_value: new.clone(),
+                });
+            }
+        }
+    }
+}
+
+// The first segment of the path is skipped, since root is the value it refers to
+fn set_value(root: &mut json::Value, path: &[PathSegment], value: json::Value) -> Result<()> {
+    let mut value_ref = root;
+    for segment in path.iter().skip(1) {
+        value_ref = match segment {
+            PathSegment::Name(name) => match value_ref.get_mut(name) {
+                Some(value) => value,
+                None => bail!("From path {path:?}: segment \"{name}\" not found"),
+            },
+            PathSegment::Index(index) => match value_ref.get_mut(index) {
+                Some(value) => value,
+                None => bail!("From path {path:?}: segment [{index}] not found"),
+            },
+        };
+    }
+    *value_ref = value;
+
+    Ok(())
+}
+
+// Bounded list of the changes to the session settings, stored next to session.json
+pub struct SettingsHistory {
+    entries: VecDeque<SettingsHistoryEntry>,
+    path: Option<PathBuf>,
+}
+
+impl SettingsHistory {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|history_string| json::from_str(&history_string).ok())
+            .unwrap_or_default();
+
+        Self { entries, path }
+    }
+
+    pub fn path_for_session(session_path: &Path) -> PathBuf {
+        session_path.with_file_name("settings_history.json")
+    }
+
+    pub fn entries(&self) -> Vec<SettingsHistoryEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    // The settings JSON values are rooted at "session_settings". Returns false if nothing changed
+    pub fn record(
+        &mut self,
+        old_settings: &json::Value,
+        new_settings: &json::Value,
+        origin: SettingsChangeOrigin,
+    ) -> bool {
+        let mut changes = vec![];
+        diff_values(
+            &mut vec![PathSegment::Name("session_settings".into())],
+            old_settings,
+            new_settings,
+            &mut changes,
+        );
+
+        if changes.is_empty() {
+            return false;
+        }
+
+        // IDs keep increasing even if the oldest entries are discarded
+        let id = self.entries.back().map(|entry| entry.id + 1).unwrap_or(0);
+        self.entries.push_back(SettingsHistoryEntry {
+            id,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+            origin,
+            changes,
+        });
+        while self.entries.len() > MAX_HISTORY_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        if let Some(path) = &self.path {
+            if let Ok(history_string) = json::to_string_pretty(&self.entries) {
+                fs::write(path, history_string).ok();
+            }
+        }
+
+        true
+    }
+
+    // Reverts the changes starting from the entry with the given ID, newest first
+    pub fn settings_before(&self, entry_id: u64, settings: &json::Value) -> Result<json::Value> {
+        if !self.entries.iter().any(|entry| entry.id == entry_id) {
+            bail!("Settings history entry {entry_id} not found");
+        }
+
+        let mut settings = settings.clone();
+        for entry in self
+            .entries
+            .iter()
+            .rev()
+            .take_while(|entry| entry.id >= entry_id)
+        {
+            for change in &entry.changes {
+                set_value(&mut settings, &change.path, change.old_value.clone())?;
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn diff(old: &json::Value, new: &json::Value) -> Vec<SettingsValueChange> {
+        let mut changes = vec![];
+        diff_values(
+            &mut vec![PathSegment::Name("session_settings".into())],
+            old,
+            new,
+            &mut changes,
+        );
+
+        changes
+    }
+
+    fn path_string(path: &[PathSegment]) -> String {
+        format!("{path:?}")
+    }
+
+    #[test]
+    fn diff_leaf_changes() {
+        let old =
+            json!({ "video": { "bitrate": 30, "codec": "H264" }, "audio": { "enabled": true } });
+        let new =
+            json!({ "video": { "bitrate": 50, "codec": "H264" }, "audio": { "enabled": false } });
+
+        let mut changes = diff(&old, &new);
+        changes.sort_by_key(|change| path_string(&change.path));
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            path_string(&changes[0].path),
+            "[session_settings, audio, enabled]"
+        );
+        assert_eq!(changes[0].old_value, json!(true));
+        assert_eq!(changes[0].new_value, json!(false));
+        assert_eq!(
+            path_string(&changes[1].path),
+            "[session_settings, video, bitrate]"
+        );
+        assert_eq!(changes[1].old_value, json!(30));
+        assert_eq!(changes[1].new_value, json!(50));
+
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn diff_object_with_different_keys() {
+        let old = json!({ "mode": { "Constant": 30 } });
+        let new = json!({ "mode": { "Adaptive": 50 } });
+
+        let changes = diff(&old, &new);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(path_string(&changes[0].path), "[session_settings, mode]");
+        assert_eq!(changes[0].old_value, old["mode"]);
+        assert_eq!(changes[0].new_value, new["mode"]);
+    }
+
+    #[test]
+    fn diff_array_with_different_length() {
+        let old = json!({ "hooks": [1, 2] });
+        let new = json!({ "hooks": [1, 2, 3] });
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(path_string(&changes[0].path), "[session_settings, hooks]");
+        assert_eq!(changes[0].new_value, json!([1, 2, 3]));
+
+        // Same length, only the changed element is recorded
+        let changes = diff(&old, &json!({ "hooks": [1, 5] }));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            path_string(&changes[0].path),
+            "[session_settings, hooks, [1]]"
+        );
+    }
+
+    #[test]
+    fn set_value_by_path() {
+        let mut root = json!({ "video": { "sizes": [1, 2] } });
+
+        set_value(
+            &mut root,
+            &[
+                "session_settings".into(),
+                "video".into(),
+                "sizes".into(),
+                1.into(),
+            ],
+            json!(5),
+        )
+        .unwrap();
+        assert_eq!(root, json!({ "video": { "sizes": [1, 5] } }));
+
+        set_value(
+            &mut root,
+            &["session_settings".into(), "video".into()],
+            json!(null),
+        )
+        .unwrap();
+        assert_eq!(root, json!({ "video": null }));
+
+        assert!(set_value(
+            &mut root,
+            &["session_settings".into(), "audio".into(), "enabled".into()],
+            json!(true),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rollback_across_entries() {
+        let mut history = SettingsHistory::load(None);
+
+        let v0 = json!({ "bitrate": 10, "mode": { "Constant": 1 }, "hooks": [] });
+        let v1 = json!({ "bitrate": 20, "mode": { "Constant": 1 }, "hooks": [] });
+        let v2 = json!({ "bitrate": 20, "mode": { "Adaptive": 2 }, "hooks": ["a"] });
+        let v3 = json!({ "bitrate": 30, "mode": { "Adaptive": 2 }, "hooks": ["a"] });
+
+        assert!(history.record(&v0, &v1, SettingsChangeOrigin::Dashboard));
+        assert!(history.record(&v1, &v2, SettingsChangeOrigin::Preset));
+        assert!(history.record(&v2, &v3, SettingsChangeOrigin::Cli));
+        assert!(!history.record(&v3, &v3, SettingsChangeOrigin::Cli));
+
+        let entries = history.entries();
+        assert_eq!(
+            entries.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(entries[1].origin, SettingsChangeOrigin::Preset);
+
+        assert_eq!(history.settings_before(2, &v3).unwrap(), v2);
+        assert_eq!(history.settings_before(1, &v3).unwrap(), v1);
+        assert_eq!(history.settings_before(0, &v3).unwrap(), v0);
+        assert!(history.settings_before(3, &v3).is_err());
+    }
+
+    #[test]
+    fn history_is_trimmed() {
+        let mut history = SettingsHistory::load(None);
+
+        let entries_count = MAX_HISTORY_ENTRIES as u64 + 10;
+        for value in 0..entries_count {
+            history.record(
+                &json!({ "value": value }),
+                &json!({ "value": value + 1 }),
+                SettingsChangeOrigin::Server,
+            );
+        }
+
+        let entries = history.entries();
+        assert_eq!(entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(entries.first().unwrap().id, 10);
+        assert_eq!(entries.last().unwrap().id, entries_count - 1);
+
+        let settings = json!({ "value": entries_count });
+        assert_eq!(
+            history.settings_before(10, &settings).unwrap(),
+            json!({ "value": 10 })
+        );
+        // Discarded entries cannot be restored anymore
+        assert!(history.settings_before(9, &settings).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+use alvr_common::{anyhow::Result, ALVR_VERSION};
+use alvr_events::StatisticsSummary;
+use alvr_filesystem::Layout;
+use alvr_session::SessionConfig;
+use serde_json as json;
+use std::{
+    fs,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const REDACTED_IP: &str = "<redacted ip>";
+const REDACTED_VALUE: &str = "<redacted>";
+
+// All the strings found under these session keys are redacted: rendezvous and relay addresses, the
+// OSC host, webhook URLs (they can contain tokens) and relay pairing tokens
+const SECRET_KEYS: [&str; 4] = [
+    "rendezvous_server",
+    "host",
+    "Webhook",
+    "relay_pairing_token",
+];
+
+fn collect_secrets(value: &json::Value, is_secret: bool, secrets: &mut Vec<String>) {
+    match value {
+        json::Value::String(string) if is_secret && !string.is_empty() => {
+            secrets.push(string.clone())
+        }
+        json::Value::Array(array) => {
+            for value in array {
+                collect_secrets(value, is_secret, secrets);
+            }
+        }
+        json::Value::Object(map) => {
+            for (key, value) in map {
+                collect_secrets(
+                    value,
+                    is_secret || SECRET_KEYS.contains(&key.as_str()),
+                    secrets,
+                );
+            }
+        }
+        _ => (),
+    }
+}
+
+// Module paths like "std::fs" are also made of hex digits and colons, so IPv6 addresses must have
+// digits and at least two groups, and cannot start with "::"
+fn is_ipv6_address(text: &str) -> bool {
+    !text.starts_with("::")
+        && text.contains(|c: char| c.is_ascii_digit())
+        && text.split(':').filter(|group| !group.is_empty()).count() >= 2
+        && text.parse::<Ipv6Addr>().is_ok()
+}
+
+// Redacts the dotted quads, with their port if any, not preceded by other digits or dots. They can
+// follow letters, like in "cafe10.0.0.2"
+fn redact_ipv4_addresses(token: &str) -> String {
+    let bytes = token.as_bytes();
+    let is_ipv4_char = |byte: u8| byte.is_ascii_digit() || byte == b'.';
+
+    let mut redacted = String::with_capacity(token.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_digit() || (index > 0 && is_ipv4_char(bytes[index - 1])) {
+            redacted.push(bytes[index] as char);
+            index += 1;
+            continue;
+        }
+
+        let run_end = (index..bytes.len())
+            .find(|&i| !is_ipv4_char(bytes[i]))
+            .unwrap_or(bytes.len());
+        // Keep the punctuation that might follow the address
+        let address = token[index..run_end].trim_end_matches('.');
+        let mut end = index + address.len();
+
+        if address.split('.').count() == 4 && address.parse::<Ipv4Addr>().is_ok() {
+            let port_len = bytes[end..]
+                .iter()
+                .skip(1)
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+            if bytes.get(end) == Some(&b':') && port_len > 0 {
+                end += 1 + port_len;
+            }
+
+            redacted.push_str(REDACTED_IP);
+        } else {
+            redacted.push_str(&token[index..end]);
+        }
+        index = end;
+    }
+
+    redacted
+}
+
+fn redact_token(token: &str, at_word_start: bool) -> String {
+    // Leading colons separate the address from a label, like in "IP:10.0.0.1"
+    let address = token.trim_start_matches(':');
+    let prefix = &token[..token.len() - address.len()];
+    let address = address.trim_end_matches(['.', ':']);
+
+    if (at_word_start || !prefix.is_empty()) && is_ipv6_address(address) {
+        format!(
+            "{prefix}{REDACTED_IP}{}",
+            &token[prefix.len() + address.len()..]
+        )
+    } else {
+        redact_ipv4_addresses(token)
+    }
+}
+
+// Replaces secrets, the user home directory and client hostnames with placeholders and removes IP
+// addresses
+struct Redactor {
+    // Pairs of text to find and replacement, in the order they are applied
+    replacements: Vec<(String, String)>,
+}
+
+impl Redactor {
+    fn new(session: &SessionConfig) -> Self {
+        let mut secrets = vec![];
+        if let Ok(session_json) = json::to_value(session) {
+            collect_secrets(&session_json, false, &mut secrets);
+        }
+
+        Self::from_values(
+            secrets,
+            dirs::home_dir().map(|path| path.to_string_lossy().into_owned()),
+            session.client_connections.keys().cloned().collect(),
+        )
+    }
+
+    fn from_values(
+        mut secrets: Vec<String>,
+        home_dir: Option<String>,
+        mut hostnames: Vec<String>,
+    ) -> Self {
+        // Values are also searched as they appear inside JSON strings, with escaped characters
+        fn with_json_escaped(value: String, replacement: &str) -> Vec<(String, String)> {
+            let escaped = json::to_string(&value)
+                .map(|string| string.trim_matches('"').to_owned())
+                .unwrap_or_default();
+
+            let mut pairs = vec![(value.clone(), replacement.to_owned())];
+            if escaped != value {
+                pairs.push((escaped, replacement.to_owned()));
+            }
+
+            pairs
+        }
+
+        secrets.sort();
+        secrets.dedup();
+        // Longer values first, in case one contains another
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+
+        let mut replacements = secrets
+            .into_iter()
+            .flat_map(|secret| with_json_escaped(secret, REDACTED_VALUE))
+            .collect::<Vec<_>>();
+
+        if let Some(home_dir) = home_dir.filter(|dir| !dir.is_empty()) {
+            replacements.extend(with_json_escaped(home_dir, "~"));
+        }
+
+        // Aliases are assigned in alphabetical order, so they are stable between bundles
+        hostnames.sort();
+        let mut hostname_aliases = hostnames
+            .into_iter()
+            .enumerate()
+            .map(|(index, hostname)| (hostname, format!("client{}", index + 1)))
+            .collect::<Vec<_>>();
+        hostname_aliases.sort_by_key(|(hostname, _)| std::cmp::Reverse(hostname.len()));
+        replacements.extend(hostname_aliases);
+
+        Self { replacements }
+    }
+
+    fn redact_text(&self, text: &str) -> String {
+        let mut text = text.to_owned();
+        for (value, replacement) in &self.replacements {
+            text = text.replace(value, replacement);
+        }
+
+        // IP addresses are found in tokens made of hex digits, dots and colons
+        let mut redacted = String::with_capacity(text.len());
+        let mut token = String::new();
+        let mut token_at_word_start = true;
+        let mut previous_char = None;
+        for c in text.chars().chain([' ']) {
+            if c.is_ascii_hexdigit() || c == '.' || c == ':' {
+                if token.is_empty() {
+                    token_at_word_start = !previous_char.is_some_and(|previous: char| {
+                        previous.is_alphanumeric() || previous == '_'
+                    });
+                }
+                token.push(c);
+            } else {
+                redacted.push_str(&redact_token(&token, token_at_word_start));
+                token.clear();
+                redacted.push(c);
+            }
+            previous_char = Some(c);
+        }
+        redacted.pop();
+
+        redacted
+    }
+
+    fn redact_session(&self, session: &SessionConfig) -> SessionConfig {
+        let mut session = session.clone();
+        session.client_connections = session
+            .client_connections
+            .into_iter()
+            .map(|(hostname, mut connection)| {
+                connection.display_name = self.redact_text(&connection.display_name);
+                connection.current_ip = None;
+                connection.manual_ips.clear();
+                connection.relay_pairing_token = None;
+
+                (self.redact_text(&hostname), connection)
+            })
+            .collect();
+
+        session
+    }
+}
+
+#[cfg(windows)]
+fn gpu_info() -> Result<String> {
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_VideoController | Format-List Name,DriverVersion,VideoProcessor",
+        ])
+        .output()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "linux")]
+fn gpu_info() -> Result<String> {
+    let output = std::process::Command::new("lspci").arg("-nn").output()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("VGA") || line.contains("3D") || line.contains("Display"))
+        .map(|line| format!("{line}\n"))
+        .collect())
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn gpu_info() -> Result<String> {
+    Ok("Not available on this platform".into())
+}
+
+// Collects everything needed to investigate a problem into a zip file in the log directory.
+// Statistics are available only if the streamer is running. Returns the path of the bundle.
+pub fn create_support_bundle(
+    layout: &Layout,
+    session: &SessionConfig,
+    recent_statistics: &[StatisticsSummary],
+    redact: bool,
+) -> Result<PathBuf> {
+    let redactor = redact.then(|| Redactor::new(session));
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    fs::create_dir_all(&layout.log_dir)?;
+    let archive_path = layout
+        .log_dir
+        .join(format!("alvr_support_bundle_{timestamp}.zip"));
+
+    let session = match &redactor {
+        Some(redactor) => redactor.redact_session(session),
+        None => session.clone(),
+    };
+
+    let drivers = match crate::get_registered_drivers() {
+        Ok(drivers) => json::to_string_pretty(&drivers)?,
+        Err(e) => format!("Failed to get the registered drivers: {e}"),
+    };
+    let gpu_info = gpu_info().unwrap_or_else(|e| format!("Failed to get the GPU info: {e}"));
+    let system_info = format!(
+        "ALVR version: {}\nOS: {} {}\n\nGPUs:\n{gpu_info}",
+        *ALVR_VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    let mut files = vec![
+        ("system.txt", system_info),
+        ("session.json", json::to_string_pretty(&session)?),
+        (
+            "clients.json",
+            json::to_string_pretty(&session.client_connections)?,
+        ),
+        ("drivers.json", drivers),
+        (
+            "statistics.json",
+            json::to_string_pretty(recent_statistics)?,
+        ),
+    ];
+    for (name, path) in [
+        ("session_log.txt", layout.session_log()),
+        ("crash_log.txt", layout.crash_log()),
+    ] {
+        if let Ok(content) = fs::read(path) {
+            files.push((name, String::from_utf8_lossy(&content).into_owned()));
+        }
+    }
+
+    // Paths and addresses can appear in any file, for example in the drivers list
+    if let Some(redactor) = &redactor {
+        for (_, content) in &mut files {
+            *content = redactor.redact_text(content);
+        }
+    }
+
+    crate::write_archive(&archive_path, &files)?;
+
+    Ok(archive_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::from_values(
+            vec![
+                "https://example.com/hook?token=abc123".into(),
+                "osc.example.com".into(),
+            ],
+            Some(r"C:\Users\alice".into()),
+            vec!["1234.client.alvr".into(), "client.alvr".into()],
+        )
+    }
+
+    #[test]
+    fn redact_ipv4_and_ipv6() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text("Connecting to 192.168.1.20 and fe80::1c2d:3e4f"),
+            "Connecting to <redacted ip> and <redacted ip>"
+        );
+        assert_eq!(
+            redactor.redact_text("Remote 2001:db8::ff00:42:8329, mapped ::ffff:10.0.0.1"),
+            "Remote <redacted ip>, mapped ::ffff:<redacted ip>"
+        );
+    }
+
+    #[test]
+    fn redact_socket_addresses() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text("Relay at 203.0.113.5:9945 accepted"),
+            "Relay at <redacted ip> accepted"
+        );
+        assert_eq!(
+            redactor.redact_text("Peer [2001:db8::1]:9944"),
+            "Peer [<redacted ip>]:9944"
+        );
+    }
+
+    #[test]
+    fn redact_with_trailing_punctuation() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text("Connected to 10.0.0.2."),
+            "Connected to <redacted ip>."
+        );
+        assert_eq!(
+            redactor.redact_text("Address 10.0.0.2:9943: refused"),
+            "Address <redacted ip>: refused"
+        );
+        assert_eq!(
+            redactor.redact_text("(10.0.0.2), \"10.0.0.3\""),
+            "(<redacted ip>), \"<redacted ip>\""
+        );
+    }
+
+    #[test]
+    fn redact_addresses_glued_to_other_text() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text("IP:192.168.1.5 and cafe10.0.0.2"),
+            "IP:<redacted ip> and cafe<redacted ip>"
+        );
+        assert_eq!(
+            redactor.redact_text("gateway=fe80::1%eth0, peer:2001:db8::1"),
+            "gateway=<redacted ip>%eth0, peer:<redacted ip>"
+        );
+    }
+
+    #[test]
+    fn keep_module_paths_and_numbers() {
+        let redactor = redactor();
+
+        for text in [
+            "alvr_common::anyhow::Error",
+            "at std::fs::read (src/lib.rs:12:5)",
+            "alvr_server_core::connection::abc123",
+            "Loopback ::1 and :: are not redacted",
+            "Build 10.0.19045.3803, values 1.2.3, 3a:4b",
+            "ALVR 20.11.1 at 12:34:56.789",
+        ] {
+            assert_eq!(redactor.redact_text(text), text);
+        }
+    }
+
+    #[test]
+    fn redact_hostnames_containing_other_hostnames() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text("1234.client.alvr and client.alvr"),
+            "client1 and client2"
+        );
+    }
+
+    #[test]
+    fn redact_secrets_and_home_dir() {
+        let redactor = redactor();
+
+        assert_eq!(
+            redactor.redact_text(
+                "POST https://example.com/hook?token=abc123 failed, OSC host osc.example.com"
+            ),
+            "POST <redacted> failed, OSC host <redacted>"
+        );
+        assert_eq!(
+            redactor.redact_text(r"Driver at C:\Users\alice\ALVR"),
+            r"Driver at ~\ALVR"
+        );
+        // JSON files escape the backslashes
+        assert_eq!(
+            redactor.redact_text(r#""path": "C:\\Users\\alice\\ALVR""#),
+            r#""path": "~\\ALVR""#
+        );
+    }
+
+    #[test]
+    fn collect_session_secrets() {
+        let session_json = json::json!({
+            "internet_streaming": { "content": { "rendezvous_server": "relay.example.com:9945" } },
+            "osc_output": { "content": { "host": "", "port": 9000 } },
+            "hooks": [{ "action": { "Webhook": "https://example.com/x", "variant": "Webhook" } }],
+        });
+
+        let mut secrets = vec![];
+        collect_secrets(&session_json, false, &mut secrets);
+        secrets.sort();
+
+        assert_eq!(secrets, ["https://example.com/x", "relay.example.com:9945"]);
+    }
+}
@@ -1,6 +1,7 @@
 #include "ALVR-common/packet_types.h"
 #include "Logger.h"
 #include "bindings.h"
+#include <cstring>
 #include <memory>
 #include <mutex>
 
@@ -93,6 +94,36 @@ void _SetChaperoneArea(float areaWidth, float areaHeight) {
 #endif
 }
 
+unsigned int GetSceneApplicationKey(char* outKey, unsigned int bufferLen) {
+#ifndef __APPLE__
+    std::unique_lock<std::mutex> lock(chaperone_mutex);
+
+    if (!isOpenvrInit) {
+        return 0;
+    }
+
+    auto compositor = vr::VRCompositor();
+    auto applications = vr::VRApplications();
+    if (compositor == nullptr || applications == nullptr) {
+        return 0;
+    }
+
+    auto processId = compositor->GetCurrentSceneFocusProcess();
+    if (processId == 0) {
+        return 0;
+    }
+
+    auto error = applications->GetApplicationKeyByProcessId(processId, outKey, bufferLen);
+    if (error != vr::VRApplicationError_None) {
+        return 0;
+    }
+
+    return (unsigned int)strnlen(outKey, bufferLen);
+#else
+    return 0;
+#endif
+}
+
 #ifdef __linux__
 std::unique_ptr<vr::HmdMatrix34_t> GetInvZeroPose() {
     std::unique_lock<std::mutex> lock(chaperone_mutex);
@@ -73,10 +73,14 @@ Hmd::Hmd()
 Hmd::~Hmd() {
     // ShutdownRuntime();
 
-    if (m_encoder) {
+    std::shared_ptr<CEncoder> encoder;
+    {
+        std::lock_guard<std::mutex> lock(m_encoderMutex);
+        encoder = std::move(m_encoder);
+    }
+    if (encoder) {
         Debug("Hmd::~Hmd(): Stopping encoder...\n");
-        m_encoder->Stop();
-        m_encoder.reset();
+        encoder->Stop();
     }
 
 #ifdef _WIN32
@@ -105,6 +109,29 @@ vr::EVRInitError Hmd::Activate(vr::TrackedDeviceIndex_t unObjectId) {
 
     vr::VRDriverInput()->CreateBooleanComponent(this->prop_container, "/proximity", &m_proximity);
 
+    if (Settings::Instance().m_enableEyeTracking) {
+        vr::VRProperties()->SetBoolProperty(
+            this->prop_container, vr::Prop_SupportsXrEyeGazeInteraction_Bool, true
+        );
+        vr::VRDriverInput()->CreateEyeTrackingComponent(
+            this->prop_container, "/eyetracking", &m_eyeTracking
+        );
+        vr::VRDriverInput()->CreateScalarComponent(
+            this->prop_container,
+            "/input/eye_blink_left/value",
+            &m_blinkLeft,
+            vr::VRScalarType_Absolute,
+            vr::VRScalarUnits_NormalizedOneSided
+        );
+        vr::VRDriverInput()->CreateScalarComponent(
+            this->prop_container,
+            "/input/eye_blink_right/value",
+            &m_blinkRight,
+            vr::VRScalarType_Absolute,
+            vr::VRScalarUnits_NormalizedOneSided
+        );
+    }
+
 #ifdef _WIN32
     float originalIPD
         = vr::VRSettings()->GetFloat(vr::k_pch_SteamVR_Section, vr::k_pch_SteamVR_IPD_Float);
@@ -239,7 +266,8 @@ void Hmd::OnPoseUpdated(uint64_t targetTimestampNs, FfiDeviceMotion motion) {
 #if !defined(_WIN32) && !defined(__APPLE__)
     // This has to be set after initialization is done, because something in vrcompositor is
     // setting it to 90Hz in the meantime
-    if (!m_refreshRateSet && m_encoder && m_encoder->IsConnected()) {
+    auto encoder = GetEncoder();
+    if (!m_refreshRateSet && encoder && encoder->IsConnected()) {
         m_refreshRateSet = true;
         vr::VRProperties()->SetFloatProperty(
             this->prop_container,
@@ -250,6 +278,31 @@ void Hmd::OnPoseUpdated(uint64_t targetTimestampNs, FfiDeviceMotion motion) {
 #endif
 }
 
+void Hmd::OnEyeTrackingUpdated(FfiEyeTracking eyeTracking) {
+    if (this->object_id == vr::k_unTrackedDeviceIndexInvalid
+        || m_eyeTracking == vr::k_ulInvalidInputComponentHandle) {
+        return;
+    }
+
+    auto eyeData = vr::VREyeTrackingData_t {};
+    eyeData.bActive = true;
+    eyeData.bValid = eyeTracking.gazeValid;
+    eyeData.bTracked = eyeTracking.gazeValid;
+    eyeData.vGazeOrigin = { eyeTracking.gazeOrigin[0],
+                            eyeTracking.gazeOrigin[1],
+                            eyeTracking.gazeOrigin[2] };
+    eyeData.vGazeTarget = { eyeTracking.gazeTarget[0],
+                            eyeTracking.gazeTarget[1],
+                            eyeTracking.gazeTarget[2] };
+
+    vr::VRDriverInput()->UpdateEyeTrackingComponent(m_eyeTracking, &eyeData, 0.0);
+
+    if (eyeTracking.blinkValid) {
+        vr::VRDriverInput()->UpdateScalarComponent(m_blinkLeft, eyeTracking.blinkLeft, 0.0);
+        vr::VRDriverInput()->UpdateScalarComponent(m_blinkRight, eyeTracking.blinkRight, 0.0);
+    }
+}
+
 void Hmd::StartStreaming() {
     vr::VRDriverInput()->UpdateBooleanComponent(m_proximity, true, 0.0);
 
@@ -259,33 +312,74 @@ void Hmd::StartStreaming() {
 
     // Spin up a separate thread to handle the overlapped encoding/transmit step.
     if (IsHMD()) {
+        CreateEncoder();
+    }
+
+    m_streamComponentsInitialized = true;
+}
+
+void Hmd::CreateEncoder() {
 #ifdef _WIN32
-        m_encoder = std::make_shared<CEncoder>();
-        try {
-            m_encoder->Initialize(m_D3DRender);
-        } catch (Exception e) {
-            Error(
-                "Your GPU does not meet the requirements for video encoding. %s %s\n%s %s\n",
-                "If you get this error after changing some settings, you can revert them by",
-                "deleting the file \"session.json\" in the installation folder.",
-                "Failed to initialize CEncoder:",
-                e.what()
-            );
-        }
-        m_encoder->Start();
+    // No encoder is reading the settings at this point
+    Settings::Instance().ApplyPendingStreamConfig();
+
+    auto encoder = std::make_shared<CEncoder>();
+    try {
+        encoder->Initialize(m_D3DRender);
+    } catch (Exception e) {
+        Error(
+            "Your GPU does not meet the requirements for video encoding. %s %s\n%s %s\n",
+            "If you get this error after changing some settings, you can revert them by",
+            "deleting the file \"session.json\" in the installation folder.",
+            "Failed to initialize CEncoder:",
+            e.what()
+        );
+    }
+    encoder->Start();
 
-        m_directModeComponent->SetEncoder(m_encoder);
+    m_directModeComponent->SetEncoder(encoder);
 
 #elif __APPLE__
-        m_encoder = std::make_shared<CEncoder>();
+    auto encoder = std::make_shared<CEncoder>();
 #else
-        m_encoder = std::make_shared<CEncoder>(m_poseHistory);
-        m_encoder->Start();
+    auto encoder = std::make_shared<CEncoder>(m_poseHistory);
+    encoder->Start();
 #endif
-        m_encoder->OnStreamStart();
+    encoder->OnStreamStart();
+
+    std::lock_guard<std::mutex> lock(m_encoderMutex);
+    m_encoder = encoder;
+}
+
+std::shared_ptr<CEncoder> Hmd::GetEncoder() {
+    std::lock_guard<std::mutex> lock(m_encoderMutex);
+    return m_encoder;
+}
+
+// The encoder is rebuilt so that it picks up the new stream settings
+void Hmd::ReconfigureEncoder() {
+    auto encoder = GetEncoder();
+    if (!m_streamComponentsInitialized || !encoder) {
+        return;
     }
 
-    m_streamComponentsInitialized = true;
+#ifdef _WIN32
+    // Waits for the frame being presented, so the old encoder is not used anymore
+    m_directModeComponent->SetEncoder(nullptr);
+    {
+        // Other threads cannot get the old encoder once it is stopping
+        std::lock_guard<std::mutex> lock(m_encoderMutex);
+        m_encoder.reset();
+    }
+    encoder->Stop();
+
+    CreateEncoder();
+#elif !defined(__APPLE__)
+    // The compositor connection is kept, only the render and encode pipeline is recreated. The
+    // encoder thread applies the pending settings before rebuilding it
+    encoder->Reconfigure();
+    encoder->OnStreamStart();
+#endif
 }
 
 void Hmd::StopStreaming() { vr::VRDriverInput()->UpdateBooleanComponent(m_proximity, false, 0.0); }
@@ -4,6 +4,7 @@
 #include "TrackedDevice.h"
 #include "openvr_driver.h"
 #include <memory>
+#include <mutex>
 #ifdef _WIN32
 #include "platform/win32/OvrDirectModeComponent.h"
 #endif
@@ -33,10 +34,14 @@ public:
 
     void OnPoseUpdated(uint64_t targetTimestampNs, FfiDeviceMotion motion);
 
+    void OnEyeTrackingUpdated(FfiEyeTracking eyeTracking);
+
     void StartStreaming();
 
     void StopStreaming();
 
+    void ReconfigureEncoder();
+
     void SetViewsConfig(FfiViewsConfig config);
 
     vr::ETrackedDeviceClass GetDeviceClass() const { return m_deviceClass; }
@@ -57,11 +62,22 @@ public:
     virtual vr::DistortionCoordinates_t ComputeDistortion(vr::EVREye eEye, float fU, float fV);
 
     vr::VRInputComponentHandle_t m_proximity;
+    vr::VRInputComponentHandle_t m_eyeTracking = vr::k_ulInvalidInputComponentHandle;
+    vr::VRInputComponentHandle_t m_blinkLeft = vr::k_ulInvalidInputComponentHandle;
+    vr::VRInputComponentHandle_t m_blinkRight = vr::k_ulInvalidInputComponentHandle;
 
-    std::shared_ptr<CEncoder> m_encoder;
     std::shared_ptr<PoseHistory> m_poseHistory;
 
+    // Null if the stream is not running. The encoder is replaced when the stream settings change,
+    // so it is accessed only through this copy from other threads
+    std::shared_ptr<CEncoder> GetEncoder();
+
 private:
+    void CreateEncoder();
+
+    std::mutex m_encoderMutex;
+    std::shared_ptr<CEncoder> m_encoder;
+
     FfiViewsConfig views_config;
 
     bool m_baseComponentsInitialized;
@@ -44,68 +44,8 @@ void Settings::Load() {
         m_recommendedTargetWidth = config.get("target_eye_resolution_width").get<int64_t>() * 2;
         m_recommendedTargetHeight = config.get("target_eye_resolution_height").get<int64_t>();
         m_nAdapterIndex = (int32_t)config.get("adapter_index").get<int64_t>();
-        m_captureFrameDir = config.get("capture_frame_dir").get<std::string>();
-
-        m_enableFoveatedEncoding = config.get("enable_foveated_encoding").get<bool>();
-        m_foveationCenterSizeX = (float)config.get("foveation_center_size_x").get<double>();
-        m_foveationCenterSizeY = (float)config.get("foveation_center_size_y").get<double>();
-        m_foveationCenterShiftX = (float)config.get("foveation_center_shift_x").get<double>();
-        m_foveationCenterShiftY = (float)config.get("foveation_center_shift_y").get<double>();
-        m_foveationEdgeRatioX = (float)config.get("foveation_edge_ratio_x").get<double>();
-        m_foveationEdgeRatioY = (float)config.get("foveation_edge_ratio_y").get<double>();
-
-        m_enableColorCorrection = config.get("enable_color_correction").get<bool>();
-        m_brightness = (float)config.get("brightness").get<double>();
-        m_contrast = (float)config.get("contrast").get<double>();
-        m_saturation = (float)config.get("saturation").get<double>();
-        m_gamma = (float)config.get("gamma").get<double>();
-        m_sharpening = (float)config.get("sharpening").get<double>();
-
-        m_codec = (int32_t)config.get("codec").get<int64_t>();
-        m_h264Profile = (int32_t)config.get("h264_profile").get<int64_t>();
-        m_rateControlMode = (uint32_t)config.get("rate_control_mode").get<int64_t>();
-        m_fillerData = config.get("filler_data").get<bool>();
-        m_entropyCoding = (uint32_t)config.get("entropy_coding").get<int64_t>();
-        m_use10bitEncoder = config.get("use_10bit_encoder").get<bool>();
-        m_useFullRangeEncoding = config.get("use_full_range_encoding").get<bool>();
-        m_encodingGamma = config.get("encoding_gamma").get<double>();
-        m_enableHdr = config.get("enable_hdr").get<bool>();
-        m_forceHdrSrgbCorrection = config.get("force_hdr_srgb_correction").get<bool>();
-        m_clampHdrExtendedRange = config.get("clamp_hdr_extended_range").get<bool>();
-        m_enablePreAnalysis = config.get("enable_pre_analysis").get<bool>();
-        m_enableVbaq = config.get("enable_vbaq").get<bool>();
-        m_enableHmqb = config.get("enable_hmqb").get<bool>();
-        m_usePreproc = config.get("use_preproc").get<bool>();
-        m_preProcSigma = (uint32_t)config.get("preproc_sigma").get<int64_t>();
-        m_preProcTor = (uint32_t)config.get("preproc_tor").get<int64_t>();
-        m_amdEncoderQualityPreset
-            = (uint32_t)config.get("amd_encoder_quality_preset").get<int64_t>();
-        m_amdBitrateCorruptionFix = (bool)config.get("amd_bitrate_corruption_fix").get<bool>();
-        m_nvencQualityPreset = (uint32_t)config.get("nvenc_quality_preset").get<int64_t>();
-        m_force_sw_encoding = config.get("force_sw_encoding").get<bool>();
-        m_swThreadCount = (int32_t)config.get("sw_thread_count").get<int64_t>();
-
-        m_nvencTuningPreset = (uint32_t)config.get("nvenc_tuning_preset").get<int64_t>();
-        m_nvencMultiPass = (uint32_t)config.get("nvenc_multi_pass").get<int64_t>();
-        m_nvencAdaptiveQuantizationMode
-            = (uint32_t)config.get("nvenc_adaptive_quantization_mode").get<int64_t>();
-        m_nvencLowDelayKeyFrameScale = config.get("nvenc_low_delay_key_frame_scale").get<int64_t>();
-        m_nvencRefreshRate = config.get("nvenc_refresh_rate").get<int64_t>();
-        m_nvencEnableIntraRefresh = config.get("enable_intra_refresh").get<bool>();
-        m_nvencIntraRefreshPeriod = config.get("intra_refresh_period").get<int64_t>();
-        m_nvencIntraRefreshCount = config.get("intra_refresh_count").get<int64_t>();
-        m_nvencMaxNumRefFrames = config.get("max_num_ref_frames").get<int64_t>();
-        m_nvencGopLength = config.get("gop_length").get<int64_t>();
-        m_nvencPFrameStrategy = config.get("p_frame_strategy").get<int64_t>();
-        m_nvencRateControlMode = config.get("nvenc_rate_control_mode").get<int64_t>();
-        m_nvencRcBufferSize = config.get("rc_buffer_size").get<int64_t>();
-        m_nvencRcInitialDelay = config.get("rc_initial_delay").get<int64_t>();
-        m_nvencRcMaxBitrate = config.get("rc_max_bitrate").get<int64_t>();
-        m_nvencRcAverageBitrate = config.get("rc_average_bitrate").get<int64_t>();
-        m_nvencEnableWeightedPrediction
-            = config.get("nvenc_enable_weighted_prediction").get<bool>();
-
-        m_aggressiveKeyframeResend = config.get("aggressive_keyframe_resend").get<bool>();
+
+        LoadStreamConfig(config);
 
         m_enableViveTrackerProxy = config.get("enable_vive_tracker_proxy").get<bool>();
         m_TrackingRefOnly = config.get("tracking_ref_only").get<bool>();
@@ -117,6 +57,7 @@ void Settings::Load() {
 
         m_enableBodyTrackingFakeVive = config.get("body_tracking_vive_enabled").get<bool>();
         m_bodyTrackingHasLegs = config.get("body_tracking_has_legs").get<bool>();
+        m_enableEyeTracking = config.get("eye_tracking_enabled").get<bool>();
 
         m_useSeparateHandTrackers = config.get("use_separate_hand_trackers").get<bool>();
 
@@ -127,3 +68,107 @@ void Settings::Load() {
         Error("Exception on parsing session config (%s): %hs\n", g_sessionPath, e.what());
     }
 }
+
+// Properties that can change without restarting SteamVR. Throws if a property is missing
+void Settings::LoadStreamConfig(const picojson::value& config) {
+    m_captureFrameDir = config.get("capture_frame_dir").get<std::string>();
+
+    m_enableFoveatedEncoding = config.get("enable_foveated_encoding").get<bool>();
+    m_foveationCenterSizeX = (float)config.get("foveation_center_size_x").get<double>();
+    m_foveationCenterSizeY = (float)config.get("foveation_center_size_y").get<double>();
+    m_foveationCenterShiftX = (float)config.get("foveation_center_shift_x").get<double>();
+    m_foveationCenterShiftY = (float)config.get("foveation_center_shift_y").get<double>();
+    m_foveationEdgeRatioX = (float)config.get("foveation_edge_ratio_x").get<double>();
+    m_foveationEdgeRatioY = (float)config.get("foveation_edge_ratio_y").get<double>();
+
+    m_enableColorCorrection = config.get("enable_color_correction").get<bool>();
+    m_brightness = (float)config.get("brightness").get<double>();
+    m_contrast = (float)config.get("contrast").get<double>();
+    m_saturation = (float)config.get("saturation").get<double>();
+    m_gamma = (float)config.get("gamma").get<double>();
+    m_sharpening = (float)config.get("sharpening").get<double>();
+
+    m_codec = (int32_t)config.get("codec").get<int64_t>();
+    m_h264Profile = (int32_t)config.get("h264_profile").get<int64_t>();
+    m_rateControlMode = (uint32_t)config.get("rate_control_mode").get<int64_t>();
+    m_fillerData = config.get("filler_data").get<bool>();
+    m_entropyCoding = (uint32_t)config.get("entropy_coding").get<int64_t>();
+    m_use10bitEncoder = config.get("use_10bit_encoder").get<bool>();
+    m_useFullRangeEncoding = config.get("use_full_range_encoding").get<bool>();
+    m_encodingGamma = config.get("encoding_gamma").get<double>();
+    m_enableHdr = config.get("enable_hdr").get<bool>();
+    m_forceHdrSrgbCorrection = config.get("force_hdr_srgb_correction").get<bool>();
+    m_clampHdrExtendedRange = config.get("clamp_hdr_extended_range").get<bool>();
+    m_enablePreAnalysis = config.get("enable_pre_analysis").get<bool>();
+    m_enableVbaq = config.get("enable_vbaq").get<bool>();
+    m_enableHmqb = config.get("enable_hmqb").get<bool>();
+    m_usePreproc = config.get("use_preproc").get<bool>();
+    m_preProcSigma = (uint32_t)config.get("preproc_sigma").get<int64_t>();
+    m_preProcTor = (uint32_t)config.get("preproc_tor").get<int64_t>();
+    m_amdEncoderQualityPreset
+        = (uint32_t)config.get("amd_encoder_quality_preset").get<int64_t>();
+    m_amdBitrateCorruptionFix = (bool)config.get("amd_bitrate_corruption_fix").get<bool>();
+    m_nvencQualityPreset = (uint32_t)config.get("nvenc_quality_preset").get<int64_t>();
+    m_force_sw_encoding = config.get("force_sw_encoding").get<bool>();
+    m_swThreadCount = (int32_t)config.get("sw_thread_count").get<int64_t>();
+
+    m_nvencTuningPreset = (uint32_t)config.get("nvenc_tuning_preset").get<int64_t>();
+    m_nvencMultiPass = (uint32_t)config.get("nvenc_multi_pass").get<int64_t>();
+    m_nvencAdaptiveQuantizationMode
+        = (uint32_t)config.get("nvenc_adaptive_quantization_mode").get<int64_t>();
+    m_nvencLowDelayKeyFrameScale = config.get("nvenc_low_delay_key_frame_scale").get<int64_t>();
+    m_nvencRefreshRate = config.get("nvenc_refresh_rate").get<int64_t>();
+    m_nvencEnableIntraRefresh = config.get("enable_intra_refresh").get<bool>();
+    m_nvencIntraRefreshPeriod = config.get("intra_refresh_period").get<int64_t>();
+    m_nvencIntraRefreshCount = config.get("intra_refresh_count").get<int64_t>();
+    m_nvencMaxNumRefFrames = config.get("max_num_ref_frames").get<int64_t>();
+    m_nvencGopLength = config.get("gop_length").get<int64_t>();
+    m_nvencPFrameStrategy = config.get("p_frame_strategy").get<int64_t>();
+    m_nvencRateControlMode = config.get("nvenc_rate_control_mode").get<int64_t>();
+    m_nvencRcBufferSize = config.get("rc_buffer_size").get<int64_t>();
+    m_nvencRcInitialDelay = config.get("rc_initial_delay").get<int64_t>();
+    m_nvencRcMaxBitrate = config.get("rc_max_bitrate").get<int64_t>();
+    m_nvencRcAverageBitrate = config.get("rc_average_bitrate").get<int64_t>();
+    m_nvencEnableWeightedPrediction
+        = config.get("nvenc_enable_weighted_prediction").get<bool>();
+
+    m_aggressiveKeyframeResend = config.get("aggressive_keyframe_resend").get<bool>();
+}
+
+void Settings::UpdateStreamConfig(const char* configJson) {
+    auto config = std::make_unique<picojson::value>();
+    std::string err = picojson::parse(*config, configJson);
+    if (!err.empty()) {
+        Error("Error on parsing stream config: %hs\n", err.c_str());
+        return;
+    }
+
+    // Parse into a scratch instance first, so a missing property leaves the config untouched
+    try {
+        Settings scratch;
+        scratch.LoadStreamConfig(*config);
+    } catch (std::exception& e) {
+        Error("Exception on parsing stream config: %hs\n", e.what());
+        return;
+    }
+
+    std::lock_guard<std::mutex> lock(m_pendingStreamConfigMutex);
+    m_pendingStreamConfig = std::move(config);
+}
+
+bool Settings::ApplyPendingStreamConfig() {
+    std::unique_ptr<picojson::value> config;
+    {
+        std::lock_guard<std::mutex> lock(m_pendingStreamConfigMutex);
+        config = std::move(m_pendingStreamConfig);
+    }
+
+    if (!config) {
+        return false;
+    }
+
+    // Already validated, cannot throw
+    LoadStreamConfig(*config);
+
+    return true;
+}
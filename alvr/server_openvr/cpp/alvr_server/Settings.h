@@ -1,8 +1,14 @@
 #pragma once
 
 #include "ALVR-common/packet_types.h"
+#include <memory>
+#include <mutex>
 #include <string>
 
+namespace picojson {
+class value;
+}
+
 class Settings {
     static Settings m_Instance;
     bool m_loaded;
@@ -10,8 +16,19 @@ class Settings {
     Settings();
     virtual ~Settings();
 
+    void LoadStreamConfig(const picojson::value& config);
+
+    // Validated stream config waiting to be applied by the encoder thread
+    std::mutex m_pendingStreamConfigMutex;
+    std::unique_ptr<picojson::value> m_pendingStreamConfig;
+
 public:
     void Load();
+    // Validates the stream-time part of the OpenVR config, sent as JSON by the server. It is
+    // applied only by ApplyPendingStreamConfig()
+    void UpdateStreamConfig(const char* configJson);
+    // Must be called while no encoder is reading the settings. Returns false if nothing changed
+    bool ApplyPendingStreamConfig();
     static Settings& Instance() { return m_Instance; }
 
     bool IsLoaded() { return m_loaded; }
@@ -91,5 +108,6 @@ public:
     int m_controllerIsTracker = false;
     int m_enableBodyTrackingFakeVive = false;
     int m_bodyTrackingHasLegs = false;
+    bool m_enableEyeTracking = false;
     bool m_useSeparateHandTrackers = false;
 };
@@ -387,11 +387,21 @@ void DeinitializeStreaming() {
     }
 }
 
+void UpdateStreamSettings(const char* openvrStreamConfigJson) {
+    Settings::Instance().UpdateStreamConfig(openvrStreamConfigJson);
+
+    if (g_driver_provider.hmd) {
+        g_driver_provider.hmd->ReconfigureEncoder();
+    }
+}
+
 void SendVSync() { vr::VRServerDriverHost()->VsyncEvent(0.0); }
 
 void RequestIDR() {
-    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
-        g_driver_provider.hmd->m_encoder->InsertIDR();
+    if (g_driver_provider.hmd) {
+        if (auto encoder = g_driver_provider.hmd->GetEncoder()) {
+            encoder->InsertIDR();
+        }
     }
 }
 
@@ -457,9 +467,17 @@ void SetTracking(
     }
 }
 
+void SetEyeTracking(FfiEyeTracking eyeTracking) {
+    if (g_driver_provider.hmd && Settings::Instance().m_enableEyeTracking) {
+        g_driver_provider.hmd->OnEyeTrackingUpdated(eyeTracking);
+    }
+}
+
 void VideoErrorReportReceive() {
     if (g_driver_provider.hmd) {
-        g_driver_provider.hmd->m_encoder->OnPacketLoss();
+        if (auto encoder = g_driver_provider.hmd->GetEncoder()) {
+            encoder->OnPacketLoss();
+        }
     }
 }
 
@@ -539,8 +557,10 @@ void SetChaperoneArea(float areaWidth, float areaHeight) {
 
 void CaptureFrame() {
 #ifndef __APPLE__
-    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
-        g_driver_provider.hmd->m_encoder->CaptureFrame();
+    if (g_driver_provider.hmd) {
+        if (auto encoder = g_driver_provider.hmd->GetEncoder()) {
+            encoder->CaptureFrame();
+        }
     }
 #endif
 }
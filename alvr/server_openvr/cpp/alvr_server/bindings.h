@@ -34,6 +34,16 @@ struct FfiBodyTracker {
     unsigned int tracking;
 };
 
+// Gaze origin and target are relative to the head
+struct FfiEyeTracking {
+    unsigned int gazeValid;
+    float gazeOrigin[3];
+    float gazeTarget[3];
+    unsigned int blinkValid;
+    float blinkLeft;
+    float blinkRight;
+};
+
 enum FfiOpenvrPropertyType {
     Bool,
     Float,
@@ -137,6 +147,7 @@ extern "C" void CppInit();
 extern "C" void* CppOpenvrEntryPoint(const char* pInterfaceName, int* pReturnCode);
 extern "C" void InitializeStreaming();
 extern "C" void DeinitializeStreaming();
+extern "C" void UpdateStreamSettings(const char* openvrStreamConfigJson);
 extern "C" void SendVSync();
 extern "C" void RequestIDR();
 extern "C" void SetTracking(
@@ -152,6 +163,7 @@ extern "C" void SetTracking(
     const FfiBodyTracker* bodyTrackers,
     int bodyTrackersCount
 );
+extern "C" void SetEyeTracking(FfiEyeTracking eyeTracking);
 extern "C" void VideoErrorReportReceive();
 extern "C" void RequestDriverResync();
 extern "C" void ShutdownSteamvr();
@@ -165,6 +177,8 @@ extern "C" void SetButton(unsigned long long buttonID, FfiButtonValue value);
 extern "C" void InitOpenvrClient();
 extern "C" void ShutdownOpenvrClient();
 extern "C" void SetChaperoneArea(float areaWidth, float areaHeight);
+// Returns the length of the key, or 0 if no application is in focus
+extern "C" unsigned int GetSceneApplicationKey(char* outKey, unsigned int bufferLen);
 
 extern "C" void CaptureFrame();
 
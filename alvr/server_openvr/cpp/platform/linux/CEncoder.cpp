@@ -204,95 +204,107 @@ void CEncoder::Run() {
 
         alvr::VkContext vk_ctx(init.device_uuid.data(), {});
 
-        FrameRender render(vk_ctx, init, m_fds);
-        auto output = render.CreateOutput();
-
-        alvr::VkFrameCtx vk_frame_ctx(vk_ctx, output.imageInfo);
-        alvr::VkFrame frame(
-            vk_ctx, output.image, output.imageInfo, output.size, output.memory, output.drm
-        );
-        auto encode_pipeline = alvr::EncodePipeline::Create(
-            &render,
-            vk_ctx,
-            frame,
-            vk_frame_ctx,
-            render.GetEncodingWidth(),
-            render.GetEncodingHeight()
-        );
-
-        bool valid_timestamps = true;
-
-        fprintf(stderr, "CEncoder starting to read present packets");
-        present_packet frame_info;
+        // Rebuilt when the stream settings change, the compositor connection is kept
         while (not m_exiting) {
-            read_latest(client, (char*)&frame_info, sizeof(frame_info), m_exiting);
+            m_reconfigure = false;
+            Settings::Instance().ApplyPendingStreamConfig();
 
-            encode_pipeline->SetParams(GetDynamicEncoderParams());
-
-            auto pose = m_poseHistory->GetBestPoseMatch((const vr::HmdMatrix34_t&)frame_info.pose);
-            if (!pose) {
-                continue;
+            // Importing the images takes ownership of the file descriptors
+            int fds[6];
+            for (int i = 0; i < 6; i++) {
+                fds[i] = dup(m_fds[i]);
             }
 
-            if (m_captureFrame) {
-                m_captureFrame = false;
-                render.CaptureInputFrame(
-                    Settings::Instance().m_captureFrameDir + "/alvr_frame_input.ppm"
-                );
-                render.CaptureOutputFrame(
-                    Settings::Instance().m_captureFrameDir + "/alvr_frame_output.ppm"
-                );
-            }
+            FrameRender render(vk_ctx, init, fds);
+            auto output = render.CreateOutput();
+
+            alvr::VkFrameCtx vk_frame_ctx(vk_ctx, output.imageInfo);
+            alvr::VkFrame frame(
+                vk_ctx, output.image, output.imageInfo, output.size, output.memory, output.drm
+            );
+            auto encode_pipeline = alvr::EncodePipeline::Create(
+                &render,
+                vk_ctx,
+                frame,
+                vk_frame_ctx,
+                render.GetEncodingWidth(),
+                render.GetEncodingHeight()
+            );
 
-            render.Render(frame_info.image, frame_info.semaphore_value);
+            bool valid_timestamps = true;
 
-            if (!valid_timestamps) {
-                ReportPresent(pose->targetTimestampNs, 0);
-                ReportComposed(pose->targetTimestampNs, 0);
-            }
+            fprintf(stderr, "CEncoder starting to read present packets");
+            present_packet frame_info;
+            while (not m_exiting && not m_reconfigure) {
+                read_latest(client, (char*)&frame_info, sizeof(frame_info), m_exiting);
 
-            encode_pipeline->PushFrame(pose->targetTimestampNs, m_scheduler.CheckIDRInsertion());
+                encode_pipeline->SetParams(GetDynamicEncoderParams());
 
-            static_assert(sizeof(frame_info.pose) == sizeof(vr::HmdMatrix34_t&));
+                auto pose = m_poseHistory->GetBestPoseMatch((const vr::HmdMatrix34_t&)frame_info.pose);
+                if (!pose) {
+                    continue;
+                }
 
-            alvr::FramePacket packet;
-            if (!encode_pipeline->GetEncoded(packet)) {
-                Error("Failed to get encoded data!");
-                continue;
-            }
+                if (m_captureFrame) {
+                    m_captureFrame = false;
+                    render.CaptureInputFrame(
+                        Settings::Instance().m_captureFrameDir + "/alvr_frame_input.ppm"
+                    );
+                    render.CaptureOutputFrame(
+                        Settings::Instance().m_captureFrameDir + "/alvr_frame_output.ppm"
+                    );
+                }
+
+                render.Render(frame_info.image, frame_info.semaphore_value);
 
-            if (valid_timestamps) {
-                auto render_timestamps = render.GetTimestamps();
-                auto encode_timestamp = encode_pipeline->GetTimestamp();
-
-                uint64_t present_offset = render_timestamps.now - render_timestamps.renderBegin;
-                uint64_t composed_offset = 0;
-
-                valid_timestamps = render_timestamps.now != 0;
-
-                if (encode_timestamp.gpu) {
-                    composed_offset = render_timestamps.now - encode_timestamp.gpu;
-                } else if (encode_timestamp.cpu) {
-                    auto now = std::chrono::duration_cast<std::chrono::nanoseconds>(
-                                   std::chrono::steady_clock::now().time_since_epoch()
-                    )
-                                   .count();
-                    composed_offset = now - encode_timestamp.cpu;
-                } else {
-                    composed_offset = render_timestamps.now - render_timestamps.renderComplete;
+                if (!valid_timestamps) {
+                    ReportPresent(pose->targetTimestampNs, 0);
+                    ReportComposed(pose->targetTimestampNs, 0);
                 }
 
-                if (present_offset < composed_offset) {
-                    present_offset = composed_offset;
+                encode_pipeline->PushFrame(pose->targetTimestampNs, m_scheduler.CheckIDRInsertion());
+
+                static_assert(sizeof(frame_info.pose) == sizeof(vr::HmdMatrix34_t&));
+
+                alvr::FramePacket packet;
+                if (!encode_pipeline->GetEncoded(packet)) {
+                    Error("Failed to get encoded data!");
+                    continue;
                 }
 
-                ReportPresent(pose->targetTimestampNs, present_offset);
-                ReportComposed(pose->targetTimestampNs, composed_offset);
-            }
+                if (valid_timestamps) {
+                    auto render_timestamps = render.GetTimestamps();
+                    auto encode_timestamp = encode_pipeline->GetTimestamp();
+
+                    uint64_t present_offset = render_timestamps.now - render_timestamps.renderBegin;
+                    uint64_t composed_offset = 0;
+
+                    valid_timestamps = render_timestamps.now != 0;
+
+                    if (encode_timestamp.gpu) {
+                        composed_offset = render_timestamps.now - encode_timestamp.gpu;
+                    } else if (encode_timestamp.cpu) {
+                        auto now = std::chrono::duration_cast<std::chrono::nanoseconds>(
+                                       std::chrono::steady_clock::now().time_since_epoch()
+                        )
+                                       .count();
+                        composed_offset = now - encode_timestamp.cpu;
+                    } else {
+                        composed_offset = render_timestamps.now - render_timestamps.renderComplete;
+                    }
+
+                    if (present_offset < composed_offset) {
+                        present_offset = composed_offset;
+                    }
+
+                    ReportPresent(pose->targetTimestampNs, present_offset);
+                    ReportComposed(pose->targetTimestampNs, composed_offset);
+                }
 
-            ParseFrameNals(
-                encode_pipeline->GetCodec(), packet.data, packet.size, packet.pts, packet.isIDR
-            );
+                ParseFrameNals(
+                    encode_pipeline->GetCodec(), packet.data, packet.size, packet.pts, packet.isIDR
+                );
+            }
         }
     } catch (std::exception& e) {
         std::stringstream err;
@@ -313,6 +325,8 @@ void CEncoder::Stop() {
 
 void CEncoder::OnStreamStart() { m_scheduler.OnStreamStart(); }
 
+void CEncoder::Reconfigure() { m_reconfigure = true; }
+
 void CEncoder::OnPacketLoss() { m_scheduler.OnPacketLoss(); }
 
 void CEncoder::InsertIDR() { m_scheduler.InsertIDR(); }
@@ -18,6 +18,7 @@ public:
 
     void Stop();
     void OnStreamStart();
+    void Reconfigure();
     void OnPacketLoss();
     void InsertIDR();
     bool IsConnected() { return m_connected; }
@@ -33,4 +34,5 @@ private:
     int m_fds[6];
     bool m_connected = false;
     std::atomic_bool m_captureFrame = false;
+    std::atomic_bool m_reconfigure = false;
 };
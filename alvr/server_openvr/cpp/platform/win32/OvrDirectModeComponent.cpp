@@ -8,6 +8,8 @@ OvrDirectModeComponent::OvrDirectModeComponent(
     , m_submitLayer(0) { }
 
 void OvrDirectModeComponent::SetEncoder(std::shared_ptr<CEncoder> pEncoder) {
+    // The encoder is used only while presenting
+    std::lock_guard<std::mutex> lock(m_presentMutex);
     m_pEncoder = pEncoder;
 }
 
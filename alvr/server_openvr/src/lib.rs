@@ -33,6 +33,8 @@ use std::{
     time::{Duration, Instant},
 };
 
+const APP_KEY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 static FILESYSTEM_LAYOUT: Lazy<afs::Layout> = Lazy::new(|| {
     afs::filesystem_layout_from_openvr_driver_root_dir(
         &alvr_server_io::get_driver_dir_from_registered().unwrap(),
@@ -63,7 +65,26 @@ extern "C" fn driver_ready_idle(set_default_chap: bool) {
         }
 
         let mut last_resync = Instant::now();
+        let mut last_app_key_poll = Instant::now();
+        let mut scene_app_key = None;
         loop {
+            if last_app_key_poll.elapsed() > APP_KEY_POLL_INTERVAL {
+                last_app_key_poll = Instant::now();
+
+                let mut buffer = [0_u8; 128];
+                let len =
+                    unsafe { GetSceneApplicationKey(buffer.as_mut_ptr() as _, buffer.len() as _) };
+                let app_key = (len > 0)
+                    .then(|| String::from_utf8_lossy(&buffer[..len as usize]).into_owned());
+
+                if app_key != scene_app_key {
+                    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+                        context.report_steamvr_app_key(app_key.clone());
+                    }
+                    scene_app_key = app_key;
+                }
+            }
+
             let event = match events_receiver.recv_timeout(Duration::from_millis(5)) {
                 Ok(event) => event,
                 Err(mpsc::RecvTimeoutError::Timeout) => continue,
@@ -74,6 +95,12 @@ extern "C" fn driver_ready_idle(set_default_chap: bool) {
                 ServerCoreEvent::SetOpenvrProperty { device_id, prop } => unsafe {
                     SetOpenvrProperty(device_id, props::to_ffi_openvr_prop(prop))
                 },
+                ServerCoreEvent::UpdateStreamConfig(config) => {
+                    if let Ok(config_json) = serde_json::to_string(&config) {
+                        let config_json = CString::new(config_json).unwrap();
+                        unsafe { UpdateStreamSettings(config_json.as_ptr()) };
+                    }
+                }
                 ServerCoreEvent::ClientConnected => {
                     unsafe {
                         InitializeStreaming();
@@ -114,11 +141,17 @@ extern "C" fn driver_ready_idle(set_default_chap: bool) {
                 } => {
                     let controllers_config;
                     let track_body;
+                    let track_eyes;
                     {
                         let headset_config = &alvr_server_core::settings().headset;
 
                         controllers_config = headset_config.controllers.clone().into_option();
                         track_body = headset_config.body_tracking.enabled();
+                        track_eyes = headset_config
+                            .face_tracking
+                            .as_option()
+                            .map(|c| c.openvr_eye_tracking)
+                            .unwrap_or(false);
                     };
 
                     let track_controllers = controllers_config
@@ -201,6 +234,12 @@ extern "C" fn driver_ready_idle(set_default_chap: bool) {
                             },
                         )
                     };
+
+                    if track_eyes {
+                        unsafe {
+                            SetEyeTracking(tracking::to_ffi_eye_tracking(&tracking.face_data))
+                        };
+                    }
                 }
                 ServerCoreEvent::Buttons(entries) => {
                     for entry in entries {
@@ -1,10 +1,12 @@
-use crate::{FfiBodyTracker, FfiDeviceMotion, FfiHandSkeleton, FfiQuat};
+use crate::{FfiBodyTracker, FfiDeviceMotion, FfiEyeTracking, FfiHandSkeleton, FfiQuat};
 use alvr_common::{
     glam::{EulerRot, Quat, Vec3},
     once_cell::sync::Lazy,
     DeviceMotion, Pose, BODY_CHEST_ID, BODY_HIPS_ID, BODY_LEFT_ELBOW_ID, BODY_LEFT_FOOT_ID,
     BODY_LEFT_KNEE_ID, BODY_RIGHT_ELBOW_ID, BODY_RIGHT_FOOT_ID, BODY_RIGHT_KNEE_ID, HAND_LEFT_ID,
 };
+use alvr_packets::FaceData;
+use alvr_server_core::{ArkitBlendshape, FaceExpressions};
 use alvr_session::HeadsetConfig;
 use std::{
     collections::HashMap,
@@ -219,3 +221,41 @@ pub fn to_ffi_body_trackers(
 
     Some(trackers)
 }
+
+// Eye gazes must be relative to the head. The gaze target is placed one meter away from the origin.
+pub fn to_ffi_eye_tracking(face_data: &FaceData) -> FfiEyeTracking {
+    let gaze = match face_data.eye_gazes {
+        [Some(left), Some(right)] => Some(Pose {
+            orientation: left.orientation.slerp(right.orientation, 0.5),
+            position: (left.position + right.position) / 2.0,
+        }),
+        [Some(gaze), None] | [None, Some(gaze)] => Some(gaze),
+        [None, None] => None,
+    };
+    let (gaze_origin, gaze_target) = gaze
+        .map(|gaze| {
+            (
+                gaze.position,
+                gaze.position + gaze.orientation * Vec3::NEG_Z,
+            )
+        })
+        .unwrap_or_default();
+
+    let blink = FaceExpressions::from_face_data(face_data)
+        .filter(|expressions| expressions.has_eyes)
+        .map(|expressions| {
+            (
+                expressions.get(ArkitBlendshape::EyeBlinkLeft),
+                expressions.get(ArkitBlendshape::EyeBlinkRight),
+            )
+        });
+
+    FfiEyeTracking {
+        gazeValid: gaze.is_some().into(),
+        gazeOrigin: gaze_origin.to_array(),
+        gazeTarget: gaze_target.to_array(),
+        blinkValid: blink.is_some().into(),
+        blinkLeft: blink.map(|(left, _)| left).unwrap_or_default(),
+        blinkRight: blink.map(|(_, right)| right).unwrap_or_default(),
+    }
+}
@@ -27,27 +27,72 @@ pub struct DriversBackup {
     pub other_paths: Vec<PathBuf>,
 }
 
+// Standalone set of manual button mappings, stored as a JSON file in the config directory. The
+// profile is activated automatically when one of the listed SteamVR application keys is running.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ButtonMappingProfile {
+    pub name: String,
+    pub steamvr_app_keys: Vec<String>,
+    pub button_mappings: Vec<(String, Vec<ButtonBindingTarget>)>,
+}
+
 // This structure is used to store the minimum configuration data that ALVR driver needs to
 // initialize OpenVR before having the chance to communicate with a client. When a client is
-// connected, a new OpenvrConfig instance is generated, then the connection is accepted only if that
-// instance is equivalent to the one stored in the session, otherwise SteamVR is restarted.
-// Other components (like the encoder, audio recorder) don't need this treatment and are initialized
+// connected, a new OpenvrConfig instance is generated. If the boot-time part differs from the one
+// stored in the session, SteamVR is restarted. Changes to the stream-time part are sent to the
+// running driver instead, which recreates the encoder for the new stream.
+// Other components (like the audio recorder) don't need this treatment and are initialized
 // dynamically.
-// todo: properties that can be set after the OpenVR initialization should be removed and set with
-// UpdateForStream.
+// Both parts are flattened, so the driver sees a single flat object.
 #[derive(Serialize, Deserialize, PartialEq, Default, Clone, Debug)]
 pub struct OpenvrConfig {
+    #[serde(flatten)]
+    pub boot: OpenvrBootConfig,
+    #[serde(flatten)]
+    pub stream: OpenvrStreamConfig,
+}
+
+// Properties read when SteamVR initializes the driver: display, tracked devices and layers
+#[derive(Serialize, Deserialize, PartialEq, Default, Clone, Debug)]
+pub struct OpenvrBootConfig {
     pub eye_resolution_width: u32,
     pub eye_resolution_height: u32,
     pub target_eye_resolution_width: u32,
     pub target_eye_resolution_height: u32,
     pub tracking_ref_only: bool,
     pub enable_vive_tracker_proxy: bool,
-    pub aggressive_keyframe_resend: bool,
     pub adapter_index: u32,
+    pub refresh_rate: u32,
+    pub controller_is_tracker: bool,
+    pub controllers_enabled: bool,
+    pub body_tracking_vive_enabled: bool,
+    pub body_tracking_has_legs: bool,
+    pub eye_tracking_enabled: bool,
+    pub linux_async_compute: bool,
+    pub linux_async_reprojection: bool,
+    pub use_separate_hand_trackers: bool,
+
+    // these settings are not used on the C++ side, but we need them to correctly trigger a SteamVR
+    // restart
+    pub _controller_profile: i32,
+    pub _server_impl_debug: bool,
+    pub _client_impl_debug: bool,
+    pub _server_core_debug: bool,
+    pub _client_core_debug: bool,
+    pub _conncection_debug: bool,
+    pub _sockets_debug: bool,
+    pub _server_gfx_debug: bool,
+    pub _client_gfx_debug: bool,
+    pub _encoder_debug: bool,
+    pub _decoder_debug: bool,
+}
+
+// Properties read when the encoder is created: codec, encoder tuning, foveation and color correction
+#[derive(Serialize, Deserialize, PartialEq, Default, Clone, Debug)]
+pub struct OpenvrStreamConfig {
+    pub aggressive_keyframe_resend: bool,
     pub codec: u8,
     pub h264_profile: u32,
-    pub refresh_rate: u32,
     pub use_10bit_encoder: bool,
     pub use_full_range_encoding: bool,
     pub encoding_gamma: f32,
@@ -66,10 +111,6 @@ pub struct OpenvrConfig {
     pub entropy_coding: u32,
     pub force_sw_encoding: bool,
     pub sw_thread_count: u32,
-    pub controller_is_tracker: bool,
-    pub controllers_enabled: bool,
-    pub body_tracking_vive_enabled: bool,
-    pub body_tracking_has_legs: bool,
     pub enable_foveated_encoding: bool,
     pub foveation_center_size_x: f32,
     pub foveation_center_size_y: f32,
@@ -83,8 +124,6 @@ pub struct OpenvrConfig {
     pub saturation: f32,
     pub gamma: f32,
     pub sharpening: f32,
-    pub linux_async_compute: bool,
-    pub linux_async_reprojection: bool,
     pub nvenc_quality_preset: u32,
     pub nvenc_tuning_preset: u32,
     pub nvenc_multi_pass: u32,
@@ -105,21 +144,14 @@ pub struct OpenvrConfig {
     pub nvenc_enable_weighted_prediction: bool,
     pub capture_frame_dir: String,
     pub amd_bitrate_corruption_fix: bool,
-    pub use_separate_hand_trackers: bool,
+}
 
-    // these settings are not used on the C++ side, but we need them to correctly trigger a SteamVR
-    // restart
-    pub _controller_profile: i32,
-    pub _server_impl_debug: bool,
-    pub _client_impl_debug: bool,
-    pub _server_core_debug: bool,
-    pub _client_core_debug: bool,
-    pub _conncection_debug: bool,
-    pub _sockets_debug: bool,
-    pub _server_gfx_debug: bool,
-    pub _client_gfx_debug: bool,
-    pub _encoder_debug: bool,
-    pub _decoder_debug: bool,
+// Streaming preferences chosen on the headset, taking precedence over the session settings
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ClientStreamingOverrides {
+    pub preferred_codec: Option<CodecType>,
+    pub max_bitrate_mbps: Option<f32>,
+    pub refresh_rate: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -130,6 +162,17 @@ pub struct ClientConnectionConfig {
     pub trusted: bool,
     pub connection_state: ConnectionState,
     pub cabled: bool,
+    pub spectator: bool,
+    // Overrides applied during the last connection
+    #[serde(default)]
+    pub streaming_overrides: ClientStreamingOverrides,
+    // Why the client cannot connect to this streamer, for example a version mismatch
+    #[serde(default)]
+    pub incompatibility: Option<String>,
+    // Secret shared with the client when it is trusted, it identifies the client on the relay used
+    // for internet streaming
+    #[serde(default)]
+    pub relay_pairing_token: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -148,21 +191,26 @@ impl Default for SessionConfig {
             server_version: ALVR_VERSION.clone(),
             drivers_backup: None,
             openvr_config: OpenvrConfig {
-                // avoid realistic resolutions, as on first start, on Linux, it
-                // could trigger direct mode on an existing monitor
-                eye_resolution_width: 800,
-                eye_resolution_height: 900,
-                target_eye_resolution_width: 800,
-                target_eye_resolution_height: 900,
-                adapter_index: 0,
-                refresh_rate: 60,
-                controllers_enabled: false,
-                body_tracking_vive_enabled: false,
-                enable_foveated_encoding: false,
-                enable_color_correction: false,
-                linux_async_reprojection: false,
-                capture_frame_dir: "/tmp".into(),
-                ..<_>::default()
+                boot: OpenvrBootConfig {
+                    // avoid realistic resolutions, as on first start, on Linux, it
+                    // could trigger direct mode on an existing monitor
+                    eye_resolution_width: 800,
+                    eye_resolution_height: 900,
+                    target_eye_resolution_width: 800,
+                    target_eye_resolution_height: 900,
+                    adapter_index: 0,
+                    refresh_rate: 60,
+                    controllers_enabled: false,
+                    body_tracking_vive_enabled: false,
+                    linux_async_reprojection: false,
+                    ..<_>::default()
+                },
+                stream: OpenvrStreamConfig {
+                    enable_foveated_encoding: false,
+                    enable_color_correction: false,
+                    capture_frame_dir: "/tmp".into(),
+                    ..<_>::default()
+                },
             },
             client_connections: HashMap::new(),
             session_settings: settings::session_settings_default(),
@@ -246,6 +294,62 @@ impl SessionConfig {
     }
 }
 
+// Converts settings of older sessions that were replaced and cannot be extrapolated by name.
+// Returns true if the session was changed. This must run before parsing the session, since unknown
+// fields are silently ignored.
+pub fn migrate_session_json(session_json: &mut json::Value) -> bool {
+    let Some(connection) = session_json
+        .pointer_mut("/session_settings/connection")
+        .and_then(|connection| connection.as_object_mut())
+    else {
+        return false;
+    };
+
+    let mut migrated = false;
+
+    // The connect and disconnect scripts were replaced by event hooks
+    for (field, event) in [
+        (
+            "on_connect_script",
+            HookEventDefaultVariant::StreamingStarted,
+        ),
+        (
+            "on_disconnect_script",
+            HookEventDefaultVariant::ClientDisconnected,
+        ),
+    ] {
+        let Some(value) = connection.remove(field) else {
+            continue;
+        };
+        migrated = true;
+
+        let Some(path) = value.as_str().filter(|path| !path.is_empty()) else {
+            continue;
+        };
+
+        let mut hook = settings::session_settings_default()
+            .connection
+            .event_hooks
+            .hooks
+            .element;
+        hook.event.variant = event;
+        hook.action.Command = path.into();
+        hook.action.variant = HookActionDefaultVariant::Command;
+
+        let hooks_json = connection.entry("event_hooks").or_insert_with(|| {
+            json::to_value(settings::session_settings_default().connection.event_hooks).unwrap()
+        });
+        if let Some(content) = hooks_json
+            .pointer_mut("/hooks/content")
+            .and_then(|content| content.as_array_mut())
+        {
+            content.push(json::to_value(hook).unwrap());
+        }
+    }
+
+    migrated
+}
+
 // Current data extrapolation strategy: match both field name and value type exactly.
 // Integer bounds are not validated, if they do not match the schema, deserialization will fail and
 // all data is lost.
@@ -651,4 +755,54 @@ mod tests {
         assert_eq!(settings.video.preferred_fps, 60.0);
         assert!(settings.headset.controllers.as_option().is_none());
     }
+
+    #[test]
+    fn test_migrate_connection_scripts() {
+        let mut session_json = json::to_value(SessionConfig::default()).unwrap();
+        let connection = session_json
+            .pointer_mut("/session_settings/connection")
+            .unwrap()
+            .as_object_mut()
+            .unwrap();
+        connection.remove("event_hooks");
+        connection.insert("on_connect_script".into(), "/path/connect.sh".into());
+        connection.insert("on_disconnect_script".into(), "".into());
+
+        assert!(migrate_session_json(&mut session_json));
+
+        let session = json::from_value::<SessionConfig>(session_json.clone()).unwrap();
+        let hooks = session.to_settings().connection.event_hooks.hooks;
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].event, HookEvent::StreamingStarted);
+        assert!(
+            matches!(&hooks[0].action, HookAction::Command(path) if path == "/path/connect.sh")
+        );
+
+        // The old fields are removed, so the migration runs only once
+        assert!(!migrate_session_json(&mut session_json));
+    }
+
+    #[test]
+    fn test_migrate_connection_scripts_existing_hooks() {
+        let mut session_json = json::to_value(SessionConfig::default()).unwrap();
+        session_json["session_settings"]["connection"]["on_disconnect_script"] =
+            "/path/disconnect.sh".into();
+
+        assert!(migrate_session_json(&mut session_json));
+
+        let session = json::from_value::<SessionConfig>(session_json).unwrap();
+        let hooks = session.to_settings().connection.event_hooks.hooks;
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].event, HookEvent::ClientDisconnected);
+        assert!(
+            matches!(&hooks[0].action, HookAction::Command(path) if path == "/path/disconnect.sh")
+        );
+    }
+
+    #[test]
+    fn test_migrate_current_session() {
+        let mut session_json = json::to_value(SessionConfig::default()).unwrap();
+
+        assert!(!migrate_session_json(&mut session_json));
+    }
 }
@@ -671,6 +671,18 @@ pub enum FaceTrackingSinkConfig {
     VrchatEyeOsc { port: u16 },
     #[schema(strings(display_name = "VRCFaceTracking"))]
     VrcFaceTracking,
+    #[schema(strings(display_name = "ARKit OSC"))]
+    ArkitOsc { port: u16 },
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[schema(gui = "button_group")]
+pub enum FaceExpressionsFormat {
+    Native,
+    #[schema(strings(display_name = "FB"))]
+    Fb,
+    #[schema(strings(display_name = "HTC"))]
+    Htc,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -678,6 +690,18 @@ pub enum FaceTrackingSinkConfig {
 pub struct FaceTrackingConfig {
     pub sources: FaceTrackingSourcesConfig,
     pub sink: FaceTrackingSinkConfig,
+    #[schema(strings(
+        help = r"Convert the face expressions before sending them to the sink. Native forwards the expressions as reported by the headset.
+For example, HTC lets Quest Pro face tracking drive consumers that only support HTC expressions."
+    ))]
+    #[schema(flag = "real-time")]
+    pub expressions_format: FaceExpressionsFormat,
+
+    #[schema(strings(
+        help = "Expose eye gaze and blink values as input components of the headset, so that SteamVR applications supporting eye tracking can use them directly"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub openvr_eye_tracking: bool,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
@@ -710,6 +734,73 @@ pub struct BodyTrackingConfig {
     pub tracked: bool,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[schema(gui = "button_group")]
+pub enum OscCoordinateConvention {
+    #[schema(strings(display_name = "OpenXR"))]
+    OpenXr,
+    Unity,
+    Unreal,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[schema(gui = "button_group")]
+pub enum OscRotationFormat {
+    Quaternion,
+    #[schema(strings(display_name = "Euler angles (degrees)"))]
+    EulerDegrees,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct OscOutputConfig {
+    #[schema(strings(help = "Address of the machine running the OSC receiver"))]
+    pub host: String,
+
+    pub port: u16,
+
+    #[schema(strings(help = r"OpenXR: right-handed, Y up, -Z forward, meters.
+Unity: left-handed, Y up, Z forward, meters.
+Unreal: left-handed, Z up, X forward, centimeters."))]
+    #[schema(flag = "real-time")]
+    pub coordinate_convention: OscCoordinateConvention,
+
+    #[schema(flag = "real-time")]
+    pub rotation_format: OscRotationFormat,
+
+    #[schema(gui(slider(min = 1.0, max = 240.0)), suffix = "Hz")]
+    #[schema(flag = "real-time")]
+    pub max_rate: f32,
+
+    #[schema(strings(help = "Send all messages of a tracking update as a single OSC bundle"))]
+    #[schema(flag = "real-time")]
+    pub use_bundles: bool,
+
+    #[schema(strings(
+        help = r"Arguments: position (3 floats) followed by rotation. {device} is replaced with the device path without the /user/ prefix, for example head or hand/left."
+    ))]
+    #[schema(flag = "real-time")]
+    pub device_motion_address: Switch<String>,
+
+    #[schema(strings(
+        help = r"Arguments: position (3 floats) followed by rotation. {hand} is replaced with left or right and {joint} with the OpenXR joint index."
+    ))]
+    #[schema(flag = "real-time")]
+    pub hand_joint_address: Switch<String>,
+
+    #[schema(strings(
+        help = r"Arguments: rotation relative to the head. {eye} is replaced with left or right."
+    ))]
+    #[schema(flag = "real-time")]
+    pub eye_gaze_address: Switch<String>,
+
+    #[schema(strings(
+        help = r"Arguments: one float. {expression} is replaced with the ARKit blendshape name, like jawOpen. Expressions of any headset are converted to ARKit blendshapes."
+    ))]
+    #[schema(flag = "real-time")]
+    pub face_expression_address: Switch<String>,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum ControllersEmulationMode {
     #[schema(strings(display_name = "Rift S Touch"))]
@@ -728,7 +819,7 @@ pub enum ControllersEmulationMode {
     },
 }
 
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct HysteresisThreshold {
     #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
     pub value: f32,
@@ -736,7 +827,7 @@ pub struct HysteresisThreshold {
     pub deviation: f32,
 }
 
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct BinaryToScalarStates {
     #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
     pub off: f32,
@@ -745,7 +836,7 @@ pub struct BinaryToScalarStates {
 }
 
 // Remaps 0..1 to custom range
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct Range {
     #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
     pub min: f32,
@@ -753,7 +844,7 @@ pub struct Range {
     pub max: f32,
 }
 
-#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ButtonMappingType {
     Passthrough,
     HysteresisThreshold(HysteresisThreshold),
@@ -761,7 +852,7 @@ pub enum ButtonMappingType {
     Remap(Range),
 }
 
-#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ButtonBindingTarget {
     pub destination: String,
     pub mapping_type: ButtonMappingType,
@@ -865,6 +956,42 @@ pub struct HapticsConfig {
     #[schema(flag = "real-time")]
     #[schema(gui(slider(min = 0.0, max = 0.1, step = 0.001)), suffix = "s")]
     pub min_duration_s: f32,
+
+    #[schema(strings(help = "Fade-in time of each vibration pulse"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 0.1, step = 0.001)), suffix = "s")]
+    pub envelope_attack_s: f32,
+
+    #[schema(strings(help = "Fade-out time of each vibration pulse"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 0.1, step = 0.001)), suffix = "s")]
+    pub envelope_release_s: f32,
+
+    #[schema(strings(
+        help = r"Measured gain of the left controller actuator at different frequencies. The vibration amplitude is divided by the interpolated gain. Leave empty for a flat response."
+    ))]
+    #[schema(flag = "real-time")]
+    pub left_frequency_response: Vec<HapticsFrequencyResponsePoint>,
+
+    #[schema(strings(
+        help = r"Measured gain of the right controller actuator at different frequencies. The vibration amplitude is divided by the interpolated gain. Leave empty for a flat response."
+    ))]
+    #[schema(flag = "real-time")]
+    pub right_frequency_response: Vec<HapticsFrequencyResponsePoint>,
+
+    #[schema(strings(
+        help = r"Waveform buffers longer than this are split into multiple queued packets"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.01, max = 0.5, step = 0.01)), suffix = "s")]
+    pub pcm_chunk_duration_s: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct HapticsFrequencyResponsePoint {
+    #[schema(suffix = "Hz")]
+    pub frequency: f32,
+    pub gain: f32,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -944,6 +1071,13 @@ Currently this cannot be reliably estimated automatically. The correct value sho
     #[schema(strings(help = "List of OpenXR-syle paths"))]
     pub button_mappings: Option<Vec<(String, Vec<ButtonBindingTarget>)>>,
 
+    #[schema(strings(
+        help = r"Switch to the button mapping profile associated with the running SteamVR application.
+Profiles are JSON files stored in the presets directory and take precedence over the mappings above."
+    ))]
+    #[schema(flag = "real-time")]
+    pub per_game_button_mapping_profiles: bool,
+
     pub button_mapping_config: AutomaticButtonMappingConfig,
 }
 
@@ -1001,9 +1135,14 @@ Tilted: the world gets tilted when long pressing the oculus button. This is usef
 
     #[schema(flag = "steamvr-restart")]
     pub body_tracking: Switch<BodyTrackingConfig>,
+
+    #[schema(strings(
+        help = "Stream tracking data to any OSC receiver using configurable addresses"
+    ))]
+    pub osc_output: Switch<OscOutputConfig>,
 }
 
-#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Debug)]
 #[schema(gui = "button_group")]
 pub enum SocketProtocol {
     #[schema(strings(display_name = "UDP"))]
@@ -1018,6 +1157,68 @@ pub struct DiscoveryConfig {
         help = "Allow untrusted clients to connect without confirmation. This is not recommended for security reasons."
     ))]
     pub auto_trust_clients: bool,
+
+    #[schema(strings(
+        help = "Also discover clients announcing themselves with UDP broadcast, for networks that filter mDNS multicast"
+    ))]
+    pub broadcast: bool,
+
+    #[schema(strings(
+        help = r#"Address (host:port) of a rendezvous server, to discover clients on other subnets. The server can be started with the alvr_rendezvous executable.
+The address must be reachable by clients too: they learn it after the first successful connection, or it can be set on the client before that."#
+    ))]
+    pub rendezvous_server: Switch<String>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HookEvent {
+    ClientDiscovered,
+    ClientTrusted,
+    ClientConnecting,
+    StreamingStarted,
+    ClientDisconnected,
+    BatteryLow,
+    BitrateDropped,
+    #[schema(strings(display_name = "SteamVR restarting"))]
+    SteamvrRestarting,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub enum HookAction {
+    #[schema(strings(
+        help = "Path of the program to run. Event data is passed as ALVR_* environment variables."
+    ))]
+    Command(String),
+    #[schema(strings(
+        display_name = "HTTP webhook",
+        help = "URL that receives a POST request with the event data as JSON body"
+    ))]
+    Webhook(String),
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct EventHook {
+    pub event: HookEvent,
+    pub action: HookAction,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct EventHooksConfig {
+    #[schema(strings(
+        help = r#"Commands or webhooks triggered on connection lifecycle events.
+Commands receive ALVR_EVENT, ALVR_HOSTNAME, ALVR_IP, ALVR_RESOLUTION, ALVR_FPS, ALVR_CODEC, ALVR_REASON, ALVR_BATTERY and ALVR_BITRATE_MBPS environment variables, when available.
+Like the old connect and disconnect scripts, commands also receive ACTION set to `connect` when streaming starts and `disconnect` when the client disconnects."#
+    ))]
+    pub hooks: Vec<EventHook>,
+
+    #[schema(strings(help = "Battery level below which the battery low event is triggered"))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub battery_low_threshold: f32,
+
+    #[schema(strings(help = "Bitrate below which the bitrate dropped event is triggered"))]
+    #[schema(gui(slider(min = 1.0, max = 1000.0, logarithmic)), suffix = "Mbps")]
+    pub bitrate_dropped_threshold_mbps: f32,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1038,15 +1239,25 @@ TCP: Slower than UDP, but more stable. Pick this if you experience video or audi
     pub client_discovery: Switch<DiscoveryConfig>,
 
     #[schema(strings(
-        help = "This script will be ran when the headset connects. Env var ACTION will be set to `connect`."
+        help = r#"Connect to trusted clients on other networks through a rendezvous server (alvr_rendezvous). Clients learn this setting after the first connection on the local network, or the rendezvous server can be set on the client.
+The stream always uses UDP in this mode."#
+    ))]
+    pub internet_streaming: Switch<InternetStreamingConfig>,
+
+    #[schema(strings(
+        help = r#"When the connection drops while streaming, keep the stream alive for a while and let the client reattach without a full reconnection.
+Not supported with internet streaming."#
     ))]
-    pub on_connect_script: String,
+    pub session_resume: Switch<SessionResumeConfig>,
 
     #[schema(strings(
-        help = "This script will be ran when the headset disconnects, or when SteamVR shuts down. Env var ACTION will be set to `disconnect`."
+        help = r#"Detect headsets connected with a USB cable through the ADB server, forward the connection ports and add them as trusted clients.
+The stream always uses TCP in this mode."#
     ))]
+    pub wired_connection: Switch<WiredConnectionConfig>,
+
     #[schema(flag = "real-time")]
-    pub on_disconnect_script: String,
+    pub event_hooks: EventHooksConfig,
 
     #[schema(strings(
         help = r#"If the client, server or the network discarded one packet, discard packets until a IDR packet is found.
@@ -1089,6 +1300,66 @@ This could happen on TCP. A IDR frame is requested in this case."#
     pub aggressive_keyframe_resend: bool,
 
     pub dscp: Option<DscpTos>,
+
+    #[schema(strings(
+        help = r#"Allow trusted clients marked as spectators to receive the same video stream as the main client.
+Spectators cannot drive SteamVR: their tracking and inputs are ignored."#
+    ))]
+    pub spectators: Switch<SpectatorsConfig>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct InternetStreamingConfig {
+    #[schema(strings(
+        help = "Address (host:port) of the rendezvous server. It must be reachable from the internet by both the streamer and the clients, on both UDP and TCP"
+    ))]
+    pub rendezvous_server: String,
+
+    #[schema(strings(
+        help = "Try to connect the stream directly through the NATs. This does not work with some routers (symmetric NAT)"
+    ))]
+    pub hole_punching: bool,
+
+    #[schema(gui(slider(min = 0.5, max = 10.0, step = 0.5)), suffix = "s")]
+    pub hole_punching_timeout_s: f32,
+
+    #[schema(strings(
+        help = "Relay the stream through the rendezvous server if hole punching fails. This adds latency and uses the bandwidth of the rendezvous server"
+    ))]
+    pub relay_fallback: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct WiredConnectionConfig {
+    #[schema(strings(
+        help = "Start the ADB server if it is not running. adb must be found in PATH"
+    ))]
+    pub start_adb_server: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct SessionResumeConfig {
+    #[schema(strings(
+        help = "How long the streamer waits for the client to come back before ending the stream"
+    ))]
+    #[schema(gui(slider(min = 1.0, max = 60.0, step = 1.0)), suffix = "s")]
+    pub grace_period_s: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct SpectatorsConfig {
+    #[schema(strings(help = "Stream the game audio to spectators too"))]
+    pub game_audio: bool,
+
+    #[schema(strings(
+        help = "Video packets are discarded for a spectator that falls behind by this many frames"
+    ))]
+    pub max_queued_video_frames: usize,
+
+    #[schema(strings(
+        help = "Each spectator streams on its own port, the first free one starting from this. These ports must be open in the firewall."
+    ))]
+    pub first_stream_port: u16,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1249,6 +1520,14 @@ pub fn session_settings_default() -> SettingsDefault {
         element: OPENVR_PROPS_DEFAULT.clone(),
         content: vec![],
     };
+    let frequency_response = VectorDefault {
+        gui_collapsed: true,
+        element: HapticsFrequencyResponsePointDefault {
+            frequency: 160.0,
+            gain: 1.0,
+        },
+        content: vec![],
+    };
     let socket_buffer = SocketBufferSizeDefault {
         Custom: 100000,
         variant: SocketBufferSizeDefaultVariant::Maximum,
@@ -1505,8 +1784,13 @@ pub fn session_settings_default() -> SettingsDefault {
                     },
                     sink: FaceTrackingSinkConfigDefault {
                         VrchatEyeOsc: FaceTrackingSinkConfigVrchatEyeOscDefault { port: 9000 },
+                        ArkitOsc: FaceTrackingSinkConfigArkitOscDefault { port: 9000 },
                         variant: FaceTrackingSinkConfigDefaultVariant::VrchatEyeOsc,
                     },
+                    expressions_format: FaceExpressionsFormatDefault {
+                        variant: FaceExpressionsFormatDefaultVariant::Native,
+                    },
+                    openvr_eye_tracking: false,
                 },
             },
             body_tracking: SwitchDefault {
@@ -1529,6 +1813,38 @@ pub fn session_settings_default() -> SettingsDefault {
                     tracked: true,
                 },
             },
+            osc_output: SwitchDefault {
+                enabled: false,
+                content: OscOutputConfigDefault {
+                    gui_collapsed: true,
+                    host: "127.0.0.1".into(),
+                    port: 9000,
+                    coordinate_convention: OscCoordinateConventionDefault {
+                        variant: OscCoordinateConventionDefaultVariant::OpenXr,
+                    },
+                    rotation_format: OscRotationFormatDefault {
+                        variant: OscRotationFormatDefaultVariant::Quaternion,
+                    },
+                    max_rate: 60.0,
+                    use_bundles: true,
+                    device_motion_address: SwitchDefault {
+                        enabled: true,
+                        content: "/alvr/{device}/pose".into(),
+                    },
+                    hand_joint_address: SwitchDefault {
+                        enabled: false,
+                        content: "/alvr/hand/{hand}/joint/{joint}".into(),
+                    },
+                    eye_gaze_address: SwitchDefault {
+                        enabled: false,
+                        content: "/alvr/eye/{eye}/gaze".into(),
+                    },
+                    face_expression_address: SwitchDefault {
+                        enabled: false,
+                        content: "/alvr/face/{expression}".into(),
+                    },
+                },
+            },
             controllers: SwitchDefault {
                 enabled: true,
                 content: ControllersConfigDefault {
@@ -1584,6 +1900,7 @@ pub fn session_settings_default() -> SettingsDefault {
                             content: vec![],
                         },
                     },
+                    per_game_button_mapping_profiles: true,
                     button_mapping_config: AutomaticButtonMappingConfigDefault {
                         gui_collapsed: true,
                         click_threshold: HysteresisThresholdDefault {
@@ -1639,6 +1956,11 @@ pub fn session_settings_default() -> SettingsDefault {
                             intensity_multiplier: 1.0,
                             amplitude_curve: 1.0,
                             min_duration_s: 0.01,
+                            envelope_attack_s: 0.0,
+                            envelope_release_s: 0.0,
+                            left_frequency_response: frequency_response.clone(),
+                            right_frequency_response: frequency_response,
+                            pcm_chunk_duration_s: 0.1,
                         },
                     },
                 },
@@ -1659,6 +1981,32 @@ pub fn session_settings_default() -> SettingsDefault {
                 enabled: true,
                 content: DiscoveryConfigDefault {
                     auto_trust_clients: cfg!(debug_assertions),
+                    broadcast: true,
+                    rendezvous_server: SwitchDefault {
+                        enabled: false,
+                        content: "192.168.1.2:9945".into(),
+                    },
+                },
+            },
+            internet_streaming: SwitchDefault {
+                enabled: false,
+                content: InternetStreamingConfigDefault {
+                    rendezvous_server: "".into(),
+                    hole_punching: true,
+                    hole_punching_timeout_s: 3.0,
+                    relay_fallback: true,
+                },
+            },
+            session_resume: SwitchDefault {
+                enabled: true,
+                content: SessionResumeConfigDefault {
+                    grace_period_s: 10.0,
+                },
+            },
+            wired_connection: SwitchDefault {
+                enabled: false,
+                content: WiredConnectionConfigDefault {
+                    start_adb_server: true,
                 },
             },
             web_server_port: 8082,
@@ -1684,8 +2032,33 @@ pub fn session_settings_default() -> SettingsDefault {
             max_queued_server_video_frames: 1024,
             avoid_video_glitching: false,
             aggressive_keyframe_resend: false,
-            on_connect_script: "".into(),
-            on_disconnect_script: "".into(),
+            spectators: SwitchDefault {
+                enabled: false,
+                content: SpectatorsConfigDefault {
+                    game_audio: true,
+                    max_queued_video_frames: 64,
+                    first_stream_port: 9950,
+                },
+            },
+            event_hooks: EventHooksConfigDefault {
+                gui_collapsed: true,
+                hooks: VectorDefault {
+                    gui_collapsed: false,
+                    element: EventHookDefault {
+                        event: HookEventDefault {
+                            variant: HookEventDefaultVariant::StreamingStarted,
+                        },
+                        action: HookActionDefault {
+                            Command: "".into(),
+                            Webhook: "http://localhost:8080/alvr".into(),
+                            variant: HookActionDefaultVariant::Command,
+                        },
+                    },
+                    content: vec![],
+                },
+                battery_low_threshold: 0.2,
+                bitrate_dropped_threshold_mbps: 10.0,
+            },
             packet_size: 1400,
             statistics_history_size: 256,
         },
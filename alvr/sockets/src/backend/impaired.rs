@@ -0,0 +1,301 @@
+// In-memory link that simulates network impairments between a SocketWriter and a SocketReader.
+// Used by the tests to exercise the transport code without a real network. Randomness is seeded,
+// so a failing test can be reproduced.
+
+use super::{SocketReader, SocketWriter};
+use alvr_common::{
+    anyhow::{bail, Result},
+    con_bail,
+    parking_lot::{Condvar, Mutex},
+    ConResult,
+};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone)]
+pub struct ImpairmentConfig {
+    pub delay: Duration,
+    // Uniformly distributed extra delay in [0, jitter)
+    pub jitter: Duration,
+    // Probability in [0, 1]. For reliable links a lost segment is retransmitted after one more
+    // round trip instead
+    pub loss: f32,
+    // Probability in [0, 1] that a datagram is held back behind the ones sent after it
+    pub reordering: f32,
+    pub bandwidth_bps: Option<u64>,
+    // Stream-like link (TCP): lossless, in order, and reads can return partial segments
+    pub reliable: bool,
+    // Writes on reliable links are split in segments of this size
+    pub segment_size: usize,
+    // Datagrams that don't fit are dropped, reliable writes block until there is space
+    pub max_queue_bytes: Option<usize>,
+    pub read_timeout: Duration,
+    pub seed: u64,
+}
+
+impl Default for ImpairmentConfig {
+    fn default() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            reordering: 0.0,
+            bandwidth_bps: None,
+            reliable: false,
+            segment_size: 1400,
+            max_queue_bytes: None,
+            read_timeout: Duration::from_millis(10),
+            seed: 1,
+        }
+    }
+}
+
+struct QueuedData {
+    deliver_at: Instant,
+    sequence: u64,
+    data: Vec<u8>,
+}
+
+struct LinkState {
+    config: ImpairmentConfig,
+    queue: VecDeque<QueuedData>,
+    queued_bytes: usize,
+    next_sequence: u64,
+    // Instant the link finishes transmitting the data already sent, used for the bandwidth cap
+    link_free_at: Instant,
+    last_delivery: Instant,
+    // Bytes already consumed from the front segment of a reliable link
+    read_offset: usize,
+    rng_state: u64,
+    writer_closed: bool,
+}
+
+impl LinkState {
+    // xorshift64, good enough to sample impairments
+    fn random(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+
+        (self.rng_state >> 40) as f32 / (1_u64 << 24) as f32
+    }
+
+    fn enqueue(&mut self, data: &[u8]) {
+        let config = self.config.clone();
+
+        let now = Instant::now();
+        let transmission_time = config
+            .bandwidth_bps
+            .map(|bps| Duration::from_secs_f64(data.len() as f64 * 8.0 / bps as f64))
+            .unwrap_or_default();
+        self.link_free_at = self.link_free_at.max(now) + transmission_time;
+
+        let mut deliver_at =
+            self.link_free_at + config.delay + config.jitter.mul_f32(self.random());
+
+        if config.reliable {
+            if self.random() < config.loss {
+                deliver_at += config.delay * 2 + config.jitter;
+            }
+
+            // Later segments wait for the previous ones
+            deliver_at = deliver_at.max(self.last_delivery);
+        } else {
+            if self.random() < config.loss {
+                return;
+            }
+
+            if self.random() < config.reordering {
+                deliver_at += config.delay + config.jitter + Duration::from_millis(1);
+            }
+        }
+        self.last_delivery = deliver_at;
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let position = self
+            .queue
+            .partition_point(|item| (item.deliver_at, item.sequence) <= (deliver_at, sequence));
+        self.queue.insert(
+            position,
+            QueuedData {
+                deliver_at,
+                sequence,
+                data: data.to_vec(),
+            },
+        );
+        self.queued_bytes += data.len();
+    }
+
+    fn is_front_ready(&self, now: Instant) -> bool {
+        self.queue
+            .front()
+            .map(|item| item.deliver_at <= now)
+            .unwrap_or(false)
+    }
+
+    // Copies the data ready to be delivered without consuming it
+    fn copy_ready(&self, buffer: &mut [u8], now: Instant) -> usize {
+        if self.config.reliable {
+            let mut count = 0;
+            let mut offset = self.read_offset;
+            for item in self.queue.iter().take_while(|item| item.deliver_at <= now) {
+                let size = usize::min(item.data.len() - offset, buffer.len() - count);
+                buffer[count..count + size].copy_from_slice(&item.data[offset..offset + size]);
+                count += size;
+                offset = 0;
+
+                if count == buffer.len() {
+                    break;
+                }
+            }
+
+            count
+        } else {
+            let data = &self.queue.front().unwrap().data;
+            let size = usize::min(data.len(), buffer.len());
+            buffer[..size].copy_from_slice(&data[..size]);
+
+            size
+        }
+    }
+
+    fn consume(&mut self, mut count: usize) {
+        if self.config.reliable {
+            while count > 0 {
+                let front_size = self.queue.front().unwrap().data.len() - self.read_offset;
+                if count < front_size {
+                    self.read_offset += count;
+                    return;
+                }
+
+                count -= front_size;
+                self.read_offset = 0;
+                self.queued_bytes -= self.queue.pop_front().unwrap().data.len();
+            }
+        } else {
+            // The rest of a truncated datagram is lost, like for UDP
+            self.queued_bytes -= self.queue.pop_front().unwrap().data.len();
+        }
+    }
+}
+
+struct Link {
+    state: Mutex<LinkState>,
+    condvar: Condvar,
+}
+
+pub struct ImpairedWriter(Arc<Link>);
+
+impl SocketWriter for ImpairedWriter {
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        let mut state = self.0.state.lock();
+
+        if !state.config.reliable {
+            if let Some(max_bytes) = state.config.max_queue_bytes {
+                if state.queued_bytes + buffer.len() > max_bytes {
+                    return Ok(());
+                }
+            }
+
+            state.enqueue(buffer);
+        } else {
+            for segment in buffer.chunks(state.config.segment_size) {
+                if let Some(max_bytes) = state.config.max_queue_bytes {
+                    if segment.len() > max_bytes {
+                        bail!("Segment bigger than the link queue");
+                    }
+
+                    while state.queued_bytes + segment.len() > max_bytes {
+                        self.0.condvar.wait(&mut state);
+                    }
+                }
+
+                state.enqueue(segment);
+            }
+        }
+
+        self.0.condvar.notify_all();
+
+        Ok(())
+    }
+}
+
+impl Drop for ImpairedWriter {
+    fn drop(&mut self) {
+        self.0.state.lock().writer_closed = true;
+        self.0.condvar.notify_all();
+    }
+}
+
+pub struct ImpairedReader(Arc<Link>);
+
+impl ImpairedReader {
+    fn read(&self, buffer: &mut [u8], consume: bool) -> ConResult<usize> {
+        let mut state = self.0.state.lock();
+        let deadline = Instant::now() + state.config.read_timeout;
+
+        loop {
+            let now = Instant::now();
+            if state.is_front_ready(now) {
+                let count = state.copy_ready(buffer, now);
+                if consume {
+                    state.consume(count);
+                    self.0.condvar.notify_all();
+                }
+
+                // Like MSG_TRUNC, peeking a datagram returns its full size
+                return if !state.config.reliable && !consume {
+                    Ok(state.queue.front().unwrap().data.len())
+                } else {
+                    Ok(count)
+                };
+            } else if state.queue.is_empty() && state.writer_closed {
+                con_bail!("Link closed");
+            } else if now >= deadline {
+                return alvr_common::try_again();
+            }
+
+            let wake_at = state
+                .queue
+                .front()
+                .map(|item| item.deliver_at.min(deadline))
+                .unwrap_or(deadline);
+            self.0.condvar.wait_until(&mut state, wake_at);
+        }
+    }
+}
+
+impl SocketReader for ImpairedReader {
+    fn recv(&mut self, buffer: &mut [u8]) -> ConResult<usize> {
+        self.read(buffer, true)
+    }
+
+    fn peek(&self, buffer: &mut [u8]) -> ConResult<usize> {
+        self.read(buffer, false)
+    }
+}
+
+pub fn impaired_link(config: ImpairmentConfig) -> (ImpairedWriter, ImpairedReader) {
+    let now = Instant::now();
+    let link = Arc::new(Link {
+        state: Mutex::new(LinkState {
+            rng_state: config.seed.max(1),
+            config,
+            queue: VecDeque::new(),
+            queued_bytes: 0,
+            next_sequence: 0,
+            link_free_at: now,
+            last_delivery: now,
+            read_offset: 0,
+            writer_closed: false,
+        }),
+        condvar: Condvar::new(),
+    });
+
+    (ImpairedWriter(Arc::clone(&link)), ImpairedReader(link))
+}
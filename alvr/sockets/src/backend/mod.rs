@@ -1,7 +1,12 @@
+#[cfg(test)]
+pub mod impaired;
 pub mod tcp;
 pub mod udp;
 
 use alvr_common::{anyhow::Result, ConResult};
+use std::{thread, time::Duration};
+
+const DETACHED_POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 pub trait SocketWriter: Send {
     fn send(&mut self, buffer: &[u8]) -> Result<()>;
@@ -17,3 +22,24 @@ pub trait SocketReader: Send {
 
     fn peek(&self, buffer: &mut [u8]) -> ConResult<usize>;
 }
+
+// Placeholder for a socket whose connection was lost. Sent data is dropped like on a lossy network
+pub struct DetachedSocket;
+
+impl SocketWriter for DetachedSocket {
+    fn send(&mut self, _: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl SocketReader for DetachedSocket {
+    fn recv(&mut self, _: &mut [u8]) -> ConResult<usize> {
+        thread::sleep(DETACHED_POLL_INTERVAL);
+        alvr_common::try_again()
+    }
+
+    fn peek(&self, _: &mut [u8]) -> ConResult<usize> {
+        thread::sleep(DETACHED_POLL_INTERVAL);
+        alvr_common::try_again()
+    }
+}
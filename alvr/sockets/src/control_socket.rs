@@ -1,13 +1,16 @@
-use crate::backend::{tcp, SocketReader, SocketWriter};
+use crate::{
+    backend::{tcp, SocketReader, SocketWriter},
+    nat::{self, RelayRole},
+};
 
 use super::CONTROL_PORT;
-use alvr_common::{anyhow::Result, ConResult, HandleTryAgain, ToCon};
+use alvr_common::{anyhow::Result, ConResult, ToCon};
 use alvr_session::SocketBufferSize;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     marker::PhantomData,
     mem,
-    net::{IpAddr, TcpListener, TcpStream},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
     time::{Duration, Instant},
 };
 
@@ -20,7 +23,7 @@ struct RecvState {
 }
 
 fn framed_send<S: Serialize>(
-    socket: &mut TcpStream,
+    socket: &mut impl SocketWriter,
     buffer: &mut Vec<u8>,
     packet: &S,
 ) -> Result<()> {
@@ -40,7 +43,7 @@ fn framed_send<S: Serialize>(
 }
 
 fn framed_recv<R: DeserializeOwned>(
-    socket: &mut TcpStream,
+    socket: &mut impl SocketReader,
     buffer: &mut Vec<u8>,
     maybe_recv_state: &mut Option<RecvState>,
     timeout: Duration,
@@ -53,7 +56,7 @@ fn framed_recv<R: DeserializeOwned>(
         let mut payload_length_bytes = [0; FRAMED_PREFIX_LENGTH];
 
         loop {
-            let count = socket.peek(&mut payload_length_bytes).handle_try_again()?;
+            let count = socket.peek(&mut payload_length_bytes)?;
             if count == FRAMED_PREFIX_LENGTH {
                 break;
             } else if Instant::now() > deadline {
@@ -146,6 +149,15 @@ pub struct ProtoControlSocket {
 pub enum PeerType<'a> {
     AnyClient(Vec<IpAddr>),
     Server(&'a TcpListener),
+    // Internet streaming, the pairing token of the client identifies the connection on the relay
+    ClientThroughRelay {
+        relay_address: SocketAddr,
+        pairing_token: &'a str,
+    },
+    ServerThroughRelay {
+        relay_address: SocketAddr,
+        pairing_token: &'a str,
+    },
 }
 
 impl ProtoControlSocket {
@@ -162,6 +174,24 @@ impl ProtoControlSocket {
                 .0
             }
             PeerType::Server(listener) => tcp::accept_from_server(listener, None, timeout)?.0,
+            PeerType::ClientThroughRelay {
+                relay_address,
+                pairing_token,
+            } => nat::connect_control_relay(
+                relay_address,
+                pairing_token,
+                RelayRole::Server,
+                timeout,
+            )?,
+            PeerType::ServerThroughRelay {
+                relay_address,
+                pairing_token,
+            } => nat::connect_control_relay(
+                relay_address,
+                pairing_token,
+                RelayRole::Client,
+                timeout,
+            )?,
         };
 
         let peer_ip = socket.peer_addr().to_con()?.ip();
@@ -198,3 +228,155 @@ impl ProtoControlSocket {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::impaired::{self, ImpairedReader, ImpairedWriter, ImpairmentConfig};
+    use alvr_common::ConnectionError;
+    use std::thread;
+
+    type TestPacket = (u32, Vec<u8>);
+
+    fn test_packet(index: u32, size: usize) -> TestPacket {
+        (index, (0..size).map(|i| (i as u32 + index) as u8).collect())
+    }
+
+    struct TestReceiver {
+        socket: ImpairedReader,
+        buffer: Vec<u8>,
+        recv_state: Option<RecvState>,
+    }
+
+    impl TestReceiver {
+        fn recv(&mut self, timeout: Duration) -> ConResult<TestPacket> {
+            framed_recv(
+                &mut self.socket,
+                &mut self.buffer,
+                &mut self.recv_state,
+                timeout,
+            )
+        }
+
+        fn recv_until(&mut self, timeout: Duration) -> TestPacket {
+            let deadline = Instant::now() + timeout;
+            loop {
+                match self.recv(Duration::from_millis(10)) {
+                    Ok(packet) => return packet,
+                    Err(ConnectionError::TryAgain(_)) if Instant::now() < deadline => (),
+                    Err(e) => panic!("Failed to receive packet: {e}"),
+                }
+            }
+        }
+    }
+
+    fn tcp_like_link(config: ImpairmentConfig) -> (ImpairedWriter, TestReceiver) {
+        let (writer, reader) = impaired::impaired_link(ImpairmentConfig {
+            reliable: true,
+            ..config
+        });
+
+        (
+            writer,
+            TestReceiver {
+                socket: reader,
+                buffer: vec![],
+                recv_state: None,
+            },
+        )
+    }
+
+    #[test]
+    fn frames_split_across_segments() {
+        // Segments smaller than the length prefix
+        let (mut writer, mut receiver) = tcp_like_link(ImpairmentConfig {
+            jitter: Duration::from_millis(1),
+            segment_size: 3,
+            ..Default::default()
+        });
+
+        let mut buffer = vec![];
+        for index in 0..20 {
+            framed_send(
+                &mut writer,
+                &mut buffer,
+                &test_packet(index, index as usize * 10),
+            )
+            .unwrap();
+        }
+
+        for index in 0..20 {
+            assert_eq!(
+                receiver.recv_until(Duration::from_secs(5)),
+                test_packet(index, index as usize * 10)
+            );
+        }
+    }
+
+    #[test]
+    fn resume_after_timeout_mid_frame() {
+        // 100 KB take at least 100 ms to be transmitted
+        let (mut writer, mut receiver) = tcp_like_link(ImpairmentConfig {
+            bandwidth_bps: Some(8_000_000),
+            ..Default::default()
+        });
+
+        framed_send(&mut writer, &mut vec![], &test_packet(0, 100_000)).unwrap();
+        framed_send(&mut writer, &mut vec![], &test_packet(1, 10)).unwrap();
+
+        let mut timeouts = 0;
+        let packet = loop {
+            match receiver.recv(Duration::from_millis(5)) {
+                Ok(packet) => break packet,
+                Err(ConnectionError::TryAgain(_)) => timeouts += 1,
+                Err(e) => panic!("Failed to receive packet: {e}"),
+            }
+        };
+
+        assert!(timeouts > 0);
+        assert_eq!(packet, test_packet(0, 100_000));
+        assert_eq!(
+            receiver.recv_until(Duration::from_secs(1)),
+            test_packet(1, 10)
+        );
+    }
+
+    #[test]
+    fn large_packets_with_backpressure() {
+        // The sender blocks when the link queue is full, like with a full TCP send buffer
+        let (mut writer, mut receiver) = tcp_like_link(ImpairmentConfig {
+            delay: Duration::from_millis(5),
+            jitter: Duration::from_millis(2),
+            loss: 0.05,
+            max_queue_bytes: Some(64 * 1024),
+            ..Default::default()
+        });
+
+        let send_thread = thread::spawn(move || {
+            let mut buffer = vec![];
+            for index in 0..5 {
+                framed_send(&mut writer, &mut buffer, &test_packet(index, 1_000_000)).unwrap();
+            }
+        });
+
+        for index in 0..5 {
+            assert_eq!(
+                receiver.recv_until(Duration::from_secs(10)),
+                test_packet(index, 1_000_000)
+            );
+        }
+
+        send_thread.join().unwrap();
+    }
+
+    #[test]
+    fn closed_link() {
+        let (writer, mut receiver) = tcp_like_link(ImpairmentConfig::default());
+        drop(writer);
+
+        assert!(matches!(
+            receiver.recv(Duration::from_millis(10)),
+            Err(ConnectionError::Other(_))
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+// Probes used by the connection diagnostics. Datagrams of increasing size are sent on a plain UDP
+// socket, to find out which sizes survive IP fragmentation and whether the DSCP marking is kept by
+// the network.
+
+use crate::backend::udp;
+use alvr_common::anyhow::Result;
+use alvr_session::{DscpTos, SocketBufferSize};
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    net::{IpAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const PROBE_PACKET_SIZES: [u32; 9] = [576, 1200, 1400, 1472, 1500, 4000, 8000, 16000, 65000];
+pub const PROBES_PER_SIZE: u32 = 10;
+
+const PROBE_INTERVAL: Duration = Duration::from_millis(2);
+const PROBE_SILENCE_TIMEOUT: Duration = Duration::from_millis(500);
+const MAX_DATAGRAM_SIZE: usize = 65507;
+
+pub struct ProbeSender(UdpSocket);
+
+impl ProbeSender {
+    pub fn connect(peer_ip: IpAddr, port: u16, dscp: Option<DscpTos>) -> Result<Self> {
+        let socket = udp::bind(
+            port,
+            dscp,
+            SocketBufferSize::Maximum,
+            SocketBufferSize::Default,
+        )?;
+        socket.connect((peer_ip, port))?;
+
+        Ok(Self(socket))
+    }
+
+    // Send errors are ignored, since the OS may refuse sizes over the MTU, which counts as loss
+    pub fn send_probes(&self) {
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+
+        for size in PROBE_PACKET_SIZES {
+            buffer[0..4].copy_from_slice(&size.to_be_bytes());
+
+            for _ in 0..PROBES_PER_SIZE {
+                self.0.send(&buffer[..size as usize]).ok();
+                thread::sleep(PROBE_INTERVAL);
+            }
+        }
+    }
+}
+
+pub struct ProbeReceiver(UdpSocket);
+
+impl ProbeReceiver {
+    pub fn bind(port: u16) -> Result<Self> {
+        let socket = udp::bind(
+            port,
+            None,
+            SocketBufferSize::Default,
+            SocketBufferSize::Maximum,
+        )?;
+        enable_tos_reception(&socket)?;
+
+        Ok(Self(socket))
+    }
+
+    // Returns the number of received probes for each size and the TOS byte of the last probe, if
+    // the platform reports it. Stops when no probe arrives for a while after the first one.
+    pub fn recv_probes(&self, timeout: Duration) -> Result<(HashMap<u32, u32>, Option<u8>)> {
+        self.0.set_read_timeout(Some(PROBE_SILENCE_TIMEOUT))?;
+
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+        let mut received = HashMap::new();
+        let mut tos = None;
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            match recv_with_tos(&self.0, &mut buffer) {
+                Ok((size, packet_tos)) => {
+                    // Truncated or unrelated datagrams are ignored
+                    if size >= 4
+                        && u32::from_be_bytes(buffer[0..4].try_into().unwrap()) == size as u32
+                    {
+                        *received.entry(size as u32).or_default() += 1;
+                        tos = packet_tos.or(tos);
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if !received.is_empty() {
+                        break;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok((received, tos))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn enable_tos_reception(socket: &UdpSocket) -> Result<()> {
+    use std::{mem, os::fd::AsRawFd};
+
+    let enable: libc::c_int = 1;
+    let res = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_RECVTOS,
+            &enable as *const _ as *const libc::c_void,
+            mem::size_of_val(&enable) as libc::socklen_t,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn enable_tos_reception(_: &UdpSocket) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_with_tos(socket: &UdpSocket, buffer: &mut [u8]) -> io::Result<(usize, Option<u8>)> {
+    use std::{mem, os::fd::AsRawFd};
+
+    let mut iov = libc::iovec {
+        iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buffer.len(),
+    };
+    let mut control = [0_u8; 64];
+
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    message.msg_controllen = control.len() as _;
+
+    let size = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, 0) };
+    if size < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut tos = None;
+    unsafe {
+        let mut header = libc::CMSG_FIRSTHDR(&message);
+        while !header.is_null() {
+            if (*header).cmsg_level == libc::IPPROTO_IP && (*header).cmsg_type == libc::IP_TOS {
+                tos = Some(*libc::CMSG_DATA(header));
+            }
+            header = libc::CMSG_NXTHDR(&message, header);
+        }
+    }
+
+    Ok((size as usize, tos))
+}
+
+// The TOS byte cannot be read, the DSCP survival is reported as unknown
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn recv_with_tos(socket: &UdpSocket, buffer: &mut [u8]) -> io::Result<(usize, Option<u8>)> {
+    socket.recv(buffer).map(|size| (size, None))
+}
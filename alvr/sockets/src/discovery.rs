@@ -0,0 +1,255 @@
+// Discovery transports used alongside mDNS. Clients announce themselves with UDP broadcast on the
+// control port (only works inside the same subnet) and by registering to a rendezvous server,
+// which the streamer queries to find clients on other subnets.
+
+use crate::CONTROL_PORT;
+use alvr_common::{anyhow::Result, ToAny};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+pub const DISCOVERY_BROADCAST_PORT: u16 = CONTROL_PORT;
+pub const RENDEZVOUS_DEFAULT_PORT: u16 = 9945;
+
+// Clients re-register every second, stale entries are removed after this timeout
+const RENDEZVOUS_ENTRY_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_PACKET_SIZE: usize = 65507;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiscoveredClient {
+    pub hostname: String,
+    pub ip: IpAddr,
+    pub protocol_id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum DiscoveryPacket {
+    ClientAnnouncement {
+        hostname: String,
+        protocol_id: String,
+    },
+    QueryClients,
+    ClientList(Vec<DiscoveredClient>),
+    // Used for internet streaming, see nat.rs
+    QueryPublicAddress,
+    PublicAddress(SocketAddr),
+    RelayRegister {
+        session: u64,
+    },
+    RelayPaired,
+    HolePunch(u32),
+    HolePunchAck(u32),
+}
+
+pub fn send_discovery_packet(
+    socket: &UdpSocket,
+    packet: &DiscoveryPacket,
+    address: impl ToSocketAddrs,
+) -> Result<()> {
+    socket.send_to(&serde_json::to_vec(packet)?, address)?;
+
+    Ok(())
+}
+
+// Returns None if there is no packet available. Malformed packets are skipped.
+pub fn recv_discovery_packet(socket: &UdpSocket) -> Result<Option<(DiscoveryPacket, SocketAddr)>> {
+    let mut buffer = vec![0; MAX_PACKET_SIZE];
+
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((size, address)) => {
+                if let Ok(packet) = serde_json::from_slice(&buffer[..size]) {
+                    return Ok(Some((packet, address)));
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+pub fn resolve_rendezvous_address(address: &str) -> Result<SocketAddr> {
+    if let Ok(address) = address.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+
+    let address = if address.contains(':') {
+        address.to_owned()
+    } else {
+        format!("{address}:{RENDEZVOUS_DEFAULT_PORT}")
+    };
+
+    address.to_socket_addrs()?.next().to_any()
+}
+
+struct RelayPeer {
+    address: SocketAddr,
+    session: u64,
+    last_seen: Instant,
+}
+
+pub struct RendezvousServer {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+    clients: HashMap<String, (DiscoveredClient, Instant)>,
+    // session -> address of the first peer that registered
+    pending_relay_sessions: HashMap<u64, (SocketAddr, Instant)>,
+    relay_peers: HashMap<SocketAddr, RelayPeer>,
+}
+
+impl RendezvousServer {
+    pub fn new(port: u16) -> Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+
+        Ok(Self {
+            socket,
+            buffer: vec![0; MAX_PACKET_SIZE],
+            clients: HashMap::new(),
+            pending_relay_sessions: HashMap::new(),
+            relay_peers: HashMap::new(),
+        })
+    }
+
+    fn register_relay_peer(
+        &mut self,
+        address: SocketAddr,
+        session: u64,
+        now: Instant,
+    ) -> Result<()> {
+        if let Some(peer) = self.relay_peers.get(&address) {
+            if peer.session == session {
+                // The previous reply got lost
+                return send_discovery_packet(&self.socket, &DiscoveryPacket::RelayPaired, address);
+            }
+
+            // The address is reused for a new session
+            let peer_address = peer.address;
+            self.relay_peers.remove(&address);
+            self.relay_peers.remove(&peer_address);
+        }
+
+        match self.pending_relay_sessions.remove(&session) {
+            Some((peer_address, _)) if peer_address != address => {
+                self.relay_peers.insert(
+                    address,
+                    RelayPeer {
+                        address: peer_address,
+                        session,
+                        last_seen: now,
+                    },
+                );
+                self.relay_peers.insert(
+                    peer_address,
+                    RelayPeer {
+                        address,
+                        session,
+                        last_seen: now,
+                    },
+                );
+
+                send_discovery_packet(&self.socket, &DiscoveryPacket::RelayPaired, address)?;
+                send_discovery_packet(&self.socket, &DiscoveryPacket::RelayPaired, peer_address)?;
+            }
+            _ => {
+                self.pending_relay_sessions.insert(session, (address, now));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Blocks for at most the timeout
+    pub fn poll(&mut self, timeout: Duration) -> Result<()> {
+        self.socket.set_read_timeout(Some(timeout))?;
+
+        let now = Instant::now();
+        self.clients
+            .retain(|_, (_, last_seen)| now < *last_seen + RENDEZVOUS_ENTRY_TIMEOUT);
+        self.pending_relay_sessions
+            .retain(|_, (_, last_seen)| now < *last_seen + RENDEZVOUS_ENTRY_TIMEOUT);
+        self.relay_peers
+            .retain(|_, peer| now < peer.last_seen + RENDEZVOUS_ENTRY_TIMEOUT);
+
+        let (size, address) = match self.socket.recv_from(&mut self.buffer) {
+            Ok(res) => res,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // Relayed stream shards start with the big endian shard length, which never begins with
+        // the '{' byte of a JSON packet
+        if let Some(peer) = self.relay_peers.get_mut(&address) {
+            if self.buffer[0] != b'{' {
+                peer.last_seen = now;
+                let peer_address = peer.address;
+                if let Some(peer) = self.relay_peers.get_mut(&peer_address) {
+                    peer.last_seen = now;
+                }
+
+                self.socket.send_to(&self.buffer[..size], peer_address)?;
+
+                return Ok(());
+            }
+        }
+
+        let Ok(packet) = serde_json::from_slice(&self.buffer[..size]) else {
+            return Ok(());
+        };
+
+        match packet {
+            DiscoveryPacket::ClientAnnouncement {
+                hostname,
+                protocol_id,
+            } => {
+                self.clients.insert(
+                    hostname.clone(),
+                    (
+                        DiscoveredClient {
+                            hostname,
+                            // The address seen by the rendezvous server is the routable one
+                            ip: address.ip(),
+                            protocol_id,
+                        },
+                        now,
+                    ),
+                );
+            }
+            DiscoveryPacket::QueryClients => {
+                let clients = self
+                    .clients
+                    .values()
+                    .map(|(client, _)| client.clone())
+                    .collect();
+
+                send_discovery_packet(
+                    &self.socket,
+                    &DiscoveryPacket::ClientList(clients),
+                    address,
+                )?;
+            }
+            DiscoveryPacket::QueryPublicAddress => send_discovery_packet(
+                &self.socket,
+                &DiscoveryPacket::PublicAddress(address),
+                address,
+            )?,
+            DiscoveryPacket::RelayRegister { session } => {
+                self.register_relay_peer(address, session, now)?
+            }
+            DiscoveryPacket::ClientList(_)
+            | DiscoveryPacket::PublicAddress(_)
+            | DiscoveryPacket::RelayPaired
+            | DiscoveryPacket::HolePunch(_)
+            | DiscoveryPacket::HolePunchAck(_) => (),
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 mod backend;
 mod control_socket;
+mod diagnostics;
+mod discovery;
+mod nat;
 mod stream_socket;
 
 use alvr_common::{anyhow::Result, info};
@@ -11,6 +14,9 @@ use std::{
 };
 
 pub use control_socket::*;
+pub use diagnostics::*;
+pub use discovery::*;
+pub use nat::*;
 pub use stream_socket::*;
 
 pub const LOCAL_IP: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
@@ -75,19 +81,22 @@ fn set_socket_buffers(
     Ok(())
 }
 
-fn set_dscp(socket: &Socket, dscp: Option<DscpTos>) {
+// Returns the 6-bit DSCP code point
+pub fn dscp_value(dscp: DscpTos) -> u8 {
     // https://en.wikipedia.org/wiki/Differentiated_services
-    if let Some(dscp) = dscp {
-        let tos = match dscp {
-            DscpTos::BestEffort => 0,
-            DscpTos::ClassSelector(precedence) => precedence << 3,
-            DscpTos::AssuredForwarding {
-                class,
-                drop_probability,
-            } => (class << 3) | drop_probability as u8,
-            DscpTos::ExpeditedForwarding => 0b101110,
-        };
+    match dscp {
+        DscpTos::BestEffort => 0,
+        DscpTos::ClassSelector(precedence) => precedence << 3,
+        DscpTos::AssuredForwarding {
+            class,
+            drop_probability,
+        } => (class << 3) | drop_probability as u8,
+        DscpTos::ExpeditedForwarding => 0b101110,
+    }
+}
 
-        socket.set_tos((tos << 2) as u32).ok();
+fn set_dscp(socket: &Socket, dscp: Option<DscpTos>) {
+    if let Some(dscp) = dscp {
+        socket.set_tos((dscp_value(dscp) << 2) as u32).ok();
     }
 }
@@ -0,0 +1,410 @@
+// NAT traversal for internet streaming. The control connection is always relayed over TCP by the
+// rendezvous server, since TCP hole punching is unreliable. The public address of the stream socket
+// is queried from the rendezvous server (STUN-like), then both peers try UDP hole punching. If that
+// fails, the stream is relayed by the rendezvous server too.
+
+use crate::{
+    backend::udp, recv_discovery_packet, send_discovery_packet, DiscoveryPacket, StreamSocket,
+};
+use alvr_common::{
+    anyhow::{bail, Result},
+    con_bail,
+    parking_lot::Mutex,
+    AnyhowToCon, ConResult, HandleTryAgain, ToCon,
+};
+use alvr_session::{DscpTos, SocketBufferSize};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const RESEND_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_DATAGRAM_SIZE: usize = 65507;
+const RELAY_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RELAY_REQUEST_SIZE: u64 = 1024;
+const RELAY_PAIRED: u8 = 1;
+const RELAY_NO_PEER: u8 = 0;
+// Clients reconnect to the relay every few seconds, older entries are abandoned connections
+const MAX_CLIENT_WAIT_TIME: Duration = Duration::from_secs(30);
+const MAX_WAITING_CLIENTS: usize = 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RelayRole {
+    Client,
+    Server,
+}
+
+// The pairing token is a secret shared by the streamer and the client when the client is trusted, so
+// that other peers cannot take the place of the client on the relay
+#[derive(Serialize, Deserialize)]
+struct ControlRelayRequest {
+    pairing_token: String,
+    role: RelayRole,
+}
+
+struct WaitingClient {
+    socket: TcpStream,
+    since: Instant,
+}
+
+// The client waits up to the timeout for the server to show up, the server returns immediately if
+// the client is not waiting
+pub(crate) fn connect_control_relay(
+    relay_address: SocketAddr,
+    pairing_token: &str,
+    role: RelayRole,
+    timeout: Duration,
+) -> ConResult<TcpStream> {
+    if pairing_token.is_empty() {
+        con_bail!("Missing relay pairing token");
+    }
+
+    let mut socket = TcpStream::connect_timeout(&relay_address, timeout).handle_try_again()?;
+    socket.set_nodelay(true).to_con()?;
+    socket.set_read_timeout(Some(timeout)).to_con()?;
+
+    let mut request = serde_json::to_vec(&ControlRelayRequest {
+        pairing_token: pairing_token.to_owned(),
+        role,
+    })
+    .to_con()?;
+    request.push(b'\n');
+    socket.write_all(&request).to_con()?;
+
+    let mut response = [RELAY_NO_PEER];
+    match socket.read(&mut response).handle_try_again()? {
+        1 if response[0] == RELAY_PAIRED => Ok(socket),
+        _ => alvr_common::try_again(),
+    }
+}
+
+fn is_connection_alive(socket: &TcpStream) -> bool {
+    if socket.set_nonblocking(true).is_err() {
+        return false;
+    }
+    // The client never sends data before being paired, so this only detects closed connections
+    let alive = matches!(socket.peek(&mut [0]), Err(e) if e.kind() == ErrorKind::WouldBlock);
+    socket.set_nonblocking(false).is_ok() && alive
+}
+
+fn splice(first: TcpStream, second: TcpStream) -> Result<()> {
+    first.set_read_timeout(None)?;
+    second.set_read_timeout(None)?;
+
+    let mut first_reader = first.try_clone()?;
+    let mut second_writer = second.try_clone()?;
+    thread::spawn(move || {
+        io::copy(&mut first_reader, &mut second_writer).ok();
+        second_writer.shutdown(Shutdown::Both).ok();
+    });
+
+    let (mut second_reader, mut first_writer) = (second, first);
+    io::copy(&mut second_reader, &mut first_writer).ok();
+    first_writer.shutdown(Shutdown::Both).ok();
+
+    Ok(())
+}
+
+fn handle_control_relay_peer(
+    socket: TcpStream,
+    waiting_clients: &Mutex<HashMap<String, WaitingClient>>,
+) -> Result<()> {
+    socket.set_read_timeout(Some(RELAY_REQUEST_TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&socket)
+        .take(MAX_RELAY_REQUEST_SIZE)
+        .read_line(&mut line)?;
+    let request = serde_json::from_str::<ControlRelayRequest>(&line)?;
+    if request.pairing_token.is_empty() {
+        bail!("Missing pairing token");
+    }
+
+    match request.role {
+        RelayRole::Client => {
+            let mut waiting_clients = waiting_clients.lock();
+
+            waiting_clients.retain(|_, client| {
+                client.since.elapsed() < MAX_CLIENT_WAIT_TIME && is_connection_alive(&client.socket)
+            });
+            if waiting_clients.len() >= MAX_WAITING_CLIENTS
+                && !waiting_clients.contains_key(&request.pairing_token)
+            {
+                bail!("Too many waiting clients");
+            }
+
+            waiting_clients.insert(
+                request.pairing_token,
+                WaitingClient {
+                    socket,
+                    since: Instant::now(),
+                },
+            );
+        }
+        RelayRole::Server => {
+            let maybe_client = waiting_clients
+                .lock()
+                .remove(&request.pairing_token)
+                .map(|client| client.socket)
+                .filter(is_connection_alive);
+
+            let mut server_socket = socket;
+            let Some(mut client_socket) = maybe_client else {
+                server_socket.write_all(&[RELAY_NO_PEER])?;
+                return Ok(());
+            };
+
+            client_socket.write_all(&[RELAY_PAIRED])?;
+            server_socket.write_all(&[RELAY_PAIRED])?;
+
+            splice(client_socket, server_socket)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Relays control connections between streamers and clients. Blocks forever
+pub fn run_control_relay(port: u16) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    let waiting_clients = Arc::new(Mutex::new(HashMap::new()));
+
+    for socket in listener.incoming() {
+        let Ok(socket) = socket else {
+            continue;
+        };
+
+        thread::spawn({
+            let waiting_clients = Arc::clone(&waiting_clients);
+            move || handle_control_relay_peer(socket, &waiting_clients).ok()
+        });
+    }
+
+    Ok(())
+}
+
+// Stream socket that can reach a peer behind a NAT
+pub struct InternetSocketBuilder {
+    socket: UdpSocket,
+    rendezvous_address: SocketAddr,
+    pub public_address: SocketAddr,
+    pub rendezvous_rtt: Duration,
+}
+
+impl InternetSocketBuilder {
+    pub fn bind(
+        rendezvous_address: SocketAddr,
+        port: u16,
+        dscp: Option<DscpTos>,
+        send_buffer_bytes: SocketBufferSize,
+        recv_buffer_bytes: SocketBufferSize,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let socket = udp::bind(port, dscp, send_buffer_bytes, recv_buffer_bytes)?;
+        socket.set_read_timeout(Some(RESEND_INTERVAL))?;
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            let query_instant = Instant::now();
+            send_discovery_packet(
+                &socket,
+                &DiscoveryPacket::QueryPublicAddress,
+                rendezvous_address,
+            )?;
+
+            while let Some((packet, address)) = recv_discovery_packet(&socket)? {
+                if let (DiscoveryPacket::PublicAddress(public_address), true) =
+                    (packet, address == rendezvous_address)
+                {
+                    return Ok(Self {
+                        socket,
+                        rendezvous_address,
+                        public_address,
+                        rendezvous_rtt: query_instant.elapsed(),
+                    });
+                }
+            }
+        }
+
+        bail!("Rendezvous server did not respond")
+    }
+
+    // Punches for the whole duration, so that both peers stop at about the same time. Returns the
+    // round trip time and the address the peer is seen from, if the peer received the punches.
+    pub fn punch_hole(
+        &self,
+        peer_address: SocketAddr,
+        duration: Duration,
+    ) -> Result<Option<(Duration, SocketAddr)>> {
+        self.socket.set_read_timeout(Some(RESEND_INTERVAL))?;
+
+        let start = Instant::now();
+        let mut send_instants = vec![];
+        let mut seen_peer_address = peer_address;
+        let mut round_trip_time = None;
+
+        while start.elapsed() < duration {
+            if start + RESEND_INTERVAL * send_instants.len() as u32 <= Instant::now() {
+                send_discovery_packet(
+                    &self.socket,
+                    &DiscoveryPacket::HolePunch(send_instants.len() as u32),
+                    seen_peer_address,
+                )?;
+                send_instants.push(Instant::now());
+            }
+
+            let Some((packet, address)) = recv_discovery_packet(&self.socket)? else {
+                continue;
+            };
+            // The port may be remapped by the peer NAT
+            if address.ip() != peer_address.ip() {
+                continue;
+            }
+
+            match packet {
+                DiscoveryPacket::HolePunch(index) => {
+                    seen_peer_address = address;
+                    send_discovery_packet(
+                        &self.socket,
+                        &DiscoveryPacket::HolePunchAck(index),
+                        address,
+                    )?;
+                }
+                DiscoveryPacket::HolePunchAck(index) => {
+                    if let Some(instant) = send_instants.get(index as usize) {
+                        let rtt = instant.elapsed();
+                        round_trip_time =
+                            Some(round_trip_time.map_or(rtt, |min: Duration| min.min(rtt)));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(round_trip_time.map(|rtt| (rtt, seen_peer_address)))
+    }
+
+    // Late punch or relay packets would be interpreted as stream shards. Stops at the first stream
+    // shard, without consuming it.
+    fn drain(&self) -> Result<()> {
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+
+        self.socket.set_nonblocking(true)?;
+        loop {
+            match self.socket.peek_from(&mut buffer) {
+                Ok((size, _)) if size > 0 && buffer[0] == b'{' => {
+                    self.socket.recv_from(&mut buffer)?;
+                }
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.socket.set_nonblocking(false)?;
+
+        Ok(())
+    }
+
+    pub fn connect_direct(
+        self,
+        peer_address: SocketAddr,
+        max_packet_size: usize,
+        timeout: Duration,
+    ) -> ConResult<StreamSocket> {
+        self.drain().to_con()?;
+
+        StreamSocket::from_udp(&self.socket, peer_address, max_packet_size, timeout).to_con()
+    }
+
+    pub fn connect_relayed(
+        self,
+        session: u64,
+        max_packet_size: usize,
+        timeout: Duration,
+    ) -> ConResult<StreamSocket> {
+        self.socket
+            .set_read_timeout(Some(RESEND_INTERVAL))
+            .to_con()?;
+
+        let deadline = Instant::now() + timeout;
+        let mut paired = false;
+        while !paired {
+            if Instant::now() > deadline {
+                con_bail!("Relay pairing timed out");
+            }
+
+            send_discovery_packet(
+                &self.socket,
+                &DiscoveryPacket::RelayRegister { session },
+                self.rendezvous_address,
+            )
+            .to_con()?;
+
+            while let Some((packet, address)) = recv_discovery_packet(&self.socket).to_con()? {
+                if matches!(packet, DiscoveryPacket::RelayPaired)
+                    && address == self.rendezvous_address
+                {
+                    paired = true;
+                }
+            }
+        }
+
+        // Wait for the replies to the last registrations before draining them
+        thread::sleep(RESEND_INTERVAL);
+        self.drain().to_con()?;
+
+        StreamSocket::from_udp(
+            &self.socket,
+            self.rendezvous_address,
+            max_packet_size,
+            timeout,
+        )
+        .to_con()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::ConnectionError;
+
+    const RELAY_PORT: u16 = 29946;
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn relay_pairs_only_with_token() {
+        thread::spawn(|| run_control_relay(RELAY_PORT));
+        thread::sleep(Duration::from_millis(100));
+        let relay_address = SocketAddr::from((Ipv4Addr::LOCALHOST, RELAY_PORT));
+
+        let client_thread = thread::spawn(move || {
+            connect_control_relay(relay_address, "client-token", RelayRole::Client, TIMEOUT)
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        // Peers that don't know the token of the waiting client are not paired with it
+        assert!(matches!(
+            connect_control_relay(relay_address, "other-token", RelayRole::Server, TIMEOUT),
+            Err(ConnectionError::TryAgain(_))
+        ));
+        assert!(matches!(
+            connect_control_relay(relay_address, "", RelayRole::Server, TIMEOUT),
+            Err(ConnectionError::Other(_))
+        ));
+
+        let mut server_socket =
+            connect_control_relay(relay_address, "client-token", RelayRole::Server, TIMEOUT)
+                .unwrap();
+        let mut client_socket = client_thread.join().unwrap().unwrap();
+
+        server_socket.write_all(b"ping").unwrap();
+        let mut buffer = [0; 4];
+        client_socket.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"ping");
+    }
+}
@@ -16,7 +16,7 @@
 // Note: We can't clone the underlying socket for each StreamSender and the mutex around the socket
 // cannot be removed. This is because we need to make sure at least shards are written whole.
 
-use crate::backend::{tcp, udp, SocketReader, SocketWriter};
+use crate::backend::{tcp, udp, DetachedSocket, SocketReader, SocketWriter};
 use alvr_common::{
     anyhow::Result, debug, parking_lot::Mutex, AnyhowToCon, ConResult, HandleTryAgain, ToCon,
 };
@@ -27,7 +27,7 @@ use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
     mem,
-    net::{IpAddr, TcpListener, UdpSocket},
+    net::{IpAddr, SocketAddr, TcpListener, UdpSocket},
     sync::{mpsc, Arc},
     time::Duration,
 };
@@ -408,6 +408,50 @@ pub struct StreamSocket {
 }
 
 impl StreamSocket {
+    // Used for sockets that already went through NAT traversal
+    pub(crate) fn from_udp(
+        socket: &UdpSocket,
+        peer_address: SocketAddr,
+        max_packet_size: usize,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let (send_socket, receive_socket) =
+            udp::connect(socket, peer_address.ip(), peer_address.port(), timeout)?;
+
+        Ok(StreamSocket {
+            // +4 is a workaround to retain compatibilty with old protocol
+            // todo: remove +4
+            max_packet_size: max_packet_size + 4,
+            send_socket: Arc::new(Mutex::new(Box::new(send_socket))),
+            receive_socket: Box::new(receive_socket),
+            shard_recv_state: None,
+            stream_recv_components: HashMap::new(),
+        })
+    }
+
+    // Closes the underlying sockets, so that the ports can be bound again for a new connection.
+    // Stream senders and receivers stay valid, and nothing is sent or received until reattach.
+    pub fn detach(&mut self) {
+        *self.send_socket.lock() = Box::new(DetachedSocket);
+        self.receive_socket = Box::new(DetachedSocket);
+        self.shard_recv_state = None;
+
+        // Partially received packets cannot be completed anymore
+        for components in self.stream_recv_components.values_mut() {
+            for (_, packet) in components.in_progress_packets.drain() {
+                components.used_buffer_sender.send(packet.buffer).ok();
+            }
+        }
+    }
+
+    // Takes over the sockets of a new connection to the same peer
+    pub fn reattach(&mut self, other: StreamSocket) {
+        self.detach();
+
+        mem::swap(&mut *self.send_socket.lock(), &mut *other.send_socket.lock());
+        self.receive_socket = other.receive_socket;
+    }
+
     pub fn request_stream<T>(&self, stream_id: u16) -> StreamSender<T> {
         StreamSender {
             inner: Arc::clone(&self.send_socket),
@@ -624,3 +668,338 @@ impl StreamSocket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::impaired::{self, ImpairmentConfig};
+    use alvr_common::ConnectionError;
+    use std::time::Instant;
+
+    const MAX_PACKET_SIZE: usize = 1400;
+    const STREAM_ID: u16 = 1;
+
+    // The stream header is (packet index, payload size)
+    type TestHeader = (u32, u32);
+
+    struct ReceivedPacket {
+        index: u32,
+        had_packet_loss: bool,
+        received_at: Instant,
+    }
+
+    // Both directions go through the same impaired link, the socket receives what it sends
+    fn loopback_socket(config: ImpairmentConfig) -> StreamSocket {
+        let (writer, reader) = impaired::impaired_link(config);
+
+        StreamSocket {
+            max_packet_size: MAX_PACKET_SIZE,
+            send_socket: Arc::new(Mutex::new(Box::new(writer))),
+            receive_socket: Box::new(reader),
+            shard_recv_state: None,
+            stream_recv_components: HashMap::new(),
+        }
+    }
+
+    fn test_payload(index: u32, size: usize) -> Vec<u8> {
+        (0..size)
+            .map(|i| (i as u32).wrapping_mul(31).wrapping_add(index * 7) as u8)
+            .collect()
+    }
+
+    fn send_packet(sender: &mut StreamSender<TestHeader>, index: u32, size: usize) {
+        let mut buffer = sender.get_buffer(&(index, size as u32)).unwrap();
+        buffer
+            .get_range_mut(0, size)
+            .copy_from_slice(&test_payload(index, size));
+        sender.send(buffer).unwrap();
+    }
+
+    // Receives until `count` packets are delivered or the timeout expires. Every delivered packet
+    // is checked for corruption
+    fn receive_packets(
+        socket: &mut StreamSocket,
+        receiver: &mut StreamReceiver<TestHeader>,
+        count: usize,
+        timeout: Duration,
+    ) -> Vec<ReceivedPacket> {
+        let deadline = Instant::now() + timeout;
+
+        let mut packets = vec![];
+        while packets.len() < count && Instant::now() < deadline {
+            if let Err(ConnectionError::Other(e)) = socket.recv() {
+                panic!("Socket error: {e}");
+            }
+
+            while let Ok(data) = receiver.recv(Duration::ZERO) {
+                let ((index, size), payload) = data.get().unwrap();
+                assert_eq!(payload, test_payload(index, size as usize));
+
+                packets.push(ReceivedPacket {
+                    index,
+                    had_packet_loss: data.had_packet_loss(),
+                    received_at: Instant::now(),
+                });
+            }
+        }
+
+        packets
+    }
+
+    fn assert_increasing(packets: &[ReceivedPacket]) {
+        assert!(packets.windows(2).all(|w| w[0].index < w[1].index));
+    }
+
+    #[test]
+    fn ideal_link() {
+        let mut socket = loopback_socket(ImpairmentConfig::default());
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 4);
+
+        // Empty, single shard, exactly one shard, and multiple shards
+        let sizes = [0, 100, MAX_PACKET_SIZE - SHARD_PREFIX_SIZE - 8, 100_000];
+        for (index, size) in sizes.into_iter().enumerate() {
+            send_packet(&mut sender, index as u32, size);
+        }
+
+        let packets = receive_packets(
+            &mut socket,
+            &mut receiver,
+            sizes.len(),
+            Duration::from_secs(5),
+        );
+
+        assert_eq!(packets.len(), sizes.len());
+        assert_increasing(&packets);
+        assert!(packets.iter().all(|p| !p.had_packet_loss));
+    }
+
+    #[test]
+    fn interleaved_streams() {
+        let mut socket = loopback_socket(ImpairmentConfig::default());
+        let mut video_sender = socket.request_stream(STREAM_ID);
+        let mut input_sender = socket.request_stream(STREAM_ID + 1);
+        let mut video_receiver = socket.subscribe_to_stream(STREAM_ID, 8);
+        let mut input_receiver = socket.subscribe_to_stream(STREAM_ID + 1, 8);
+
+        for index in 0..5 {
+            send_packet(&mut video_sender, index, 20_000);
+            send_packet(&mut input_sender, index, 50);
+        }
+
+        // Each stream is reassembled independently
+        let mut video_packets = vec![];
+        let mut input_packets = vec![];
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while (video_packets.len() < 5 || input_packets.len() < 5) && Instant::now() < deadline {
+            video_packets.extend(receive_packets(
+                &mut socket,
+                &mut video_receiver,
+                1,
+                Duration::from_millis(1),
+            ));
+            input_packets.extend(receive_packets(
+                &mut socket,
+                &mut input_receiver,
+                1,
+                Duration::from_millis(1),
+            ));
+        }
+
+        assert_eq!(video_packets.len(), 5);
+        assert_eq!(input_packets.len(), 5);
+        assert_increasing(&video_packets);
+        assert_increasing(&input_packets);
+    }
+
+    #[test]
+    fn shards_reordered_within_packet() {
+        let mut socket = loopback_socket(ImpairmentConfig {
+            jitter: Duration::from_millis(5),
+            reordering: 0.3,
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 4);
+
+        // One packet in flight at a time, so only the order of the shards is shuffled
+        for index in 0..10 {
+            send_packet(&mut sender, index, 50_000);
+
+            let packets = receive_packets(&mut socket, &mut receiver, 1, Duration::from_secs(5));
+            assert_eq!(packets.len(), 1);
+            assert_eq!(packets[0].index, index);
+            assert!(!packets[0].had_packet_loss);
+        }
+    }
+
+    #[test]
+    fn reordered_packets_are_not_delivered_late() {
+        let mut socket = loopback_socket(ImpairmentConfig {
+            jitter: Duration::from_millis(5),
+            reordering: 0.2,
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 8);
+
+        for index in 0..100 {
+            send_packet(&mut sender, index, 3000);
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 100, Duration::from_secs(1));
+
+        assert!(!packets.is_empty());
+        assert_increasing(&packets);
+    }
+
+    #[test]
+    fn lossy_link() {
+        let mut socket = loopback_socket(ImpairmentConfig {
+            jitter: Duration::from_millis(1),
+            loss: 0.05,
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 8);
+
+        for index in 0..200 {
+            send_packet(&mut sender, index, 3000);
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 200, Duration::from_secs(1));
+
+        assert!(!packets.is_empty() && packets.len() < 200);
+        assert_increasing(&packets);
+
+        // Every gap in the indices is reported
+        for pair in packets.windows(2) {
+            assert_eq!(pair[1].had_packet_loss, pair[1].index != pair[0].index + 1);
+        }
+    }
+
+    #[test]
+    fn reliable_link() {
+        let mut socket = loopback_socket(ImpairmentConfig {
+            jitter: Duration::from_millis(2),
+            loss: 0.1,
+            reliable: true,
+            // Not aligned with the shards, reads return partial shards
+            segment_size: 500,
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 4);
+
+        for index in 0..50 {
+            send_packet(&mut sender, index, 5000);
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 50, Duration::from_secs(5));
+
+        assert_eq!(packets.len(), 50);
+        assert!(packets.iter().enumerate().all(|(i, p)| p.index == i as u32));
+        assert!(packets.iter().all(|p| !p.had_packet_loss));
+    }
+
+    #[test]
+    fn delay_bounds_latency() {
+        let delay = Duration::from_millis(50);
+        let mut socket = loopback_socket(ImpairmentConfig {
+            delay,
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 4);
+
+        let send_instant = Instant::now();
+        send_packet(&mut sender, 0, 10_000);
+
+        let packets = receive_packets(&mut socket, &mut receiver, 1, Duration::from_secs(5));
+
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].received_at - send_instant >= delay);
+    }
+
+    #[test]
+    fn bandwidth_cap() {
+        // 1 MB/s
+        let mut socket = loopback_socket(ImpairmentConfig {
+            bandwidth_bps: Some(8_000_000),
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 4);
+
+        let send_instant = Instant::now();
+        for index in 0..10 {
+            send_packet(&mut sender, index, 20_000);
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 10, Duration::from_secs(5));
+
+        assert_eq!(packets.len(), 10);
+        let elapsed = packets.last().unwrap().received_at - send_instant;
+        assert!(elapsed >= Duration::from_millis(190));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn link_queue_overflow() {
+        let mut socket = loopback_socket(ImpairmentConfig {
+            max_queue_bytes: Some(10_000),
+            ..Default::default()
+        });
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 8);
+
+        for index in 0..50 {
+            send_packet(&mut sender, index, 5000);
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 50, Duration::from_millis(200));
+        assert!(!packets.is_empty() && packets.len() < 50);
+        assert_increasing(&packets);
+
+        // Once the queue is drained packets go through again
+        send_packet(&mut sender, 50, 5000);
+        let packets = receive_packets(&mut socket, &mut receiver, 1, Duration::from_secs(5));
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].index, 50);
+    }
+
+    #[test]
+    fn receiver_buffers_exhausted() {
+        let mut socket = loopback_socket(ImpairmentConfig::default());
+        let mut sender = socket.request_stream(STREAM_ID);
+        let mut receiver = socket.subscribe_to_stream(STREAM_ID, 2);
+
+        for index in 0..20 {
+            send_packet(&mut sender, index, 5000);
+        }
+
+        // The receiver is not read, so the shards of the packets after the first two are discarded
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if let Err(ConnectionError::Other(e)) = socket.recv() {
+                panic!("Socket error: {e}");
+            }
+        }
+
+        let packets = receive_packets(&mut socket, &mut receiver, 20, Duration::from_millis(50));
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].index, 0);
+        assert_eq!(packets[1].index, 1);
+
+        // The buffers are returned when the received data is dropped
+        for index in 20..25 {
+            send_packet(&mut sender, index, 5000);
+        }
+        let packets = receive_packets(&mut socket, &mut receiver, 5, Duration::from_secs(5));
+
+        assert_eq!(packets.len(), 5);
+        assert_eq!(packets[0].index, 20);
+        assert!(packets[0].had_packet_loss);
+        assert!(packets[1..].iter().all(|p| !p.had_packet_loss));
+    }
+}
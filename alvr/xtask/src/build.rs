@@ -76,8 +76,10 @@ pub fn build_server_lib(
             .unwrap();
     }
 
-    let out = build_dir.join("alvr_server_core.h");
-    cmd!(sh, "cbindgen --output {out}").run().unwrap();
+    // The header is checked in, so API changes show up in the diff
+    let header_path = afs::crate_dir("server_core").join("include/alvr_server_core.h");
+    cmd!(sh, "cbindgen --output {header_path}").run().unwrap();
+    sh.copy_file(header_path, &build_dir).unwrap();
 }
 
 pub fn build_streamer(
@@ -175,6 +177,18 @@ pub fn build_streamer(
         .unwrap();
     }
 
+    // Build CLI
+    {
+        let _push_guard = sh.push_dir(afs::crate_dir("cli"));
+        cmd!(sh, "cargo build {common_flags_ref...}").run().unwrap();
+
+        sh.copy_file(
+            artifacts_dir.join(afs::exec_fname("alvr_cli")),
+            build_layout.cli_exe(),
+        )
+        .unwrap();
+    }
+
     // copy dependencies
     if cfg!(windows) {
         sh.copy_file(
@@ -269,6 +283,54 @@ pub fn build_streamer(
     }
 }
 
+// Requires the wasm32-unknown-unknown target and wasm-bindgen-cli. The output is placed in the
+// streamer build folder, so this must run after build_streamer()
+pub fn build_web_dashboard(profile: Profile) {
+    let sh = Shell::new().unwrap();
+
+    let build_layout = Layout::new(&afs::streamer_build_dir());
+
+    let mut flags = vec![];
+    match profile {
+        Profile::Distribution => {
+            flags.push("--profile");
+            flags.push("distribution");
+        }
+        Profile::Release => flags.push("--release"),
+        Profile::Debug => (),
+    }
+    let flags_ref = &flags;
+
+    let wasm_path = afs::target_dir()
+        .join("wasm32-unknown-unknown")
+        .join(profile.to_string())
+        .join("alvr_dashboard.wasm");
+    let out_dir = build_layout.dashboard_dir();
+    let resources_dir = afs::crate_dir("dashboard").join("resources");
+
+    let _push_guard = sh.push_dir(afs::crate_dir("dashboard"));
+    cmd!(
+        sh,
+        "cargo build --target wasm32-unknown-unknown {flags_ref...}"
+    )
+    .run()
+    .unwrap();
+
+    sh.remove_path(&out_dir).unwrap();
+    sh.create_dir(&out_dir).unwrap();
+    cmd!(
+        sh,
+        "wasm-bindgen --target web --no-typescript --out-dir {out_dir} {wasm_path}"
+    )
+    .run()
+    .unwrap();
+
+    sh.copy_file(resources_dir.join("index.html"), &out_dir)
+        .unwrap();
+    sh.copy_file(resources_dir.join("dashboard.ico"), &out_dir)
+        .unwrap();
+}
+
 pub fn build_launcher(profile: Profile, enable_messagebox: bool, reproducible: bool) {
     let sh = Shell::new().unwrap();
 
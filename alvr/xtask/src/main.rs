@@ -23,6 +23,8 @@ SUBCOMMANDS:
     prepare-deps        Download and compile streamer and client external dependencies
     build-streamer      Build streamer, then copy binaries to build folder
     build-launcher      Build launcher, then copy binaries to build folder
+    build-web-dashboard Build the browser dashboard served by the streamer. Run after build-streamer.
+                        Requires the wasm32-unknown-unknown target and wasm-bindgen-cli
     build-server-lib    Build a C-ABI ALVR server library and header
     build-client        Build client, then copy binaries to build folder
     build-client-lib    Build a C-ABI ALVR client library and header
@@ -203,6 +205,7 @@ fn main() {
                     build::build_streamer(profile, true, gpl, None, false, profiling, keep_config)
                 }
                 "build-launcher" => build::build_launcher(profile, true, false),
+                "build-web-dashboard" => build::build_web_dashboard(profile),
                 "build-server-lib" => build::build_server_lib(profile, true, None, false),
                 "build-client" => build::build_android_client(profile),
                 "build-client-lib" => {